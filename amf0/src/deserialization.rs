@@ -2,13 +2,16 @@
 //! that were encoded via the AMF0 specification
 //! (http://wwwimages.adobe.com/content/dam/Adobe/en/devnet/amf/pdf/amf0-file-format-specification.pdf)
 
-use std::io::Read;
-use std::collections::HashMap;
+use std::io::{self, Read};
+use rml_amf3;
 use Amf0Value;
 use errors::Amf0DeserializationError;
 use markers;
 use byteorder::{BigEndian, ReadBytesExt};
 
+/// The most levels objects and arrays can be nested inside each other
+const MAX_DEPTH: usize = 64;
+
 struct ObjectProperty {
     label: String,
     value: Amf0Value,
@@ -19,7 +22,7 @@ pub fn deserialize<R: Read>(bytes: &mut R) -> Result<Vec<Amf0Value>, Amf0Deseria
     let mut results = vec![];
 
     loop {
-        match read_next_value(bytes)? {
+        match read_next_value(bytes, 0)? {
             Some(x) => {
                 results.push(x)
             },
@@ -30,7 +33,8 @@ pub fn deserialize<R: Read>(bytes: &mut R) -> Result<Vec<Amf0Value>, Amf0Deseria
     Ok(results)
 }
 
-fn read_next_value<R: Read>(bytes: &mut R) -> Result<Option<Amf0Value>, Amf0DeserializationError> {
+/// Reads the next value, where `depth` is the number of objects and arrays the value is inside of
+fn read_next_value<R: Read>(bytes: &mut R, depth: usize) -> Result<Option<Amf0Value>, Amf0DeserializationError> {
     let mut buffer: [u8; 1] = [0];
     let bytes_read = bytes.read(&mut buffer)?;
 
@@ -47,9 +51,17 @@ fn read_next_value<R: Read>(bytes: &mut R) -> Result<Option<Amf0Value>, Amf0Dese
         markers::NULL_MARKER => parse_null().map(Some),
         markers::UNDEFINED_MARKER => parse_undefined().map(Some),
         markers::NUMBER_MARKER => parse_number(bytes).map(Some),
-        markers::OBJECT_MARKER => parse_object(bytes).map(Some),
-        markers::ECMA_ARRAY_MARKER => parse_ecma_array(bytes).map(Some),
+        markers::OBJECT_MARKER => parse_object(bytes, depth).map(Some),
+        markers::ECMA_ARRAY_MARKER => parse_ecma_array(bytes, depth).map(Some),
         markers::STRING_MARKER => parse_string(bytes).map(Some),
+        markers::REFERENCE_MARKER => parse_reference(bytes).map(Some),
+        markers::STRICT_ARRAY_MARKER => parse_strict_array(bytes, depth).map(Some),
+        markers::DATE_MARKER => parse_date(bytes).map(Some),
+        markers::LONG_STRING_MARKER => parse_long_string(bytes).map(Some),
+        markers::UNSUPPORTED_MARKER => Ok(Some(Amf0Value::Unsupported)),
+        markers::XML_DOCUMENT_MARKER => parse_xml_document(bytes).map(Some),
+        markers::TYPED_OBJECT_MARKER => parse_typed_object(bytes, depth).map(Some),
+        markers::AVMPLUS_OBJECT_MARKER => parse_avmplus_object(bytes).map(Some),
         _ => Err(Amf0DeserializationError::UnknownMarker{ marker: buffer[0] })
     }
 }
@@ -82,43 +94,104 @@ fn parse_bool<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationEr
 
 fn parse_string<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
     let length = bytes.read_u16::<BigEndian>()?;
-    let mut buffer: Vec<u8> = vec![0_u8; length as usize];
-    bytes.read(&mut buffer)?;
-
-    let value = String::from_utf8(buffer)?;
+    let value = read_utf8(bytes, length as usize)?;
     Ok(Amf0Value::Utf8String(value))
 }
 
-fn parse_object<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
-    let mut properties = HashMap::new();
+fn parse_long_string<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+    let length = bytes.read_u32::<BigEndian>()?;
+    let value = read_utf8(bytes, length as usize)?;
+    Ok(Amf0Value::LongString(value))
+}
 
-    loop {
-        match parse_object_property(bytes)? {
-            Some(property) => properties.insert(property.label, property.value),
-            None => break,
-        };
-    }
+fn parse_xml_document<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+    // XML documents are encoded the same way as long strings
+    let length = bytes.read_u32::<BigEndian>()?;
+    let value = read_utf8(bytes, length as usize)?;
+    Ok(Amf0Value::XmlDocument(value))
+}
+
+fn parse_object<R: Read>(bytes: &mut R, depth: usize) -> Result<Amf0Value, Amf0DeserializationError> {
+    let properties = parse_object_properties(bytes, depth)?
+        .into_iter()
+        .collect();
 
     let deserialized_value = Amf0Value::Object(properties);
     Ok(deserialized_value)
 }
 
-fn parse_ecma_array<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+fn parse_typed_object<R: Read>(bytes: &mut R, depth: usize) -> Result<Amf0Value, Amf0DeserializationError> {
+    let class_name_length = bytes.read_u16::<BigEndian>()?;
+    let class_name = read_utf8(bytes, class_name_length as usize)?;
+    let properties = parse_object_properties(bytes, depth)?
+        .into_iter()
+        .collect();
+
+    Ok(Amf0Value::TypedObject {class_name, properties})
+}
+
+fn parse_ecma_array<R: Read>(bytes: &mut R, depth: usize) -> Result<Amf0Value, Amf0DeserializationError> {
     // An ECMA array is an array of values indexed via strings instead of numeric indexes (so
-    // essentially a hash map).  It seems functionally equivalent to an object so for simplicity
-    // treat it as such.
+    // essentially a hash map).
 
     // While the spec says it gives you the count of items in the array, it is vague about if
     // the object end marker is used.  In real world usages I have found the associative array
     // actually ends with a 0x000009 ending (same as objects do).  If we don't consume this
     // then the buffer will start at that ending and funky things will happen.  So for now it seems
-    // like we can ignore the associative count and just read exactly as we would an object.
+    // like we can ignore the associative count and just read exactly as we would an object.  The
+    // count is still kept so it can be written back out as it was received.
 
-    let _associative_count = bytes.read_u32::<BigEndian>()?;
-    parse_object(bytes)
+    let associative_count = bytes.read_u32::<BigEndian>()?;
+    let properties = parse_object_properties(bytes, depth)?;
+    Ok(Amf0Value::EcmaArray {associative_count, properties})
 }
 
-fn parse_object_property<R: Read>(bytes: &mut R) -> Result<Option<ObjectProperty>, Amf0DeserializationError> {
+fn parse_strict_array<R: Read>(bytes: &mut R, depth: usize) -> Result<Amf0Value, Amf0DeserializationError> {
+    check_depth(depth)?;
+
+    let count = bytes.read_u32::<BigEndian>()?;
+    let mut values = Vec::new();
+    for _ in 0..count {
+        match read_next_value(bytes, depth + 1)? {
+            Some(value) => values.push(value),
+            None => return Err(Amf0DeserializationError::UnexpectedEof),
+        }
+    }
+
+    Ok(Amf0Value::StrictArray(values))
+}
+
+fn parse_date<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+    let unix_time = bytes.read_f64::<BigEndian>()?;
+    let time_zone = bytes.read_i16::<BigEndian>()?;
+    Ok(Amf0Value::Date {unix_time, time_zone})
+}
+
+fn parse_reference<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+    let index = bytes.read_u16::<BigEndian>()?;
+    Ok(Amf0Value::Reference(index))
+}
+
+fn parse_avmplus_object<R: Read>(bytes: &mut R) -> Result<Amf0Value, Amf0DeserializationError> {
+    let value = rml_amf3::deserialize_value(bytes)?;
+    Ok(Amf0Value::AvmPlus(value))
+}
+
+fn parse_object_properties<R: Read>(bytes: &mut R, depth: usize) -> Result<Vec<(String, Amf0Value)>, Amf0DeserializationError> {
+    check_depth(depth)?;
+
+    let mut properties = Vec::new();
+    loop {
+        match parse_object_property(bytes, depth + 1)? {
+            Some(property) => properties.push((property.label, property.value)),
+            None => break,
+        };
+    }
+
+    Ok(properties)
+}
+
+fn parse_object_property<R: Read>(bytes: &mut R, depth: usize) -> Result<Option<ObjectProperty>, Amf0DeserializationError> {
     let label_length = bytes.read_u16::<BigEndian>()?;
     if label_length == 0 {
         // Next byte should be the end of object marker.  We need to read this
@@ -131,12 +204,9 @@ fn parse_object_property<R: Read>(bytes: &mut R) -> Result<Option<ObjectProperty
         return Ok(None);
     }
 
-    let mut label_buffer = vec![0; label_length as usize];
-    bytes.read(&mut label_buffer)?;
+    let label = read_utf8(bytes, label_length as usize)?;
 
-    let label = String::from_utf8(label_buffer)?;
-
-    match read_next_value(bytes)? {
+    match read_next_value(bytes, depth)? {
         None => Err(Amf0DeserializationError::UnexpectedEof),
         Some(property_value) => {
             Ok(Some(ObjectProperty {
@@ -147,12 +217,36 @@ fn parse_object_property<R: Read>(bytes: &mut R) -> Result<Option<ObjectProperty
     }
 }
 
+/// Makes sure an object or array at the specified depth isn't nested too deeply, so malicious
+/// input can't overflow the stack
+fn check_depth(depth: usize) -> Result<(), Amf0DeserializationError> {
+    if depth >= MAX_DEPTH {
+        return Err(Amf0DeserializationError::NestingTooDeep {max_depth: MAX_DEPTH});
+    }
+
+    Ok(())
+}
+
+fn read_utf8<R: Read>(bytes: &mut R, length: usize) -> Result<String, Amf0DeserializationError> {
+    // Only allocate for bytes that actually arrive, since the length comes from the peer
+    let mut buffer: Vec<u8> = Vec::new();
+    bytes.take(length as u64).read_to_end(&mut buffer)?;
+    if buffer.len() < length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    let value = String::from_utf8(buffer)?;
+    Ok(value)
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
     use std::collections::HashMap;
-    use super::deserialize;
+    use super::{deserialize, MAX_DEPTH};
     use super::super::Amf0Value;
+    use errors::Amf0DeserializationError;
+    use rml_amf3::Amf3Value;
     use markers;
     use byteorder::{BigEndian, WriteBytesExt};
 
@@ -268,14 +362,34 @@ mod tests {
         let mut input = Cursor::new(vector);
         let result = deserialize(&mut input).unwrap();
 
-        let mut properties = HashMap::new();
-        properties.insert("test1".to_string(), Amf0Value::Number(1.0));
-        properties.insert("test2".to_string(), Amf0Value::Utf8String("second".to_string()));
+        let properties = vec![
+            ("test1".to_string(), Amf0Value::Number(1.0)),
+            ("test2".to_string(), Amf0Value::Utf8String("second".to_string())),
+        ];
 
-        let expected = vec![Amf0Value::Object(properties)];
+        let expected = vec![Amf0Value::EcmaArray {associative_count: 2, properties}];
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn ecma_array_keeps_declared_count() {
+        let mut vector = vec![];
+        vector.push(markers::ECMA_ARRAY_MARKER);
+        vector.write_u32::<BigEndian>(0).unwrap();
+        vector.write_u16::<BigEndian>(1).unwrap();
+        vector.extend("a".as_bytes());
+        vector.push(markers::NULL_MARKER);
+        vector.write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER).unwrap();
+        vector.push(markers::OBJECT_END_MARKER);
+
+        let result = deserialize(&mut Cursor::new(vector.clone())).unwrap();
+        let properties = vec![("a".to_string(), Amf0Value::Null)];
+        assert_eq!(result, vec![Amf0Value::EcmaArray {associative_count: 0, properties}]);
+
+        let serialized = ::serialization::serialize(&result).unwrap();
+        assert_eq!(serialized, vector, "Re-serialized bytes did not match the original");
+    }
+
     #[test]
     fn can_deserialize_undefined() {
         let mut vector = vec![];
@@ -287,4 +401,234 @@ mod tests {
         let expected = vec![Amf0Value::Undefined];
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn can_deserialize_strict_array() {
+        let mut vector = vec![];
+        vector.push(markers::STRICT_ARRAY_MARKER);
+        vector.write_u32::<BigEndian>(2).unwrap();
+        vector.push(markers::NUMBER_MARKER);
+        vector.write_f64::<BigEndian>(1.0).unwrap();
+        vector.push(markers::STRING_MARKER);
+        vector.write_u16::<BigEndian>(4).unwrap();
+        vector.extend("test".as_bytes());
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf0Value::StrictArray(vec![
+            Amf0Value::Number(1.0),
+            Amf0Value::Utf8String("test".to_string()),
+        ])];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn error_when_strict_array_has_less_values_than_count() {
+        let mut vector = vec![];
+        vector.push(markers::STRICT_ARRAY_MARKER);
+        vector.write_u32::<BigEndian>(2).unwrap();
+        vector.push(markers::NUMBER_MARKER);
+        vector.write_f64::<BigEndian>(1.0).unwrap();
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf0DeserializationError::UnexpectedEof) => (),
+            x => panic!("Expected unexpected eof error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_values_are_nested_too_deeply() {
+        let mut vector = vec![];
+        for _ in 0..(MAX_DEPTH + 1) {
+            vector.push(markers::STRICT_ARRAY_MARKER);
+            vector.write_u32::<BigEndian>(1).unwrap();
+        }
+
+        vector.push(markers::NULL_MARKER);
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf0DeserializationError::NestingTooDeep {max_depth: MAX_DEPTH}) => (),
+            x => panic!("Expected nesting too deep error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_objects_are_nested_too_deeply() {
+        let mut vector = vec![];
+        vector.push(markers::OBJECT_MARKER);
+        for _ in 0..MAX_DEPTH {
+            vector.write_u16::<BigEndian>(1).unwrap();
+            vector.extend("a".as_bytes());
+            vector.push(markers::ECMA_ARRAY_MARKER);
+            vector.write_u32::<BigEndian>(1).unwrap();
+        }
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf0DeserializationError::NestingTooDeep {max_depth: MAX_DEPTH}) => (),
+            x => panic!("Expected nesting too deep error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_deserialize_values_nested_to_max_depth() {
+        let mut vector = vec![];
+        for _ in 0..MAX_DEPTH {
+            vector.push(markers::STRICT_ARRAY_MARKER);
+            vector.write_u32::<BigEndian>(1).unwrap();
+        }
+
+        vector.push(markers::NULL_MARKER);
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let mut expected = Amf0Value::Null;
+        for _ in 0..MAX_DEPTH {
+            expected = Amf0Value::StrictArray(vec![expected]);
+        }
+
+        assert_eq!(result, vec![expected]);
+    }
+
+    #[test]
+    fn can_deserialize_date() {
+        let mut vector = vec![];
+        vector.push(markers::DATE_MARKER);
+        vector.write_f64::<BigEndian>(1_500_000_000_000.0).unwrap();
+        vector.write_i16::<BigEndian>(0).unwrap();
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf0Value::Date {unix_time: 1_500_000_000_000.0, time_zone: 0}];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_long_string() {
+        let value = "test";
+
+        let mut vector = vec![];
+        vector.push(markers::LONG_STRING_MARKER);
+        vector.write_u32::<BigEndian>(value.len() as u32).unwrap();
+        vector.extend(value.as_bytes());
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf0Value::LongString(value.to_string())];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_xml_document() {
+        let value = "<a>b</a>";
+
+        let mut vector = vec![];
+        vector.push(markers::XML_DOCUMENT_MARKER);
+        vector.write_u32::<BigEndian>(value.len() as u32).unwrap();
+        vector.extend(value.as_bytes());
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf0Value::XmlDocument(value.to_string())];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_typed_object() {
+        let mut vector = vec![];
+        vector.push(markers::TYPED_OBJECT_MARKER);
+        vector.write_u16::<BigEndian>(5).unwrap();
+        vector.extend("class".as_bytes());
+        vector.write_u16::<BigEndian>(4).unwrap();
+        vector.extend("test".as_bytes());
+        vector.push(markers::NUMBER_MARKER);
+        vector.write_f64::<BigEndian>(5.0).unwrap();
+        vector.write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER).unwrap();
+        vector.push(markers::OBJECT_END_MARKER);
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let mut properties = HashMap::new();
+        properties.insert("test".to_string(), Amf0Value::Number(5.0));
+
+        let expected = vec![Amf0Value::TypedObject {class_name: "class".to_string(), properties}];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_reference() {
+        let mut vector = vec![];
+        vector.push(markers::REFERENCE_MARKER);
+        vector.write_u16::<BigEndian>(3).unwrap();
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf0Value::Reference(3)];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_unsupported() {
+        let vector = vec![markers::UNSUPPORTED_MARKER];
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf0Value::Unsupported];
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn error_when_string_is_shorter_than_its_length() {
+        let mut vector = vec![];
+        vector.push(markers::STRING_MARKER);
+        vector.write_u16::<BigEndian>(10).unwrap();
+        vector.extend("test".as_bytes());
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf0DeserializationError::BufferReadError(_)) => (),
+            x => panic!("Expected buffer read error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_without_allocating_when_long_string_length_exceeds_data() {
+        let mut vector = vec![];
+        vector.push(markers::LONG_STRING_MARKER);
+        vector.write_u32::<BigEndian>(0xffff_ffff).unwrap();
+        vector.extend("test".as_bytes());
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf0DeserializationError::BufferReadError(_)) => (),
+            x => panic!("Expected buffer read error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_deserialize_avmplus_object() {
+        let mut vector = vec![];
+        vector.push(markers::AVMPLUS_OBJECT_MARKER);
+        vector.push(0x04); // AMF3 integer marker
+        vector.push(0x05);
+        vector.push(markers::NUMBER_MARKER);
+        vector.write_f64::<BigEndian>(1.0).unwrap();
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf0Value::AvmPlus(Amf3Value::Integer(5)), Amf0Value::Number(1.0)];
+        assert_eq!(result, expected);
+    }
 }
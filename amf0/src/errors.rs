@@ -1,4 +1,5 @@
 use std::{io, string};
+use rml_amf3::{Amf3DeserializationError, Amf3SerializationError};
 
 /// Errors that can occur during the deserialization process
 #[derive(Debug,Fail)]
@@ -29,7 +30,17 @@ pub enum Amf0DeserializationError {
     /// Strings in AMF0 are UTF-8 encoded, so if the bytes read are not valid
     /// UTF-8 this error will be raised.
     #[fail(display = "Failed to read a utf8 string from the byte buffer: {}", _0)]
-    StringParseError(#[cause] string::FromUtf8Error)
+    StringParseError(#[cause] string::FromUtf8Error),
+
+    /// Objects and arrays were nested inside each other deeper than the deserializer allows
+    #[fail(display = "Values were nested more than {} levels deep", max_depth)]
+    NestingTooDeep {
+        max_depth: usize
+    },
+
+    /// An error occurred while deserializing an AMF3 value following an avmplus object marker
+    #[fail(display = "Failed to deserialize an AMF3 value: {}", _0)]
+    Amf3DeserializationError(#[cause] Amf3DeserializationError),
 }
 
 // Since an IO error can only be thrown while reading the buffer, auto-conversion should work
@@ -45,6 +56,12 @@ impl From<string::FromUtf8Error> for Amf0DeserializationError {
     }
 }
 
+impl From<Amf3DeserializationError> for Amf0DeserializationError {
+    fn from(error: Amf3DeserializationError) -> Self {
+        Amf0DeserializationError::Amf3DeserializationError(error)
+    }
+}
+
 /// Errors raised during to the serialization process
 #[derive(Debug, Fail)]
 pub enum Amf0SerializationError {
@@ -53,13 +70,27 @@ pub enum Amf0SerializationError {
     #[fail(display = "String length greater than 65,535")]
     NormalStringTooLong,
 
+    /// Amf0 long strings (and XML documents) cannot be more than 4,294,967,295 bytes.
+    #[fail(display = "Long string length greater than 4,294,967,295")]
+    LongStringTooLong,
+
     /// An I/O error occurred while writing to the output buffer.
     #[fail(display = "Failed to write to byte buffer")]
-    BufferWriteError(#[cause] io::Error)
+    BufferWriteError(#[cause] io::Error),
+
+    /// An error occurred while serializing an AMF3 value
+    #[fail(display = "Failed to serialize an AMF3 value: {}", _0)]
+    Amf3SerializationError(#[cause] Amf3SerializationError),
 }
 
 impl From<io::Error> for Amf0SerializationError {
     fn from(error: io::Error) -> Self {
         Amf0SerializationError::BufferWriteError(error)
     }
+}
+
+impl From<Amf3SerializationError> for Amf0SerializationError {
+    fn from(error: Amf3SerializationError) -> Self {
+        Amf0SerializationError::Amf3SerializationError(error)
+    }
 }
\ No newline at end of file
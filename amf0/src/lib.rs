@@ -31,6 +31,7 @@
 
 #[macro_use] extern crate failure;
 extern crate byteorder;
+extern crate rml_amf3;
 
 mod serialization;
 mod deserialization;
@@ -41,6 +42,7 @@ pub use deserialization::deserialize;
 pub use errors::{Amf0DeserializationError, Amf0SerializationError};
 
 use std::collections::HashMap;
+use rml_amf3::Amf3Value;
 
 /// An Enum representing the different supported types of Amf0 values
 #[derive(PartialEq, Debug, Clone)]
@@ -51,6 +53,47 @@ pub enum Amf0Value {
     Object(HashMap<String, Amf0Value>),
     Null,
     Undefined,
+
+    /// An index into the table of complex values (objects, arrays, etc...) previously
+    /// encountered in the same AMF0 stream.
+    Reference(u16),
+
+    /// An associative array.  Properties are kept in the order they were encoded in, along with
+    /// the count the encoder declared (which does not always match the number of properties), so
+    /// the value can be re-serialized to the exact same bytes.
+    EcmaArray {
+        associative_count: u32,
+        properties: Vec<(String, Amf0Value)>,
+    },
+
+    /// An array of values with ordinal indexes
+    StrictArray(Vec<Amf0Value>),
+
+    /// A date encoded as the number of milliseconds since the unix epoch in UTC.  The time
+    /// zone is reserved by the specification and should be zero.
+    Date {
+        unix_time: f64,
+        time_zone: i16,
+    },
+
+    /// A string that is too large to be encoded as a `Utf8String` (more than 65,535 bytes)
+    LongString(String),
+
+    /// Represents a type that the sender could not serialize
+    Unsupported,
+
+    /// An XML document encoded as a string
+    XmlDocument(String),
+
+    /// An object that is an instance of a registered class
+    TypedObject {
+        class_name: String,
+        properties: HashMap<String, Amf0Value>,
+    },
+
+    /// An AMF3 encoded value, signaled by the avmplus object marker.  Each AMF3 value embedded
+    /// this way has its own AMF3 reference tables.
+    AvmPlus(Amf3Value),
 }
 
 impl Amf0Value {
@@ -71,13 +114,24 @@ impl Amf0Value {
     pub fn get_string(self) -> Option<String> {
         match self {
             Amf0Value::Utf8String(value) => Some(value),
+            Amf0Value::LongString(value) => Some(value),
             _ => None,
         }
     }
 
+    /// Returns the properties of an object, typed object or ECMA array
     pub fn get_object_properties(self) -> Option<HashMap<String, Amf0Value>> {
         match self {
             Amf0Value::Object(properties) => Some(properties),
+            Amf0Value::TypedObject {properties, ..} => Some(properties),
+            Amf0Value::EcmaArray {properties, ..} => Some(properties.into_iter().collect()),
+            _ => None,
+        }
+    }
+
+    pub fn get_strict_array(self) -> Option<Vec<Amf0Value>> {
+        match self {
+            Amf0Value::StrictArray(values) => Some(values),
             _ => None,
         }
     }
@@ -90,7 +144,15 @@ mod markers {
     pub const OBJECT_MARKER: u8 = 3;
     pub const NULL_MARKER: u8 = 5;
     pub const UNDEFINED_MARKER: u8 = 6;
+    pub const REFERENCE_MARKER: u8 = 7;
     pub const ECMA_ARRAY_MARKER: u8 = 8;
     pub const OBJECT_END_MARKER: u8 = 9;
+    pub const STRICT_ARRAY_MARKER: u8 = 10;
+    pub const DATE_MARKER: u8 = 11;
+    pub const LONG_STRING_MARKER: u8 = 12;
+    pub const UNSUPPORTED_MARKER: u8 = 13;
+    pub const XML_DOCUMENT_MARKER: u8 = 15;
+    pub const TYPED_OBJECT_MARKER: u8 = 16;
+    pub const AVMPLUS_OBJECT_MARKER: u8 = 17;
     pub const UTF_8_EMPTY_MARKER: u16 = 0;
 }
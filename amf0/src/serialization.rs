@@ -3,6 +3,7 @@
 //! (http://wwwimages.adobe.com/content/dam/Adobe/en/devnet/amf/pdf/amf0-file-format-specification.pdf)
 
 use std::collections::HashMap;
+use rml_amf3::{self, Amf3Value};
 use Amf0Value;
 use errors::Amf0SerializationError;
 use markers;
@@ -20,28 +21,43 @@ pub fn serialize(values: &Vec<Amf0Value>) -> Result<Vec<u8>, Amf0SerializationEr
 
 fn serialize_value(value: &Amf0Value, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
     match *value {
-        Amf0Value::Boolean(ref val) => Ok(serialize_bool(&val, bytes)),
-        Amf0Value::Null => Ok(serialize_null(bytes)),
-        Amf0Value::Undefined => Ok(serialize_undefined(bytes)),
-        Amf0Value::Number(ref val) => serialize_number(&val, bytes),
-        Amf0Value::Utf8String(ref val) => serialize_string(&val, bytes),
-        Amf0Value::Object(ref val) => serialize_object(&val, bytes)
+        Amf0Value::Boolean(val) => serialize_bool(val, bytes),
+        Amf0Value::Null => serialize_marker(markers::NULL_MARKER, bytes),
+        Amf0Value::Undefined => serialize_marker(markers::UNDEFINED_MARKER, bytes),
+        Amf0Value::Unsupported => serialize_marker(markers::UNSUPPORTED_MARKER, bytes),
+        Amf0Value::Number(val) => serialize_number(val, bytes),
+        Amf0Value::Utf8String(ref val) => serialize_string(val, bytes),
+        Amf0Value::LongString(ref val) => serialize_long_string(markers::LONG_STRING_MARKER, val, bytes),
+        Amf0Value::XmlDocument(ref val) => serialize_long_string(markers::XML_DOCUMENT_MARKER, val, bytes),
+        Amf0Value::Object(ref val) => serialize_object(val, bytes),
+        Amf0Value::TypedObject {ref class_name, ref properties} => serialize_typed_object(class_name, properties, bytes),
+        Amf0Value::EcmaArray {associative_count, ref properties} => serialize_ecma_array(associative_count, properties, bytes),
+        Amf0Value::StrictArray(ref val) => serialize_strict_array(val, bytes),
+        Amf0Value::Date {unix_time, time_zone} => serialize_date(unix_time, time_zone, bytes),
+        Amf0Value::Reference(val) => serialize_reference(val, bytes),
+        Amf0Value::AvmPlus(ref val) => serialize_avmplus_object(val, bytes),
     }
 }
 
-fn serialize_number(value: &f64, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+fn serialize_number(value: f64, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
     bytes.push(markers::NUMBER_MARKER);
-    bytes.write_f64::<BigEndian>(value.clone())?;
+    bytes.write_f64::<BigEndian>(value)?;
     Ok(())
 }
 
-fn serialize_bool(value: &bool, bytes: &mut Vec<u8>) {
+fn serialize_bool(value: bool, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
     bytes.push(markers::BOOLEAN_MARKER);
-    bytes.push((value.clone()) as u8);
+    bytes.push(value as u8);
+    Ok(())
+}
+
+fn serialize_marker(marker: u8, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    bytes.push(marker);
+    Ok(())
 }
 
-fn serialize_string(value: &String, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
-    if value.len() > (u16::max_value() as usize) {
+fn serialize_string(value: &str, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    if value.len() > (u16::MAX as usize) {
         return Err(Amf0SerializationError::NormalStringTooLong)
     }
 
@@ -51,22 +67,76 @@ fn serialize_string(value: &String, bytes: &mut Vec<u8>) -> Result<(), Amf0Seria
     Ok(())
 }
 
-fn serialize_null(bytes: &mut Vec<u8>) {
-    bytes.push(markers::NULL_MARKER);
+fn serialize_long_string(marker: u8, value: &str, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    if value.len() > (u32::MAX as usize) {
+        return Err(Amf0SerializationError::LongStringTooLong)
+    }
+
+    bytes.push(marker);
+    bytes.write_u32::<BigEndian>(value.len() as u32)?;
+    bytes.extend(value.as_bytes());
+    Ok(())
 }
 
-fn serialize_undefined(bytes: &mut Vec<u8>) {
-    bytes.push(markers::UNDEFINED_MARKER);
+fn serialize_reference(index: u16, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    bytes.push(markers::REFERENCE_MARKER);
+    bytes.write_u16::<BigEndian>(index)?;
+    Ok(())
+}
+
+fn serialize_date(unix_time: f64, time_zone: i16, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    bytes.push(markers::DATE_MARKER);
+    bytes.write_f64::<BigEndian>(unix_time)?;
+    bytes.write_i16::<BigEndian>(time_zone)?;
+    Ok(())
 }
 
 fn serialize_object(properties: &HashMap<String, Amf0Value>, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
     bytes.push(markers::OBJECT_MARKER);
+    serialize_object_properties(properties.iter(), bytes)
+}
+
+fn serialize_typed_object(class_name: &str, properties: &HashMap<String, Amf0Value>, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    if class_name.len() > (u16::MAX as usize) {
+        return Err(Amf0SerializationError::NormalStringTooLong)
+    }
+
+    bytes.push(markers::TYPED_OBJECT_MARKER);
+    bytes.write_u16::<BigEndian>(class_name.len() as u16)?;
+    bytes.extend(class_name.as_bytes());
+    serialize_object_properties(properties.iter(), bytes)
+}
+
+fn serialize_ecma_array(associative_count: u32, properties: &[(String, Amf0Value)], bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    bytes.push(markers::ECMA_ARRAY_MARKER);
+    bytes.write_u32::<BigEndian>(associative_count)?;
+    serialize_object_properties(properties.iter().map(|(name, value)| (name, value)), bytes)
+}
+
+fn serialize_strict_array(values: &[Amf0Value], bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    bytes.push(markers::STRICT_ARRAY_MARKER);
+    bytes.write_u32::<BigEndian>(values.len() as u32)?;
+    for value in values {
+        serialize_value(value, bytes)?;
+    }
+
+    Ok(())
+}
+
+fn serialize_avmplus_object(value: &Amf3Value, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError> {
+    bytes.push(markers::AVMPLUS_OBJECT_MARKER);
+    rml_amf3::serialize_value(value, bytes)?;
+    Ok(())
+}
+
+fn serialize_object_properties<'a, I>(properties: I, bytes: &mut Vec<u8>) -> Result<(), Amf0SerializationError>
+    where I: Iterator<Item = (&'a String, &'a Amf0Value)> {
 
     for (name, value) in properties {
         // TODO: Add check that property name isn't greater than a u16
         bytes.write_u16::<BigEndian>(name.len() as u16)?;
         bytes.extend(name.as_bytes());
-        serialize_value(&value, bytes)?;
+        serialize_value(value, bytes)?;
     }
 
     bytes.write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER)?;
@@ -80,6 +150,7 @@ mod tests {
     use super::serialize;
     use super::super::Amf0Value;
     use super::super::errors::Amf0SerializationError;
+    use rml_amf3::Amf3Value;
     use markers;
     use byteorder::{BigEndian, WriteBytesExt};
 
@@ -196,4 +267,138 @@ mod tests {
 
         assert_eq!(result, expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn can_serialize_ecma_array_in_original_order() {
+        let properties = vec![
+            ("b".to_string(), Amf0Value::Number(1.0)),
+            ("a".to_string(), Amf0Value::Boolean(true)),
+        ];
+
+        let input = vec![Amf0Value::EcmaArray {associative_count: 2, properties}];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.push(markers::ECMA_ARRAY_MARKER);
+        expected.write_u32::<BigEndian>(2).unwrap();
+        expected.write_u16::<BigEndian>(1).unwrap();
+        expected.extend("b".as_bytes());
+        expected.push(markers::NUMBER_MARKER);
+        expected.write_f64::<BigEndian>(1.0).unwrap();
+        expected.write_u16::<BigEndian>(1).unwrap();
+        expected.extend("a".as_bytes());
+        expected.push(markers::BOOLEAN_MARKER);
+        expected.push(1);
+        expected.write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER).unwrap();
+        expected.push(markers::OBJECT_END_MARKER);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_serialize_strict_array() {
+        let input = vec![Amf0Value::StrictArray(vec![Amf0Value::Number(1.0), Amf0Value::Null])];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.push(markers::STRICT_ARRAY_MARKER);
+        expected.write_u32::<BigEndian>(2).unwrap();
+        expected.push(markers::NUMBER_MARKER);
+        expected.write_f64::<BigEndian>(1.0).unwrap();
+        expected.push(markers::NULL_MARKER);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_serialize_date() {
+        let input = vec![Amf0Value::Date {unix_time: 1_500_000_000_000.0, time_zone: 0}];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.push(markers::DATE_MARKER);
+        expected.write_f64::<BigEndian>(1_500_000_000_000.0).unwrap();
+        expected.write_i16::<BigEndian>(0).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_serialize_long_string() {
+        let value = "test";
+        let input = vec![Amf0Value::LongString(value.to_string())];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.push(markers::LONG_STRING_MARKER);
+        expected.write_u32::<BigEndian>(value.len() as u32).unwrap();
+        expected.extend(value.as_bytes());
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_serialize_xml_document() {
+        let value = "<a>b</a>";
+        let input = vec![Amf0Value::XmlDocument(value.to_string())];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.push(markers::XML_DOCUMENT_MARKER);
+        expected.write_u32::<BigEndian>(value.len() as u32).unwrap();
+        expected.extend(value.as_bytes());
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_serialize_typed_object() {
+        let mut properties = HashMap::new();
+        properties.insert("test".to_string(), Amf0Value::Number(5.0));
+
+        let input = vec![Amf0Value::TypedObject {class_name: "class".to_string(), properties}];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.push(markers::TYPED_OBJECT_MARKER);
+        expected.write_u16::<BigEndian>(5).unwrap();
+        expected.extend("class".as_bytes());
+        expected.write_u16::<BigEndian>(4).unwrap();
+        expected.extend("test".as_bytes());
+        expected.push(markers::NUMBER_MARKER);
+        expected.write_f64::<BigEndian>(5.0).unwrap();
+        expected.write_u16::<BigEndian>(markers::UTF_8_EMPTY_MARKER).unwrap();
+        expected.push(markers::OBJECT_END_MARKER);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_serialize_reference() {
+        let input = vec![Amf0Value::Reference(3)];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![];
+        expected.push(markers::REFERENCE_MARKER);
+        expected.write_u16::<BigEndian>(3).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_serialize_unsupported() {
+        let input = vec![Amf0Value::Unsupported];
+        let result = serialize(&input).unwrap();
+
+        assert_eq!(result, vec![markers::UNSUPPORTED_MARKER]);
+    }
+
+    #[test]
+    fn can_serialize_avmplus_object() {
+        let input = vec![Amf0Value::AvmPlus(Amf3Value::Utf8String("a".to_string()))];
+        let result = serialize(&input).unwrap();
+
+        let expected = vec![markers::AVMPLUS_OBJECT_MARKER, 0x06, (1 << 1) | 1, b'a'];
+        assert_eq!(result, expected);
+    }
+}
@@ -0,0 +1,886 @@
+//! This module contains functionality to deserialize values from bytes
+//! that were encoded via the AMF3 specification
+//! (https://www.adobe.com/content/dam/acom/en/devnet/pdf/amf-file-format-spec.pdf)
+
+use std::io::Read;
+use std::mem;
+use Amf3Value;
+use Traits;
+use errors::Amf3DeserializationError;
+use markers;
+use byteorder::{BigEndian, ReadBytesExt};
+
+/// The most levels complex values can be nested inside each other
+const MAX_DEPTH: usize = 64;
+
+/// The approximate number of bytes of memory a single deserialization can expand to, counting
+/// each value as well as its string, xml and byte array data.  Referenced values count again every
+/// time they are referenced, since each reference produces a full copy of the value, and complex
+/// values count again for the copy kept in the reference table.
+const MAX_DECODED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Turns any readable byte stream and converts it into an array of AMF3 values.  All values
+/// share the same reference tables.
+pub fn deserialize<R: Read>(bytes: &mut R) -> Result<Vec<Amf3Value>, Amf3DeserializationError> {
+    let mut deserializer = Deserializer::new(bytes);
+    let mut results = vec![];
+
+    while let Some(value) = deserializer.read_next_value()? {
+        results.push(value);
+    }
+
+    Ok(results)
+}
+
+/// Reads a single AMF3 value from the byte stream with fresh reference tables.  This is used
+/// when an AMF3 value is embedded in another format (such as AMF0's avmplus marker).
+pub fn deserialize_value<R: Read>(bytes: &mut R) -> Result<Amf3Value, Amf3DeserializationError> {
+    let mut deserializer = Deserializer::new(bytes);
+    match deserializer.read_next_value()? {
+        Some(value) => Ok(value),
+        None => Err(Amf3DeserializationError::UnexpectedEof),
+    }
+}
+
+struct Deserializer<'a, R: Read + 'a> {
+    bytes: &'a mut R,
+    strings: Vec<String>,
+
+    // Slots are reserved before a complex value's children are read so that indexes line up
+    // with the encoder's.  A `None` slot is a value that is still being deserialized.  Each
+    // value is stored with its decoded size, which is counted again whenever it's referenced.
+    objects: Vec<Option<(Amf3Value, usize)>>,
+    traits: Vec<Traits>,
+    depth: usize,
+    decoded_size: usize,
+
+    // The size of the copies held by the object reference table.  This is kept apart from the
+    // decoded size so a value's size does not include the table copies of its children.
+    table_size: usize,
+}
+
+/// A reserved entry in the object reference table
+struct ObjectSlot {
+    index: usize,
+    start_size: usize,
+}
+
+/// The result of reading a U29 value whose low bit denotes if it's a reference
+enum ReferenceOrValue {
+    Reference(u32),
+    Value(u32),
+}
+
+impl<'a, R: Read> Deserializer<'a, R> {
+    fn new(bytes: &'a mut R) -> Self {
+        Deserializer {
+            bytes,
+            strings: Vec::new(),
+            objects: Vec::new(),
+            traits: Vec::new(),
+            depth: 0,
+            decoded_size: 0,
+            table_size: 0,
+        }
+    }
+
+    fn read_next_value(&mut self) -> Result<Option<Amf3Value>, Amf3DeserializationError> {
+        let mut buffer: [u8; 1] = [0];
+        let bytes_read = self.bytes.read(&mut buffer)?;
+
+        if bytes_read == 0 {
+            return Ok(None);
+        }
+
+        self.parse_value(buffer[0]).map(Some)
+    }
+
+    fn read_required_value(&mut self) -> Result<Amf3Value, Amf3DeserializationError> {
+        match self.read_next_value()? {
+            Some(value) => Ok(value),
+            None => Err(Amf3DeserializationError::UnexpectedEof),
+        }
+    }
+
+    fn parse_value(&mut self, marker: u8) -> Result<Amf3Value, Amf3DeserializationError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Amf3DeserializationError::NestingTooDeep {max_depth: MAX_DEPTH});
+        }
+
+        self.add_decoded_size(mem::size_of::<Amf3Value>())?;
+        self.depth += 1;
+        let result = self.parse_marked_value(marker);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_marked_value(&mut self, marker: u8) -> Result<Amf3Value, Amf3DeserializationError> {
+        match marker {
+            markers::UNDEFINED_MARKER => Ok(Amf3Value::Undefined),
+            markers::NULL_MARKER => Ok(Amf3Value::Null),
+            markers::FALSE_MARKER => Ok(Amf3Value::Boolean(false)),
+            markers::TRUE_MARKER => Ok(Amf3Value::Boolean(true)),
+            markers::INTEGER_MARKER => self.parse_integer(),
+            markers::DOUBLE_MARKER => Ok(Amf3Value::Double(self.bytes.read_f64::<BigEndian>()?)),
+            markers::STRING_MARKER => Ok(Amf3Value::Utf8String(self.read_string()?)),
+            markers::XML_DOCUMENT_MARKER => self.parse_xml(true),
+            markers::DATE_MARKER => self.parse_date(),
+            markers::ARRAY_MARKER => self.parse_array(),
+            markers::OBJECT_MARKER => self.parse_object(),
+            markers::XML_MARKER => self.parse_xml(false),
+            markers::BYTE_ARRAY_MARKER => self.parse_byte_array(),
+            markers::VECTOR_INT_MARKER
+            | markers::VECTOR_UINT_MARKER
+            | markers::VECTOR_DOUBLE_MARKER
+            | markers::VECTOR_OBJECT_MARKER => self.parse_vector(marker),
+            markers::DICTIONARY_MARKER => self.parse_dictionary(),
+            _ => Err(Amf3DeserializationError::UnknownMarker {marker}),
+        }
+    }
+
+    fn parse_integer(&mut self) -> Result<Amf3Value, Amf3DeserializationError> {
+        let value = self.read_u29()?;
+
+        // Integers are 29 bit two's complement values, so we need to sign extend them
+        let value = if value & 0x1000_0000 != 0 {
+            (value as i32) - 0x2000_0000
+        } else {
+            value as i32
+        };
+
+        Ok(Amf3Value::Integer(value))
+    }
+
+    fn parse_xml(&mut self, is_document: bool) -> Result<Amf3Value, Amf3DeserializationError> {
+        let length = match self.read_reference_or_value()? {
+            ReferenceOrValue::Reference(index) => return self.get_object_reference(index),
+            ReferenceOrValue::Value(length) => length,
+        };
+
+        let slot = self.reserve_object_slot();
+        let text = self.read_utf8(length as usize)?;
+        let value = if is_document {
+            Amf3Value::XmlDocument(text)
+        } else {
+            Amf3Value::Xml(text)
+        };
+
+        self.fill_object_slot(slot, &value)?;
+        Ok(value)
+    }
+
+    fn parse_date(&mut self) -> Result<Amf3Value, Amf3DeserializationError> {
+        if let ReferenceOrValue::Reference(index) = self.read_reference_or_value()? {
+            return self.get_object_reference(index);
+        }
+
+        let slot = self.reserve_object_slot();
+        let unix_time = self.bytes.read_f64::<BigEndian>()?;
+        let value = Amf3Value::Date {unix_time};
+        self.fill_object_slot(slot, &value)?;
+        Ok(value)
+    }
+
+    fn parse_byte_array(&mut self) -> Result<Amf3Value, Amf3DeserializationError> {
+        let length = match self.read_reference_or_value()? {
+            ReferenceOrValue::Reference(index) => return self.get_object_reference(index),
+            ReferenceOrValue::Value(length) => length,
+        };
+
+        let slot = self.reserve_object_slot();
+        let buffer = self.read_bytes(length as usize)?;
+        let value = Amf3Value::ByteArray(buffer);
+        self.fill_object_slot(slot, &value)?;
+        Ok(value)
+    }
+
+    fn parse_array(&mut self) -> Result<Amf3Value, Amf3DeserializationError> {
+        let dense_count = match self.read_reference_or_value()? {
+            ReferenceOrValue::Reference(index) => return self.get_object_reference(index),
+            ReferenceOrValue::Value(count) => count,
+        };
+
+        let slot = self.reserve_object_slot();
+
+        // Associative values come first and are terminated by an empty key
+        let associative = self.read_key_value_pairs()?;
+
+        let mut dense = Vec::new();
+        for _ in 0..dense_count {
+            dense.push(self.read_required_value()?);
+        }
+
+        let value = Amf3Value::Array {associative, dense};
+        self.fill_object_slot(slot, &value)?;
+        Ok(value)
+    }
+
+    fn parse_object(&mut self) -> Result<Amf3Value, Amf3DeserializationError> {
+        let header = match self.read_reference_or_value()? {
+            ReferenceOrValue::Reference(index) => return self.get_object_reference(index),
+            ReferenceOrValue::Value(header) => header,
+        };
+
+        let slot = self.reserve_object_slot();
+        let traits = if header & 0x01 == 0 {
+            // Traits reference
+            let index = header >> 1;
+            match self.traits.get(index as usize) {
+                Some(traits) => traits.clone(),
+                None => return Err(Amf3DeserializationError::InvalidTraitsReference {index}),
+            }
+        } else {
+            let is_externalizable = header & 0x02 != 0;
+            let is_dynamic = header & 0x04 != 0;
+            let sealed_count = header >> 3;
+            let class_name = self.read_string()?;
+
+            let mut sealed_names = Vec::new();
+            if !is_externalizable {
+                for _ in 0..sealed_count {
+                    sealed_names.push(self.read_string()?);
+                }
+            }
+
+            let traits = Traits {class_name, sealed_names, is_dynamic, is_externalizable};
+            self.traits.push(traits.clone());
+            traits
+        };
+
+        if traits.is_externalizable {
+            return Err(Amf3DeserializationError::ExternalizableObjectNotSupported {
+                class_name: traits.class_name,
+            });
+        }
+
+        let mut sealed_properties = Vec::new();
+        for name in traits.sealed_names {
+            let value = self.read_required_value()?;
+            sealed_properties.push((name, value));
+        }
+
+        let dynamic_properties = if traits.is_dynamic {
+            Some(self.read_key_value_pairs()?)
+        } else {
+            None
+        };
+
+        let class_name = if traits.class_name.is_empty() {
+            None
+        } else {
+            Some(traits.class_name)
+        };
+
+        let value = Amf3Value::Object {class_name, sealed_properties, dynamic_properties};
+        self.fill_object_slot(slot, &value)?;
+        Ok(value)
+    }
+
+    fn parse_vector(&mut self, marker: u8) -> Result<Amf3Value, Amf3DeserializationError> {
+        let count = match self.read_reference_or_value()? {
+            ReferenceOrValue::Reference(index) => return self.get_object_reference(index),
+            ReferenceOrValue::Value(count) => count,
+        };
+
+        let slot = self.reserve_object_slot();
+        let fixed_length = self.bytes.read_u8()? != 0;
+
+        let value = match marker {
+            markers::VECTOR_INT_MARKER => {
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.bytes.read_i32::<BigEndian>()?);
+                }
+
+                Amf3Value::VectorInt {fixed_length, values}
+            },
+
+            markers::VECTOR_UINT_MARKER => {
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.bytes.read_u32::<BigEndian>()?);
+                }
+
+                Amf3Value::VectorUInt {fixed_length, values}
+            },
+
+            markers::VECTOR_DOUBLE_MARKER => {
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.bytes.read_f64::<BigEndian>()?);
+                }
+
+                Amf3Value::VectorDouble {fixed_length, values}
+            },
+
+            _ => {
+                let type_name = self.read_string()?;
+                let mut values = Vec::new();
+                for _ in 0..count {
+                    values.push(self.read_required_value()?);
+                }
+
+                Amf3Value::VectorObject {fixed_length, type_name, values}
+            },
+        };
+
+        self.fill_object_slot(slot, &value)?;
+        Ok(value)
+    }
+
+    fn parse_dictionary(&mut self) -> Result<Amf3Value, Amf3DeserializationError> {
+        let count = match self.read_reference_or_value()? {
+            ReferenceOrValue::Reference(index) => return self.get_object_reference(index),
+            ReferenceOrValue::Value(count) => count,
+        };
+
+        let slot = self.reserve_object_slot();
+        let weak_keys = self.bytes.read_u8()? != 0;
+
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let key = self.read_required_value()?;
+            let value = self.read_required_value()?;
+            entries.push((key, value));
+        }
+
+        let value = Amf3Value::Dictionary {weak_keys, entries};
+        self.fill_object_slot(slot, &value)?;
+        Ok(value)
+    }
+
+    fn read_key_value_pairs(&mut self) -> Result<Vec<(String, Amf3Value)>, Amf3DeserializationError> {
+        let mut pairs = Vec::new();
+        loop {
+            let key = self.read_string()?;
+            if key.is_empty() {
+                break;
+            }
+
+            let value = self.read_required_value()?;
+            pairs.push((key, value));
+        }
+
+        Ok(pairs)
+    }
+
+    fn read_string(&mut self) -> Result<String, Amf3DeserializationError> {
+        match self.read_reference_or_value()? {
+            ReferenceOrValue::Reference(index) => {
+                let length = match self.strings.get(index as usize) {
+                    Some(value) => value.len(),
+                    None => return Err(Amf3DeserializationError::InvalidStringReference {index}),
+                };
+
+                self.add_decoded_size(length)?;
+                Ok(self.strings[index as usize].clone())
+            },
+
+            ReferenceOrValue::Value(length) => {
+                let value = self.read_utf8(length as usize)?;
+
+                // Empty strings are never sent as references, so they are not added to the table
+                if !value.is_empty() {
+                    self.strings.push(value.clone());
+                }
+
+                Ok(value)
+            },
+        }
+    }
+
+    fn get_object_reference(&mut self, index: u32) -> Result<Amf3Value, Amf3DeserializationError> {
+        let size = match self.objects.get(index as usize) {
+            Some(Some((_, size))) => *size,
+            _ => return Err(Amf3DeserializationError::InvalidObjectReference {index}),
+        };
+
+        // Check the size before cloning, so a reference can't expand past the limit
+        self.add_decoded_size(size)?;
+        match self.objects[index as usize] {
+            Some((ref value, _)) => Ok(value.clone()),
+            None => Err(Amf3DeserializationError::InvalidObjectReference {index}),
+        }
+    }
+
+    fn reserve_object_slot(&mut self) -> ObjectSlot {
+        self.objects.push(None);
+        ObjectSlot {
+            index: self.objects.len() - 1,
+
+            // The value itself has already been counted
+            start_size: self.decoded_size - mem::size_of::<Amf3Value>(),
+        }
+    }
+
+    fn fill_object_slot(&mut self, slot: ObjectSlot, value: &Amf3Value) -> Result<(), Amf3DeserializationError> {
+        // The table holds its own copy of the value, so it's charged against the limit as well
+        let size = self.decoded_size - slot.start_size;
+        self.table_size += size;
+        self.check_size_limit()?;
+
+        self.objects[slot.index] = Some((value.clone(), size));
+        Ok(())
+    }
+
+    fn add_decoded_size(&mut self, size: usize) -> Result<(), Amf3DeserializationError> {
+        self.decoded_size += size;
+        self.check_size_limit()
+    }
+
+    fn check_size_limit(&self) -> Result<(), Amf3DeserializationError> {
+        if self.decoded_size + self.table_size > MAX_DECODED_SIZE {
+            return Err(Amf3DeserializationError::DecodedSizeLimitExceeded {max_size: MAX_DECODED_SIZE});
+        }
+
+        Ok(())
+    }
+
+    fn read_reference_or_value(&mut self) -> Result<ReferenceOrValue, Amf3DeserializationError> {
+        let value = self.read_u29()?;
+        if value & 0x01 == 0 {
+            Ok(ReferenceOrValue::Reference(value >> 1))
+        } else {
+            Ok(ReferenceOrValue::Value(value >> 1))
+        }
+    }
+
+    fn read_u29(&mut self) -> Result<u32, Amf3DeserializationError> {
+        // The first 3 bytes use the high bit as a flag that another byte follows, while
+        // the 4th byte (if present) uses all 8 bits.
+        let mut result: u32 = 0;
+        for _ in 0..3 {
+            let byte = self.bytes.read_u8()?;
+            result = (result << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+
+        let byte = self.bytes.read_u8()?;
+        Ok((result << 8) | byte as u32)
+    }
+
+    fn read_utf8(&mut self, length: usize) -> Result<String, Amf3DeserializationError> {
+        let buffer = self.read_bytes(length)?;
+        let value = String::from_utf8(buffer)?;
+        Ok(value)
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>, Amf3DeserializationError> {
+        self.add_decoded_size(length)?;
+
+        // Only allocate for bytes that actually arrive, since the length comes from the peer
+        let mut buffer = Vec::new();
+        (&mut *self.bytes).take(length as u64).read_to_end(&mut buffer)?;
+        if buffer.len() < length {
+            return Err(Amf3DeserializationError::UnexpectedEof);
+        }
+
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::{deserialize, deserialize_value};
+    use super::super::Amf3Value;
+    use errors::Amf3DeserializationError;
+    use markers;
+    use byteorder::{BigEndian, WriteBytesExt};
+
+    #[test]
+    fn can_deserialize_simple_markers() {
+        let vector = vec![
+            markers::UNDEFINED_MARKER,
+            markers::NULL_MARKER,
+            markers::FALSE_MARKER,
+            markers::TRUE_MARKER,
+        ];
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![
+            Amf3Value::Undefined,
+            Amf3Value::Null,
+            Amf3Value::Boolean(false),
+            Amf3Value::Boolean(true),
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_integers_of_every_u29_length() {
+        let vector = vec![
+            markers::INTEGER_MARKER, 0x7f,
+            markers::INTEGER_MARKER, 0x81, 0x00,
+            markers::INTEGER_MARKER, 0x81, 0x80, 0x00,
+            markers::INTEGER_MARKER, 0xbf, 0xff, 0xff, 0xff,
+            markers::INTEGER_MARKER, 0xff, 0xff, 0xff, 0xff,
+        ];
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![
+            Amf3Value::Integer(127),
+            Amf3Value::Integer(128),
+            Amf3Value::Integer(16384),
+            Amf3Value::Integer(0x0fff_ffff),
+            Amf3Value::Integer(-1),
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_double() {
+        let mut vector = vec![markers::DOUBLE_MARKER];
+        vector.write_f64::<BigEndian>(332.5).unwrap();
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        assert_eq!(result, vec![Amf3Value::Double(332.5)]);
+    }
+
+    #[test]
+    fn can_deserialize_string_and_string_reference() {
+        let mut vector = vec![markers::STRING_MARKER, (4 << 1) | 1];
+        vector.extend("test".as_bytes());
+        vector.push(markers::STRING_MARKER);
+        vector.push(0); // reference to string 0
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![
+            Amf3Value::Utf8String("test".to_string()),
+            Amf3Value::Utf8String("test".to_string()),
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn error_when_string_reference_is_not_in_table() {
+        let vector = vec![markers::STRING_MARKER, 2 << 1];
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::InvalidStringReference {index: 2}) => (),
+            x => panic!("Expected invalid string reference error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_deserialize_date_and_object_reference() {
+        let mut vector = vec![markers::DATE_MARKER, 0x01];
+        vector.write_f64::<BigEndian>(1_500_000_000_000.0).unwrap();
+        vector.push(markers::DATE_MARKER);
+        vector.push(0); // reference to object 0
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![
+            Amf3Value::Date {unix_time: 1_500_000_000_000.0},
+            Amf3Value::Date {unix_time: 1_500_000_000_000.0},
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_array() {
+        let mut vector = vec![markers::ARRAY_MARKER, (2 << 1) | 1];
+        vector.push((1 << 1) | 1);
+        vector.extend("a".as_bytes());
+        vector.push(markers::TRUE_MARKER);
+        vector.push(0x01); // empty string ends the associative portion
+        vector.extend(&[markers::INTEGER_MARKER, 5]);
+        vector.push(markers::NULL_MARKER);
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf3Value::Array {
+            associative: vec![("a".to_string(), Amf3Value::Boolean(true))],
+            dense: vec![Amf3Value::Integer(5), Amf3Value::Null],
+        }];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_objects_with_traits_reference() {
+        let mut vector = vec![markers::OBJECT_MARKER];
+        vector.push((1 << 4) | 0b0011); // 1 sealed member, not dynamic, inline traits
+        vector.push((3 << 1) | 1);
+        vector.extend("Foo".as_bytes());
+        vector.push((1 << 1) | 1);
+        vector.extend("x".as_bytes());
+        vector.extend(&[markers::INTEGER_MARKER, 1]);
+
+        vector.push(markers::OBJECT_MARKER);
+        vector.push(0b0001); // traits reference 0
+        vector.extend(&[markers::INTEGER_MARKER, 2]);
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![
+            Amf3Value::Object {
+                class_name: Some("Foo".to_string()),
+                sealed_properties: vec![("x".to_string(), Amf3Value::Integer(1))],
+                dynamic_properties: None,
+            },
+            Amf3Value::Object {
+                class_name: Some("Foo".to_string()),
+                sealed_properties: vec![("x".to_string(), Amf3Value::Integer(2))],
+                dynamic_properties: None,
+            },
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_anonymous_dynamic_object() {
+        let mut vector = vec![markers::OBJECT_MARKER];
+        vector.push(0b1011); // 0 sealed members, dynamic, inline traits
+        vector.push(0x01); // anonymous class name
+        vector.push((3 << 1) | 1);
+        vector.extend("app".as_bytes());
+        vector.extend(&[markers::STRING_MARKER, 0]); // reference to "app"
+        vector.push(0x01);
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf3Value::Object {
+            class_name: None,
+            sealed_properties: Vec::new(),
+            dynamic_properties: Some(vec![("app".to_string(), Amf3Value::Utf8String("app".to_string()))]),
+        }];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn error_when_object_references_itself() {
+        let vector = vec![
+            markers::OBJECT_MARKER, 0b1011, 0x01,
+            (1 << 1) | 1, b'a',
+            markers::OBJECT_MARKER, 0, // reference to the object being deserialized
+            0x01,
+        ];
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::InvalidObjectReference {index: 0}) => (),
+            x => panic!("Expected invalid object reference error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_object_is_externalizable() {
+        let mut vector = vec![markers::OBJECT_MARKER, 0b0111, (3 << 1) | 1];
+        vector.extend("Foo".as_bytes());
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::ExternalizableObjectNotSupported {ref class_name}) if class_name == "Foo" => (),
+            x => panic!("Expected externalizable error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_deserialize_xml_and_byte_array() {
+        let mut vector = vec![markers::XML_MARKER, (3 << 1) | 1];
+        vector.extend("<a>".as_bytes());
+        vector.extend(&[markers::XML_DOCUMENT_MARKER, (3 << 1) | 1]);
+        vector.extend("<b>".as_bytes());
+        vector.extend(&[markers::BYTE_ARRAY_MARKER, (2 << 1) | 1, 9, 8]);
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![
+            Amf3Value::Xml("<a>".to_string()),
+            Amf3Value::XmlDocument("<b>".to_string()),
+            Amf3Value::ByteArray(vec![9, 8]),
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_vectors() {
+        let mut vector = vec![markers::VECTOR_INT_MARKER, (2 << 1) | 1, 1];
+        vector.write_i32::<BigEndian>(-5).unwrap();
+        vector.write_i32::<BigEndian>(6).unwrap();
+        vector.extend(&[markers::VECTOR_UINT_MARKER, (1 << 1) | 1, 0]);
+        vector.write_u32::<BigEndian>(7).unwrap();
+        vector.extend(&[markers::VECTOR_DOUBLE_MARKER, (1 << 1) | 1, 0]);
+        vector.write_f64::<BigEndian>(8.5).unwrap();
+        vector.extend(&[markers::VECTOR_OBJECT_MARKER, (1 << 1) | 1, 0, (1 << 1) | 1, b'*']);
+        vector.push(markers::NULL_MARKER);
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![
+            Amf3Value::VectorInt {fixed_length: true, values: vec![-5, 6]},
+            Amf3Value::VectorUInt {fixed_length: false, values: vec![7]},
+            Amf3Value::VectorDouble {fixed_length: false, values: vec![8.5]},
+            Amf3Value::VectorObject {fixed_length: false, type_name: "*".to_string(), values: vec![Amf3Value::Null]},
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_deserialize_dictionary() {
+        let vector = vec![
+            markers::DICTIONARY_MARKER, (1 << 1) | 1, 1,
+            markers::INTEGER_MARKER, 1,
+            markers::TRUE_MARKER,
+        ];
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize(&mut input).unwrap();
+
+        let expected = vec![Amf3Value::Dictionary {
+            weak_keys: true,
+            entries: vec![(Amf3Value::Integer(1), Amf3Value::Boolean(true))],
+        }];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn deserialize_value_only_reads_a_single_value() {
+        let vector = vec![markers::TRUE_MARKER, markers::FALSE_MARKER];
+
+        let mut input = Cursor::new(vector);
+        let result = deserialize_value(&mut input).unwrap();
+
+        assert_eq!(result, Amf3Value::Boolean(true));
+        assert_eq!(input.position(), 1, "Unexpected number of bytes consumed");
+    }
+
+    #[test]
+    fn error_on_unknown_marker() {
+        let vector = vec![0x20];
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::UnknownMarker {marker: 0x20}) => (),
+            x => panic!("Expected unknown marker error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_references_expand_past_size_limit() {
+        // Each array holds two references to the previous one, doubling in size every time
+        let mut vector = vec![markers::ARRAY_MARKER, 0x01, 0x01];
+        for index in 0..40 {
+            vector.extend_from_slice(&[markers::ARRAY_MARKER, 0x05, 0x01]);
+            vector.extend_from_slice(&[markers::ARRAY_MARKER, index << 1, markers::ARRAY_MARKER, index << 1]);
+        }
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::DecodedSizeLimitExceeded {..}) => (),
+            x => panic!("Expected size limit error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_reference_table_copies_of_nested_values_exceed_size_limit() {
+        // Every array the byte array is nested in keeps its own copy of it in the reference
+        // table, so 2MB of data is retained 61 times over
+        let length: u32 = 2 * 1024 * 1024;
+        let header = (length << 1) | 1;
+
+        let mut vector = Vec::new();
+        for _ in 0..60 {
+            vector.extend_from_slice(&[markers::ARRAY_MARKER, 0x03, 0x01]);
+        }
+
+        vector.push(markers::BYTE_ARRAY_MARKER);
+        vector.extend_from_slice(&[
+            ((header >> 22) & 0x7f) as u8 | 0x80,
+            ((header >> 15) & 0x7f) as u8 | 0x80,
+            ((header >> 8) & 0x7f) as u8 | 0x80,
+            (header & 0xff) as u8,
+        ]);
+        vector.resize(vector.len() + length as usize, 0);
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::DecodedSizeLimitExceeded {..}) => (),
+            x => panic!("Expected size limit error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn nested_values_within_size_limit_are_deserialized() {
+        let mut vector = Vec::new();
+        for _ in 0..60 {
+            vector.extend_from_slice(&[markers::ARRAY_MARKER, 0x03, 0x01]);
+        }
+
+        vector.extend_from_slice(&[markers::BYTE_ARRAY_MARKER, 0x07, 1, 2, 3]);
+
+        let mut input = Cursor::new(vector);
+        let mut value = deserialize_value(&mut input).unwrap();
+        for _ in 0..60 {
+            value = match value {
+                Amf3Value::Array {mut dense, ..} => dense.remove(0),
+                x => panic!("Expected array, instead received: {:?}", x),
+            };
+        }
+
+        assert_eq!(value, Amf3Value::ByteArray(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn error_when_values_are_nested_too_deep() {
+        let mut vector = Vec::new();
+        for _ in 0..100 {
+            vector.extend_from_slice(&[markers::ARRAY_MARKER, 0x03, 0x01]);
+        }
+
+        vector.push(markers::NULL_MARKER);
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::NestingTooDeep {..}) => (),
+            x => panic!("Expected nesting error, instead received: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_without_allocating_when_byte_array_length_exceeds_data() {
+        let vector = vec![markers::BYTE_ARRAY_MARKER, 0xff, 0xff, 0xff, 0xff, 1, 2, 3];
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::DecodedSizeLimitExceeded {..}) => (),
+            x => panic!("Expected size limit error, instead received: {:?}", x),
+        }
+
+        let vector = vec![markers::STRING_MARKER, 0x81, 0x80, 0x01, 0x61, 0x62];
+
+        let mut input = Cursor::new(vector);
+        match deserialize(&mut input) {
+            Err(Amf3DeserializationError::UnexpectedEof) => (),
+            x => panic!("Expected unexpected eof error, instead received: {:?}", x),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::{io, string};
+
+/// Errors that can occur during the deserialization process
+#[derive(Debug, Fail)]
+pub enum Amf3DeserializationError {
+    /// Every Amf3 value starts with a marker byte describing the type of value that was
+    /// encoded.  This error is encountered when we see a maker value that we do not recognize.
+    #[fail(display = "Encountered unknown marker: {}", marker)]
+    UnknownMarker {
+        marker: u8
+    },
+
+    /// A string reference pointed to an index that was not in the string reference table
+    #[fail(display = "Invalid string reference index {}", index)]
+    InvalidStringReference {
+        index: u32
+    },
+
+    /// A complex value reference pointed to an index that was not in the object reference
+    /// table, or pointed to a value that is still being deserialized (a cyclic reference).
+    #[fail(display = "Invalid object reference index {}", index)]
+    InvalidObjectReference {
+        index: u32
+    },
+
+    /// An object traits reference pointed to an index that was not in the traits reference table
+    #[fail(display = "Invalid traits reference index {}", index)]
+    InvalidTraitsReference {
+        index: u32
+    },
+
+    /// Externalizable objects use a custom, class specific, encoding and therefore can not
+    /// be deserialized without knowledge of that class.
+    #[fail(display = "Class '{}' is externalizable and can not be deserialized", class_name)]
+    ExternalizableObjectNotSupported {
+        class_name: String
+    },
+
+    /// Values were nested (arrays in arrays, objects in objects, etc...) deeper than the
+    /// deserializer allows
+    #[fail(display = "Values were nested more than {} levels deep", max_depth)]
+    NestingTooDeep {
+        max_depth: usize
+    },
+
+    /// The values being deserialized would expand to more than the deserializer allows, such
+    /// as when complex values are referenced many times over
+    #[fail(display = "Deserialized values exceeded the size limit of {}", max_size)]
+    DecodedSizeLimitExceeded {
+        max_size: usize
+    },
+
+    /// This occurs when we are expecting more data but hit the end of the buffer
+    #[fail(display = "Hit end of the byte buffer but was expecting more data")]
+    UnexpectedEof,
+
+    /// An I/O Error occurred while reading the data buffer
+    #[fail(display = "Failed to read byte buffer: {}", _0)]
+    BufferReadError(#[cause] io::Error),
+
+    /// Strings in AMF3 are UTF-8 encoded, so if the bytes read are not valid
+    /// UTF-8 this error will be raised.
+    #[fail(display = "Failed to read a utf8 string from the byte buffer: {}", _0)]
+    StringParseError(#[cause] string::FromUtf8Error)
+}
+
+impl From<io::Error> for Amf3DeserializationError {
+    fn from(error: io::Error) -> Self {
+        Amf3DeserializationError::BufferReadError(error)
+    }
+}
+
+impl From<string::FromUtf8Error> for Amf3DeserializationError {
+    fn from(error: string::FromUtf8Error) -> Self {
+        Amf3DeserializationError::StringParseError(error)
+    }
+}
+
+/// Errors raised during to the serialization process
+#[derive(Debug, Fail)]
+pub enum Amf3SerializationError {
+    /// Lengths and counts in AMF3 are encoded as 28 bit unsigned integers, so strings, arrays,
+    /// and other values cannot have more than 268,435,455 items.
+    #[fail(display = "Length of {} is greater than 268,435,455", length)]
+    LengthTooLarge {
+        length: usize
+    },
+
+    /// An I/O error occurred while writing to the output buffer.
+    #[fail(display = "Failed to write to byte buffer")]
+    BufferWriteError(#[cause] io::Error)
+}
+
+impl From<io::Error> for Amf3SerializationError {
+    fn from(error: io::Error) -> Self {
+        Amf3SerializationError::BufferWriteError(error)
+    }
+}
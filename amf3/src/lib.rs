@@ -0,0 +1,170 @@
+//! This crate provides functionality for serializing and deserializing data
+//! based on the Adobe AMF3 encoding specification located at
+//! <https://www.adobe.com/content/dam/acom/en/devnet/pdf/amf-file-format-spec.pdf>
+//!
+//! AMF3 uses reference tables for strings, complex values and object traits so that
+//! repeated values only need to be encoded once.  References are resolved while deserializing,
+//! so every `Amf3Value` returned is a complete value.  When serializing, strings and object
+//! traits that have already been written are encoded as references.
+//!
+//! # Examples
+//! ```
+//! use std::io::Cursor;
+//! use rml_amf3::{Amf3Value, serialize, deserialize};
+//!
+//! let object = Amf3Value::Object {
+//!     class_name: None,
+//!     sealed_properties: Vec::new(),
+//!     dynamic_properties: Some(vec![
+//!         ("app".to_string(), Amf3Value::Integer(99)),
+//!         ("second".to_string(), Amf3Value::Utf8String("test".to_string())),
+//!     ]),
+//! };
+//!
+//! let input = vec![Amf3Value::Double(32.5), object, Amf3Value::Boolean(true)];
+//!
+//! // Serialize the values into a vector of bytes
+//! let serialized_data = serialize(&input).unwrap();
+//!
+//! // Deserialize the vector of bytes back into Amf3Value types
+//! let mut serialized_cursor = Cursor::new(serialized_data);
+//! let results = deserialize(&mut serialized_cursor).unwrap();
+//!
+//! assert_eq!(input, results);
+//! ```
+
+#[macro_use] extern crate failure;
+extern crate byteorder;
+
+mod serialization;
+mod deserialization;
+mod errors;
+
+pub use serialization::{serialize, serialize_value};
+pub use deserialization::{deserialize, deserialize_value};
+pub use errors::{Amf3DeserializationError, Amf3SerializationError};
+
+/// An Enum representing the different supported types of Amf3 values
+#[derive(PartialEq, Debug, Clone)]
+pub enum Amf3Value {
+    Undefined,
+    Null,
+    Boolean(bool),
+
+    /// A 29 bit signed integer.  Values outside of that range are serialized as doubles.
+    Integer(i32),
+    Double(f64),
+    Utf8String(String),
+
+    /// A legacy `flash.xml.XMLDocument` instance
+    XmlDocument(String),
+
+    /// A date encoded as the number of milliseconds since the unix epoch in UTC
+    Date {
+        unix_time: f64,
+    },
+
+    /// An array that can contain both string keyed (associative) values and ordinal (dense) values
+    Array {
+        associative: Vec<(String, Amf3Value)>,
+        dense: Vec<Amf3Value>,
+    },
+
+    /// An object instance.  Anonymous objects have no class name.  Sealed properties are the
+    /// ones declared by the object's traits and are kept in their declared order.  Dynamic
+    /// properties are only present if the object's traits are marked as dynamic.
+    Object {
+        class_name: Option<String>,
+        sealed_properties: Vec<(String, Amf3Value)>,
+        dynamic_properties: Option<Vec<(String, Amf3Value)>>,
+    },
+
+    /// An E4X `XML` instance
+    Xml(String),
+
+    ByteArray(Vec<u8>),
+
+    VectorInt {
+        fixed_length: bool,
+        values: Vec<i32>,
+    },
+
+    VectorUInt {
+        fixed_length: bool,
+        values: Vec<u32>,
+    },
+
+    VectorDouble {
+        fixed_length: bool,
+        values: Vec<f64>,
+    },
+
+    VectorObject {
+        fixed_length: bool,
+        type_name: String,
+        values: Vec<Amf3Value>,
+    },
+
+    Dictionary {
+        weak_keys: bool,
+        entries: Vec<(Amf3Value, Amf3Value)>,
+    },
+}
+
+impl Amf3Value {
+    pub fn get_number(self) -> Option<f64> {
+        match self {
+            Amf3Value::Integer(value) => Some(value as f64),
+            Amf3Value::Double(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_boolean(self) -> Option<bool> {
+        match self {
+            Amf3Value::Boolean(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get_string(self) -> Option<String> {
+        match self {
+            Amf3Value::Utf8String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+mod markers {
+    pub const UNDEFINED_MARKER: u8 = 0x00;
+    pub const NULL_MARKER: u8 = 0x01;
+    pub const FALSE_MARKER: u8 = 0x02;
+    pub const TRUE_MARKER: u8 = 0x03;
+    pub const INTEGER_MARKER: u8 = 0x04;
+    pub const DOUBLE_MARKER: u8 = 0x05;
+    pub const STRING_MARKER: u8 = 0x06;
+    pub const XML_DOCUMENT_MARKER: u8 = 0x07;
+    pub const DATE_MARKER: u8 = 0x08;
+    pub const ARRAY_MARKER: u8 = 0x09;
+    pub const OBJECT_MARKER: u8 = 0x0a;
+    pub const XML_MARKER: u8 = 0x0b;
+    pub const BYTE_ARRAY_MARKER: u8 = 0x0c;
+    pub const VECTOR_INT_MARKER: u8 = 0x0d;
+    pub const VECTOR_UINT_MARKER: u8 = 0x0e;
+    pub const VECTOR_DOUBLE_MARKER: u8 = 0x0f;
+    pub const VECTOR_OBJECT_MARKER: u8 = 0x10;
+    pub const DICTIONARY_MARKER: u8 = 0x11;
+
+    pub const MAX_U29: u32 = 0x1fff_ffff;
+    pub const MIN_INTEGER: i32 = -0x1000_0000;
+    pub const MAX_INTEGER: i32 = 0x0fff_ffff;
+}
+
+/// The traits (class definition) of an object, used to track the traits reference table
+#[derive(PartialEq, Debug, Clone)]
+struct Traits {
+    class_name: String,
+    sealed_names: Vec<String>,
+    is_dynamic: bool,
+    is_externalizable: bool,
+}
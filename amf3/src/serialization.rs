@@ -0,0 +1,403 @@
+//! Module contains functionality for serializing values into an
+//! bytes based on the AMF3 specification
+//! (https://www.adobe.com/content/dam/acom/en/devnet/pdf/amf-file-format-spec.pdf)
+
+use std::collections::HashMap;
+use Amf3Value;
+use Traits;
+use errors::Amf3SerializationError;
+use markers;
+use byteorder::{BigEndian, WriteBytesExt};
+
+/// Serializes values into an amf3 encoded vector of bytes.  All values share the same
+/// reference tables.
+pub fn serialize(values: &[Amf3Value]) -> Result<Vec<u8>, Amf3SerializationError> {
+    let mut serializer = Serializer::new();
+    let mut bytes = vec![];
+    for value in values {
+        serializer.write_value(value, &mut bytes)?;
+    }
+
+    Ok(bytes)
+}
+
+/// Serializes a single value with fresh reference tables, appending the encoded value to the
+/// provided bytes.  This is used when an AMF3 value is embedded in another format (such
+/// as AMF0's avmplus marker).
+pub fn serialize_value(value: &Amf3Value, bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+    let mut serializer = Serializer::new();
+    serializer.write_value(value, bytes)
+}
+
+struct Serializer {
+    strings: HashMap<String, u32>,
+    traits: Vec<Traits>,
+}
+
+impl Serializer {
+    fn new() -> Self {
+        Serializer {
+            strings: HashMap::new(),
+            traits: Vec::new(),
+        }
+    }
+
+    fn write_value(&mut self, value: &Amf3Value, bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+        match *value {
+            Amf3Value::Undefined => bytes.push(markers::UNDEFINED_MARKER),
+            Amf3Value::Null => bytes.push(markers::NULL_MARKER),
+            Amf3Value::Boolean(false) => bytes.push(markers::FALSE_MARKER),
+            Amf3Value::Boolean(true) => bytes.push(markers::TRUE_MARKER),
+            Amf3Value::Integer(val) => write_integer(val, bytes)?,
+            Amf3Value::Double(val) => write_double(val, bytes)?,
+
+            Amf3Value::Utf8String(ref val) => {
+                bytes.push(markers::STRING_MARKER);
+                self.write_string(val, bytes)?;
+            },
+
+            Amf3Value::XmlDocument(ref val) => write_xml(markers::XML_DOCUMENT_MARKER, val, bytes)?,
+            Amf3Value::Xml(ref val) => write_xml(markers::XML_MARKER, val, bytes)?,
+
+            Amf3Value::Date {unix_time} => {
+                bytes.push(markers::DATE_MARKER);
+                write_u29(1, bytes)?;
+                bytes.write_f64::<BigEndian>(unix_time)?;
+            },
+
+            Amf3Value::Array {ref associative, ref dense} => {
+                bytes.push(markers::ARRAY_MARKER);
+                write_length(dense.len(), bytes)?;
+                self.write_key_value_pairs(associative, bytes)?;
+                for item in dense {
+                    self.write_value(item, bytes)?;
+                }
+            },
+
+            Amf3Value::Object {ref class_name, ref sealed_properties, ref dynamic_properties} => {
+                self.write_object(class_name, sealed_properties, dynamic_properties, bytes)?;
+            },
+
+            Amf3Value::ByteArray(ref val) => {
+                bytes.push(markers::BYTE_ARRAY_MARKER);
+                write_length(val.len(), bytes)?;
+                bytes.extend(val);
+            },
+
+            Amf3Value::VectorInt {fixed_length, ref values} => {
+                bytes.push(markers::VECTOR_INT_MARKER);
+                write_length(values.len(), bytes)?;
+                bytes.push(fixed_length as u8);
+                for item in values {
+                    bytes.write_i32::<BigEndian>(*item)?;
+                }
+            },
+
+            Amf3Value::VectorUInt {fixed_length, ref values} => {
+                bytes.push(markers::VECTOR_UINT_MARKER);
+                write_length(values.len(), bytes)?;
+                bytes.push(fixed_length as u8);
+                for item in values {
+                    bytes.write_u32::<BigEndian>(*item)?;
+                }
+            },
+
+            Amf3Value::VectorDouble {fixed_length, ref values} => {
+                bytes.push(markers::VECTOR_DOUBLE_MARKER);
+                write_length(values.len(), bytes)?;
+                bytes.push(fixed_length as u8);
+                for item in values {
+                    bytes.write_f64::<BigEndian>(*item)?;
+                }
+            },
+
+            Amf3Value::VectorObject {fixed_length, ref type_name, ref values} => {
+                bytes.push(markers::VECTOR_OBJECT_MARKER);
+                write_length(values.len(), bytes)?;
+                bytes.push(fixed_length as u8);
+                self.write_string(type_name, bytes)?;
+                for item in values {
+                    self.write_value(item, bytes)?;
+                }
+            },
+
+            Amf3Value::Dictionary {weak_keys, ref entries} => {
+                bytes.push(markers::DICTIONARY_MARKER);
+                write_length(entries.len(), bytes)?;
+                bytes.push(weak_keys as u8);
+                for (key, item) in entries {
+                    self.write_value(key, bytes)?;
+                    self.write_value(item, bytes)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn write_object(&mut self,
+                    class_name: &Option<String>,
+                    sealed_properties: &[(String, Amf3Value)],
+                    dynamic_properties: &Option<Vec<(String, Amf3Value)>>,
+                    bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+        bytes.push(markers::OBJECT_MARKER);
+
+        let traits = Traits {
+            class_name: class_name.clone().unwrap_or_default(),
+            sealed_names: sealed_properties.iter().map(|(name, _)| name.clone()).collect(),
+            is_dynamic: dynamic_properties.is_some(),
+            is_externalizable: false,
+        };
+
+        match self.traits.iter().position(|x| *x == traits) {
+            Some(index) => write_u29(((index as u32) << 2) | 0b01, bytes)?,
+            None => {
+                let sealed_count = traits.sealed_names.len();
+                if sealed_count > (markers::MAX_U29 >> 4) as usize {
+                    return Err(Amf3SerializationError::LengthTooLarge {length: sealed_count});
+                }
+
+                let dynamic_flag = if traits.is_dynamic { 0b1000 } else { 0 };
+                write_u29(((sealed_count as u32) << 4) | dynamic_flag | 0b0011, bytes)?;
+                self.write_string(&traits.class_name, bytes)?;
+                for name in &traits.sealed_names {
+                    self.write_string(name, bytes)?;
+                }
+
+                self.traits.push(traits);
+            }
+        }
+
+        for (_, value) in sealed_properties {
+            self.write_value(value, bytes)?;
+        }
+
+        if let Some(ref properties) = *dynamic_properties {
+            self.write_key_value_pairs(properties, bytes)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_key_value_pairs(&mut self, pairs: &[(String, Amf3Value)], bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+        for (key, value) in pairs {
+            self.write_string(key, bytes)?;
+            self.write_value(value, bytes)?;
+        }
+
+        // An empty string terminates the list
+        self.write_string("", bytes)
+    }
+
+    fn write_string(&mut self, value: &str, bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+        if let Some(index) = self.strings.get(value) {
+            return write_u29(index << 1, bytes);
+        }
+
+        write_length(value.len(), bytes)?;
+        bytes.extend(value.as_bytes());
+
+        // Empty strings are never sent as references
+        if !value.is_empty() {
+            let index = self.strings.len() as u32;
+            self.strings.insert(value.to_string(), index);
+        }
+
+        Ok(())
+    }
+}
+
+fn write_integer(value: i32, bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+    if !(markers::MIN_INTEGER..=markers::MAX_INTEGER).contains(&value) {
+        // Too large to be represented as a 29 bit integer
+        return write_double(value as f64, bytes);
+    }
+
+    bytes.push(markers::INTEGER_MARKER);
+    write_u29((value as u32) & markers::MAX_U29, bytes)
+}
+
+fn write_double(value: f64, bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+    bytes.push(markers::DOUBLE_MARKER);
+    bytes.write_f64::<BigEndian>(value)?;
+    Ok(())
+}
+
+fn write_xml(marker: u8, value: &str, bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+    // Unlike strings, xml values are part of the object reference table and not the string table
+    bytes.push(marker);
+    write_length(value.len(), bytes)?;
+    bytes.extend(value.as_bytes());
+    Ok(())
+}
+
+/// Writes a length (or count) with the low bit set to mark it as an inline value instead
+/// of a reference.
+fn write_length(length: usize, bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+    if length > (markers::MAX_U29 >> 1) as usize {
+        return Err(Amf3SerializationError::LengthTooLarge {length});
+    }
+
+    write_u29(((length as u32) << 1) | 1, bytes)
+}
+
+fn write_u29(value: u32, bytes: &mut Vec<u8>) -> Result<(), Amf3SerializationError> {
+    let value = value & markers::MAX_U29;
+    if value < 0x80 {
+        bytes.push(value as u8);
+    } else if value < 0x4000 {
+        bytes.push(((value >> 7) | 0x80) as u8);
+        bytes.push((value & 0x7f) as u8);
+    } else if value < 0x20_0000 {
+        bytes.push(((value >> 14) | 0x80) as u8);
+        bytes.push(((value >> 7) | 0x80) as u8);
+        bytes.push((value & 0x7f) as u8);
+    } else {
+        bytes.push(((value >> 22) | 0x80) as u8);
+        bytes.push(((value >> 15) | 0x80) as u8);
+        bytes.push(((value >> 8) | 0x80) as u8);
+        bytes.push(value as u8);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::serialize;
+    use super::super::Amf3Value;
+    use deserialization::deserialize;
+    use markers;
+    use byteorder::{BigEndian, WriteBytesExt};
+
+    #[test]
+    fn can_serialize_simple_markers() {
+        let input = vec![
+            Amf3Value::Undefined,
+            Amf3Value::Null,
+            Amf3Value::Boolean(false),
+            Amf3Value::Boolean(true),
+        ];
+
+        let result = serialize(&input).unwrap();
+
+        let expected = vec![
+            markers::UNDEFINED_MARKER,
+            markers::NULL_MARKER,
+            markers::FALSE_MARKER,
+            markers::TRUE_MARKER,
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_serialize_integers_of_every_u29_length() {
+        let input = vec![
+            Amf3Value::Integer(127),
+            Amf3Value::Integer(128),
+            Amf3Value::Integer(16384),
+            Amf3Value::Integer(0x0fff_ffff),
+            Amf3Value::Integer(-1),
+        ];
+
+        let result = serialize(&input).unwrap();
+
+        let expected = vec![
+            markers::INTEGER_MARKER, 0x7f,
+            markers::INTEGER_MARKER, 0x81, 0x00,
+            markers::INTEGER_MARKER, 0x81, 0x80, 0x00,
+            markers::INTEGER_MARKER, 0xbf, 0xff, 0xff, 0xff,
+            markers::INTEGER_MARKER, 0xff, 0xff, 0xff, 0xff,
+        ];
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn integer_outside_of_29_bit_range_is_serialized_as_double() {
+        let input = vec![Amf3Value::Integer(0x1000_0000)];
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![markers::DOUBLE_MARKER];
+        expected.write_f64::<BigEndian>(0x1000_0000 as f64).unwrap();
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn repeated_strings_are_serialized_as_references() {
+        let input = vec![
+            Amf3Value::Utf8String("test".to_string()),
+            Amf3Value::Utf8String("".to_string()),
+            Amf3Value::Utf8String("test".to_string()),
+        ];
+
+        let result = serialize(&input).unwrap();
+
+        let mut expected = vec![markers::STRING_MARKER, (4 << 1) | 1];
+        expected.extend("test".as_bytes());
+        expected.extend(&[markers::STRING_MARKER, 0x01]);
+        expected.extend(&[markers::STRING_MARKER, 0]);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn repeated_object_traits_are_serialized_as_references() {
+        let object1 = Amf3Value::Object {
+            class_name: Some("Foo".to_string()),
+            sealed_properties: vec![("x".to_string(), Amf3Value::Integer(1))],
+            dynamic_properties: None,
+        };
+
+        let object2 = Amf3Value::Object {
+            class_name: Some("Foo".to_string()),
+            sealed_properties: vec![("x".to_string(), Amf3Value::Integer(2))],
+            dynamic_properties: None,
+        };
+
+        let result = serialize(&[object1, object2]).unwrap();
+
+        let mut expected = vec![markers::OBJECT_MARKER, (1 << 4) | 0b0011, (3 << 1) | 1];
+        expected.extend("Foo".as_bytes());
+        expected.extend(&[(1 << 1) | 1, b'x']);
+        expected.extend(&[markers::INTEGER_MARKER, 1]);
+        expected.extend(&[markers::OBJECT_MARKER, 0b0001]);
+        expected.extend(&[markers::INTEGER_MARKER, 2]);
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_round_trip_complex_values() {
+        let input = vec![
+            Amf3Value::Array {
+                associative: vec![("key".to_string(), Amf3Value::Double(1.5))],
+                dense: vec![Amf3Value::Utf8String("key".to_string()), Amf3Value::Null],
+            },
+            Amf3Value::Object {
+                class_name: None,
+                sealed_properties: Vec::new(),
+                dynamic_properties: Some(vec![
+                    ("date".to_string(), Amf3Value::Date {unix_time: 1000.0}),
+                    ("bytes".to_string(), Amf3Value::ByteArray(vec![1, 2, 3])),
+                ]),
+            },
+            Amf3Value::Xml("<a/>".to_string()),
+            Amf3Value::XmlDocument("<b/>".to_string()),
+            Amf3Value::VectorInt {fixed_length: false, values: vec![-1, 2]},
+            Amf3Value::VectorUInt {fixed_length: true, values: vec![3]},
+            Amf3Value::VectorDouble {fixed_length: false, values: vec![4.5]},
+            Amf3Value::VectorObject {fixed_length: false, type_name: "*".to_string(), values: vec![Amf3Value::Integer(5)]},
+            Amf3Value::Dictionary {weak_keys: false, entries: vec![(Amf3Value::Utf8String("k".to_string()), Amf3Value::Boolean(true))]},
+        ];
+
+        let bytes = serialize(&input).unwrap();
+        let mut cursor = Cursor::new(bytes);
+        let result = deserialize(&mut cursor).unwrap();
+
+        assert_eq!(result, input);
+    }
+}
@@ -37,11 +37,12 @@ fn main() {
     let video_message = RtmpMessage::VideoData {data: bytes};
     let video_payload = video_message.into_message_payload(RtmpTimestamp::new(0), 1).unwrap();
     let video_packet = publisher_serializer.serialize(&video_payload, true, true).unwrap();
+    let video_bytes = Bytes::from(video_packet.bytes);
 
     let start = SystemTime::now();
 
     for _ in 0..iteration_count {
-        let results = publisher.handle_input(&video_packet.bytes[..]).unwrap();
+        let results = publisher.handle_input(video_bytes.clone()).unwrap();
 
         for result in results {
             match result {
@@ -49,7 +50,7 @@ fn main() {
                 ServerSessionResult::UnhandleableMessageReceived(_) => (),
                 ServerSessionResult::RaisedEvent(event) => {
                     match event {
-                        ServerSessionEvent::VideoDataReceived {app_name: _, stream_key: _, data, timestamp} => {
+                        ServerSessionEvent::VideoDataReceived {app_name: _, stream_key: _, data, timestamp, track_id: _} => {
                             player1.send_video_data(1, data.clone(), timestamp.clone(), true).unwrap();
                             player2.send_video_data(1, data.clone(), timestamp.clone(), true).unwrap();
                         },
@@ -98,7 +99,7 @@ fn create_player_session() -> ServerSession {
 fn perform_connection(app_name: &str, session: &mut ServerSession, serializer: &mut ChunkSerializer) {
     let connect_payload = create_connect_message(app_name.to_string(), 15, 0, 0.0);
     let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
-    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
 
     for result in connect_results {
         match result {
@@ -106,7 +107,7 @@ fn perform_connection(app_name: &str, session: &mut ServerSession, serializer: &
             ServerSessionResult::UnhandleableMessageReceived(_) => (),
             ServerSessionResult::RaisedEvent(event) => {
                 match event {
-                    ServerSessionEvent::ConnectionRequested {app_name: _, request_id} => {
+                    ServerSessionEvent::ConnectionRequested {request_id, ..} => {
                         session.accept_request(request_id).unwrap();
                     },
 
@@ -144,7 +145,7 @@ fn create_active_stream(session: &mut ServerSession, serializer: &mut ChunkSeria
 
     let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let packet = serializer.serialize(&payload, true, false).unwrap();
-    let _ = session.handle_input(&packet.bytes[..]).unwrap();
+    let _ = session.handle_input(Bytes::from(packet.bytes)).unwrap();
 
     1
 }
@@ -162,7 +163,7 @@ fn start_publishing(session: &mut ServerSession, serializer: &mut ChunkSerialize
 
     let publish_payload = message.into_message_payload(RtmpTimestamp::new(0), 1).unwrap();
     let publish_packet = serializer.serialize(&publish_payload, false, false).unwrap();
-    let publish_results = session.handle_input(&publish_packet.bytes[..]).unwrap();
+    let publish_results = session.handle_input(Bytes::from(publish_packet.bytes)).unwrap();
 
     for result in publish_results {
         match result {
@@ -196,7 +197,7 @@ fn start_playing(session: &mut ServerSession, serializer: &mut ChunkSerializer)
 
     let play_payload = message.into_message_payload(RtmpTimestamp::new(0), 1).unwrap();
     let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
-    let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
+    let play_results = session.handle_input(Bytes::from(play_packet.bytes)).unwrap();
 
     for result in play_results {
         match result {
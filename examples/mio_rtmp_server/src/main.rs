@@ -170,7 +170,13 @@ fn main() {
             for token in connections_to_close {
                 println!("Closing connection id {}", token);
                 connections.remove(token);
-                server.notify_connection_closed(token);
+                for result in server.notify_connection_closed(token) {
+                    if let ServerResult::OutboundPacket { target_connection_id, packet } = result {
+                        if let Some(connection) = connections.get_mut(target_connection_id) {
+                            connection.enqueue_packet(&mut poll, packet).unwrap()
+                        }
+                    }
+                }
             }
         }
 
@@ -1,8 +1,9 @@
 use super::PushOptions;
 use bytes::Bytes;
 use rml_rtmp::chunk_io::Packet;
+use rml_rtmp::media::{AudioTagHeader, VideoTagHeader};
 use rml_rtmp::sessions::{ClientSession, ClientSessionConfig, ClientSessionEvent, ClientSessionResult};
-use rml_rtmp::sessions::{PublishRequestType, StreamMetadata};
+use rml_rtmp::sessions::{PublishRequestType, StreamHandle, StreamMetadata};
 use rml_rtmp::sessions::{ServerSession, ServerSessionConfig, ServerSessionEvent, ServerSessionResult};
 use rml_rtmp::time::RtmpTimestamp;
 use slab::Slab;
@@ -69,6 +70,7 @@ struct PushClient {
     push_app: String,
     push_source_stream: String,
     push_target_stream: String,
+    stream: Option<StreamHandle>,
     state: PushState,
 }
 
@@ -109,6 +111,7 @@ impl Server {
             push_target_stream: options.target_stream.clone(),
             connection_id: None,
             session: None,
+            stream: None,
             state: PushState::Inactive,
         });
 
@@ -161,6 +164,7 @@ impl Server {
         bytes: &[u8],
     ) -> Result<Vec<ServerResult>, String> {
         let mut server_results = Vec::new();
+        let bytes = Bytes::copy_from_slice(bytes);
 
         let push_client_connection_id = self.push_client.as_ref().and_then(|c| c.connection_id);
 
@@ -260,7 +264,8 @@ impl Server {
     }
 
 
-    pub fn notify_connection_closed(&mut self, connection_id: usize) {
+    pub fn notify_connection_closed(&mut self, connection_id: usize) -> Vec<ServerResult> {
+        let mut server_results = Vec::new();
         if self
             .pull_client
             .as_ref()
@@ -272,7 +277,7 @@ impl Server {
                 let client = self.clients.remove(client_id);
                 match client.current_action {
                     InboundClientAction::Publishing(stream_key) => {
-                        self.publishing_ended(stream_key)
+                        self.publishing_ended(stream_key, &mut server_results)
                     }
                     InboundClientAction::Watching {
                         stream_key,
@@ -282,6 +287,8 @@ impl Server {
                 }
             }
         }
+
+        server_results
     }
 
     fn handle_server_session_results(
@@ -315,7 +322,7 @@ impl Server {
         server_results: &mut Vec<ServerResult>,
     ) {
         match event {
-            ServerSessionEvent::ConnectionRequested {request_id, app_name} => {
+            ServerSessionEvent::ConnectionRequested {request_id, app_name, ..} => {
                 self.handle_connection_requested(executed_connection_id, request_id, app_name, server_results);
             },
 
@@ -331,11 +338,13 @@ impl Server {
                 self.handle_metadata_received(app_name, stream_key, metadata, server_results);
             }
 
-            ServerSessionEvent::VideoDataReceived {app_name: _, stream_key, data, timestamp} => {
+            // Multitrack demuxing is left off, so data is the publisher's original message and
+            // relaying it passes every track through to watchers unchanged
+            ServerSessionEvent::VideoDataReceived {app_name: _, stream_key, data, timestamp, track_id: _} => {
                 self.handle_audio_video_data_received(stream_key, timestamp, data, ReceivedDataType::Video, server_results);
             },
 
-            ServerSessionEvent::AudioDataReceived {app_name: _, stream_key, data, timestamp} => {
+            ServerSessionEvent::AudioDataReceived {app_name: _, stream_key, data, timestamp, track_id: _} => {
                 self.handle_audio_video_data_received(stream_key, timestamp, data, ReceivedDataType::Audio, server_results);
             },
 
@@ -680,12 +689,13 @@ impl Server {
         {
             if let Some(ref mut client) = self.push_client {
                 if client.state == PushState::Pushing {
+                    let stream = client.stream.unwrap();
                     let result = match data_type {
                         ReceivedDataType::Video => client.session.as_mut().unwrap()
-                            .publish_video_data(data, timestamp, true),
+                            .publish_video_data(stream, data, timestamp, true),
 
                         ReceivedDataType::Audio => client.session.as_mut().unwrap()
-                            .publish_audio_data(data, timestamp, true),
+                            .publish_audio_data(stream, data, timestamp, true),
                     };
 
                     match result {
@@ -703,7 +713,7 @@ impl Server {
         }
     }
 
-    fn publishing_ended(&mut self, stream_key: String) {
+    fn publishing_ended(&mut self, stream_key: String, server_results: &mut Vec<ServerResult>) {
         let channel = match self.channels.get_mut(&stream_key) {
             Some(channel) => channel,
             None => return,
@@ -711,6 +721,40 @@ impl Server {
 
         channel.publishing_client_id = None;
         channel.metadata = None;
+
+        // Let watchers know the stream is no longer live so players can show that it ended
+        for client_id in &channel.watching_client_ids {
+            let client = match self.clients.get_mut(*client_id) {
+                Some(client) => client,
+                None => continue,
+            };
+
+            let stream_id = match client.current_action {
+                InboundClientAction::Watching { stream_id, .. } => stream_id,
+                _ => continue,
+            };
+
+            // A new publisher's video has to start from a keyframe again
+            client.has_received_video_keyframe = false;
+
+            match client.session.notify_unpublished(stream_id) {
+                Ok(results) => {
+                    for result in results {
+                        if let ServerSessionResult::OutboundResponse(packet) = result {
+                            server_results.push(ServerResult::OutboundPacket {
+                                target_connection_id: client.connection_id,
+                                packet,
+                            });
+                        }
+                    }
+                }
+
+                Err(error) => println!(
+                    "Error notifying client on connection id {} of unpublish: {:?}",
+                    client.connection_id, error
+                ),
+            }
+        }
     }
 
     fn play_ended(&mut self, client_id: usize, stream_key: String) {
@@ -771,15 +815,15 @@ impl Server {
                     self.handle_pull_playback_accepted_event(server_results);
                 }
 
-                ClientSessionEvent::VideoDataReceived { data, timestamp } => {
+                ClientSessionEvent::VideoDataReceived { data, timestamp, .. } => {
                     self.handle_pull_audio_video_data_received(data, ReceivedDataType::Video, timestamp, server_results);
                 }
 
-                ClientSessionEvent::AudioDataReceived { data, timestamp } => {
+                ClientSessionEvent::AudioDataReceived { data, timestamp, .. } => {
                     self.handle_pull_audio_video_data_received(data, ReceivedDataType::Audio, timestamp, server_results);
                 }
 
-                ClientSessionEvent::StreamMetadataReceived { metadata } => {
+                ClientSessionEvent::StreamMetadataReceived { metadata, .. } => {
                     self.handle_pull_metadata_received(metadata, server_results);
                 }
 
@@ -794,7 +838,7 @@ impl Server {
             println!("Pull accepted for app '{}'", client.pull_app);
             client.state = PullState::Connected;
 
-            let result = client.session.as_mut().unwrap().request_playback(client.pull_stream.clone()).unwrap();
+            let (_, result) = client.session.as_mut().unwrap().request_playback(client.pull_stream.clone()).unwrap();
             let mut results = vec![result];
             new_results.append(&mut results);
         }
@@ -891,7 +935,7 @@ impl Server {
                     self.handle_push_connection_accepted_event(server_results);
                 }
 
-                ClientSessionEvent::PublishRequestAccepted => {
+                ClientSessionEvent::PublishRequestAccepted { .. } => {
                     self.handle_push_publish_accepted_event(server_results);
                 }
 
@@ -906,10 +950,12 @@ impl Server {
             println!("push accepted for app '{}'", client.push_app);
             client.state = PushState::Connected;
 
-            let result = client.session.as_mut().unwrap()
+            let (stream, result) = client.session.as_mut().unwrap()
                 .request_publishing(client.push_target_stream.clone(), PublishRequestType::Live)
                 .unwrap();
 
+            client.stream = Some(stream);
+
             let mut results = vec![result];
             new_results.append(&mut results);
         }
@@ -926,22 +972,23 @@ impl Server {
             client.state = PushState::Pushing;
 
             // Send out any metadata or header information if we have any
+            let stream = client.stream.unwrap();
             if let Some(channel) = self.channels.get(&client.push_source_stream) {
                 if let Some(ref metadata) = channel.metadata {
-                    let result = client.session.as_mut().unwrap().publish_metadata(metadata).unwrap();
+                    let result = client.session.as_mut().unwrap().publish_metadata(stream, metadata).unwrap();
                     new_results.push(result);
                 }
 
                 if let Some(ref bytes) = channel.video_sequence_header {
                     let result = client.session.as_mut().unwrap()
-                        .publish_video_data(bytes.clone(), RtmpTimestamp::new(0), false).unwrap();
+                        .publish_video_data(stream, bytes.clone(), RtmpTimestamp::new(0), false).unwrap();
 
                     new_results.push(result);
                 }
 
                 if let Some(ref bytes) = channel.audio_sequence_header {
                     let result = client.session.as_mut().unwrap()
-                        .publish_audio_data(bytes.clone(), RtmpTimestamp::new(0), false).unwrap();
+                        .publish_audio_data(stream, bytes.clone(), RtmpTimestamp::new(0), false).unwrap();
 
                     new_results.push(result);
                 }
@@ -955,22 +1002,22 @@ impl Server {
 }
 
 fn is_video_sequence_header(data: Bytes) -> bool {
-    // This is assuming h264.
-    data.len() >= 2
-        && data[0] == 0x17
-        && data[1] == 0x00
+    match VideoTagHeader::parse(&data) {
+        Ok(header) => header.is_sequence_header(),
+        Err(_) => false,
+    }
 }
 
 fn is_audio_sequence_header(data: Bytes) -> bool {
-    // This is assuming aac
-    data.len() >= 2
-        && data[0] == 0xaf
-        && data[1] == 0x00
+    match AudioTagHeader::parse(&data) {
+        Ok(header) => header.is_sequence_header(),
+        Err(_) => false,
+    }
 }
 
 fn is_video_keyframe(data: Bytes) -> bool {
-    // assumings h264
-    data.len() >= 2
-        && data[0] == 0x17
-        && data[1] != 0x00 // 0x00 is the sequence header, don't count that for now
+    match VideoTagHeader::parse(&data) {
+        Ok(header) => header.is_keyframe(),
+        Err(_) => false,
+    }
 }
@@ -105,7 +105,13 @@ fn handle_connections(connection_receiver: Receiver<TcpStream>) {
             println!("Connection {} closed", closed_id);
             connection_ids.remove(&closed_id);
             connections.remove(closed_id);
-            server.notify_connection_closed(closed_id);
+            for result in server.notify_connection_closed(closed_id) {
+                if let ServerResult::OutboundPacket {target_connection_id, packet} = result {
+                    if let Some(connection) = connections.get_mut(target_connection_id) {
+                        connection.write(packet.bytes);
+                    }
+                }
+            }
         }
     }
 }
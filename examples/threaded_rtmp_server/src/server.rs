@@ -5,6 +5,7 @@ use slab::Slab;
 use rml_rtmp::sessions::{ServerSession, ServerSessionConfig, ServerSessionResult, ServerSessionEvent};
 use rml_rtmp::sessions::StreamMetadata;
 use rml_rtmp::chunk_io::Packet;
+use rml_rtmp::media::{AudioTagHeader, VideoTagHeader};
 use rml_rtmp::time::RtmpTimestamp;
 
 enum ClientAction {
@@ -69,6 +70,7 @@ impl Server {
 
     pub fn bytes_received(&mut self, connection_id: usize, bytes: &[u8]) -> Result<Vec<ServerResult>, String> {
         let mut server_results = Vec::new();
+        let bytes = Bytes::copy_from_slice(bytes);
 
         if !self.connection_to_client_map.contains_key(&connection_id) {
             let config = ServerSessionConfig::new();
@@ -103,18 +105,21 @@ impl Server {
         Ok(server_results)
     }
 
-    pub fn notify_connection_closed(&mut self, connection_id: usize) {
+    pub fn notify_connection_closed(&mut self, connection_id: usize) -> Vec<ServerResult> {
+        let mut server_results = Vec::new();
         match self.connection_to_client_map.remove(&connection_id) {
             None => (),
             Some(client_id) => {
                 let client = self.clients.remove(client_id);
                 match client.current_action {
-                    ClientAction::Publishing(stream_key) => self.publishing_ended(stream_key),
+                    ClientAction::Publishing(stream_key) => self.publishing_ended(stream_key, &mut server_results),
                     ClientAction::Watching{stream_key, stream_id: _} => self.play_ended(client_id, stream_key),
                     ClientAction::Waiting => (),
                 }
             },
         }
+
+        server_results
     }
 
     fn handle_session_results(&mut self,
@@ -143,7 +148,7 @@ impl Server {
                            event: ServerSessionEvent,
                            server_results: &mut Vec<ServerResult>) {
         match event {
-            ServerSessionEvent::ConnectionRequested {request_id, app_name} => {
+            ServerSessionEvent::ConnectionRequested {request_id, app_name, ..} => {
                 self.handle_connection_requested(executed_connection_id, request_id, app_name, server_results);
             },
 
@@ -159,11 +164,13 @@ impl Server {
                 self.handle_metadata_received(app_name, stream_key, metadata, server_results);
             },
 
-            ServerSessionEvent::VideoDataReceived {app_name: _, stream_key, data, timestamp} => {
+            // Multitrack demuxing is left off, so data is the publisher's original message and
+            // relaying it passes every track through to watchers unchanged
+            ServerSessionEvent::VideoDataReceived {app_name: _, stream_key, data, timestamp, track_id: _} => {
                 self.handle_audio_video_data_received(stream_key, timestamp, data, ReceivedDataType::Video, server_results);
             },
 
-            ServerSessionEvent::AudioDataReceived {app_name: _, stream_key, data, timestamp} => {
+            ServerSessionEvent::AudioDataReceived {app_name: _, stream_key, data, timestamp, track_id: _} => {
                 self.handle_audio_video_data_received(stream_key, timestamp, data, ReceivedDataType::Audio, server_results);
             },
 
@@ -494,7 +501,7 @@ impl Server {
         }
     }
 
-    fn publishing_ended(&mut self, stream_key: String) {
+    fn publishing_ended(&mut self, stream_key: String, server_results: &mut Vec<ServerResult>) {
         let channel = match self.channels.get_mut(&stream_key) {
             Some(channel) => channel,
             None => return,
@@ -502,6 +509,37 @@ impl Server {
 
         channel.publishing_client_id = None;
         channel.metadata = None;
+
+        // Let watchers know the stream is no longer live so players can show that it ended
+        for client_id in &channel.watching_client_ids {
+            let client = match self.clients.get_mut(*client_id) {
+                Some(client) => client,
+                None => continue,
+            };
+
+            let stream_id = match client.get_active_stream_id() {
+                Some(stream_id) => stream_id,
+                None => continue,
+            };
+
+            // A new publisher's video has to start from a keyframe again
+            client.has_received_video_keyframe = false;
+
+            match client.session.notify_unpublished(stream_id) {
+                Ok(results) => {
+                    for result in results {
+                        if let ServerSessionResult::OutboundResponse(packet) = result {
+                            server_results.push(ServerResult::OutboundPacket {
+                                target_connection_id: client.connection_id,
+                                packet,
+                            });
+                        }
+                    }
+                },
+
+                Err(error) => println!("Error notifying client on connection id {} of unpublish: {:?}", client.connection_id, error),
+            }
+        }
     }
 
     fn play_ended(&mut self, client_id: usize, stream_key: String) {
@@ -515,22 +553,22 @@ impl Server {
 }
 
 fn is_video_sequence_header(data: Bytes) -> bool {
-    // This is assuming h264.
-    return data.len() >= 2 &&
-        data[0] == 0x17 &&
-        data[1] == 0x00;
+    match VideoTagHeader::parse(&data) {
+        Ok(header) => header.is_sequence_header(),
+        Err(_) => false,
+    }
 }
 
 fn is_audio_sequence_header(data: Bytes) -> bool {
-    // This is assuming aac
-    return data.len() >= 2 &&
-        data[0] == 0xaf &&
-        data[1] == 0x00;
+    match AudioTagHeader::parse(&data) {
+        Ok(header) => header.is_sequence_header(),
+        Err(_) => false,
+    }
 }
 
 fn is_video_keyframe(data: Bytes) -> bool {
-    // assumings h264
-    return data.len() >= 2 &&
-        data[0] == 0x17 &&
-        data[1] != 0x00; // 0x00 is the sequence header, don't count that for now
+    match VideoTagHeader::parse(&data) {
+        Ok(header) => header.is_keyframe(),
+        Err(_) => false,
+    }
 }
@@ -0,0 +1,76 @@
+//! Errors that can occur while validating a stream token
+
+use failure::{Backtrace, Fail};
+use std::fmt;
+
+/// Data pertaining to why a stream token was not valid
+#[derive(Debug)]
+pub struct TokenValidationError {
+    /// The reason the token was not valid
+    pub kind: TokenValidationErrorKind,
+}
+
+/// Enumeration that represents the reasons a stream token may not be valid
+#[derive(Debug, Fail, PartialEq)]
+pub enum TokenValidationErrorKind {
+    /// Neither the stream key nor the app name contained a `token` query parameter
+    #[fail(display = "No token was provided")]
+    MissingToken,
+
+    /// A token was provided without an `expires` query parameter alongside it
+    #[fail(display = "No expiration was provided with the token")]
+    MissingExpiration,
+
+    /// The `expires` query parameter was not a unix timestamp
+    #[fail(display = "The token's expiration is not a valid unix timestamp")]
+    InvalidExpiration,
+
+    /// The token was not signed with the secret for this app name, stream name and expiration
+    #[fail(display = "The token's signature is not valid")]
+    InvalidSignature,
+
+    /// The token is properly signed but its expiration (in unix seconds) has passed
+    #[fail(display = "The token expired at {}", expired_at)]
+    Expired { expired_at: u64 },
+}
+
+/// The type of request a token was validated for
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TokenRequestType {
+    Publish,
+    Play,
+}
+
+impl TokenValidationError {
+    /// Returns the status code to pass to `ServerSession::reject_request()` when rejecting a
+    /// request of the specified type due to this error.  The error's display string is suitable
+    /// as the description.
+    pub fn get_rejection_code(&self, request_type: TokenRequestType) -> &'static str {
+        match request_type {
+            TokenRequestType::Publish => "NetStream.Publish.BadName",
+            TokenRequestType::Play => "NetStream.Play.Failed",
+        }
+    }
+}
+
+impl fmt::Display for TokenValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.kind, f)
+    }
+}
+
+impl Fail for TokenValidationError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.kind.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.kind.backtrace()
+    }
+}
+
+impl From<TokenValidationErrorKind> for TokenValidationError {
+    fn from(kind: TokenValidationErrorKind) -> Self {
+        TokenValidationError { kind }
+    }
+}
@@ -0,0 +1,373 @@
+/*!
+This module contains validation of signed, expiring tokens that authorize publishing or playing
+a stream.
+
+A token is passed as query parameters of either the stream key (e.g.
+`my-stream?token=<token>&expires=<expires>`) or the app name the client connected to (e.g.
+`live?token=<token>&expires=<expires>`).  The `expires` value is a unix timestamp in seconds, and
+the `token` is the lowercase hex encoded HMAC-SHA256 of
+`<app name length>:<app name>:<stream name length>:<stream name>:<expires>` (e.g.
+`4:live:9:my-stream:1060`) using a secret shared with whatever issues the tokens.  Names are
+without any query strings and their lengths are in bytes, so the signed value can't be split into
+a different app and stream name.  Tokens can be issued with `TokenValidator::generate_token()`.
+
+```
+use rml_rtmp::auth::TokenValidator;
+use rml_rtmp::sessions::ManualClock;
+use std::sync::Arc;
+
+let clock = ManualClock::new(1_000_000);
+let validator = TokenValidator::with_clock(b"secret", Arc::new(clock.clone()));
+
+let token = validator.generate_token("live", "my-stream", 1_060);
+let stream_key = format!("my-stream?token={}&expires=1060", token);
+assert!(validator.validate("live", &stream_key).is_ok());
+
+clock.advance(61_000);
+assert!(validator.validate("live", &stream_key).is_err());
+```
+*/
+
+mod errors;
+
+pub use self::errors::{TokenRequestType, TokenValidationError, TokenValidationErrorKind};
+
+use hmac::{Hmac, Mac, NewMac};
+use sessions::{Clock, ServerSessionEvent, SystemClock};
+use sha2::Sha256;
+use std::sync::Arc;
+
+/// The details of a stream token that was successfully validated
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValidatedToken {
+    /// The app name the token was issued for, without any query string
+    pub app_name: String,
+
+    /// The stream name the token was issued for, without any query string
+    pub stream_name: String,
+
+    /// The unix time (in seconds) the token expires at
+    pub expires: u64,
+}
+
+/// The outcome of validating the token of a publish or play request raised by a `ServerSession`
+#[derive(Debug)]
+pub struct TokenCheck {
+    /// The id to pass to `ServerSession::accept_request()` or `ServerSession::reject_request()`
+    pub request_id: u32,
+
+    /// Whether the request was to publish or play the stream
+    pub request_type: TokenRequestType,
+
+    /// The validated token, or the reason the request should be rejected
+    pub result: Result<ValidatedToken, TokenValidationError>,
+}
+
+/// Validates stream tokens signed with a shared secret
+pub struct TokenValidator {
+    secret: Vec<u8>,
+    clock: Arc<dyn Clock>,
+}
+
+impl TokenValidator {
+    /// Creates a validator for tokens signed with the specified secret, using the system time
+    /// to check for expiration
+    pub fn new(secret: &[u8]) -> TokenValidator {
+        TokenValidator::with_clock(secret, Arc::new(SystemClock))
+    }
+
+    /// Creates a validator for tokens signed with the specified secret, using the specified clock
+    /// to check for expiration.  The clock's time is treated as milliseconds since the unix epoch.
+    pub fn with_clock(secret: &[u8], clock: Arc<dyn Clock>) -> TokenValidator {
+        TokenValidator {
+            secret: secret.to_vec(),
+            clock,
+        }
+    }
+
+    /// Creates the token that authorizes the stream name on the app name until the specified
+    /// unix time (in seconds)
+    pub fn generate_token(&self, app_name: &str, stream_name: &str, expires: u64) -> String {
+        self.create_mac(app_name, stream_name, expires)
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|x| format!("{:02x}", x))
+            .collect()
+    }
+
+    /// Validates the token contained in the query string of the stream key, or of the app name
+    /// if the stream key does not have one
+    pub fn validate(&self, app_name: &str, stream_key: &str) -> Result<ValidatedToken, TokenValidationError> {
+        let (app_name, app_query) = split_query(app_name);
+        let (stream_name, stream_query) = split_query(stream_key);
+
+        let query = if get_query_value(stream_query, "token").is_some() {
+            stream_query
+        } else {
+            app_query
+        };
+
+        let token = match get_query_value(query, "token") {
+            Some(token) => token,
+            None => return Err(TokenValidationErrorKind::MissingToken.into()),
+        };
+
+        let expires = match get_query_value(query, "expires") {
+            Some(expires) => match expires.parse::<u64>() {
+                Ok(expires) => expires,
+                Err(_) => return Err(TokenValidationErrorKind::InvalidExpiration.into()),
+            },
+
+            None => return Err(TokenValidationErrorKind::MissingExpiration.into()),
+        };
+
+        let signature = match decode_hex(token) {
+            Some(signature) => signature,
+            None => return Err(TokenValidationErrorKind::InvalidSignature.into()),
+        };
+
+        // Verification is done through the mac so the comparison is constant time
+        if self.create_mac(app_name, stream_name, expires).verify(&signature).is_err() {
+            return Err(TokenValidationErrorKind::InvalidSignature.into());
+        }
+
+        if self.clock.get_time_ms() / 1000 > expires {
+            return Err(TokenValidationErrorKind::Expired { expired_at: expires }.into());
+        }
+
+        Ok(ValidatedToken {
+            app_name: app_name.to_string(),
+            stream_name: stream_name.to_string(),
+            expires,
+        })
+    }
+
+    /// Validates the token of a `PublishStreamRequested` or `PlayStreamRequested` event.  `None`
+    /// is returned for any other event.
+    pub fn validate_request(&self, event: &ServerSessionEvent) -> Option<TokenCheck> {
+        let (request_id, request_type, app_name, stream_key) = match *event {
+            ServerSessionEvent::PublishStreamRequested { request_id, ref app_name, ref stream_key, .. }
+                => (request_id, TokenRequestType::Publish, app_name, stream_key),
+
+            ServerSessionEvent::PlayStreamRequested { request_id, ref app_name, ref stream_key, .. }
+                => (request_id, TokenRequestType::Play, app_name, stream_key),
+
+            _ => return None,
+        };
+
+        Some(TokenCheck {
+            request_id,
+            request_type,
+            result: self.validate(app_name, stream_key),
+        })
+    }
+
+    fn create_mac(&self, app_name: &str, stream_name: &str, expires: u64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_varkey(&self.secret).unwrap();
+        let message = format!("{}:{}:{}:{}:{}", app_name.len(), app_name, stream_name.len(), stream_name, expires);
+        mac.update(message.as_bytes());
+        mac
+    }
+}
+
+fn split_query(value: &str) -> (&str, &str) {
+    match value.find('?') {
+        Some(index) => (&value[..index], &value[index + 1..]),
+        None => (value, ""),
+    }
+}
+
+fn get_query_value<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if key == name => Some(value),
+                _ => None,
+            }
+        })
+        .next()
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    value.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((hex_digit(*high)? << 4) | hex_digit(*low)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(value: u8) -> Option<u8> {
+    (value as char).to_digit(16).map(|x| x as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sessions::{ManualClock, PlayStartValue, PublishMode};
+
+    const SECRET: &[u8] = b"secret";
+
+    #[test]
+    fn token_in_stream_key_is_valid() {
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(ManualClock::new(100_000)));
+        let token = validator.generate_token("live", "stream", 200);
+        let stream_key = format!("stream?token={}&expires=200", token);
+
+        let result = validator.validate("live", &stream_key).unwrap();
+
+        assert_eq!(result, ValidatedToken {
+            app_name: "live".to_string(),
+            stream_name: "stream".to_string(),
+            expires: 200,
+        });
+    }
+
+    #[test]
+    fn token_in_app_query_is_valid() {
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(ManualClock::new(100_000)));
+        let token = validator.generate_token("live", "stream", 200);
+        let app_name = format!("live?expires=200&token={}", token);
+
+        let result = validator.validate(&app_name, "stream").unwrap();
+
+        assert_eq!(result.app_name, "live");
+        assert_eq!(result.stream_name, "stream");
+    }
+
+    #[test]
+    fn token_for_different_stream_is_invalid() {
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(ManualClock::new(100_000)));
+        let token = validator.generate_token("live", "other", 200);
+        let stream_key = format!("stream?token={}&expires=200", token);
+
+        let error = validator.validate("live", &stream_key).unwrap_err();
+
+        assert_eq!(error.kind, TokenValidationErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn token_cannot_be_moved_between_app_and_stream_name() {
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(ManualClock::new(100_000)));
+        let token = validator.generate_token("live/a", "b", 200);
+        let stream_key = format!("a/b?token={}&expires=200", token);
+
+        let error = validator.validate("live", &stream_key).unwrap_err();
+
+        assert_eq!(error.kind, TokenValidationErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn token_matches_documented_signature() {
+        let validator = TokenValidator::new(SECRET);
+        let mut mac = Hmac::<Sha256>::new_varkey(SECRET).unwrap();
+        mac.update(b"4:live:9:my-stream:1060");
+        let expected: String = mac.finalize().into_bytes().iter().map(|x| format!("{:02x}", x)).collect();
+
+        assert_eq!(validator.generate_token("live", "my-stream", 1060), expected);
+    }
+
+    #[test]
+    fn token_signed_with_different_secret_is_invalid() {
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(ManualClock::new(100_000)));
+        let other = TokenValidator::with_clock(b"other", Arc::new(ManualClock::new(100_000)));
+        let token = other.generate_token("live", "stream", 200);
+        let stream_key = format!("stream?token={}&expires=200", token);
+
+        let error = validator.validate("live", &stream_key).unwrap_err();
+
+        assert_eq!(error.kind, TokenValidationErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn changed_expiration_is_invalid() {
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(ManualClock::new(100_000)));
+        let token = validator.generate_token("live", "stream", 200);
+        let stream_key = format!("stream?token={}&expires=300", token);
+
+        let error = validator.validate("live", &stream_key).unwrap_err();
+
+        assert_eq!(error.kind, TokenValidationErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn token_is_invalid_once_expired() {
+        let clock = ManualClock::new(100_000);
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(clock.clone()));
+        let token = validator.generate_token("live", "stream", 200);
+        let stream_key = format!("stream?token={}&expires=200", token);
+
+        clock.set(200_999);
+        assert!(validator.validate("live", &stream_key).is_ok(), "Expected token to be valid at expiration");
+
+        clock.advance(1);
+        let error = validator.validate("live", &stream_key).unwrap_err();
+        assert_eq!(error.kind, TokenValidationErrorKind::Expired { expired_at: 200 });
+    }
+
+    #[test]
+    fn missing_parameters_are_reported() {
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(ManualClock::new(100_000)));
+
+        let error = validator.validate("live", "stream").unwrap_err();
+        assert_eq!(error.kind, TokenValidationErrorKind::MissingToken);
+
+        let error = validator.validate("live", "stream?token=abcd").unwrap_err();
+        assert_eq!(error.kind, TokenValidationErrorKind::MissingExpiration);
+
+        let error = validator.validate("live", "stream?token=abcd&expires=soon").unwrap_err();
+        assert_eq!(error.kind, TokenValidationErrorKind::InvalidExpiration);
+
+        let error = validator.validate("live", "stream?token=xyz&expires=200").unwrap_err();
+        assert_eq!(error.kind, TokenValidationErrorKind::InvalidSignature);
+    }
+
+    #[test]
+    fn publish_and_play_requests_are_validated() {
+        let validator = TokenValidator::with_clock(SECRET, Arc::new(ManualClock::new(100_000)));
+        let token = validator.generate_token("live", "stream", 200);
+
+        let event = ServerSessionEvent::PublishStreamRequested {
+            request_id: 5,
+            app_name: "live".to_string(),
+            stream_key: format!("stream?token={}&expires=200", token),
+            mode: PublishMode::Live,
+        };
+
+        let check = validator.validate_request(&event).unwrap();
+        assert_eq!(check.request_id, 5);
+        assert_eq!(check.request_type, TokenRequestType::Publish);
+        assert!(check.result.is_ok(), "Expected a valid token");
+
+        let event = ServerSessionEvent::PlayStreamRequested {
+            request_id: 6,
+            app_name: "live".to_string(),
+            stream_key: "stream".to_string(),
+            start_at: PlayStartValue::LiveOrRecorded,
+            duration: None,
+            reset: false,
+            stream_id: 1,
+        };
+
+        let check = validator.validate_request(&event).unwrap();
+        assert_eq!(check.request_id, 6);
+        assert_eq!(check.request_type, TokenRequestType::Play);
+
+        let error = check.result.unwrap_err();
+        assert_eq!(error.get_rejection_code(check.request_type), "NetStream.Play.Failed");
+    }
+
+    #[test]
+    fn other_events_are_not_validated() {
+        let validator = TokenValidator::new(SECRET);
+        let event = ServerSessionEvent::PlayStreamFinished {
+            app_name: "live".to_string(),
+            stream_key: "stream".to_string(),
+        };
+
+        assert!(validator.validate_request(&event).is_none());
+    }
+}
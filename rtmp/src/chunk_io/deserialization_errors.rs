@@ -31,6 +31,43 @@ pub enum ChunkDeserializationErrorKind {
     )]
     InvalidMaxChunkSize { chunk_size: usize },
 
+    /// The peer sent a chunk header for a message that is larger than the deserializer's
+    /// `max_message_size` limit
+    #[fail(
+        display = "Received a message length of {} which is larger than the limit of {}",
+        message_length, max_message_size
+    )]
+    MessageTooLarge { message_length: usize, max_message_size: usize },
+
+    /// The peer started more messages across chunk streams without finishing them than the
+    /// deserializer's `max_partial_messages` limit allows
+    #[fail(
+        display = "More than {} messages were partially received at the same time",
+        max_partial_messages
+    )]
+    TooManyPartialMessages { max_partial_messages: usize },
+
+    /// The peer used more distinct chunk stream ids than the deserializer's
+    /// `max_chunk_stream_ids` limit allows
+    #[fail(display = "More than {} chunk stream ids were used", max_chunk_stream_ids)]
+    TooManyChunkStreamIds { max_chunk_stream_ids: usize },
+
+    /// Accepting more bytes would have the deserializer hold on to more than its
+    /// `max_buffered_bytes` limit allows
+    #[fail(
+        display = "Buffering {} bytes would exceed the limit of {} bytes",
+        buffered_bytes, max_buffered_bytes
+    )]
+    BufferedBytesLimitExceeded { buffered_bytes: usize, max_buffered_bytes: usize },
+
+    /// The peer sent a chunk header with a different message length while a message on the same
+    /// chunk stream was still partially received
+    #[fail(
+        display = "Message length changed from {} to {} on csid {} before the message was complete",
+        previous_length, new_length, csid
+    )]
+    MessageLengthChangedMidMessage { csid: u32, previous_length: usize, new_length: usize },
+
     /// An I/O error occurred while reading the input buffer
     #[fail(display = "_0")]
     Io(#[cause] io::Error),
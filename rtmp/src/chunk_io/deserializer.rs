@@ -3,7 +3,7 @@ use std::collections::{HashMap};
 use std::io::{Cursor};
 use std::mem;
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
-use bytes::{BytesMut, BufMut};
+use bytes::{Bytes, BytesMut};
 use ::chunk_io::{ChunkDeserializationError, ChunkDeserializationErrorKind};
 use ::messages::MessagePayload;
 use super::chunk_header::{ChunkHeader, ChunkHeaderFormat};
@@ -11,6 +11,51 @@ use super::chunk_header::{ChunkHeader, ChunkHeaderFormat};
 const INITIAL_MAX_CHUNK_SIZE: usize = 128;
 const MAX_INITIAL_TIMESTAMP: u32 = 16777215;
 
+/// Limits on how much a peer can make a `ChunkDeserializer` hold on to.  These protect against
+/// peers that announce large messages, or spread messages across many chunk streams, in order to
+/// exhaust memory.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChunkDeserializerLimits {
+    /// The largest message length that will be accepted in a chunk header
+    pub max_message_size: usize,
+
+    /// The most messages that can be partially received (split across chunks) at the same time
+    pub max_partial_messages: usize,
+
+    /// The most distinct chunk stream ids the peer can use
+    pub max_chunk_stream_ids: usize,
+
+    /// The most bytes that can be buffered at once.  This counts both input that has not been
+    /// deserialized yet and the full length of every partially received message, as space for
+    /// the whole message is set aside when its first chunk is received.
+    pub max_buffered_bytes: usize,
+}
+
+impl ChunkDeserializerLimits {
+    /// Creates a new set of limits with overridable defaults
+    pub fn new() -> ChunkDeserializerLimits {
+        ChunkDeserializerLimits {
+            max_message_size: 8 * 1024 * 1024,
+            max_partial_messages: 64,
+            max_chunk_stream_ids: 256,
+            max_buffered_bytes: 32 * 1024 * 1024,
+        }
+    }
+}
+
+impl Default for ChunkDeserializerLimits {
+    fn default() -> Self {
+        ChunkDeserializerLimits::new()
+    }
+}
+
+/// The data received so far for a message split across chunks, along with the number of bytes
+/// that were counted against the buffered bytes limit for it
+struct PartialMessage {
+    data: BytesMut,
+    reserved_length: usize,
+}
+
 /// Allows deserializing bytes representing RTMP chunks into RTMP message payloads.
 ///
 /// Due to the nature of the RTMP chunk protocol it is required that every byte going through the
@@ -23,8 +68,12 @@ pub struct ChunkDeserializer {
     current_stage: ParseStage,
     current_payload: MessagePayload,
     current_payload_data: BytesMut,
+    current_payload_reserved_length: usize,
     buffer: BytesMut,
     previous_headers: HashMap<u32, ChunkHeader>,
+    partial_messages: HashMap<u32, PartialMessage>,
+    partial_message_bytes: usize,
+    limits: ChunkDeserializerLimits,
 }
 
 enum ParsedValue<T> {
@@ -54,6 +103,12 @@ impl ChunkDeserializer {
     /// Per the RTMP specification an initial `ChunkDeserializer` is expecting RTMP chunks with
     /// a max size of 128 bytes.
     pub fn new() -> ChunkDeserializer {
+        ChunkDeserializer::with_limits(ChunkDeserializerLimits::new())
+    }
+
+    /// Create a new `ChunkDeserializer` that enforces the specified limits on what the peer
+    /// can make it hold on to.
+    pub fn with_limits(limits: ChunkDeserializerLimits) -> ChunkDeserializer {
         ChunkDeserializer {
             max_chunk_size: INITIAL_MAX_CHUNK_SIZE,
             current_header_format: ChunkHeaderFormat::Full,
@@ -63,6 +118,10 @@ impl ChunkDeserializer {
             previous_headers: HashMap::new(),
             current_payload: MessagePayload::new(),
             current_payload_data: BytesMut::new(),
+            current_payload_reserved_length: 0,
+            partial_messages: HashMap::new(),
+            partial_message_bytes: 0,
+            limits,
         }
     }
 
@@ -145,24 +204,30 @@ impl ChunkDeserializer {
     /// # }
     /// ```
     pub fn get_next_message(&mut self, bytes: &[u8]) -> Result<Option<MessagePayload>, ChunkDeserializationError> {
+        self.check_buffered_bytes(bytes.len())?;
         self.buffer.extend_from_slice(bytes);
+        self.read_next_message()
+    }
 
-        loop {
-            let mut complete_message = None;
-            let result = match self.current_stage {
-                ParseStage::Csid => self.form_header()?,
-                ParseStage::InitialTimestamp => self.get_initial_timestamp()?,
-                ParseStage::MessageLength => self.get_message_length()?,
-                ParseStage::MessageTypeId => self.get_message_type_id()?,
-                ParseStage::MessageStreamId => self.get_message_stream_id()?,
-                ParseStage::ExtendedTimestamp => self.get_extended_timestamp()?,
-                ParseStage::MessagePayload => self.get_message_data(&mut complete_message)?,
-            };
-
-            if result == ParseStageResult::NotEnoughBytes || complete_message.is_some() {
-                return Ok(complete_message);
-            }
+    /// Attempts to read a complete RTMP message from the passed in bytes, taking ownership of them.
+    ///
+    /// This behaves the same as `get_next_message()`, except that when there are no partial
+    /// chunks waiting to be completed the bytes are used as the deserializer's buffer without being
+    /// copied.  The data of any message that is contained within a single chunk is handed out as
+    /// a slice of those bytes, so media data does not get copied on its way from the socket to
+    /// the caller.
+    ///
+    /// Subsequent calls for the remaining messages can pass in an empty `Bytes` value or use
+    /// `get_next_message()` with an empty slice.
+    pub fn get_next_message_from_bytes(&mut self, bytes: Bytes) -> Result<Option<MessagePayload>, ChunkDeserializationError> {
+        self.check_buffered_bytes(bytes.len())?;
+        if self.buffer.is_empty() {
+            self.buffer = BytesMut::from(bytes);
+        } else {
+            self.buffer.extend_from_slice(&bytes[..]);
         }
+
+        self.read_next_message()
     }
 
     /// Tells the deserializer that the peer will start sending RTMP chunks with a different
@@ -191,6 +256,55 @@ impl ChunkDeserializer {
         self.max_chunk_size
     }
 
+    /// Discards the partially received message on the specified chunk stream id, so the next
+    /// chunk on that chunk stream starts a new message.
+    ///
+    /// This should be called in reaction to receiving an `Abort` message from the peer, which
+    /// signals that it gave up on sending the rest of the message.
+    pub fn abort_message(&mut self, csid: u32) {
+        if let Some(partial_message) = self.partial_messages.remove(&csid) {
+            self.partial_message_bytes -= partial_message.reserved_length;
+        }
+    }
+
+    /// Returns the limits the deserializer is enforcing
+    pub fn get_limits(&self) -> &ChunkDeserializerLimits {
+        &self.limits
+    }
+
+    fn check_buffered_bytes(&self, incoming_byte_count: usize) -> Result<(), ChunkDeserializationError> {
+        let buffered_bytes = self.buffer.len() + self.partial_message_bytes + incoming_byte_count;
+        if buffered_bytes > self.limits.max_buffered_bytes {
+            let kind = ChunkDeserializationErrorKind::BufferedBytesLimitExceeded {
+                buffered_bytes,
+                max_buffered_bytes: self.limits.max_buffered_bytes,
+            };
+
+            return Err(ChunkDeserializationError {kind});
+        }
+
+        Ok(())
+    }
+
+    fn read_next_message(&mut self) -> Result<Option<MessagePayload>, ChunkDeserializationError> {
+        loop {
+            let mut complete_message = None;
+            let result = match self.current_stage {
+                ParseStage::Csid => self.form_header()?,
+                ParseStage::InitialTimestamp => self.get_initial_timestamp()?,
+                ParseStage::MessageLength => self.get_message_length()?,
+                ParseStage::MessageTypeId => self.get_message_type_id()?,
+                ParseStage::MessageStreamId => self.get_message_stream_id()?,
+                ParseStage::ExtendedTimestamp => self.get_extended_timestamp()?,
+                ParseStage::MessagePayload => self.get_message_data(&mut complete_message)?,
+            };
+
+            if result == ParseStageResult::NotEnoughBytes || complete_message.is_some() {
+                return Ok(complete_message);
+            }
+        }
+    }
+
     fn form_header(&mut self) -> Result<ParseStageResult, ChunkDeserializationError> {
         if self.buffer.is_empty() {
             return Ok(ParseStageResult::NotEnoughBytes);
@@ -204,6 +318,11 @@ impl ChunkDeserializer {
 
         self.current_header = match self.current_header_format {
             ChunkHeaderFormat::Full => {
+                if !self.previous_headers.contains_key(&csid) && self.previous_headers.len() >= self.limits.max_chunk_stream_ids {
+                    let kind = ChunkDeserializationErrorKind::TooManyChunkStreamIds {max_chunk_stream_ids: self.limits.max_chunk_stream_ids};
+                    return Err(ChunkDeserializationError {kind});
+                }
+
                 let mut new_header = ChunkHeader::new();
                 new_header.chunk_stream_id = csid;
                 new_header
@@ -215,6 +334,12 @@ impl ChunkDeserializer {
             }
         };
 
+        // Continue any message that was left partially received on this chunk stream
+        if let Some(partial_message) = self.partial_messages.remove(&csid) {
+            self.current_payload_data = partial_message.data;
+            self.current_payload_reserved_length = partial_message.reserved_length;
+        }
+
         let _ = self.buffer.split_to(next_index as usize);
         self.current_stage = ParseStage::InitialTimestamp;
         Ok(ParseStageResult::Success)
@@ -280,6 +405,15 @@ impl ChunkDeserializer {
             length = cursor.read_u24::<BigEndian>()?;
         }
 
+        if length as usize > self.limits.max_message_size {
+            let kind = ChunkDeserializationErrorKind::MessageTooLarge {
+                message_length: length as usize,
+                max_message_size: self.limits.max_message_size,
+            };
+
+            return Err(ChunkDeserializationError {kind});
+        }
+
         self.current_header.message_length = length;
         self.current_stage = ParseStage::MessageTypeId;
         Ok(ParseStageResult::Success)
@@ -356,6 +490,16 @@ impl ChunkDeserializer {
     fn get_message_data(&mut self, message_to_return: &mut Option<MessagePayload>) -> Result<ParseStageResult, ChunkDeserializationError> {
         let mut length = self.current_header.message_length as usize;
         let current_payload_length = self.current_payload_data.len();
+        if current_payload_length > 0 && length != self.current_payload_reserved_length {
+            let kind = ChunkDeserializationErrorKind::MessageLengthChangedMidMessage {
+                csid: self.current_header.chunk_stream_id,
+                previous_length: self.current_payload_reserved_length,
+                new_length: length,
+            };
+
+            return Err(ChunkDeserializationError {kind});
+        }
+
         let remaining_bytes = length - current_payload_length;
         if length > self.max_chunk_size as usize {
             length = min(remaining_bytes, self.max_chunk_size as usize);
@@ -369,23 +513,44 @@ impl ChunkDeserializer {
         self.current_payload.type_id = self.current_header.message_type_id;
         self.current_payload.message_stream_id = self.current_header.message_stream_id;
 
-        // Make sure the we have enough capacity for the whole message data.  This
-        // helps with performance when there are smaller chunk sizes.
-        if remaining_bytes > self.current_payload_data.remaining_mut() {
-            let capacity_needed = remaining_bytes - self.current_payload_data.remaining_mut();
-            self.current_payload_data.reserve(capacity_needed);
-        }
-
-        let bytes = self.buffer.split_to(length as usize);
-        self.current_payload_data.extend_from_slice(&bytes[..]);
-
-        // Check if this completes the message
-        if self.current_payload_data.len() == self.current_header.message_length as usize {
-            let data = mem::replace(&mut self.current_payload_data, BytesMut::new());
-            self.current_payload.data = data.freeze();
+        if current_payload_length == 0 && length == self.current_header.message_length as usize {
+            // The whole message is in this chunk, so hand out its data straight from the buffer
+            // without copying it
+            self.current_payload.data = self.buffer.split_to(length).freeze();
 
             let payload = mem::replace(&mut self.current_payload, MessagePayload::new());
             *message_to_return = Some(payload)
+        } else {
+            let message_length = self.current_header.message_length as usize;
+            if current_payload_length == 0 {
+                self.start_partial_message(message_length)?;
+            }
+
+            // Make sure the we have enough capacity for the whole message data.  This
+            // helps with performance when there are smaller chunk sizes.  Once the data of
+            // the previous message has been dropped its allocation is reclaimed here.
+            self.current_payload_data.reserve(remaining_bytes);
+
+            let bytes = self.buffer.split_to(length);
+            self.current_payload_data.extend_from_slice(&bytes[..]);
+
+            // Check if this completes the message
+            if self.current_payload_data.len() == message_length {
+                self.partial_message_bytes -= self.current_payload_reserved_length;
+                self.current_payload_reserved_length = 0;
+                self.current_payload.data = self.current_payload_data.split().freeze();
+
+                let payload = mem::replace(&mut self.current_payload, MessagePayload::new());
+                *message_to_return = Some(payload)
+            } else {
+                // Set the data aside until the next chunk on this chunk stream arrives
+                let partial_message = PartialMessage {
+                    data: mem::replace(&mut self.current_payload_data, BytesMut::new()),
+                    reserved_length: mem::replace(&mut self.current_payload_reserved_length, 0),
+                };
+
+                self.partial_messages.insert(self.current_header.chunk_stream_id, partial_message);
+            }
         }
 
         // This completes the current chunk, so cycle the header into the map and start a new one
@@ -394,6 +559,27 @@ impl ChunkDeserializer {
         self.current_stage = ParseStage::Csid;
         Ok(ParseStageResult::Success)
     }
+
+    fn start_partial_message(&mut self, message_length: usize) -> Result<(), ChunkDeserializationError> {
+        if self.partial_messages.len() >= self.limits.max_partial_messages {
+            let kind = ChunkDeserializationErrorKind::TooManyPartialMessages {max_partial_messages: self.limits.max_partial_messages};
+            return Err(ChunkDeserializationError {kind});
+        }
+
+        let buffered_bytes = self.buffer.len() + self.partial_message_bytes + message_length;
+        if buffered_bytes > self.limits.max_buffered_bytes {
+            let kind = ChunkDeserializationErrorKind::BufferedBytesLimitExceeded {
+                buffered_bytes,
+                max_buffered_bytes: self.limits.max_buffered_bytes,
+            };
+
+            return Err(ChunkDeserializationError {kind});
+        }
+
+        self.partial_message_bytes += message_length;
+        self.current_payload_reserved_length = message_length;
+        Ok(())
+    }
 }
 
 fn get_format(byte: &u8) -> ChunkHeaderFormat {
@@ -762,6 +948,182 @@ mod tests {
         assert_eq!(&payload.data[..], &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07], "Incorrect payload data");
     }
 
+    #[test]
+    fn single_chunk_message_data_is_not_copied_from_owned_bytes() {
+        let payload = [1_u8, 2_u8, 3_u8];
+        let bytes = Bytes::from(form_type_0_chunk(50, 25, 5, 3, &payload, INITIAL_MAX_CHUNK_SIZE));
+        let payload_start = bytes[bytes.len() - payload.len()..].as_ptr();
+
+        let mut deserializer = ChunkDeserializer::new();
+        let result = deserializer.get_next_message_from_bytes(bytes).unwrap().unwrap();
+
+        assert_eq!(&result.data[..], &payload[..], "Incorrect data");
+        assert_eq!(result.data.as_ptr(), payload_start, "Data was copied out of the input bytes");
+    }
+
+    #[test]
+    fn can_read_multiple_messages_from_owned_bytes() {
+        let mut chunks = form_type_0_chunk(50, 25, 5, 3, &[1, 2, 3, 4, 5, 6], 4);
+        chunks.extend(form_type_2_chunk(50, 10, &[7, 8, 9, 10]));
+        chunks.extend(form_type_3_chunk(50, &[11, 12], 4, None));
+
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(4).unwrap();
+        let first = deserializer.get_next_message_from_bytes(Bytes::from(chunks)).unwrap().unwrap();
+        let second = deserializer.get_next_message_from_bytes(Bytes::new()).unwrap().unwrap();
+        let third = deserializer.get_next_message_from_bytes(Bytes::new()).unwrap();
+
+        assert_eq!(&first.data[..], &[1, 2, 3, 4, 5, 6], "Incorrect first message data");
+        assert_eq!(&second.data[..], &[7, 8, 9, 10, 11, 12], "Incorrect second message data");
+        assert_eq!(second.timestamp, RtmpTimestamp::new(35), "Incorrect second message timestamp");
+        assert_eq!(third, None, "Expected no more messages");
+    }
+
+    #[test]
+    fn can_read_messages_interleaved_across_chunk_streams() {
+        let first = form_type_0_chunk(50, 25, 5, 3, &[1, 2, 3, 4], 2);
+        let second = form_type_0_chunk(51, 25, 5, 3, &[5, 6, 7, 8], 2);
+
+        // Send the first chunk of each message before the remaining chunks
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&first[..first.len() - 3]);
+        bytes.extend_from_slice(&second[..second.len() - 3]);
+        bytes.extend_from_slice(&first[first.len() - 3..]);
+        bytes.extend_from_slice(&second[second.len() - 3..]);
+
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(2).unwrap();
+        let result1 = deserializer.get_next_message(&bytes).unwrap().unwrap();
+        let result2 = deserializer.get_next_message(&[]).unwrap().unwrap();
+
+        assert_eq!(&result1.data[..], &[1, 2, 3, 4], "Incorrect first message data");
+        assert_eq!(&result2.data[..], &[5, 6, 7, 8], "Incorrect second message data");
+    }
+
+    #[test]
+    fn aborted_partial_message_is_discarded() {
+        let aborted = form_type_0_chunk(50, 25, 5, 3, &[1, 2, 3, 4], 2);
+        let next = form_type_0_chunk(50, 35, 5, 3, &[5, 6, 7], 2);
+
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(2).unwrap();
+        let result = deserializer.get_next_message(&aborted[..aborted.len() - 3]).unwrap();
+        assert_eq!(result, None, "Unexpected message");
+
+        deserializer.abort_message(50);
+        let result = deserializer.get_next_message(&next).unwrap().unwrap();
+
+        assert_eq!(&result.data[..], &[5, 6, 7], "Incorrect message data");
+        assert_eq!(result.timestamp, RtmpTimestamp::new(35), "Incorrect timestamp");
+        assert_eq!(deserializer.partial_message_bytes, 0, "Aborted message was still counted");
+    }
+
+    #[test]
+    fn error_when_message_length_exceeds_limit() {
+        let mut limits = ChunkDeserializerLimits::new();
+        limits.max_message_size = 2;
+
+        let bytes = form_type_0_chunk(50, 25, 5, 3, &[1, 2, 3], INITIAL_MAX_CHUNK_SIZE);
+        let mut deserializer = ChunkDeserializer::with_limits(limits);
+        let error = deserializer.get_next_message(&bytes).unwrap_err();
+
+        match error.kind {
+            ChunkDeserializationErrorKind::MessageTooLarge {message_length: 3, max_message_size: 2} => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_too_many_messages_are_partially_received() {
+        let mut limits = ChunkDeserializerLimits::new();
+        limits.max_partial_messages = 1;
+
+        let first = form_type_0_chunk(50, 25, 5, 3, &[1, 2, 3, 4], 2);
+        let second = form_type_0_chunk(51, 25, 5, 3, &[5, 6, 7, 8], 2);
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&first[..first.len() - 3]);
+        bytes.extend_from_slice(&second[..second.len() - 3]);
+
+        let mut deserializer = ChunkDeserializer::with_limits(limits);
+        deserializer.set_max_chunk_size(2).unwrap();
+        let error = deserializer.get_next_message(&bytes).unwrap_err();
+
+        match error.kind {
+            ChunkDeserializationErrorKind::TooManyPartialMessages {max_partial_messages: 1} => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_too_many_chunk_stream_ids_are_used() {
+        let mut limits = ChunkDeserializerLimits::new();
+        limits.max_chunk_stream_ids = 2;
+
+        let mut bytes = Vec::new();
+        for csid in 3..6 {
+            bytes.extend(form_type_0_chunk(csid, 25, 5, 3, &[1], INITIAL_MAX_CHUNK_SIZE));
+        }
+
+        let mut deserializer = ChunkDeserializer::with_limits(limits);
+        let _ = deserializer.get_next_message(&bytes).unwrap().unwrap();
+        let _ = deserializer.get_next_message(&[]).unwrap().unwrap();
+        let error = deserializer.get_next_message(&[]).unwrap_err();
+
+        match error.kind {
+            ChunkDeserializationErrorKind::TooManyChunkStreamIds {max_chunk_stream_ids: 2} => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_partial_message_would_exceed_buffered_bytes_limit() {
+        let mut limits = ChunkDeserializerLimits::new();
+        limits.max_buffered_bytes = 50;
+
+        let bytes = form_type_0_chunk(50, 25, 5, 3, &[0; 100], 10);
+        let mut deserializer = ChunkDeserializer::with_limits(limits);
+        deserializer.set_max_chunk_size(10).unwrap();
+        let error = deserializer.get_next_message(&bytes[..30]).unwrap_err();
+
+        match error.kind {
+            ChunkDeserializationErrorKind::BufferedBytesLimitExceeded {max_buffered_bytes: 50, ..} => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_input_would_exceed_buffered_bytes_limit() {
+        let mut limits = ChunkDeserializerLimits::new();
+        limits.max_buffered_bytes = 10;
+
+        let mut deserializer = ChunkDeserializer::with_limits(limits);
+        let error = deserializer.get_next_message(&[0; 11]).unwrap_err();
+
+        match error.kind {
+            ChunkDeserializationErrorKind::BufferedBytesLimitExceeded {buffered_bytes: 11, max_buffered_bytes: 10} => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn error_when_message_length_changes_mid_message() {
+        let bytes = [
+            0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x09, 0x01, 0x00, 0x00, 0x00, 0x01, 0x02,
+            0x43, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06, 0x09, 0x03, 0x04,
+            0xC3, 0x05, 0x06,
+        ];
+
+        let mut deserializer = ChunkDeserializer::new();
+        deserializer.set_max_chunk_size(2).unwrap();
+        let error = deserializer.get_next_message(&bytes).unwrap_err();
+
+        match error.kind {
+            ChunkDeserializationErrorKind::MessageLengthChangedMidMessage {csid: 3, previous_length: 4, new_length: 6} => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
     fn form_type_0_chunk(csid: u32, timestamp: u32, message_stream_id: u32, type_id: u8, payload: &[u8], max_chunk_length: usize) -> Vec<u8> {
         let mut cursor = Cursor::new(Vec::new());
         if csid < 64 {
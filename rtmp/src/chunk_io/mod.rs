@@ -59,7 +59,7 @@ mod serialization_errors;
 mod serializer;
 
 pub use self::deserialization_errors::{ChunkDeserializationError, ChunkDeserializationErrorKind};
-pub use self::deserializer::ChunkDeserializer;
+pub use self::deserializer::{ChunkDeserializer, ChunkDeserializerLimits};
 pub use self::serialization_errors::{ChunkSerializationError, ChunkSerializationErrorKind};
 pub use self::serializer::{ChunkSerializer, Packet};
 
@@ -70,6 +70,27 @@ impl ChunkSerializer {
         Ok(packet)
     }
 
+    /// Creates an `Abort` message telling the peer to discard the partially sent message that
+    /// had the specified message type id.
+    ///
+    /// This is used when only part of a packet's bytes were sent (for example the connection
+    /// became backed up and the rest of a video packet was dropped).  The next message of the
+    /// same type will always be serialized with a full chunk header, so the peer does not have
+    /// to rely on the aborted chunks to read it.  The returned packet *must* be sent and cannot
+    /// be ignored.
+    pub fn abort_message(
+        &mut self,
+        message_type_id: u8,
+        time: RtmpTimestamp,
+    ) -> Result<Packet, ChunkSerializationError> {
+        let csid = get_csid_for_message_type(message_type_id);
+        self.previous_headers.remove(&csid);
+
+        let abort_message = RtmpMessage::Abort { stream_id: csid };
+        let message_payload = MessagePayload::from_rtmp_message(abort_message, time, 0)?;
+        self.serialize(&message_payload, true, false)
+    }
+
     /// Turns an RTMP message payload into binary data (representing RTMP chunks) that can be
     /// sent over the network.
     ///
@@ -303,10 +324,10 @@ fn add_message_payload(bytes: &mut dyn Write, data: &[u8]) -> Result<(), ChunkSe
     Ok(())
 }
 
+#[rustfmt::skip]
 fn get_csid_for_message_type(message_type_id: u8) -> u32 {
     // Naive resolution, purpose (afaik) is to allow repeated messages
     // to utilize header compression by spreading them across chunk streams
-    #[rustfmt::skip]
     match message_type_id {
         1..=6   => 2,
         18 | 19 => 3,
@@ -1098,4 +1119,38 @@ mod tests {
             "Unexpected payload contents"
         );
     }
+
+    #[test]
+    fn abort_message_uses_chunk_stream_of_message_type_and_resets_its_header() {
+        let message = MessagePayload {
+            timestamp: RtmpTimestamp::new(72),
+            type_id: 9,
+            message_stream_id: 12,
+            data: Bytes::from(vec![1, 2, 3, 4]),
+        };
+
+        let mut serializer = ChunkSerializer::new();
+        let _ = serializer.serialize(&message, false, false).unwrap();
+        let abort_packet = serializer
+            .abort_message(9, RtmpTimestamp::new(80))
+            .unwrap();
+        let next_packet = serializer.serialize(&message, false, false).unwrap();
+
+        let mut cursor = Cursor::new(abort_packet.bytes);
+        assert_eq!(cursor.read_u8().unwrap(), 2, "Unexpected abort csid");
+        let _ = cursor.read_u24::<BigEndian>().unwrap();
+        assert_eq!(cursor.read_u24::<BigEndian>().unwrap(), 4, "Unexpected abort length");
+        assert_eq!(cursor.read_u8().unwrap(), 2, "Unexpected abort type id");
+        let _ = cursor.read_u32::<LittleEndian>().unwrap();
+        assert_eq!(
+            cursor.read_u32::<BigEndian>().unwrap(),
+            4,
+            "Abort did not reference the video chunk stream"
+        );
+
+        assert_eq!(
+            next_packet.bytes[0], 4,
+            "Message after abort did not use a type 0 chunk"
+        );
+    }
 }
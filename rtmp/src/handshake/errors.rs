@@ -15,10 +15,16 @@ pub struct HandshakeError {
 #[derive(Debug, Fail)]
 pub enum HandshakeErrorKind {
     /// The RTMP specification requires the first byte in the handshake process to start with a
-    /// 3, so this error is encountered if any other value is in the first byte.
-    #[fail(display = "First byte of the handshake did not start with a 3")]
+    /// 3 (or a 6 or 8 for RTMPE), so this error is encountered if any other value is in the first
+    /// byte.
+    #[fail(display = "First byte of the handshake did not start with a 3, 6 or 8")]
     BadVersionId,
 
+    /// This occurs when the peer's packet 0 requested a different type of encryption than the
+    /// one we are using (e.g. we already sent an unencrypted packet 0 but the peer wants RTMPE).
+    #[fail(display = "Peer's packet 0 did not match the negotiated encryption type")]
+    EncryptionTypeMismatch,
+
     /// The RTMP specification requires the 2nd set of 4 bytes to all be zeroes, so this error
     /// is encountered if any of those values are not zeros.
     #[fail(display = "Packet 1's 2nd time field was expected to be empty, but wasn't")]
@@ -50,6 +56,16 @@ pub enum HandshakeErrorKind {
     #[fail(display = "Invalid handshake packet 2 received")]
     InvalidP2Packet,
 
+    /// This occurs when strict verification is enabled and the peer's packet 1 did not contain a
+    /// valid hmac digest under either of the known digest schemes.
+    #[fail(display = "Packet 1 digest could not be verified with any known digest scheme")]
+    DigestVerificationFailed,
+
+    /// This occurs when the Diffie-Hellman public key sent by an RTMPE peer is outside of the
+    /// valid range, and thus can not be used to negotiate encryption keys.
+    #[fail(display = "Peer sent an invalid Diffie-Hellman public key")]
+    InvalidDhPublicKey,
+
     /// This occurs when an IO error is encountered while reading the input.
     #[fail(display = "_0")]
     Io(#[cause] io::Error),
@@ -19,14 +19,29 @@ exactly and verifies that bytes 4-7 are zeroes.  At this point in time any (with
 of h.264 video) all clients and servers should work against the fp9 method so this should not
 be an issue.
 
-**Note:** At this point of time we only accept (and send) command bytes of 3, meaning that
-no encryption is used.
+By default command bytes of 3 are sent, meaning that no encryption is used.  Encrypted RTMPE
+handshakes (command bytes 6 and 8) are also supported.  Clients opt into RTMPE via
+`Handshake::set_encryption()` while servers answer with the command byte the client requested.
+RTMPE peers exchange Diffie-Hellman public keys as part of their fp9 packet 1, and once the
+handshake completes all further traffic must be passed through the `RtmpeCipher` returned by
+`Handshake::take_cipher()`.  Command byte 8 handshakes additionally obfuscate the packet 2
+signatures with XTEA.  The variant that obfuscates them with Blowfish (command byte 9) is not
+supported.
+
+By default the hmac digests sent by the peer are only used to detect whether the fp9 handshake
+is being used.  Strict verification can be enabled via `Handshake::set_strict_verification()`,
+which causes the handshake to fail if the peer's packet 1 digest cannot be validated against either
+known digest scheme, and causes clients to also validate the server's packet 2 signature.
 
 */
 
 mod errors;
+mod rtmpe;
 
 pub use self::errors::{HandshakeError, HandshakeErrorKind};
+pub use self::rtmpe::RtmpeCipher;
+
+use self::rtmpe::{obfuscate_signature, DhKeyPair, DH_KEY_LENGTH};
 
 use hmac::{Hmac, Mac, NewMac};
 use rand;
@@ -43,6 +58,10 @@ const RANDOM_CRUD: [u8; 32] = [
 ];
 const GENUINE_FMS_CONST: &str = "Genuine Adobe Flash Media Server 001";
 const GENUINE_FP_CONST: &str = "Genuine Adobe Flash Player 001";
+const DEFAULT_VERSION: (u8, u8, u8, u8) = (128, 0, 7, 2); // Copied from jw player handshake
+const PLAIN_VERSION_ID: u8 = 3;
+const RTMPE_VERSION_ID: u8 = 6;
+const RTMPE_XTEA_VERSION_ID: u8 = 8;
 
 /// Contains the result after processing bytes for the handshaking process
 #[derive(PartialEq, Eq, Debug)]
@@ -63,6 +82,85 @@ pub enum HandshakeProcessResult {
     },
 }
 
+/// The position of the digest within a fp9 packet 1.
+///
+/// The unofficial specification describes two schemes for placing the digest, with no known reason
+/// for why a peer would use one over the other.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum DigestScheme {
+    /// The digest offset is calculated from bytes 8-11, placing the digest in the first half of
+    /// the packet.
+    Scheme0,
+
+    /// The digest offset is calculated from bytes 772-775, placing the digest in the second half
+    /// of the packet.
+    Scheme1,
+}
+
+/// The method the peer used for its packet 1
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum HandshakeMethod {
+    /// The original handshake from the RTMP specification, where packet 1 only contains
+    /// random data that must be echoed back
+    Simple,
+
+    /// The Flash Player 9 handshake, where packet 1 contains an hmac digest
+    Digest,
+}
+
+/// Information about the peer gathered from its packet 1.  This can be used to fingerprint
+/// the software on the other end of the connection.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct HandshakeInfo {
+    /// The epoch (time field) the peer sent in its packet 1
+    pub peer_epoch: u32,
+
+    /// The version field the peer sent in its packet 1 (e.g. `(9, 0, 124, 2)` for FFmpeg).
+    /// Peers using the simple handshake usually send all zeroes.
+    pub peer_version: (u8, u8, u8, u8),
+
+    /// Whether the peer used the simple or the fp9 digest handshake
+    pub method: HandshakeMethod,
+
+    /// The scheme the peer's digest was found with, if the digest handshake was used
+    pub digest_scheme: Option<DigestScheme>,
+}
+
+/// The type of encryption being negotiated by the handshake, as signaled by the command byte of
+/// packet 0.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum EncryptionType {
+    /// Command byte 3, traffic is not encrypted
+    None,
+
+    /// Command byte 6, traffic is encrypted with RC4 keys derived from a Diffie-Hellman key
+    /// exchange performed during the handshake
+    Rtmpe,
+
+    /// Command byte 8, traffic is encrypted the same way as `Rtmpe` but the packet 2 signatures
+    /// are also encrypted with XTEA
+    RtmpeXtea,
+}
+
+impl EncryptionType {
+    fn version_id(self) -> u8 {
+        match self {
+            EncryptionType::None => PLAIN_VERSION_ID,
+            EncryptionType::Rtmpe => RTMPE_VERSION_ID,
+            EncryptionType::RtmpeXtea => RTMPE_XTEA_VERSION_ID,
+        }
+    }
+
+    fn from_version_id(version_id: u8) -> Option<EncryptionType> {
+        match version_id {
+            PLAIN_VERSION_ID => Some(EncryptionType::None),
+            RTMPE_VERSION_ID => Some(EncryptionType::Rtmpe),
+            RTMPE_XTEA_VERSION_ID => Some(EncryptionType::RtmpeXtea),
+            _ => None,
+        }
+    }
+}
+
 /// The type of peer being represented by the handshake.
 ///
 /// This only matters due to the FP9 handshaking process, where the client and server use different
@@ -93,10 +191,10 @@ enum Stage {
 
 /// Struct that handles the handshaking process.
 ///
-/// It should be noted that the current system does not perform validation on the peer's p2 packet.
-/// This is due to the complicated hmac verification.  While this verification was successful when
-/// tested agains OBS, Ffmpeg, Mplayer, and Evostream, but for some reason Flash clients would fail
-/// the hmac verification.
+/// It should be noted that by default the system does not perform validation on the peer's p2
+/// packet.  This is due to the complicated hmac verification.  While this verification was
+/// successful when tested agains OBS, Ffmpeg, Mplayer, and Evostream, but for some reason Flash
+/// clients would fail the hmac verification.
 ///
 /// Due to the documentation on the fp9 handshake being third party, the hmac verification was
 /// removed.  It is now assumed that as long as the peer sent us a p2 packet, and they did not
@@ -104,6 +202,11 @@ enum Stage {
 /// allowed us to succeed in handshaking with flash players, and there are still enough checks that
 /// it should be unlikely for too many false positives.
 ///
+/// When stricter checks are desired (e.g. to reject garbage or probing connections before any
+/// session is set up) strict verification can be turned on via `set_strict_verification()`.  In
+/// strict mode the peer's packet 1 must contain a valid digest under one of the known digest
+/// schemes, and clients additionally validate the signature of the server's packet 2.
+///
 /// ## Examples
 ///
 /// ```
@@ -142,6 +245,14 @@ pub struct Handshake {
     input_buffer: Vec<u8>,
     sent_p1: [u8; RTMP_PACKET_SIZE],
     sent_digest: [u8; SHA256_DIGEST_LENGTH],
+    strict_verification: bool,
+    digest_scheme: Option<DigestScheme>,
+    encryption: EncryptionType,
+    dh_key_pair: Option<DhKeyPair>,
+    cipher: Option<RtmpeCipher>,
+    outbound_version: (u8, u8, u8, u8),
+    outbound_epoch: u32,
+    peer_info: Option<HandshakeInfo>,
 }
 
 impl Handshake {
@@ -158,7 +269,80 @@ impl Handshake {
             sent_p1: [0_u8; RTMP_PACKET_SIZE],
             peer_type,
             sent_digest: [0_u8; SHA256_DIGEST_LENGTH],
+            strict_verification: false,
+            digest_scheme: None,
+            encryption: EncryptionType::None,
+            dh_key_pair: None,
+            cipher: None,
+            outbound_version: DEFAULT_VERSION,
+            outbound_epoch: 0,
+            peer_info: None,
+        }
+    }
+
+    /// Sets the version field sent in our packet 1, allowing us to identify as a specific
+    /// flash player or media server version.  Defaults to `(128, 0, 7, 2)`.
+    ///
+    /// This must be called prior to generating the outbound packets 0 and 1.
+    pub fn set_outbound_version(&mut self, version: (u8, u8, u8, u8)) {
+        self.outbound_version = version;
+    }
+
+    /// Sets the epoch (time field) sent in our packet 1.  Defaults to zero.
+    ///
+    /// This must be called prior to generating the outbound packets 0 and 1.
+    pub fn set_outbound_epoch(&mut self, epoch: u32) {
+        self.outbound_epoch = epoch;
+    }
+
+    /// Returns the information gathered about the peer during the handshake.  This is only
+    /// available once the handshake has completed.
+    pub fn get_info(&self) -> Option<&HandshakeInfo> {
+        if self.current_stage != Stage::Complete {
+            return None;
+        }
+
+        self.peer_info.as_ref()
+    }
+
+    /// Sets the type of encryption to request from the peer.  This only needs to be called by
+    /// clients, as servers will automatically use the type of encryption the client requested
+    /// (unless packets 0 and 1 were already generated before the client's packet 0 was received).
+    ///
+    /// This must be called prior to generating the outbound packets 0 and 1.
+    pub fn set_encryption(&mut self, encryption: EncryptionType) {
+        self.encryption = encryption;
+    }
+
+    /// Takes the RC4 transform negotiated by a completed RTMPE handshake.  All bytes exchanged
+    /// with the peer after the handshake must be passed through this cipher.  The
+    /// `remaining_bytes` returned upon completion of the handshake have already been decrypted.
+    ///
+    /// Returns `None` if the handshake has not completed yet, if no encryption was negotiated,
+    /// or if the cipher was already taken.
+    pub fn take_cipher(&mut self) -> Option<RtmpeCipher> {
+        if self.current_stage != Stage::Complete {
+            return None;
         }
+
+        self.cipher.take()
+    }
+
+    /// Enables or disables strict hmac digest verification of the peer's packets.
+    ///
+    /// When enabled, a packet 1 that does not contain a valid digest under either digest scheme
+    /// results in a `DigestVerificationFailed` error instead of falling back to the original
+    /// RTMP specification handshake.  Clients will also verify the signature of the server's
+    /// packet 2, and fail with an `InvalidP2Packet` error if it does not match.
+    pub fn set_strict_verification(&mut self, strict: bool) {
+        self.strict_verification = strict;
+    }
+
+    /// Returns the digest scheme the peer's packet 1 was validated with.  This will be `None`
+    /// if the peer's packet 1 has not been received yet or if the peer used the original RTMP
+    /// specification handshake.
+    pub fn get_digest_scheme(&self) -> Option<DigestScheme> {
+        self.digest_scheme
     }
 
     /// Creates the packets 0 and 1 that should get sent to the peer.  This is only strictly
@@ -166,18 +350,34 @@ impl Handshake {
     /// process.  The server can wait until `process_bytes()` is called, and the outbound
     /// packets #0 and #1 will be included as the handshake's response.
     ///
-    /// For now this only sends a command byte of 3 (no encryption).
+    /// This sends a command byte of 3 (no encryption) unless RTMPE encryption was requested, in
+    /// which case our Diffie-Hellman public key is embedded in packet 1.
     pub fn generate_outbound_p0_and_p1(&mut self) -> Result<Vec<u8>, HandshakeError> {
-        const ADOBE_VERSION: [u8; 4] = [128_u8, 0_u8, 7_u8, 2_u8]; // Copied from jw player handshake
-
-        // Leave time field as zero, version field as ADOBE_VERSION, and the rest of the packet
+        // Set the time and version fields to the configured values, and the rest of the packet
         // should be random data.  Part of the random data will be used to determine placement
         // of the digest offset
         fill_with_random_data(&mut self.sent_p1[8..1532]);
-        self.sent_p1[4] = ADOBE_VERSION[0];
-        self.sent_p1[5] = ADOBE_VERSION[1];
-        self.sent_p1[6] = ADOBE_VERSION[2];
-        self.sent_p1[7] = ADOBE_VERSION[3];
+        self.sent_p1[0..4].copy_from_slice(&self.outbound_epoch.to_be_bytes());
+        self.sent_p1[4] = self.outbound_version.0;
+        self.sent_p1[5] = self.outbound_version.1;
+        self.sent_p1[6] = self.outbound_version.2;
+        self.sent_p1[7] = self.outbound_version.3;
+
+        let scheme = match self.peer_type {
+            PeerType::Server => DigestScheme::Scheme1,
+            PeerType::Client => DigestScheme::Scheme0,
+        };
+
+        if self.encryption != EncryptionType::None {
+            // The public key has to be written prior to calculating the digest, since the
+            // digest covers the whole packet
+            let key_pair = DhKeyPair::generate();
+            let key_offset = get_dh_offset(&self.sent_p1, scheme) as usize;
+            self.sent_p1[key_offset..(key_offset + DH_KEY_LENGTH)]
+                .copy_from_slice(key_pair.public_key());
+
+            self.dh_key_pair = Some(key_pair);
+        }
 
         let (digest_offset, constant_key) = match self.peer_type {
             PeerType::Server => (get_server_digest_offset(&self.sent_p1), GENUINE_FMS_CONST),
@@ -194,7 +394,7 @@ impl Handshake {
         self.sent_p1[(digest_offset as usize)..(digest_offset as usize + SHA256_DIGEST_LENGTH)]
             .clone_from_slice(&self.sent_digest[..SHA256_DIGEST_LENGTH]);
 
-        let mut output = vec![3_u8];
+        let mut output = vec![self.encryption.version_id()];
         output.extend_from_slice(&self.sent_p1);
 
         self.current_stage = Stage::WaitingForPacket0;
@@ -224,12 +424,26 @@ impl Handshake {
         loop {
             let starting_stage = self.current_stage.clone();
             let result = match self.current_stage {
-                Stage::NeedToSendP0AndP1 => match self.generate_outbound_p0_and_p1() {
-                    Err(x) => Err(x),
-                    Ok(bytes) => Ok(HandshakeProcessResult::InProgress {
-                        response_bytes: bytes,
-                    }),
-                },
+                Stage::NeedToSendP0AndP1 => {
+                    // Servers need to respond with the same command byte the client requested
+                    if self.peer_type == PeerType::Server {
+                        let requested = self
+                            .input_buffer
+                            .first()
+                            .and_then(|x| EncryptionType::from_version_id(*x));
+
+                        if let Some(encryption) = requested {
+                            self.encryption = encryption;
+                        }
+                    }
+
+                    match self.generate_outbound_p0_and_p1() {
+                        Err(x) => Err(x),
+                        Ok(bytes) => Ok(HandshakeProcessResult::InProgress {
+                            response_bytes: bytes,
+                        }),
+                    }
+                }
                 Stage::WaitingForPacket0 => self.parse_p0(),
                 Stage::WaitingForPacket1 => self.parse_p1(),
                 Stage::WaitingForPacket2 => self.parse_p2(),
@@ -278,10 +492,18 @@ impl Handshake {
         }
 
         self.command_byte = self.input_buffer.remove(0);
-        if self.command_byte != 3_u8 {
-            return Err(HandshakeError {
-                kind: HandshakeErrorKind::BadVersionId,
-            });
+        match self.command_byte {
+            x if x == self.encryption.version_id() => (),
+            PLAIN_VERSION_ID | RTMPE_VERSION_ID | RTMPE_XTEA_VERSION_ID => {
+                return Err(HandshakeError {
+                    kind: HandshakeErrorKind::EncryptionTypeMismatch,
+                });
+            }
+            _ => {
+                return Err(HandshakeError {
+                    kind: HandshakeErrorKind::BadVersionId,
+                });
+            }
         };
 
         self.current_stage = Stage::WaitingForPacket1;
@@ -308,6 +530,20 @@ impl Handshake {
             received_packet_1 = handshake;
         }
 
+        let mut peer_epoch = [0_u8; 4];
+        peer_epoch.copy_from_slice(&received_packet_1[0..4]);
+        self.peer_info = Some(HandshakeInfo {
+            peer_epoch: u32::from_be_bytes(peer_epoch),
+            peer_version: (
+                received_packet_1[4],
+                received_packet_1[5],
+                received_packet_1[6],
+                received_packet_1[7],
+            ),
+            method: HandshakeMethod::Simple,
+            digest_scheme: None,
+        });
+
         // Test against the expected constant string the peer sent over
         let p1_key = match self.peer_type {
             PeerType::Server => GENUINE_FP_CONST.as_bytes().to_vec(),
@@ -315,7 +551,24 @@ impl Handshake {
         };
 
         let received_digest = match get_digest_for_received_packet(&received_packet_1, &p1_key) {
-            Ok(digest) => digest,
+            Ok((digest, scheme)) => {
+                self.digest_scheme = Some(scheme);
+                if let Some(ref mut info) = self.peer_info {
+                    info.method = HandshakeMethod::Digest;
+                    info.digest_scheme = Some(scheme);
+                }
+
+                digest
+            }
+            Err(HandshakeError {
+                kind: HandshakeErrorKind::UnknownPacket1Format,
+            }) if self.strict_verification || self.encryption != EncryptionType::None => {
+                // Encrypted handshakes can't fall back to the original handshake, since the
+                // peer's public key can only be located via the digest scheme
+                return Err(HandshakeError {
+                    kind: HandshakeErrorKind::DigestVerificationFailed,
+                });
+            }
             Err(HandshakeError {
                 kind: HandshakeErrorKind::UnknownPacket1Format,
             }) => {
@@ -337,6 +590,19 @@ impl Handshake {
             Err(x) => return Err(x),
         };
 
+        if let Some(ref key_pair) = self.dh_key_pair {
+            let scheme = self.digest_scheme.unwrap_or(DigestScheme::Scheme0);
+            let key_offset = get_dh_offset(&received_packet_1, scheme) as usize;
+            let peer_public_key = &received_packet_1[key_offset..(key_offset + DH_KEY_LENGTH)];
+            let shared_secret = key_pair.compute_shared_secret(peer_public_key)?;
+
+            self.cipher = Some(RtmpeCipher::new(
+                &shared_secret,
+                key_pair.public_key(),
+                peer_public_key,
+            ));
+        }
+
         // generate packet 2 for a response
         let mut output_packet = [0_u8; RTMP_PACKET_SIZE];
         fill_with_random_data(&mut output_packet);
@@ -349,7 +615,10 @@ impl Handshake {
         p2_key.extend_from_slice(&RANDOM_CRUD[..]);
 
         let hmac1 = calc_hmac(&received_digest, &p2_key[..]);
-        let hmac2 = calc_hmac(&output_packet[..P2_SIG_START_INDEX], &hmac1);
+        let mut hmac2 = calc_hmac(&output_packet[..P2_SIG_START_INDEX], &hmac1);
+        if self.encryption == EncryptionType::RtmpeXtea {
+            obfuscate_signature(&mut hmac2, &hmac1);
+        }
 
         // the hmac2 signature is written to the end of the p2 packet
         output_packet[P2_SIG_START_INDEX..(P2_SIG_START_INDEX + SHA256_DIGEST_LENGTH)]
@@ -378,10 +647,11 @@ impl Handshake {
         }
 
         // If the peer sent back a p2 that is an exact copy of our p1, accept it as that mean's it
-        // is the old style handshake
-        if self.sent_p1[..] == received_packet_2[..] {
+        // is the old style handshake.  Strict verification never allows the old style handshake,
+        // so an echoed p1 has to pass the signature check like any other p2.
+        if !self.strict_verification && self.sent_p1[..] == received_packet_2[..] {
             self.current_stage = Stage::Complete;
-            let remaining_bytes = self.input_buffer.drain(..).collect();
+            let remaining_bytes = self.take_remaining_bytes();
             return Ok(HandshakeProcessResult::Completed {
                 response_bytes: Vec::new(),
                 remaining_bytes,
@@ -396,33 +666,47 @@ impl Handshake {
 
         peer_key.extend_from_slice(&RANDOM_CRUD[..]);
 
-        // TODO: Re-enable P2 verification.
-        // Verification of packet 2 had to be commented out for flash players to work.  For some
+        // Verification of packet 2 is only performed by clients in strict mode.  For some
         // reason flash players are failing the p2 validation even though VLC, ffmpeg, and others
-        // are handshaking just fine.  For now I am just going to assume that the p2 they sent
-        // us is fine if they don't disconnect after we sent them our p2, and can look at this
-        // later if there's a reason to really care.
+        // are handshaking just fine.  So by default we assume that the p2 they sent us is fine
+        // if they don't disconnect after we sent them our p2.
+        if self.strict_verification && self.peer_type == PeerType::Client {
+            let expected_hmac = &received_packet_2[P2_SIG_START_INDEX..RTMP_PACKET_SIZE];
+            let hmac1 = calc_hmac(&self.sent_digest, &peer_key[..]);
+            let mut hmac2 = calc_hmac(&received_packet_2[..P2_SIG_START_INDEX], &hmac1);
+            if self.encryption == EncryptionType::RtmpeXtea {
+                obfuscate_signature(&mut hmac2, &hmac1);
+            }
 
-        //let expected_hmac = &received_packet_2[P2_SIG_START_INDEX..RTMP_PACKET_SIZE];
-        //let hmac1 = calc_hmac(&self.sent_digest, &peer_key[..]);
-        //let hmac2 = calc_hmac(&received_packet_2[..P2_SIG_START_INDEX], &hmac1);
-        //if &expected_hmac[..] != &hmac2[..] {
-        //    return Err(HandshakeError{kind: HandshakeErrorKind::InvalidP2Packet});
-        //}
+            if expected_hmac[..] != hmac2[..] {
+                return Err(HandshakeError {
+                    kind: HandshakeErrorKind::InvalidP2Packet,
+                });
+            }
+        }
 
         self.current_stage = Stage::Complete;
-        let bytes_left = self.input_buffer.drain(..).collect();
+        let bytes_left = self.take_remaining_bytes();
         Ok(HandshakeProcessResult::Completed {
             response_bytes: Vec::new(),
             remaining_bytes: bytes_left,
         })
     }
+
+    fn take_remaining_bytes(&mut self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.input_buffer.drain(..).collect();
+        if let Some(ref mut cipher) = self.cipher {
+            cipher.decrypt(&mut bytes);
+        }
+
+        bytes
+    }
 }
 
 fn get_digest_for_received_packet(
     packet: &[u8; RTMP_PACKET_SIZE],
     key: &[u8],
-) -> Result<[u8; SHA256_DIGEST_LENGTH], HandshakeError> {
+) -> Result<([u8; SHA256_DIGEST_LENGTH], DigestScheme), HandshakeError> {
     // According to the unofficial specification, peers may send messages with the digest pointer
     // either at index 8 or 772 with no known reason for why one would be used over the other.  For
     // the best compatibility just try both.
@@ -436,8 +720,8 @@ fn get_digest_for_received_packet(
     let v2_hmac = calc_hmac_from_parts(&v2_parts.before_digest, &v2_parts.after_digest, key);
 
     match true {
-        _ if v1_hmac == v1_parts.digest => Ok(v1_parts.digest),
-        _ if v2_hmac == v2_parts.digest => Ok(v2_parts.digest),
+        _ if v1_hmac == v1_parts.digest => Ok((v1_parts.digest, DigestScheme::Scheme0)),
+        _ if v2_hmac == v2_parts.digest => Ok((v2_parts.digest, DigestScheme::Scheme1)),
         _ => Err(HandshakeError {
             kind: HandshakeErrorKind::UnknownPacket1Format,
         }),
@@ -456,6 +740,25 @@ fn get_client_digest_offset(data: &[u8; RTMP_PACKET_SIZE]) -> u32 {
     (first_four_byte_sum % 728) + 12 // offset
 }
 
+fn get_dh_offset(data: &[u8; RTMP_PACKET_SIZE], scheme: DigestScheme) -> u32 {
+    // The public key is placed in the half of the packet that does not contain the digest
+    match scheme {
+        DigestScheme::Scheme0 => {
+            let sum = (data[1532] as u32)
+                + (data[1533] as u32)
+                + (data[1534] as u32)
+                + (data[1535] as u32);
+            (sum % 632) + 772
+        }
+
+        DigestScheme::Scheme1 => {
+            let sum =
+                (data[768] as u32) + (data[769] as u32) + (data[770] as u32) + (data[771] as u32);
+            (sum % 632) + 8
+        }
+    }
+}
+
 fn get_message_parts(
     handshake: &[u8; RTMP_PACKET_SIZE],
     digest_offset: u32,
@@ -958,4 +1261,425 @@ mod tests {
         assert_eq!(client_offset_2, 234, "Bad client offset #2");
         assert_eq!(server_offset_2, 1153, "Bad server offset #2");
     }
+
+    #[test]
+    fn strict_handshake_with_itself_reports_digest_schemes() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+        client.set_strict_verification(true);
+        server.set_strict_verification(true);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        let s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        let c2 = match client.process_bytes(&s0_s1_and_s2[..]) {
+            Ok(HandshakeProcessResult::Completed {
+                response_bytes: bytes,
+                remaining_bytes: _,
+            }) => bytes,
+            x => panic!("Unexpected s0_s1_and_s2 process_bytes response: {:?}", x),
+        };
+
+        match server.process_bytes(&c2[..]) {
+            Ok(HandshakeProcessResult::Completed {
+                response_bytes: _,
+                remaining_bytes: _,
+            }) => {}
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        }
+
+        assert_eq!(server.get_digest_scheme(), Some(DigestScheme::Scheme0));
+        assert_eq!(client.get_digest_scheme(), Some(DigestScheme::Scheme1));
+    }
+
+    #[test]
+    fn strict_handshake_accepts_jw_player_p1() {
+        let mut handshake = Handshake::new(PeerType::Server);
+        handshake.set_strict_verification(true);
+
+        let mut input = JWPLAYER_C0.to_vec();
+        input.extend_from_slice(&JWPLAYER_C1);
+        match handshake.process_bytes(&input) {
+            Ok(HandshakeProcessResult::InProgress { response_bytes: _ }) => {}
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        }
+
+        assert_eq!(handshake.current_stage, Stage::WaitingForPacket2);
+        assert_eq!(handshake.get_digest_scheme(), Some(DigestScheme::Scheme1));
+    }
+
+    #[test]
+    fn strict_handshake_rejects_p1_without_valid_digest() {
+        let mut c0_and_c1 = [0_u8; RTMP_PACKET_SIZE + 1];
+        c0_and_c1[0] = 3;
+        fill_with_random_data(&mut c0_and_c1[9..RTMP_PACKET_SIZE + 1]);
+
+        let mut handshake = Handshake::new(PeerType::Server);
+        handshake.set_strict_verification(true);
+
+        match handshake.process_bytes(&c0_and_c1) {
+            Err(HandshakeError {
+                kind: HandshakeErrorKind::DigestVerificationFailed,
+            }) => {}
+            x => panic!("Expected DigestVerificationFailed, got {:?}", x),
+        }
+
+        assert_eq!(handshake.get_digest_scheme(), None);
+    }
+
+    #[test]
+    fn strict_client_rejects_s2_with_invalid_signature() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+        client.set_strict_verification(true);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        let mut s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        let last_index = s0_s1_and_s2.len() - 1;
+        s0_s1_and_s2[last_index] ^= 0xff;
+
+        match client.process_bytes(&s0_s1_and_s2[..]) {
+            Err(HandshakeError {
+                kind: HandshakeErrorKind::InvalidP2Packet,
+            }) => {}
+            x => panic!("Expected InvalidP2Packet, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn strict_client_rejects_s2_that_echoes_c1() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+        client.set_strict_verification(true);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        let mut s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        let s2_start = s0_s1_and_s2.len() - RTMP_PACKET_SIZE;
+        s0_s1_and_s2[s2_start..].copy_from_slice(&c0_and_c1[1..]);
+
+        match client.process_bytes(&s0_s1_and_s2[..]) {
+            Err(HandshakeError {
+                kind: HandshakeErrorKind::InvalidP2Packet,
+            }) => {}
+            x => panic!("Expected InvalidP2Packet, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_perform_rtmpe_handshake_with_itself() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+        client.set_encryption(EncryptionType::Rtmpe);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        assert_eq!(c0_and_c1[0], 6, "Expected an RTMPE c0");
+
+        let s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        assert_eq!(s0_s1_and_s2[0], 6, "Expected an RTMPE s0");
+
+        let c2 = match client.process_bytes(&s0_s1_and_s2[..]) {
+            Ok(HandshakeProcessResult::Completed {
+                response_bytes: bytes,
+                remaining_bytes: _,
+            }) => bytes,
+            x => panic!("Unexpected s0_s1_and_s2 process_bytes response: {:?}", x),
+        };
+
+        let mut client_cipher = client.take_cipher().expect("No client cipher returned");
+        let mut encrypted = b"first chunk".to_vec();
+        client_cipher.encrypt(&mut encrypted);
+
+        let mut input = c2.clone();
+        input.extend_from_slice(&encrypted);
+        let remaining_bytes = match server.process_bytes(&input[..]) {
+            Ok(HandshakeProcessResult::Completed {
+                response_bytes: _,
+                remaining_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        assert_eq!(&remaining_bytes[..], &b"first chunk"[..]);
+
+        let mut server_cipher = server.take_cipher().expect("No server cipher returned");
+        let mut data = b"response chunk".to_vec();
+        server_cipher.encrypt(&mut data);
+        client_cipher.decrypt(&mut data);
+        assert_eq!(&data[..], &b"response chunk"[..]);
+
+        assert!(server.take_cipher().is_none(), "Cipher should only be taken once");
+    }
+
+    #[test]
+    fn unencrypted_handshake_has_no_cipher() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        let s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        match client.process_bytes(&s0_s1_and_s2[..]) {
+            Ok(HandshakeProcessResult::Completed { .. }) => {}
+            x => panic!("Unexpected s0_s1_and_s2 process_bytes response: {:?}", x),
+        }
+
+        assert!(client.take_cipher().is_none(), "Expected no cipher");
+    }
+
+    #[test]
+    fn encryption_mismatch_when_server_already_sent_unencrypted_p0() {
+        let mut handshake = Handshake::new(PeerType::Server);
+        handshake.generate_outbound_p0_and_p1().unwrap();
+
+        match handshake.process_bytes(&[6_u8]) {
+            Err(HandshakeError {
+                kind: HandshakeErrorKind::EncryptionTypeMismatch,
+            }) => {}
+            x => panic!("Expected EncryptionTypeMismatch, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn encryption_mismatch_when_rtmpe_client_receives_unencrypted_p0() {
+        let mut handshake = Handshake::new(PeerType::Client);
+        handshake.set_encryption(EncryptionType::Rtmpe);
+        handshake.generate_outbound_p0_and_p1().unwrap();
+
+        match handshake.process_bytes(&[3_u8]) {
+            Err(HandshakeError {
+                kind: HandshakeErrorKind::EncryptionTypeMismatch,
+            }) => {}
+            x => panic!("Expected EncryptionTypeMismatch, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_perform_strict_rtmpe_xtea_handshake_with_itself() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+        client.set_encryption(EncryptionType::RtmpeXtea);
+        client.set_strict_verification(true);
+        server.set_strict_verification(true);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        assert_eq!(c0_and_c1[0], 8, "Expected an RTMPE type 8 c0");
+
+        let s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        assert_eq!(s0_s1_and_s2[0], 8, "Expected an RTMPE type 8 s0");
+
+        let c2 = match client.process_bytes(&s0_s1_and_s2[..]) {
+            Ok(HandshakeProcessResult::Completed {
+                response_bytes: bytes,
+                remaining_bytes: _,
+            }) => bytes,
+            x => panic!("Unexpected s0_s1_and_s2 process_bytes response: {:?}", x),
+        };
+
+        match server.process_bytes(&c2[..]) {
+            Ok(HandshakeProcessResult::Completed { .. }) => {}
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        }
+
+        let mut client_cipher = client.take_cipher().expect("No client cipher returned");
+        let mut server_cipher = server.take_cipher().expect("No server cipher returned");
+        let mut data = b"some chunk".to_vec();
+        client_cipher.encrypt(&mut data);
+        server_cipher.decrypt(&mut data);
+        assert_eq!(&data[..], &b"some chunk"[..]);
+    }
+
+    #[test]
+    fn strict_rtmpe_xtea_client_rejects_s2_without_obfuscated_signature() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+        client.set_encryption(EncryptionType::RtmpeXtea);
+        client.set_strict_verification(true);
+
+        // The server answers as a type 6 server would, but with a type 8 command byte
+        let mut c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        c0_and_c1[0] = 6;
+        let mut s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        s0_s1_and_s2[0] = 8;
+        match client.process_bytes(&s0_s1_and_s2[..]) {
+            Err(HandshakeError {
+                kind: HandshakeErrorKind::InvalidP2Packet,
+            }) => {}
+            x => panic!("Expected InvalidP2Packet, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn bad_version_for_unsupported_rtmpe_variant() {
+        let mut handshake = Handshake::new(PeerType::Server);
+
+        match handshake.process_bytes(&[9_u8]) {
+            Err(HandshakeError {
+                kind: HandshakeErrorKind::BadVersionId,
+            }) => {}
+            x => panic!("Expected BadVersionId, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn handshake_info_contains_configured_outbound_version_and_epoch() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+        client.set_outbound_version((9, 0, 124, 2));
+        client.set_outbound_epoch(1234);
+        server.set_outbound_version((3, 5, 7, 1));
+        server.set_outbound_epoch(5678);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        let s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        assert!(server.get_info().is_none(), "Info available before completion");
+
+        let c2 = match client.process_bytes(&s0_s1_and_s2[..]) {
+            Ok(HandshakeProcessResult::Completed {
+                response_bytes: bytes,
+                remaining_bytes: _,
+            }) => bytes,
+            x => panic!("Unexpected s0_s1_and_s2 process_bytes response: {:?}", x),
+        };
+
+        match server.process_bytes(&c2[..]) {
+            Ok(HandshakeProcessResult::Completed { .. }) => {}
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        }
+
+        assert_eq!(
+            server.get_info(),
+            Some(&HandshakeInfo {
+                peer_epoch: 1234,
+                peer_version: (9, 0, 124, 2),
+                method: HandshakeMethod::Digest,
+                digest_scheme: Some(DigestScheme::Scheme0),
+            })
+        );
+
+        assert_eq!(
+            client.get_info(),
+            Some(&HandshakeInfo {
+                peer_epoch: 5678,
+                peer_version: (3, 5, 7, 1),
+                method: HandshakeMethod::Digest,
+                digest_scheme: Some(DigestScheme::Scheme1),
+            })
+        );
+    }
+
+    #[test]
+    fn handshake_info_from_jw_player_p1() {
+        let mut handshake = Handshake::new(PeerType::Server);
+        let s0_and_s1 = handshake.generate_outbound_p0_and_p1().unwrap();
+
+        let mut input = JWPLAYER_C0.to_vec();
+        input.extend_from_slice(&JWPLAYER_C1);
+        input.extend_from_slice(&s0_and_s1[1..]);
+        match handshake.process_bytes(&input) {
+            Ok(HandshakeProcessResult::Completed { .. }) => {}
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        }
+
+        let info = handshake.get_info().expect("No handshake info available");
+        assert_eq!(info.peer_epoch, 0x00126cbb, "Unexpected peer epoch");
+        assert_eq!(info.peer_version, (128, 0, 7, 2), "Unexpected peer version");
+        assert_eq!(info.method, HandshakeMethod::Digest, "Unexpected method");
+        assert_eq!(info.digest_scheme, Some(DigestScheme::Scheme1));
+    }
+
+    #[test]
+    fn handshake_info_from_simple_handshake() {
+        let mut c0_and_c1 = [0_u8; RTMP_PACKET_SIZE + 1];
+        c0_and_c1[0] = 3;
+        c0_and_c1[4] = 42;
+        fill_with_random_data(&mut c0_and_c1[9..RTMP_PACKET_SIZE + 1]);
+
+        let mut handshake = Handshake::new(PeerType::Server);
+        let s0_and_s1 = handshake.generate_outbound_p0_and_p1().unwrap();
+
+        let mut input = c0_and_c1.to_vec();
+        input.extend_from_slice(&s0_and_s1[1..]);
+        match handshake.process_bytes(&input) {
+            Ok(HandshakeProcessResult::Completed { .. }) => {}
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        }
+
+        assert_eq!(
+            handshake.get_info(),
+            Some(&HandshakeInfo {
+                peer_epoch: 42,
+                peer_version: (0, 0, 0, 0),
+                method: HandshakeMethod::Simple,
+                digest_scheme: None,
+            })
+        );
+    }
+
+    #[test]
+    fn non_strict_client_accepts_s2_with_invalid_signature() {
+        let mut client = Handshake::new(PeerType::Client);
+        let mut server = Handshake::new(PeerType::Server);
+
+        let c0_and_c1 = client.generate_outbound_p0_and_p1().unwrap();
+        let mut s0_s1_and_s2 = match server.process_bytes(&c0_and_c1[..]) {
+            Ok(HandshakeProcessResult::InProgress {
+                response_bytes: bytes,
+            }) => bytes,
+            x => panic!("Unexpected process_bytes response: {:?}", x),
+        };
+
+        let last_index = s0_s1_and_s2.len() - 1;
+        s0_s1_and_s2[last_index] ^= 0xff;
+
+        match client.process_bytes(&s0_s1_and_s2[..]) {
+            Ok(HandshakeProcessResult::Completed { .. }) => {}
+            x => panic!("Unexpected s0_s1_and_s2 process_bytes response: {:?}", x),
+        }
+    }
 }
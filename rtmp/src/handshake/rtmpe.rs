@@ -0,0 +1,349 @@
+//! Cryptographic primitives used by the RTMPE (encrypted RTMP) handshake.
+//!
+//! RTMPE peers exchange Diffie-Hellman public keys inside of their packet 1 and use the resulting
+//! shared secret to derive a pair of RC4 keys, one for each direction of traffic.  Once the
+//! handshake is complete every byte sent over the connection is run through the RC4 key stream.
+//! Type 8 RTMPE handshakes also encrypt the packet 2 signatures with XTEA.
+
+use super::{
+    calc_hmac, HandshakeError, HandshakeErrorKind, RTMP_PACKET_SIZE, SHA256_DIGEST_LENGTH,
+};
+use num_bigint::BigUint;
+use rand;
+use rand::Rng;
+
+/// Length of the public key (and shared secret) of a 1024 bit Diffie-Hellman exchange
+pub(super) const DH_KEY_LENGTH: usize = 128;
+
+const RC4_KEY_LENGTH: usize = 16;
+
+// The 1024 bit MODP group from RFC 2409 (Oakley group 2), used with a generator of 2
+const DH_PRIME_HEX: &str = "\
+    FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD1\
+    29024E088A67CC74020BBEA63B139B22514A08798E3404DD\
+    EF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245\
+    E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7ED\
+    EE386BFB5A899FA5AE9F24117C4B1FE649286651ECE65381\
+    FFFFFFFFFFFFFFFF";
+
+const DH_GENERATOR: u32 = 2;
+
+const XTEA_BLOCK_SIZE: usize = 8;
+const XTEA_ROUNDS: usize = 32;
+const XTEA_DELTA: u32 = 0x9e37_79b9;
+
+// Keys used to encrypt the packet 2 signatures of type 8 handshakes.  Only the first 15 are used,
+// as the key is selected by a digest byte modulo 15.
+#[rustfmt::skip]
+const XTEA_SIGNATURE_KEYS: [[u8; 16]; 16] = [
+    [0xbf, 0xf0, 0x34, 0xb2, 0x11, 0xd9, 0x08, 0x1f, 0xcc, 0xdf, 0xb7, 0x95, 0x74, 0x8d, 0xe7, 0x32],
+    [0x08, 0x6a, 0x5e, 0xb6, 0x17, 0x43, 0x09, 0x0e, 0x6e, 0xf0, 0x5a, 0xb8, 0xfe, 0x5a, 0x39, 0xe2],
+    [0x7b, 0x10, 0x95, 0x6f, 0x76, 0xce, 0x05, 0x21, 0x23, 0x88, 0xa7, 0x3a, 0x44, 0x01, 0x49, 0xa1],
+    [0xa9, 0x43, 0xf3, 0x17, 0xeb, 0xf1, 0x1b, 0xb2, 0xa6, 0x91, 0xa5, 0xee, 0x17, 0xf3, 0x63, 0x39],
+    [0x7a, 0x30, 0xe0, 0x0a, 0xb5, 0x29, 0xe2, 0x2c, 0xa0, 0x87, 0xae, 0xa5, 0xc0, 0xcb, 0x79, 0xac],
+    [0xbd, 0xce, 0x0c, 0x23, 0x2f, 0xeb, 0xde, 0xff, 0x1c, 0xfa, 0xae, 0x16, 0x11, 0x23, 0x23, 0x9d],
+    [0x55, 0xdd, 0x3f, 0x7b, 0x77, 0xe7, 0xe6, 0x2e, 0x9b, 0xb8, 0xc4, 0x99, 0xc9, 0x48, 0x1e, 0xe4],
+    [0x40, 0x7b, 0xb6, 0xb4, 0x71, 0xe8, 0x91, 0x36, 0xa7, 0xae, 0xbf, 0x55, 0xca, 0x33, 0xb8, 0x39],
+    [0xfc, 0xf6, 0xbd, 0xc3, 0xb6, 0x3c, 0x36, 0x97, 0x7c, 0xe4, 0xf8, 0x25, 0x04, 0xd9, 0x59, 0xb2],
+    [0x28, 0xe0, 0x91, 0xfd, 0x41, 0x95, 0x4c, 0x4c, 0x7f, 0xb7, 0xdb, 0x00, 0xe3, 0xa0, 0x66, 0xf8],
+    [0x57, 0x84, 0x5b, 0x76, 0x4f, 0x25, 0x1b, 0x03, 0x46, 0xd4, 0x5b, 0xcd, 0xa2, 0xc3, 0x0d, 0x29],
+    [0x0a, 0xcc, 0xee, 0xf8, 0xda, 0x55, 0xb5, 0x46, 0x03, 0x47, 0x34, 0x52, 0x58, 0x63, 0x71, 0x3b],
+    [0xb8, 0x20, 0x75, 0xdc, 0xa7, 0x5f, 0x1f, 0xee, 0xd8, 0x42, 0x68, 0xe8, 0xa7, 0x2a, 0x44, 0xcc],
+    [0x07, 0xcf, 0x6e, 0x9e, 0xa1, 0x6d, 0x7b, 0x25, 0x9f, 0xa7, 0xae, 0x6c, 0xd9, 0x2f, 0x56, 0x29],
+    [0xfe, 0xb1, 0xea, 0xe4, 0x8c, 0x8c, 0x3c, 0xe1, 0x4e, 0x00, 0x64, 0xa7, 0x6a, 0x38, 0x7c, 0x2a],
+    [0x89, 0x3a, 0x94, 0x27, 0xcc, 0x30, 0x13, 0xa2, 0xf1, 0x06, 0x38, 0x5b, 0xa8, 0x29, 0xf9, 0x27],
+];
+
+/// Diffie-Hellman key pair generated for a single RTMPE handshake
+pub(super) struct DhKeyPair {
+    private_key: BigUint,
+    public_key: [u8; DH_KEY_LENGTH],
+}
+
+impl DhKeyPair {
+    pub(super) fn generate() -> DhKeyPair {
+        let mut private_bytes = [0_u8; DH_KEY_LENGTH];
+        let mut rng = rand::thread_rng();
+        for x in private_bytes.iter_mut() {
+            *x = rng.gen();
+        }
+
+        let prime = get_prime();
+        let private_key = BigUint::from_bytes_be(&private_bytes) % (&prime - 1_u32);
+        let public_key = BigUint::from(DH_GENERATOR).modpow(&private_key, &prime);
+
+        DhKeyPair {
+            private_key,
+            public_key: to_padded_bytes(&public_key),
+        }
+    }
+
+    pub(super) fn public_key(&self) -> &[u8; DH_KEY_LENGTH] {
+        &self.public_key
+    }
+
+    /// Computes the shared secret from the public key the peer sent us.  Public keys outside of
+    /// the range of 2 to p - 2 are rejected, as they would lead to a trivially guessable secret.
+    pub(super) fn compute_shared_secret(
+        &self,
+        peer_public_key: &[u8],
+    ) -> Result<[u8; DH_KEY_LENGTH], HandshakeError> {
+        let prime = get_prime();
+        let peer_key = BigUint::from_bytes_be(peer_public_key);
+        if peer_key < BigUint::from(2_u32) || peer_key > &prime - 2_u32 {
+            return Err(HandshakeError {
+                kind: HandshakeErrorKind::InvalidDhPublicKey,
+            });
+        }
+
+        let secret = peer_key.modpow(&self.private_key, &prime);
+        Ok(to_padded_bytes(&secret))
+    }
+}
+
+/// The negotiated encryption state of a completed RTMPE handshake.
+///
+/// All bytes received from the peer after the handshake must be passed through `decrypt()`
+/// before being handed to a session (e.g. `ServerSession::handle_input()`), and the bytes of
+/// every outbound `Packet` must be passed through `encrypt()` before being sent to the peer.
+/// Since RC4 is a stream cipher, bytes must be processed in the exact order they are sent or
+/// received.
+#[derive(Clone)]
+pub struct RtmpeCipher {
+    encryptor: Rc4,
+    decryptor: Rc4,
+}
+
+impl RtmpeCipher {
+    /// Derives the RC4 key streams for each direction from the Diffie-Hellman shared secret.
+    ///
+    /// Both key streams are advanced past the first 1536 bytes, as the handshake packets
+    /// themselves are never encrypted.
+    pub(super) fn new(
+        shared_secret: &[u8],
+        own_public_key: &[u8],
+        peer_public_key: &[u8],
+    ) -> RtmpeCipher {
+        let encrypt_key = calc_hmac(peer_public_key, shared_secret);
+        let decrypt_key = calc_hmac(own_public_key, shared_secret);
+
+        let mut cipher = RtmpeCipher {
+            encryptor: Rc4::new(&encrypt_key[..RC4_KEY_LENGTH]),
+            decryptor: Rc4::new(&decrypt_key[..RC4_KEY_LENGTH]),
+        };
+
+        let mut skipped = [0_u8; RTMP_PACKET_SIZE];
+        cipher.encryptor.apply(&mut skipped);
+        cipher.decryptor.apply(&mut skipped);
+
+        cipher
+    }
+
+    /// Encrypts bytes that are about to be sent to the peer, in place
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        self.encryptor.apply(data);
+    }
+
+    /// Decrypts bytes that were received from the peer, in place
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        self.decryptor.apply(data);
+    }
+}
+
+/// Encrypts the signature of a type 8 handshake's packet 2, in place.  Each 8 byte block of the
+/// signature is encrypted with the key selected by the digest byte at the start of the block,
+/// where the digest is the one the signature was calculated with.
+pub(super) fn obfuscate_signature(
+    signature: &mut [u8; SHA256_DIGEST_LENGTH],
+    digest: &[u8; SHA256_DIGEST_LENGTH],
+) {
+    for (index, block) in signature.chunks_mut(XTEA_BLOCK_SIZE).enumerate() {
+        let key_index = digest[index * XTEA_BLOCK_SIZE] % 15;
+        xtea_encrypt(block, &XTEA_SIGNATURE_KEYS[key_index as usize]);
+    }
+}
+
+/// Encrypts a single 8 byte block with XTEA, with the block and key read as little endian words
+fn xtea_encrypt(block: &mut [u8], key: &[u8; 16]) {
+    let mut words = [0_u32; 4];
+    for (word, bytes) in words.iter_mut().zip(key.chunks(4)) {
+        *word = read_u32_le(bytes);
+    }
+
+    let mut v0 = read_u32_le(&block[0..4]);
+    let mut v1 = read_u32_le(&block[4..8]);
+    let mut sum = 0_u32;
+    for _ in 0..XTEA_ROUNDS {
+        let mixed = ((v1 << 4) ^ (v1 >> 5)).wrapping_add(v1);
+        v0 = v0.wrapping_add(mixed ^ sum.wrapping_add(words[(sum & 3) as usize]));
+        sum = sum.wrapping_add(XTEA_DELTA);
+
+        let mixed = ((v0 << 4) ^ (v0 >> 5)).wrapping_add(v0);
+        v1 = v1.wrapping_add(mixed ^ sum.wrapping_add(words[((sum >> 11) & 3) as usize]));
+    }
+
+    block[0..4].copy_from_slice(&v0.to_le_bytes());
+    block[4..8].copy_from_slice(&v1.to_le_bytes());
+}
+
+fn read_u32_le(bytes: &[u8]) -> u32 {
+    let mut word = [0_u8; 4];
+    word.copy_from_slice(&bytes[..4]);
+    u32::from_le_bytes(word)
+}
+
+#[derive(Clone)]
+struct Rc4 {
+    state: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    fn new(key: &[u8]) -> Rc4 {
+        let mut state = [0_u8; 256];
+        for (index, x) in state.iter_mut().enumerate() {
+            *x = index as u8;
+        }
+
+        let mut j = 0_u8;
+        for i in 0..256 {
+            j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+            state.swap(i, j as usize);
+        }
+
+        Rc4 { state, i: 0, j: 0 }
+    }
+
+    fn apply(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            self.i = self.i.wrapping_add(1);
+            self.j = self.j.wrapping_add(self.state[self.i as usize]);
+            self.state.swap(self.i as usize, self.j as usize);
+
+            let index = self.state[self.i as usize].wrapping_add(self.state[self.j as usize]);
+            *byte ^= self.state[index as usize];
+        }
+    }
+}
+
+fn get_prime() -> BigUint {
+    BigUint::parse_bytes(DH_PRIME_HEX.as_bytes(), 16)
+        .expect("Diffie-Hellman prime is not valid hex")
+}
+
+fn to_padded_bytes(value: &BigUint) -> [u8; DH_KEY_LENGTH] {
+    let bytes = value.to_bytes_be();
+    let mut padded = [0_u8; DH_KEY_LENGTH];
+    padded[(DH_KEY_LENGTH - bytes.len())..].copy_from_slice(&bytes);
+    padded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rc4_matches_known_test_vectors() {
+        let mut data = b"Plaintext".to_vec();
+        Rc4::new(b"Key").apply(&mut data);
+        assert_eq!(
+            &data[..],
+            &[0xbb, 0xf3, 0x16, 0xe8, 0xd9, 0x40, 0xaf, 0x0a, 0xd3][..]
+        );
+
+        let mut data = b"Attack at dawn".to_vec();
+        Rc4::new(b"Secret").apply(&mut data);
+        assert_eq!(
+            &data[..],
+            &[0x45, 0xa0, 0x1f, 0x64, 0x5f, 0xc3, 0x5b, 0x38, 0x35, 0x52, 0x54, 0x4b, 0x9b, 0xf5][..]
+        );
+    }
+
+    #[test]
+    fn xtea_matches_known_test_vector() {
+        // Key 000102030405060708090a0b0c0d0e0f and plaintext 4142434445464748 encrypt to
+        // 497df3d072612cb5 when read as big endian words, so each word is byte swapped here
+        let key = [
+            0x03, 0x02, 0x01, 0x00, 0x07, 0x06, 0x05, 0x04, 0x0b, 0x0a, 0x09, 0x08, 0x0f, 0x0e,
+            0x0d, 0x0c,
+        ];
+
+        let mut block = [0x44, 0x43, 0x42, 0x41, 0x48, 0x47, 0x46, 0x45];
+        xtea_encrypt(&mut block, &key);
+        assert_eq!(block, [0xd0, 0xf3, 0x7d, 0x49, 0xb5, 0x2c, 0x61, 0x72]);
+    }
+
+    #[test]
+    fn signature_blocks_use_key_selected_by_digest() {
+        let mut digest = [0_u8; SHA256_DIGEST_LENGTH];
+        digest[0] = 15; // key 0
+        digest[8] = 3;
+        digest[16] = 29; // key 14
+
+        let mut signature = [0x5a_u8; SHA256_DIGEST_LENGTH];
+        obfuscate_signature(&mut signature, &digest);
+
+        for (index, key_index) in [0, 3, 14, 0].iter().enumerate() {
+            let mut expected = [0x5a_u8; XTEA_BLOCK_SIZE];
+            xtea_encrypt(&mut expected, &XTEA_SIGNATURE_KEYS[*key_index]);
+
+            let start = index * XTEA_BLOCK_SIZE;
+            assert_eq!(
+                &signature[start..(start + XTEA_BLOCK_SIZE)],
+                &expected[..],
+                "Unexpected block {}",
+                index
+            );
+        }
+    }
+
+    #[test]
+    fn both_peers_compute_the_same_shared_secret() {
+        let first = DhKeyPair::generate();
+        let second = DhKeyPair::generate();
+
+        let secret1 = first.compute_shared_secret(second.public_key()).unwrap();
+        let secret2 = second.compute_shared_secret(first.public_key()).unwrap();
+
+        assert_eq!(&secret1[..], &secret2[..]);
+    }
+
+    #[test]
+    fn rejects_trivial_public_keys() {
+        let key_pair = DhKeyPair::generate();
+        let mut one = [0_u8; DH_KEY_LENGTH];
+        one[DH_KEY_LENGTH - 1] = 1;
+
+        for public_key in [[0_u8; DH_KEY_LENGTH], one, [0xff_u8; DH_KEY_LENGTH]].iter() {
+            match key_pair.compute_shared_secret(&public_key[..]) {
+                Err(HandshakeError {
+                    kind: HandshakeErrorKind::InvalidDhPublicKey,
+                }) => {}
+                Err(x) => panic!("Unexpected error: {:?}", x),
+                Ok(_) => panic!("Expected an error for an invalid public key"),
+            }
+        }
+    }
+
+    #[test]
+    fn ciphers_of_both_peers_are_compatible() {
+        let client = DhKeyPair::generate();
+        let server = DhKeyPair::generate();
+        let secret = client.compute_shared_secret(server.public_key()).unwrap();
+
+        let mut client_cipher = RtmpeCipher::new(&secret, client.public_key(), server.public_key());
+        let mut server_cipher = RtmpeCipher::new(&secret, server.public_key(), client.public_key());
+
+        let mut data = b"some rtmp chunk".to_vec();
+        client_cipher.encrypt(&mut data);
+        assert_ne!(&data[..], &b"some rtmp chunk"[..]);
+        server_cipher.decrypt(&mut data);
+        assert_eq!(&data[..], &b"some rtmp chunk"[..]);
+
+        let mut data = b"a response".to_vec();
+        server_cipher.encrypt(&mut data);
+        client_cipher.decrypt(&mut data);
+        assert_eq!(&data[..], &b"a response"[..]);
+    }
+}
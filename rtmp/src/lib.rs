@@ -43,6 +43,9 @@ The `messages` module allows the unwrapping of inbound message payloads into pro
 as well as wrapping outbound messages into their payloads (to be then wrapped back into an RTMP
 chunk).
 
+The `media` module allows reading and writing the codec information at the start of the data in
+audio and video messages.
+
 ## High Level APIs
 
 Part of the RTMP protocol describes particular flows of messages back and forth between the client
@@ -71,7 +74,11 @@ extern crate bytes;
 extern crate rand;
 extern crate hmac;
 extern crate sha2;
+extern crate num_bigint;
+extern crate md5;
+extern crate base64;
 extern crate rml_amf0;
+extern crate rml_amf3;
 
 #[cfg(test)]
 #[macro_use]
@@ -84,4 +91,6 @@ pub mod time;
 pub mod handshake;
 pub mod messages;
 pub mod chunk_io;
+pub mod media;
 pub mod sessions;
+pub mod auth;
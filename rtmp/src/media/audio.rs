@@ -0,0 +1,555 @@
+use std::fmt;
+use bytes::{BufMut, Bytes, BytesMut};
+use super::errors::{MediaParseError, MediaParseErrorKind};
+use super::multitrack::{self, MultitrackType, TrackHeader};
+
+/// The codec used to encode audio data
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum SoundFormat {
+    LinearPcmPlatformEndian,
+    Adpcm,
+    Mp3,
+    LinearPcmLittleEndian,
+    Nellymoser16KhzMono,
+    Nellymoser8KhzMono,
+    Nellymoser,
+    G711ALaw,
+    G711MuLaw,
+
+    /// An Enhanced RTMP audio header, where the codec is identified by a FourCC code
+    ExHeader,
+    Aac,
+    Speex,
+    Mp38Khz,
+    DeviceSpecific,
+
+    /// A sound format that is reserved or not part of the FLV specification
+    Unknown(u8),
+}
+
+/// The sampling rate of the audio data.  AAC audio always reports 44 kHz, with the actual rate
+/// being specified in the AAC sequence header.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum SoundRate {
+    Khz5_5,
+    Khz11,
+    Khz22,
+    Khz44,
+}
+
+/// The size of each uncompressed audio sample
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum SoundSize {
+    Bits8,
+    Bits16,
+}
+
+/// Whether the audio is mono or stereo
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum SoundType {
+    Mono,
+    Stereo,
+}
+
+/// The type of data contained in AAC audio data
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum AacPacketType {
+    /// The data is an AAC audio specific config
+    SequenceHeader,
+
+    /// The data is raw AAC frame data
+    Raw,
+
+    /// An AAC packet type that is not part of the FLV specification
+    Unknown(u8),
+}
+
+/// The FourCC codes used to identify codecs in Enhanced RTMP audio headers
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum AudioFourCc {
+    /// Dolby AC-3 (`ac-3`)
+    Ac3,
+
+    /// Dolby Digital Plus (`ec-3`)
+    Eac3,
+
+    /// Opus (`Opus`)
+    Opus,
+
+    /// MP3 (`.mp3`)
+    Mp3,
+
+    /// FLAC (`fLaC`)
+    Flac,
+
+    /// AAC (`mp4a`)
+    Aac,
+
+    /// A FourCC code that is not part of the Enhanced RTMP specification
+    Unknown([u8; 4]),
+}
+
+/// The type of data contained in an Enhanced RTMP audio message
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum ExAudioPacketType {
+    /// The data is the codec's configuration (e.g. an AAC audio specific config)
+    SequenceStart,
+
+    /// The data contains coded audio frames
+    CodedFrames,
+
+    /// The end of the audio sequence
+    SequenceEnd,
+
+    /// The data describes the channel order of multichannel audio
+    MultichannelConfig,
+
+    /// A packet type that is not part of the Enhanced RTMP specification
+    Unknown(u8),
+}
+
+/// The header at the start of the data of an audio message.
+///
+/// Enhanced RTMP headers do not carry a rate, size or type, so those are always reported as
+/// 44 kHz 16 bit stereo for them.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct AudioTagHeader {
+    pub sound_format: SoundFormat,
+    pub sound_rate: SoundRate,
+    pub sound_size: SoundSize,
+    pub sound_type: SoundType,
+
+    /// The type of AAC data, only present for AAC audio
+    pub aac_packet_type: Option<AacPacketType>,
+
+    /// The type of data in an Enhanced RTMP audio message
+    pub ex_packet_type: Option<ExAudioPacketType>,
+
+    /// The codec of an Enhanced RTMP audio message
+    pub fourcc: Option<AudioFourCc>,
+
+    /// How the tracks are laid out when this is an Enhanced RTMP multitrack message.  The rest
+    /// of the header then describes the first track of the message.
+    pub multitrack_type: Option<MultitrackType>,
+
+    /// The track the header describes.  Messages that are not multitrack always carry track 0.
+    pub track_id: u8,
+}
+
+impl AudioTagHeader {
+    /// Reads the audio tag header from the start of the data of an audio message
+    pub fn parse(data: &[u8]) -> Result<AudioTagHeader, MediaParseError> {
+        if data.is_empty() {
+            return Err(MediaParseErrorKind::NotEnoughData { expected: 1, actual: 0 }.into());
+        }
+
+        let sound_format = SoundFormat::from_u8(data[0] >> 4);
+        if sound_format == SoundFormat::ExHeader {
+            return AudioTagHeader::parse_ex_header(data);
+        }
+
+        let sound_rate = match (data[0] >> 2) & 0x03 {
+            0 => SoundRate::Khz5_5,
+            1 => SoundRate::Khz11,
+            2 => SoundRate::Khz22,
+            _ => SoundRate::Khz44,
+        };
+
+        let sound_size = if data[0] & 0x02 == 0 { SoundSize::Bits8 } else { SoundSize::Bits16 };
+        let sound_type = if data[0] & 0x01 == 0 { SoundType::Mono } else { SoundType::Stereo };
+
+        let aac_packet_type = if sound_format == SoundFormat::Aac {
+            if data.len() < 2 {
+                return Err(MediaParseErrorKind::NotEnoughData { expected: 2, actual: data.len() }.into());
+            }
+
+            Some(AacPacketType::from_u8(data[1]))
+        } else {
+            None
+        };
+
+        Ok(AudioTagHeader {
+            sound_format,
+            sound_rate,
+            sound_size,
+            sound_type,
+            aac_packet_type,
+            ex_packet_type: None,
+            fourcc: None,
+            multitrack_type: None,
+            track_id: 0,
+        })
+    }
+
+    /// The number of bytes the header takes up at the start of the audio data
+    pub fn header_size(&self) -> usize {
+        match self.sound_format {
+            SoundFormat::ExHeader => match self.multitrack_type {
+                Some(multitrack_type) => 1 + multitrack::first_track_header_size(multitrack_type),
+                None => 5,
+            },
+
+            SoundFormat::Aac => 2,
+            _ => 1,
+        }
+    }
+
+    /// Returns true if this is an AAC sequence header or an Enhanced RTMP sequence start
+    pub fn is_sequence_header(&self) -> bool {
+        self.aac_packet_type == Some(AacPacketType::SequenceHeader) ||
+            self.ex_packet_type == Some(ExAudioPacketType::SequenceStart)
+    }
+
+    /// Creates the bytes for the header.
+    ///
+    /// Multitrack headers are written as a message containing only the track the header
+    /// describes.
+    pub fn to_bytes(&self) -> Bytes {
+        self.to_bytes_with_body(&[])
+    }
+
+    /// Creates the data for an audio message by placing the header in front of the codec
+    /// specific audio data.
+    pub fn to_bytes_with_body(&self, body: &[u8]) -> Bytes {
+        if self.sound_format == SoundFormat::ExHeader {
+            return self.ex_header_to_bytes_with_body(body);
+        }
+
+        let sound_rate = match self.sound_rate {
+            SoundRate::Khz5_5 => 0,
+            SoundRate::Khz11 => 1,
+            SoundRate::Khz22 => 2,
+            SoundRate::Khz44 => 3,
+        };
+
+        let sound_size = match self.sound_size {
+            SoundSize::Bits8 => 0,
+            SoundSize::Bits16 => 1,
+        };
+
+        let sound_type = match self.sound_type {
+            SoundType::Mono => 0,
+            SoundType::Stereo => 1,
+        };
+
+        let mut bytes = BytesMut::with_capacity(self.header_size() + body.len());
+        bytes.put_u8((self.sound_format.to_u8() << 4) | (sound_rate << 2) | (sound_size << 1) | sound_type);
+
+        if self.sound_format == SoundFormat::Aac {
+            bytes.put_u8(self.aac_packet_type.unwrap_or(AacPacketType::Raw).to_u8());
+        }
+
+        bytes.extend_from_slice(body);
+        bytes.freeze()
+    }
+
+    fn parse_ex_header(data: &[u8]) -> Result<AudioTagHeader, MediaParseError> {
+        let mut header = AudioTagHeader {
+            sound_format: SoundFormat::ExHeader,
+            sound_rate: SoundRate::Khz44,
+            sound_size: SoundSize::Bits16,
+            sound_type: SoundType::Stereo,
+            aac_packet_type: None,
+            ex_packet_type: None,
+            fourcc: None,
+            multitrack_type: None,
+            track_id: 0,
+        };
+
+        if data[0] & 0x0f == MULTITRACK_PACKET_TYPE {
+            let (track, _) = multitrack::read_first_track_header(data)?;
+            header.ex_packet_type = Some(ExAudioPacketType::from_u8(track.packet_type));
+            header.fourcc = Some(AudioFourCc::from_bytes(track.fourcc));
+            header.multitrack_type = Some(track.multitrack_type);
+            header.track_id = track.track_id;
+        } else {
+            if data.len() < 5 {
+                return Err(MediaParseErrorKind::NotEnoughData { expected: 5, actual: data.len() }.into());
+            }
+
+            header.ex_packet_type = Some(ExAudioPacketType::from_u8(data[0] & 0x0f));
+            header.fourcc = Some(AudioFourCc::from_bytes([data[1], data[2], data[3], data[4]]));
+        }
+
+        Ok(header)
+    }
+
+    fn ex_header_to_bytes_with_body(&self, body: &[u8]) -> Bytes {
+        let packet_type = self.ex_packet_type.unwrap_or(ExAudioPacketType::CodedFrames).to_u8();
+        let fourcc = self.fourcc.unwrap_or(AudioFourCc::Unknown([0; 4])).to_bytes();
+
+        let mut bytes = BytesMut::with_capacity(self.header_size() + body.len());
+        if let Some(multitrack_type) = self.multitrack_type {
+            bytes.put_u8((AUDIO_EX_HEADER_FORMAT << 4) | MULTITRACK_PACKET_TYPE);
+            multitrack::write_first_track_header(&mut bytes, &TrackHeader {
+                multitrack_type,
+                packet_type,
+                fourcc,
+                track_id: self.track_id,
+                track_size: Some(body.len()),
+            });
+        } else {
+            bytes.put_u8((AUDIO_EX_HEADER_FORMAT << 4) | (packet_type & 0x0f));
+            bytes.extend_from_slice(&fourcc);
+        }
+
+        bytes.extend_from_slice(body);
+        bytes.freeze()
+    }
+}
+
+pub(super) const AUDIO_EX_HEADER_FORMAT: u8 = 9;
+const MULTITRACK_PACKET_TYPE: u8 = 5;
+
+impl SoundFormat {
+    fn from_u8(value: u8) -> SoundFormat {
+        match value {
+            0 => SoundFormat::LinearPcmPlatformEndian,
+            1 => SoundFormat::Adpcm,
+            2 => SoundFormat::Mp3,
+            3 => SoundFormat::LinearPcmLittleEndian,
+            4 => SoundFormat::Nellymoser16KhzMono,
+            5 => SoundFormat::Nellymoser8KhzMono,
+            6 => SoundFormat::Nellymoser,
+            7 => SoundFormat::G711ALaw,
+            8 => SoundFormat::G711MuLaw,
+            9 => SoundFormat::ExHeader,
+            10 => SoundFormat::Aac,
+            11 => SoundFormat::Speex,
+            14 => SoundFormat::Mp38Khz,
+            15 => SoundFormat::DeviceSpecific,
+            x => SoundFormat::Unknown(x),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            SoundFormat::LinearPcmPlatformEndian => 0,
+            SoundFormat::Adpcm => 1,
+            SoundFormat::Mp3 => 2,
+            SoundFormat::LinearPcmLittleEndian => 3,
+            SoundFormat::Nellymoser16KhzMono => 4,
+            SoundFormat::Nellymoser8KhzMono => 5,
+            SoundFormat::Nellymoser => 6,
+            SoundFormat::G711ALaw => 7,
+            SoundFormat::G711MuLaw => 8,
+            SoundFormat::ExHeader => 9,
+            SoundFormat::Aac => 10,
+            SoundFormat::Speex => 11,
+            SoundFormat::Mp38Khz => 14,
+            SoundFormat::DeviceSpecific => 15,
+            SoundFormat::Unknown(x) => x & 0x0f,
+        }
+    }
+}
+
+impl AacPacketType {
+    fn from_u8(value: u8) -> AacPacketType {
+        match value {
+            0 => AacPacketType::SequenceHeader,
+            1 => AacPacketType::Raw,
+            x => AacPacketType::Unknown(x),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            AacPacketType::SequenceHeader => 0,
+            AacPacketType::Raw => 1,
+            AacPacketType::Unknown(x) => x,
+        }
+    }
+}
+
+impl AudioFourCc {
+    /// Gets the FourCC code for a codec from its string form (e.g. `Opus`)
+    pub fn parse(value: &str) -> Option<AudioFourCc> {
+        let bytes = value.as_bytes();
+        if bytes.len() != 4 {
+            return None;
+        }
+
+        Some(AudioFourCc::from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn from_bytes(bytes: [u8; 4]) -> AudioFourCc {
+        match &bytes {
+            b"ac-3" => AudioFourCc::Ac3,
+            b"ec-3" => AudioFourCc::Eac3,
+            b"Opus" => AudioFourCc::Opus,
+            b".mp3" => AudioFourCc::Mp3,
+            b"fLaC" => AudioFourCc::Flac,
+            b"mp4a" => AudioFourCc::Aac,
+            _ => AudioFourCc::Unknown(bytes),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 4] {
+        match self {
+            AudioFourCc::Ac3 => *b"ac-3",
+            AudioFourCc::Eac3 => *b"ec-3",
+            AudioFourCc::Opus => *b"Opus",
+            AudioFourCc::Mp3 => *b".mp3",
+            AudioFourCc::Flac => *b"fLaC",
+            AudioFourCc::Aac => *b"mp4a",
+            AudioFourCc::Unknown(bytes) => bytes,
+        }
+    }
+}
+
+impl fmt::Display for AudioFourCc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
+
+impl ExAudioPacketType {
+    fn from_u8(value: u8) -> ExAudioPacketType {
+        match value {
+            0 => ExAudioPacketType::SequenceStart,
+            1 => ExAudioPacketType::CodedFrames,
+            2 => ExAudioPacketType::SequenceEnd,
+            4 => ExAudioPacketType::MultichannelConfig,
+            x => ExAudioPacketType::Unknown(x),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            ExAudioPacketType::SequenceStart => 0,
+            ExAudioPacketType::CodedFrames => 1,
+            ExAudioPacketType::SequenceEnd => 2,
+            ExAudioPacketType::MultichannelConfig => 4,
+            ExAudioPacketType::Unknown(x) => x,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use media::MediaParseErrorKind;
+
+    #[test]
+    fn can_parse_aac_sequence_header() {
+        let header = AudioTagHeader::parse(&[0xaf, 0x00, 0x12, 0x10]).unwrap();
+
+        assert_eq!(header.sound_format, SoundFormat::Aac, "Incorrect sound format");
+        assert_eq!(header.sound_rate, SoundRate::Khz44, "Incorrect sound rate");
+        assert_eq!(header.sound_size, SoundSize::Bits16, "Incorrect sound size");
+        assert_eq!(header.sound_type, SoundType::Stereo, "Incorrect sound type");
+        assert_eq!(header.aac_packet_type, Some(AacPacketType::SequenceHeader), "Incorrect packet type");
+        assert_eq!(header.header_size(), 2, "Incorrect header length");
+        assert!(header.is_sequence_header(), "Expected a sequence header");
+    }
+
+    #[test]
+    fn can_parse_raw_aac_header() {
+        let header = AudioTagHeader::parse(&[0xaf, 0x01, 0x21]).unwrap();
+
+        assert_eq!(header.aac_packet_type, Some(AacPacketType::Raw), "Incorrect packet type");
+        assert!(!header.is_sequence_header(), "Raw data should not be a sequence header");
+    }
+
+    #[test]
+    fn can_parse_mp3_header() {
+        let header = AudioTagHeader::parse(&[0x26, 0xff]).unwrap();
+
+        assert_eq!(header.sound_format, SoundFormat::Mp3, "Incorrect sound format");
+        assert_eq!(header.sound_rate, SoundRate::Khz11, "Incorrect sound rate");
+        assert_eq!(header.sound_size, SoundSize::Bits16, "Incorrect sound size");
+        assert_eq!(header.sound_type, SoundType::Mono, "Incorrect sound type");
+        assert_eq!(header.aac_packet_type, None, "Unexpected packet type");
+        assert_eq!(header.header_size(), 1, "Incorrect header length");
+    }
+
+    #[test]
+    fn error_when_aac_header_is_truncated() {
+        let error = AudioTagHeader::parse(&[0xaf]).unwrap_err();
+
+        match error.kind {
+            MediaParseErrorKind::NotEnoughData { expected: 2, actual: 1 } => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_build_aac_header_with_body() {
+        let header = AudioTagHeader {
+            sound_format: SoundFormat::Aac,
+            sound_rate: SoundRate::Khz44,
+            sound_size: SoundSize::Bits16,
+            sound_type: SoundType::Stereo,
+            aac_packet_type: Some(AacPacketType::SequenceHeader),
+            ex_packet_type: None,
+            fourcc: None,
+            multitrack_type: None,
+            track_id: 0,
+        };
+
+        let bytes = header.to_bytes_with_body(&[0x12, 0x10]);
+
+        assert_eq!(&bytes[..], &[0xaf, 0x00, 0x12, 0x10]);
+        assert_eq!(AudioTagHeader::parse(&bytes).unwrap(), header, "Header did not round trip");
+    }
+
+    #[test]
+    fn can_build_non_aac_header() {
+        let header = AudioTagHeader {
+            sound_format: SoundFormat::Speex,
+            sound_rate: SoundRate::Khz5_5,
+            sound_size: SoundSize::Bits8,
+            sound_type: SoundType::Mono,
+            aac_packet_type: None,
+            ex_packet_type: None,
+            fourcc: None,
+            multitrack_type: None,
+            track_id: 0,
+        };
+
+        assert_eq!(&header.to_bytes()[..], &[0xb0]);
+    }
+
+    #[test]
+    fn can_parse_ex_header_sequence_start() {
+        let header = AudioTagHeader::parse(&[0x90, b'O', b'p', b'u', b's', 0x01]).unwrap();
+
+        assert_eq!(header.sound_format, SoundFormat::ExHeader, "Incorrect sound format");
+        assert_eq!(header.fourcc, Some(AudioFourCc::Opus), "Incorrect codec");
+        assert_eq!(header.ex_packet_type, Some(ExAudioPacketType::SequenceStart), "Incorrect packet type");
+        assert_eq!(header.multitrack_type, None, "Unexpected multitrack type");
+        assert_eq!(header.header_size(), 5, "Incorrect header length");
+        assert!(header.is_sequence_header(), "Expected a sequence header");
+    }
+
+    #[test]
+    fn can_parse_one_track_multitrack_header() {
+        let header = AudioTagHeader::parse(&[0x95, 0x01, b'm', b'p', b'4', b'a', 0x02, 0xaa]).unwrap();
+
+        assert_eq!(header.fourcc, Some(AudioFourCc::Aac), "Incorrect codec");
+        assert_eq!(header.ex_packet_type, Some(ExAudioPacketType::CodedFrames), "Incorrect packet type");
+        assert_eq!(header.multitrack_type, Some(MultitrackType::OneTrack), "Incorrect multitrack type");
+        assert_eq!(header.track_id, 2, "Incorrect track id");
+        assert_eq!(header.header_size(), 7, "Incorrect header length");
+    }
+
+    #[test]
+    fn can_build_multitrack_header_with_body() {
+        let header = AudioTagHeader {
+            sound_format: SoundFormat::ExHeader,
+            sound_rate: SoundRate::Khz44,
+            sound_size: SoundSize::Bits16,
+            sound_type: SoundType::Stereo,
+            aac_packet_type: None,
+            ex_packet_type: Some(ExAudioPacketType::CodedFrames),
+            fourcc: Some(AudioFourCc::Flac),
+            multitrack_type: Some(MultitrackType::ManyTracksManyCodecs),
+            track_id: 1,
+        };
+
+        let bytes = header.to_bytes_with_body(&[1, 2]);
+
+        assert_eq!(&bytes[..], &[0x95, 0x21, b'f', b'L', b'a', b'C', 0x01, 0x00, 0x00, 0x02, 1, 2]);
+        assert_eq!(AudioTagHeader::parse(&bytes).unwrap(), header, "Header did not round trip");
+    }
+}
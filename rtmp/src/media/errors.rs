@@ -0,0 +1,45 @@
+//! Errors that can occur while parsing audio and video tag headers
+
+use failure::{Backtrace, Fail};
+use std::fmt;
+
+/// Data pertaining to errors that occurred while parsing a media tag header
+#[derive(Debug)]
+pub struct MediaParseError {
+    /// The type of error that was observed
+    pub kind: MediaParseErrorKind,
+}
+
+/// Enumeration that represents the various errors that can occur while parsing a media tag header
+#[derive(Debug, Fail)]
+pub enum MediaParseErrorKind {
+    /// The audio or video data ended before the full tag header could be read
+    #[fail(display = "Expected at least {} bytes for the tag header but only {} were provided", expected, actual)]
+    NotEnoughData { expected: usize, actual: usize },
+
+    /// An Enhanced RTMP multitrack message specified a track layout that is not known
+    #[fail(display = "Unknown multitrack type of {}", _0)]
+    UnknownMultitrackType(u8),
+}
+
+impl fmt::Display for MediaParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.kind, f)
+    }
+}
+
+impl Fail for MediaParseError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.kind.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.kind.backtrace()
+    }
+}
+
+impl From<MediaParseErrorKind> for MediaParseError {
+    fn from(kind: MediaParseErrorKind) -> Self {
+        MediaParseError { kind }
+    }
+}
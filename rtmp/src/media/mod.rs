@@ -0,0 +1,39 @@
+/*!
+This module contains types for reading and writing the tag headers that prefix the data of
+audio and video RTMP messages.
+
+Headers using the Enhanced RTMP format (where codecs such as HEVC, AV1 and Opus are identified
+by a FourCC code) are also supported.  Enhanced RTMP multitrack messages carry several tracks
+(such as different languages or renditions) in one message, and can be split into a regular
+message per track with `demux_audio` and `demux_video`.
+
+The data of `RtmpMessage::AudioData` and `RtmpMessage::VideoData` messages is laid out the same
+as the body of an FLV audio or video tag.  The first few bytes describe the codec and framing of
+the media, followed by the codec specific media data.
+
+# Examples
+```
+use rml_rtmp::media::{VideoTagHeader, VideoCodecId, VideoFrameType, AvcPacketType};
+
+let data = vec![0x17, 0x00, 0x00, 0x00, 0x00, 0x01, 0x64];
+let header = VideoTagHeader::parse(&data).unwrap();
+
+assert_eq!(header.frame_type, VideoFrameType::Keyframe);
+assert_eq!(header.codec_id, VideoCodecId::Avc);
+assert_eq!(header.avc_packet_type, Some(AvcPacketType::SequenceHeader));
+assert!(header.is_sequence_header());
+
+// The codec specific data follows the header
+assert_eq!(&data[header.header_size()..], &[0x01, 0x64]);
+```
+*/
+
+mod audio;
+mod errors;
+mod multitrack;
+mod video;
+
+pub use self::audio::{AacPacketType, AudioFourCc, AudioTagHeader, ExAudioPacketType, SoundFormat, SoundRate, SoundSize, SoundType};
+pub use self::errors::{MediaParseError, MediaParseErrorKind};
+pub use self::multitrack::{demux_audio, demux_video, AudioTrackFrame, MultitrackType, VideoTrackFrame};
+pub use self::video::{AvcPacketType, ExVideoPacketType, VideoCodecId, VideoFourCc, VideoFrameType, VideoTagHeader};
@@ -0,0 +1,336 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use super::audio::{AudioTagHeader, AUDIO_EX_HEADER_FORMAT};
+use super::errors::{MediaParseError, MediaParseErrorKind};
+use super::video::{VideoTagHeader, EX_HEADER_FLAG};
+
+/// How the tracks of an Enhanced RTMP multitrack audio or video message are laid out
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum MultitrackType {
+    /// The message contains a single track
+    OneTrack,
+
+    /// The message contains one or more tracks that all use the same codec
+    ManyTracks,
+
+    /// The message contains one or more tracks that each specify their own codec
+    ManyTracksManyCodecs,
+}
+
+/// The frame of a single track that was taken out of a (possibly multitrack) video message
+#[derive(PartialEq, Debug, Clone)]
+pub struct VideoTrackFrame {
+    pub track_id: u8,
+
+    /// The header of the frame's data
+    pub header: VideoTagHeader,
+
+    /// The frame laid out as the data of a regular, single track, video message
+    pub data: Bytes,
+}
+
+/// The frame of a single track that was taken out of a (possibly multitrack) audio message
+#[derive(PartialEq, Debug, Clone)]
+pub struct AudioTrackFrame {
+    pub track_id: u8,
+
+    /// The header of the frame's data
+    pub header: AudioTagHeader,
+
+    /// The frame laid out as the data of a regular, single track, audio message
+    pub data: Bytes,
+}
+
+/// Splits the data of a video message into a frame for each track it contains.
+///
+/// Data that is not an Enhanced RTMP multitrack message is returned untouched as a single frame
+/// for track 0.
+pub fn demux_video(data: &Bytes) -> Result<Vec<VideoTrackFrame>, MediaParseError> {
+    let header = VideoTagHeader::parse(data)?;
+    if header.multitrack_type.is_none() {
+        return Ok(vec![VideoTrackFrame {track_id: 0, header, data: data.clone()}]);
+    }
+
+    // Every track shares the frame type of the multitrack message
+    let first_byte = EX_HEADER_FLAG | (data[0] & 0x70);
+
+    let mut frames = Vec::new();
+    for (track, body_start, body_end) in read_tracks(data)? {
+        let data = build_track_data(first_byte | track.packet_type, &track, &data[body_start..body_end]);
+        let header = VideoTagHeader::parse(&data)?;
+        frames.push(VideoTrackFrame {track_id: track.track_id, header, data});
+    }
+
+    Ok(frames)
+}
+
+/// Splits the data of an audio message into a frame for each track it contains.
+///
+/// Data that is not an Enhanced RTMP multitrack message is returned untouched as a single frame
+/// for track 0.
+pub fn demux_audio(data: &Bytes) -> Result<Vec<AudioTrackFrame>, MediaParseError> {
+    let header = AudioTagHeader::parse(data)?;
+    if header.multitrack_type.is_none() {
+        return Ok(vec![AudioTrackFrame {track_id: 0, header, data: data.clone()}]);
+    }
+
+    let first_byte = AUDIO_EX_HEADER_FORMAT << 4;
+
+    let mut frames = Vec::new();
+    for (track, body_start, body_end) in read_tracks(data)? {
+        let data = build_track_data(first_byte | track.packet_type, &track, &data[body_start..body_end]);
+        let header = AudioTagHeader::parse(&data)?;
+        frames.push(AudioTrackFrame {track_id: track.track_id, header, data});
+    }
+
+    Ok(frames)
+}
+
+/// The fields that come before the body of a track in a multitrack message.  Audio and video
+/// messages lay these out the same way after their first byte.
+#[derive(Debug, Clone)]
+pub(super) struct TrackHeader {
+    pub multitrack_type: MultitrackType,
+    pub packet_type: u8,
+    pub fourcc: [u8; 4],
+    pub track_id: u8,
+
+    /// The size of the track's body, which is only present when the message can contain
+    /// more than one track
+    pub track_size: Option<usize>,
+}
+
+/// The number of bytes that the first track's header takes up after the first byte of the message
+pub(super) fn first_track_header_size(multitrack_type: MultitrackType) -> usize {
+    match multitrack_type {
+        MultitrackType::OneTrack => 6,
+        MultitrackType::ManyTracks => 9,
+        MultitrackType::ManyTracksManyCodecs => 9,
+    }
+}
+
+/// Reads the header of the first track in a multitrack message, returning the offset in the data
+/// where the track's body starts.
+pub(super) fn read_first_track_header(data: &[u8]) -> Result<(TrackHeader, usize), MediaParseError> {
+    ensure_length(data, 2)?;
+
+    let multitrack_type = match data[1] >> 4 {
+        0 => MultitrackType::OneTrack,
+        1 => MultitrackType::ManyTracks,
+        2 => MultitrackType::ManyTracksManyCodecs,
+        x => return Err(MediaParseErrorKind::UnknownMultitrackType(x).into()),
+    };
+
+    let shared_fourcc = match multitrack_type {
+        MultitrackType::ManyTracksManyCodecs => None,
+        _ => {
+            ensure_length(data, 6)?;
+            Some([data[2], data[3], data[4], data[5]])
+        }
+    };
+
+    let offset = if shared_fourcc.is_some() { 6 } else { 2 };
+    read_track_header(data, offset, multitrack_type, data[1] & 0x0f, shared_fourcc)
+}
+
+/// Writes the header of the first track of a multitrack message, which follows the first byte
+pub(super) fn write_first_track_header(bytes: &mut BytesMut, header: &TrackHeader) {
+    let multitrack_type = match header.multitrack_type {
+        MultitrackType::OneTrack => 0,
+        MultitrackType::ManyTracks => 1,
+        MultitrackType::ManyTracksManyCodecs => 2,
+    };
+
+    bytes.put_u8((multitrack_type << 4) | (header.packet_type & 0x0f));
+    bytes.extend_from_slice(&header.fourcc);
+    bytes.put_u8(header.track_id);
+
+    if header.multitrack_type != MultitrackType::OneTrack {
+        let track_size = header.track_size.unwrap_or(0) as u32;
+        bytes.put_u8((track_size >> 16) as u8);
+        bytes.put_u8((track_size >> 8) as u8);
+        bytes.put_u8(track_size as u8);
+    }
+}
+
+fn read_track_header(data: &[u8],
+                     mut offset: usize,
+                     multitrack_type: MultitrackType,
+                     packet_type: u8,
+                     shared_fourcc: Option<[u8; 4]>) -> Result<(TrackHeader, usize), MediaParseError> {
+    let fourcc = match shared_fourcc {
+        Some(fourcc) => fourcc,
+        None => {
+            ensure_length(data, offset + 4)?;
+            offset += 4;
+            [data[offset - 4], data[offset - 3], data[offset - 2], data[offset - 1]]
+        }
+    };
+
+    ensure_length(data, offset + 1)?;
+    let track_id = data[offset];
+    offset += 1;
+
+    let track_size = if multitrack_type == MultitrackType::OneTrack {
+        None
+    } else {
+        ensure_length(data, offset + 3)?;
+        let size = ((data[offset] as usize) << 16) | ((data[offset + 1] as usize) << 8) | (data[offset + 2] as usize);
+        offset += 3;
+        Some(size)
+    };
+
+    let header = TrackHeader {
+        multitrack_type,
+        packet_type,
+        fourcc,
+        track_id,
+        track_size,
+    };
+
+    Ok((header, offset))
+}
+
+/// Reads the header of every track in the message along with the range of its body
+fn read_tracks(data: &[u8]) -> Result<Vec<(TrackHeader, usize, usize)>, MediaParseError> {
+    let (mut track, mut body_start) = read_first_track_header(data)?;
+    let shared_fourcc = match track.multitrack_type {
+        MultitrackType::ManyTracksManyCodecs => None,
+        _ => Some(track.fourcc),
+    };
+
+    let mut tracks = Vec::new();
+    loop {
+        let body_end = match track.track_size {
+            Some(size) => body_start + size,
+            None => data.len(),
+        };
+
+        ensure_length(data, body_end)?;
+
+        let multitrack_type = track.multitrack_type;
+        let packet_type = track.packet_type;
+        tracks.push((track, body_start, body_end));
+
+        if body_end == data.len() {
+            break;
+        }
+
+        let (next_track, next_body_start) = read_track_header(data, body_end, multitrack_type, packet_type, shared_fourcc)?;
+        track = next_track;
+        body_start = next_body_start;
+    }
+
+    Ok(tracks)
+}
+
+fn build_track_data(first_byte: u8, track: &TrackHeader, body: &[u8]) -> Bytes {
+    let mut bytes = BytesMut::with_capacity(5 + body.len());
+    bytes.put_u8(first_byte);
+    bytes.extend_from_slice(&track.fourcc);
+    bytes.extend_from_slice(body);
+    bytes.freeze()
+}
+
+fn ensure_length(data: &[u8], expected: usize) -> Result<(), MediaParseError> {
+    if data.len() < expected {
+        return Err(MediaParseErrorKind::NotEnoughData {expected, actual: data.len()}.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use media::{AudioFourCc, ExAudioPacketType, ExVideoPacketType, VideoCodecId, VideoFourCc, VideoFrameType};
+
+    #[test]
+    fn single_track_video_is_returned_as_track_zero() {
+        let data = Bytes::from(vec![0x17, 0x01, 0x00, 0x00, 0x00, 0xaa]);
+        let frames = demux_video(&data).unwrap();
+
+        assert_eq!(frames.len(), 1, "Unexpected number of frames");
+        assert_eq!(frames[0].track_id, 0, "Incorrect track id");
+        assert_eq!(frames[0].data, data, "Data should be untouched");
+    }
+
+    #[test]
+    fn can_demux_one_track_video() {
+        let data = Bytes::from(vec![0x96, 0x01, b'a', b'v', b'0', b'1', 0x02, 0xaa, 0xbb]);
+        let frames = demux_video(&data).unwrap();
+
+        assert_eq!(frames.len(), 1, "Unexpected number of frames");
+        assert_eq!(frames[0].track_id, 2, "Incorrect track id");
+        assert_eq!(frames[0].header.codec_id, VideoCodecId::FourCc(VideoFourCc::Av1), "Incorrect codec");
+        assert_eq!(frames[0].header.ex_packet_type, Some(ExVideoPacketType::CodedFrames), "Incorrect packet type");
+        assert_eq!(&frames[0].data[..], &[0x91, b'a', b'v', b'0', b'1', 0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn can_demux_many_track_video() {
+        let data = Bytes::from(vec![
+            0xa6, 0x11, b'h', b'v', b'c', b'1',
+            0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x21, 0xaa,
+            0x01, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0xbb, 0xcc,
+        ]);
+
+        let frames = demux_video(&data).unwrap();
+
+        assert_eq!(frames.len(), 2, "Unexpected number of frames");
+        assert_eq!(frames[0].track_id, 0, "Incorrect first track id");
+        assert_eq!(frames[0].header.frame_type, VideoFrameType::InterFrame, "Incorrect first frame type");
+        assert_eq!(frames[0].header.composition_time, Some(33), "Incorrect first composition time");
+        assert_eq!(&frames[0].data[..], &[0xa1, b'h', b'v', b'c', b'1', 0x00, 0x00, 0x21, 0xaa]);
+
+        assert_eq!(frames[1].track_id, 1, "Incorrect second track id");
+        assert_eq!(&frames[1].data[..], &[0xa1, b'h', b'v', b'c', b'1', 0x00, 0x00, 0x00, 0xbb, 0xcc]);
+    }
+
+    #[test]
+    fn can_demux_many_track_many_codec_video() {
+        let data = Bytes::from(vec![
+            0x96, 0x20,
+            b'a', b'v', b'0', b'1', 0x00, 0x00, 0x00, 0x01, 0xaa,
+            b'v', b'p', b'0', b'9', 0x01, 0x00, 0x00, 0x01, 0xbb,
+        ]);
+
+        let frames = demux_video(&data).unwrap();
+
+        assert_eq!(frames.len(), 2, "Unexpected number of frames");
+        assert_eq!(frames[0].header.codec_id, VideoCodecId::FourCc(VideoFourCc::Av1), "Incorrect first codec");
+        assert_eq!(frames[1].header.codec_id, VideoCodecId::FourCc(VideoFourCc::Vp9), "Incorrect second codec");
+        assert!(frames[1].header.is_sequence_header(), "Expected a sequence start");
+        assert_eq!(&frames[1].data[..], &[0x90, b'v', b'p', b'0', b'9', 0xbb]);
+    }
+
+    #[test]
+    fn error_when_track_size_exceeds_data() {
+        let data = Bytes::from(vec![0x96, 0x11, b'a', b'v', b'0', b'1', 0x00, 0x00, 0x00, 0x05, 0xaa]);
+        let error = demux_video(&data).unwrap_err();
+
+        match error.kind {
+            MediaParseErrorKind::NotEnoughData {expected: 15, actual: 11} => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_demux_many_track_audio() {
+        let data = Bytes::from(vec![
+            0x95, 0x11, b'O', b'p', b'u', b's',
+            0x00, 0x00, 0x00, 0x01, 0xaa,
+            0x01, 0x00, 0x00, 0x02, 0xbb, 0xcc,
+        ]);
+
+        let frames = demux_audio(&data).unwrap();
+
+        assert_eq!(frames.len(), 2, "Unexpected number of frames");
+        assert_eq!(frames[0].track_id, 0, "Incorrect first track id");
+        assert_eq!(frames[0].header.fourcc, Some(AudioFourCc::Opus), "Incorrect first codec");
+        assert_eq!(frames[0].header.ex_packet_type, Some(ExAudioPacketType::CodedFrames), "Incorrect packet type");
+        assert_eq!(&frames[0].data[..], &[0x91, b'O', b'p', b'u', b's', 0xaa]);
+
+        assert_eq!(frames[1].track_id, 1, "Incorrect second track id");
+        assert_eq!(&frames[1].data[..], &[0x91, b'O', b'p', b'u', b's', 0xbb, 0xcc]);
+    }
+}
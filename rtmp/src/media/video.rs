@@ -0,0 +1,689 @@
+use std::fmt;
+use bytes::{BufMut, Bytes, BytesMut};
+use super::errors::{MediaParseError, MediaParseErrorKind};
+use super::multitrack::{self, MultitrackType, TrackHeader};
+
+/// The codec used to encode video data
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum VideoCodecId {
+    Jpeg,
+    SorensonH263,
+    ScreenVideo,
+    On2Vp6,
+    On2Vp6WithAlpha,
+    ScreenVideo2,
+
+    /// H.264
+    Avc,
+
+    /// A codec identified by a FourCC code in an Enhanced RTMP video header
+    FourCc(VideoFourCc),
+
+    /// A codec id that is not part of the FLV specification
+    Unknown(u8),
+}
+
+/// The FourCC codes used to identify codecs in Enhanced RTMP video headers
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum VideoFourCc {
+    /// H.264 (`avc1`)
+    Avc,
+
+    /// H.265 (`hvc1`)
+    Hevc,
+
+    /// AV1 (`av01`)
+    Av1,
+
+    /// VP8 (`vp08`)
+    Vp8,
+
+    /// VP9 (`vp09`)
+    Vp9,
+
+    /// A FourCC code that is not part of the Enhanced RTMP specification
+    Unknown([u8; 4]),
+}
+
+/// The type of frame contained in video data
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum VideoFrameType {
+    /// A seekable frame
+    Keyframe,
+
+    /// A non-seekable frame
+    InterFrame,
+
+    /// A non-seekable frame that H.263 decoders can throw away
+    DisposableInterFrame,
+
+    /// A keyframe generated by the server
+    GeneratedKeyframe,
+
+    /// The data contains video info or a command instead of a frame
+    VideoInfoOrCommand,
+
+    /// A frame type that is not part of the FLV specification
+    Unknown(u8),
+}
+
+/// The type of data contained in AVC (H.264) video data
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum AvcPacketType {
+    /// The data is an AVC decoder configuration record
+    SequenceHeader,
+
+    /// The data contains one or more NAL units
+    Nalu,
+
+    /// The end of the AVC sequence
+    EndOfSequence,
+
+    /// An AVC packet type that is not part of the FLV specification
+    Unknown(u8),
+}
+
+/// The type of data contained in an Enhanced RTMP video message
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum ExVideoPacketType {
+    /// The data is the codec's decoder configuration record
+    SequenceStart,
+
+    /// The data contains coded frames, preceded by a composition time for AVC and HEVC
+    CodedFrames,
+
+    /// The end of the sequence
+    SequenceEnd,
+
+    /// The data contains coded frames with an implied composition time of zero
+    CodedFramesX,
+
+    /// The data contains AMF encoded metadata, such as HDR information
+    Metadata,
+
+    /// The data is an MPEG-2 TS sequence start (used for AV1)
+    Mpeg2TsSequenceStart,
+
+    /// A packet type that is not part of the Enhanced RTMP specification
+    Unknown(u8),
+}
+
+/// The header at the start of the data of a video message
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct VideoTagHeader {
+    pub frame_type: VideoFrameType,
+    pub codec_id: VideoCodecId,
+
+    /// The type of AVC data, only present for legacy AVC video frames
+    pub avc_packet_type: Option<AvcPacketType>,
+
+    /// The type of data in an Enhanced RTMP video message.  When present the codec is always
+    /// identified by a FourCC code.
+    pub ex_packet_type: Option<ExVideoPacketType>,
+
+    /// The offset (in milliseconds) between the presentation time and the decode time of the
+    /// frame.  Only present for AVC video frames and Enhanced RTMP AVC/HEVC coded frames.
+    pub composition_time: Option<i32>,
+
+    /// How the tracks are laid out when this is an Enhanced RTMP multitrack message.  The rest
+    /// of the header then describes the first track of the message.
+    pub multitrack_type: Option<MultitrackType>,
+
+    /// The track the header describes.  Messages that are not multitrack always carry track 0.
+    pub track_id: u8,
+}
+
+impl VideoTagHeader {
+    /// Reads the video tag header from the start of the data of a video message
+    pub fn parse(data: &[u8]) -> Result<VideoTagHeader, MediaParseError> {
+        if data.is_empty() {
+            return Err(MediaParseErrorKind::NotEnoughData { expected: 1, actual: 0 }.into());
+        }
+
+        if data[0] & EX_HEADER_FLAG != 0 {
+            return VideoTagHeader::parse_ex_header(data);
+        }
+
+        let frame_type = VideoFrameType::from_u8(data[0] >> 4);
+        let codec_id = VideoCodecId::from_u8(data[0] & 0x0f);
+
+        let mut header = VideoTagHeader {
+            frame_type,
+            codec_id,
+            avc_packet_type: None,
+            ex_packet_type: None,
+            composition_time: None,
+            multitrack_type: None,
+            track_id: 0,
+        };
+
+        if header.has_avc_fields() {
+            if data.len() < 5 {
+                return Err(MediaParseErrorKind::NotEnoughData { expected: 5, actual: data.len() }.into());
+            }
+
+            header.avc_packet_type = Some(AvcPacketType::from_u8(data[1]));
+            header.composition_time = Some(read_composition_time(&data[2..5]));
+        }
+
+        Ok(header)
+    }
+
+    /// The number of bytes the header takes up at the start of the video data
+    pub fn header_size(&self) -> usize {
+        if self.ex_packet_type.is_some() {
+            let size = match self.multitrack_type {
+                Some(multitrack_type) => 1 + multitrack::first_track_header_size(multitrack_type),
+                None if self.has_fourcc() => 5,
+                None => 1,
+            };
+
+            if self.has_ex_composition_time() { size + 3 } else { size }
+        } else if self.has_avc_fields() {
+            5
+        } else {
+            1
+        }
+    }
+
+    /// Returns true if this is an AVC sequence header or an Enhanced RTMP sequence start
+    pub fn is_sequence_header(&self) -> bool {
+        self.avc_packet_type == Some(AvcPacketType::SequenceHeader) ||
+            self.ex_packet_type == Some(ExVideoPacketType::SequenceStart)
+    }
+
+    /// Returns true if this is a keyframe that contains actual video (i.e. not a sequence header)
+    pub fn is_keyframe(&self) -> bool {
+        let has_frames = self.ex_packet_type.is_none() ||
+            self.ex_packet_type == Some(ExVideoPacketType::CodedFrames) ||
+            self.ex_packet_type == Some(ExVideoPacketType::CodedFramesX);
+
+        self.frame_type == VideoFrameType::Keyframe && has_frames && !self.is_sequence_header()
+    }
+
+    /// Creates the bytes for the header.  The composition time is truncated to 24 bits.
+    ///
+    /// Multitrack headers are written as a message containing only the track the header
+    /// describes.
+    pub fn to_bytes(&self) -> Bytes {
+        self.to_bytes_with_body(&[])
+    }
+
+    /// Creates the data for a video message by placing the header in front of the codec
+    /// specific video data.
+    pub fn to_bytes_with_body(&self, body: &[u8]) -> Bytes {
+        let mut bytes = BytesMut::with_capacity(self.header_size() + body.len());
+
+        if let Some(packet_type) = self.ex_packet_type {
+            let fourcc = match self.codec_id {
+                VideoCodecId::FourCc(fourcc) => fourcc,
+                _ => VideoFourCc::Unknown([0; 4]),
+            };
+
+            if let Some(multitrack_type) = self.multitrack_type {
+                bytes.put_u8(EX_HEADER_FLAG | ((self.frame_type.to_u8() & 0x07) << 4) | MULTITRACK_PACKET_TYPE);
+
+                let composition_time_size = if self.has_ex_composition_time() { 3 } else { 0 };
+                multitrack::write_first_track_header(&mut bytes, &TrackHeader {
+                    multitrack_type,
+                    packet_type: packet_type.to_u8(),
+                    fourcc: fourcc.to_bytes(),
+                    track_id: self.track_id,
+                    track_size: Some(composition_time_size + body.len()),
+                });
+            } else {
+                bytes.put_u8(EX_HEADER_FLAG | ((self.frame_type.to_u8() & 0x07) << 4) | (packet_type.to_u8() & 0x0f));
+
+                if self.has_fourcc() {
+                    bytes.extend_from_slice(&fourcc.to_bytes());
+                }
+            }
+
+            if self.has_ex_composition_time() {
+                write_composition_time(&mut bytes, self.composition_time.unwrap_or(0));
+            }
+        } else {
+            bytes.put_u8((self.frame_type.to_u8() << 4) | (self.codec_id.to_u8() & 0x0f));
+        }
+
+        if self.ex_packet_type.is_none() && self.has_avc_fields() {
+            bytes.put_u8(self.avc_packet_type.unwrap_or(AvcPacketType::Nalu).to_u8());
+            write_composition_time(&mut bytes, self.composition_time.unwrap_or(0));
+        }
+
+        bytes.extend_from_slice(body);
+        bytes.freeze()
+    }
+
+    fn parse_ex_header(data: &[u8]) -> Result<VideoTagHeader, MediaParseError> {
+        let mut header = VideoTagHeader {
+            frame_type: VideoFrameType::from_u8((data[0] >> 4) & 0x07),
+            codec_id: VideoCodecId::Unknown(0),
+            avc_packet_type: None,
+            ex_packet_type: Some(ExVideoPacketType::from_u8(data[0] & 0x0f)),
+            composition_time: None,
+            multitrack_type: None,
+            track_id: 0,
+        };
+
+        let offset = if data[0] & 0x0f == MULTITRACK_PACKET_TYPE {
+            let (track, offset) = multitrack::read_first_track_header(data)?;
+            header.ex_packet_type = Some(ExVideoPacketType::from_u8(track.packet_type));
+            header.codec_id = VideoCodecId::FourCc(VideoFourCc::from_bytes(track.fourcc));
+            header.multitrack_type = Some(track.multitrack_type);
+            header.track_id = track.track_id;
+            offset
+        } else if header.has_fourcc() {
+            if data.len() < 5 {
+                return Err(MediaParseErrorKind::NotEnoughData { expected: 5, actual: data.len() }.into());
+            }
+
+            header.codec_id = VideoCodecId::FourCc(VideoFourCc::from_bytes([data[1], data[2], data[3], data[4]]));
+            5
+        } else {
+            // Command frames are followed by a single command byte instead of a codec
+            return Ok(header);
+        };
+
+        if header.has_ex_composition_time() {
+            if data.len() < offset + 3 {
+                return Err(MediaParseErrorKind::NotEnoughData { expected: offset + 3, actual: data.len() }.into());
+            }
+
+            header.composition_time = Some(read_composition_time(&data[offset..offset + 3]));
+        }
+
+        Ok(header)
+    }
+
+    fn has_avc_fields(&self) -> bool {
+        // Video info/command frames only contain a single byte after the header
+        self.codec_id == VideoCodecId::Avc && self.frame_type != VideoFrameType::VideoInfoOrCommand
+    }
+
+    fn has_fourcc(&self) -> bool {
+        self.multitrack_type.is_some() ||
+            self.frame_type != VideoFrameType::VideoInfoOrCommand ||
+            self.ex_packet_type == Some(ExVideoPacketType::Metadata)
+    }
+
+    fn has_ex_composition_time(&self) -> bool {
+        let has_composition_time = self.codec_id == VideoCodecId::FourCc(VideoFourCc::Avc) ||
+            self.codec_id == VideoCodecId::FourCc(VideoFourCc::Hevc);
+
+        self.ex_packet_type == Some(ExVideoPacketType::CodedFrames) && has_composition_time
+    }
+}
+
+pub(super) const EX_HEADER_FLAG: u8 = 0x80;
+const MULTITRACK_PACKET_TYPE: u8 = 6;
+
+fn read_composition_time(bytes: &[u8]) -> i32 {
+    // The composition time is a signed 24 bit integer
+    let composition_time = ((bytes[0] as i32) << 16) | ((bytes[1] as i32) << 8) | (bytes[2] as i32);
+    (composition_time << 8) >> 8
+}
+
+fn write_composition_time(bytes: &mut BytesMut, composition_time: i32) {
+    let composition_time = composition_time as u32;
+    bytes.put_u8((composition_time >> 16) as u8);
+    bytes.put_u8((composition_time >> 8) as u8);
+    bytes.put_u8(composition_time as u8);
+}
+
+impl VideoCodecId {
+    fn from_u8(value: u8) -> VideoCodecId {
+        match value {
+            1 => VideoCodecId::Jpeg,
+            2 => VideoCodecId::SorensonH263,
+            3 => VideoCodecId::ScreenVideo,
+            4 => VideoCodecId::On2Vp6,
+            5 => VideoCodecId::On2Vp6WithAlpha,
+            6 => VideoCodecId::ScreenVideo2,
+            7 => VideoCodecId::Avc,
+            x => VideoCodecId::Unknown(x),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            VideoCodecId::Jpeg => 1,
+            VideoCodecId::SorensonH263 => 2,
+            VideoCodecId::ScreenVideo => 3,
+            VideoCodecId::On2Vp6 => 4,
+            VideoCodecId::On2Vp6WithAlpha => 5,
+            VideoCodecId::ScreenVideo2 => 6,
+            VideoCodecId::Avc => 7,
+            VideoCodecId::FourCc(_) => 0,
+            VideoCodecId::Unknown(x) => x,
+        }
+    }
+}
+
+impl VideoFourCc {
+    /// Gets the FourCC code for a codec from its string form (e.g. `hvc1`)
+    pub fn parse(value: &str) -> Option<VideoFourCc> {
+        let bytes = value.as_bytes();
+        if bytes.len() != 4 {
+            return None;
+        }
+
+        Some(VideoFourCc::from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn from_bytes(bytes: [u8; 4]) -> VideoFourCc {
+        match &bytes {
+            b"avc1" => VideoFourCc::Avc,
+            b"hvc1" => VideoFourCc::Hevc,
+            b"av01" => VideoFourCc::Av1,
+            b"vp08" => VideoFourCc::Vp8,
+            b"vp09" => VideoFourCc::Vp9,
+            _ => VideoFourCc::Unknown(bytes),
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 4] {
+        match self {
+            VideoFourCc::Avc => *b"avc1",
+            VideoFourCc::Hevc => *b"hvc1",
+            VideoFourCc::Av1 => *b"av01",
+            VideoFourCc::Vp8 => *b"vp08",
+            VideoFourCc::Vp9 => *b"vp09",
+            VideoFourCc::Unknown(bytes) => bytes,
+        }
+    }
+}
+
+impl fmt::Display for VideoFourCc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.to_bytes()))
+    }
+}
+
+impl ExVideoPacketType {
+    fn from_u8(value: u8) -> ExVideoPacketType {
+        match value {
+            0 => ExVideoPacketType::SequenceStart,
+            1 => ExVideoPacketType::CodedFrames,
+            2 => ExVideoPacketType::SequenceEnd,
+            3 => ExVideoPacketType::CodedFramesX,
+            4 => ExVideoPacketType::Metadata,
+            5 => ExVideoPacketType::Mpeg2TsSequenceStart,
+            x => ExVideoPacketType::Unknown(x),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            ExVideoPacketType::SequenceStart => 0,
+            ExVideoPacketType::CodedFrames => 1,
+            ExVideoPacketType::SequenceEnd => 2,
+            ExVideoPacketType::CodedFramesX => 3,
+            ExVideoPacketType::Metadata => 4,
+            ExVideoPacketType::Mpeg2TsSequenceStart => 5,
+            ExVideoPacketType::Unknown(x) => x,
+        }
+    }
+}
+
+impl VideoFrameType {
+    fn from_u8(value: u8) -> VideoFrameType {
+        match value {
+            1 => VideoFrameType::Keyframe,
+            2 => VideoFrameType::InterFrame,
+            3 => VideoFrameType::DisposableInterFrame,
+            4 => VideoFrameType::GeneratedKeyframe,
+            5 => VideoFrameType::VideoInfoOrCommand,
+            x => VideoFrameType::Unknown(x),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            VideoFrameType::Keyframe => 1,
+            VideoFrameType::InterFrame => 2,
+            VideoFrameType::DisposableInterFrame => 3,
+            VideoFrameType::GeneratedKeyframe => 4,
+            VideoFrameType::VideoInfoOrCommand => 5,
+            VideoFrameType::Unknown(x) => x,
+        }
+    }
+}
+
+impl AvcPacketType {
+    fn from_u8(value: u8) -> AvcPacketType {
+        match value {
+            0 => AvcPacketType::SequenceHeader,
+            1 => AvcPacketType::Nalu,
+            2 => AvcPacketType::EndOfSequence,
+            x => AvcPacketType::Unknown(x),
+        }
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            AvcPacketType::SequenceHeader => 0,
+            AvcPacketType::Nalu => 1,
+            AvcPacketType::EndOfSequence => 2,
+            AvcPacketType::Unknown(x) => x,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use media::MediaParseErrorKind;
+
+    #[test]
+    fn can_parse_avc_sequence_header() {
+        let header = VideoTagHeader::parse(&[0x17, 0x00, 0x00, 0x00, 0x00, 0x01]).unwrap();
+
+        assert_eq!(header.frame_type, VideoFrameType::Keyframe, "Incorrect frame type");
+        assert_eq!(header.codec_id, VideoCodecId::Avc, "Incorrect codec id");
+        assert_eq!(header.avc_packet_type, Some(AvcPacketType::SequenceHeader), "Incorrect packet type");
+        assert_eq!(header.composition_time, Some(0), "Incorrect composition time");
+        assert_eq!(header.header_size(), 5, "Incorrect header length");
+        assert!(header.is_sequence_header(), "Expected a sequence header");
+        assert!(!header.is_keyframe(), "Sequence header should not be a keyframe");
+    }
+
+    #[test]
+    fn can_parse_avc_keyframe() {
+        let header = VideoTagHeader::parse(&[0x17, 0x01, 0x00, 0x00, 0x21]).unwrap();
+
+        assert_eq!(header.avc_packet_type, Some(AvcPacketType::Nalu), "Incorrect packet type");
+        assert_eq!(header.composition_time, Some(33), "Incorrect composition time");
+        assert!(header.is_keyframe(), "Expected a keyframe");
+    }
+
+    #[test]
+    fn can_parse_negative_composition_time() {
+        let header = VideoTagHeader::parse(&[0x27, 0x01, 0xff, 0xff, 0xdf]).unwrap();
+
+        assert_eq!(header.frame_type, VideoFrameType::InterFrame, "Incorrect frame type");
+        assert_eq!(header.composition_time, Some(-33), "Incorrect composition time");
+        assert!(!header.is_keyframe(), "Inter frame should not be a keyframe");
+    }
+
+    #[test]
+    fn can_parse_non_avc_header() {
+        let header = VideoTagHeader::parse(&[0x24, 0xaa]).unwrap();
+
+        assert_eq!(header.frame_type, VideoFrameType::InterFrame, "Incorrect frame type");
+        assert_eq!(header.codec_id, VideoCodecId::On2Vp6, "Incorrect codec id");
+        assert_eq!(header.avc_packet_type, None, "Unexpected packet type");
+        assert_eq!(header.composition_time, None, "Unexpected composition time");
+        assert_eq!(header.header_size(), 1, "Incorrect header length");
+    }
+
+    #[test]
+    fn error_when_avc_header_is_truncated() {
+        let error = VideoTagHeader::parse(&[0x17, 0x01]).unwrap_err();
+
+        match error.kind {
+            MediaParseErrorKind::NotEnoughData { expected: 5, actual: 2 } => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_build_avc_header_with_body() {
+        let header = VideoTagHeader {
+            frame_type: VideoFrameType::InterFrame,
+            codec_id: VideoCodecId::Avc,
+            avc_packet_type: Some(AvcPacketType::Nalu),
+            ex_packet_type: None,
+            composition_time: Some(-33),
+            multitrack_type: None,
+            track_id: 0,
+        };
+
+        let bytes = header.to_bytes_with_body(&[1, 2, 3]);
+
+        assert_eq!(&bytes[..], &[0x27, 0x01, 0xff, 0xff, 0xdf, 1, 2, 3]);
+        assert_eq!(VideoTagHeader::parse(&bytes).unwrap(), header, "Header did not round trip");
+    }
+
+    #[test]
+    fn can_build_non_avc_header() {
+        let header = VideoTagHeader {
+            frame_type: VideoFrameType::Keyframe,
+            codec_id: VideoCodecId::SorensonH263,
+            avc_packet_type: None,
+            ex_packet_type: None,
+            composition_time: None,
+            multitrack_type: None,
+            track_id: 0,
+        };
+
+        assert_eq!(&header.to_bytes()[..], &[0x12]);
+    }
+
+    #[test]
+    fn can_parse_ex_header_sequence_start() {
+        let header = VideoTagHeader::parse(&[0x90, b'h', b'v', b'c', b'1', 0x01]).unwrap();
+
+        assert_eq!(header.frame_type, VideoFrameType::Keyframe, "Incorrect frame type");
+        assert_eq!(header.codec_id, VideoCodecId::FourCc(VideoFourCc::Hevc), "Incorrect codec id");
+        assert_eq!(header.ex_packet_type, Some(ExVideoPacketType::SequenceStart), "Incorrect packet type");
+        assert_eq!(header.avc_packet_type, None, "Unexpected avc packet type");
+        assert_eq!(header.composition_time, None, "Unexpected composition time");
+        assert_eq!(header.header_size(), 5, "Incorrect header length");
+        assert!(header.is_sequence_header(), "Expected a sequence header");
+        assert!(!header.is_keyframe(), "Sequence start should not be a keyframe");
+    }
+
+    #[test]
+    fn can_parse_ex_header_hevc_coded_frames_with_composition_time() {
+        let header = VideoTagHeader::parse(&[0x91, b'h', b'v', b'c', b'1', 0x00, 0x00, 0x21, 0xaa]).unwrap();
+
+        assert_eq!(header.ex_packet_type, Some(ExVideoPacketType::CodedFrames), "Incorrect packet type");
+        assert_eq!(header.composition_time, Some(33), "Incorrect composition time");
+        assert_eq!(header.header_size(), 8, "Incorrect header length");
+        assert!(header.is_keyframe(), "Expected a keyframe");
+    }
+
+    #[test]
+    fn can_parse_ex_header_av1_coded_frames_without_composition_time() {
+        let header = VideoTagHeader::parse(&[0xa1, b'a', b'v', b'0', b'1', 0xaa]).unwrap();
+
+        assert_eq!(header.frame_type, VideoFrameType::InterFrame, "Incorrect frame type");
+        assert_eq!(header.codec_id, VideoCodecId::FourCc(VideoFourCc::Av1), "Incorrect codec id");
+        assert_eq!(header.composition_time, None, "Unexpected composition time");
+        assert_eq!(header.header_size(), 5, "Incorrect header length");
+    }
+
+    #[test]
+    fn can_parse_ex_header_packet_types() {
+        let packet_types = [
+            (0x93, ExVideoPacketType::CodedFramesX),
+            (0x92, ExVideoPacketType::SequenceEnd),
+            (0x94, ExVideoPacketType::Metadata),
+            (0x95, ExVideoPacketType::Mpeg2TsSequenceStart),
+        ];
+
+        for &(byte, packet_type) in packet_types.iter() {
+            let header = VideoTagHeader::parse(&[byte, b'v', b'p', b'0', b'9']).unwrap();
+            assert_eq!(header.ex_packet_type, Some(packet_type), "Incorrect packet type for {:x}", byte);
+            assert_eq!(header.codec_id, VideoCodecId::FourCc(VideoFourCc::Vp9), "Incorrect codec id for {:x}", byte);
+        }
+    }
+
+    #[test]
+    fn can_build_ex_header_with_body() {
+        let header = VideoTagHeader {
+            frame_type: VideoFrameType::InterFrame,
+            codec_id: VideoCodecId::FourCc(VideoFourCc::Hevc),
+            avc_packet_type: None,
+            ex_packet_type: Some(ExVideoPacketType::CodedFrames),
+            composition_time: Some(-33),
+            multitrack_type: None,
+            track_id: 0,
+        };
+
+        let bytes = header.to_bytes_with_body(&[1, 2]);
+
+        assert_eq!(&bytes[..], &[0xa1, b'h', b'v', b'c', b'1', 0xff, 0xff, 0xdf, 1, 2]);
+        assert_eq!(VideoTagHeader::parse(&bytes).unwrap(), header, "Header did not round trip");
+    }
+
+    #[test]
+    fn can_convert_fourcc_to_and_from_strings() {
+        assert_eq!(VideoFourCc::parse("av01"), Some(VideoFourCc::Av1));
+        assert_eq!(VideoFourCc::parse("abcd"), Some(VideoFourCc::Unknown(*b"abcd")));
+        assert_eq!(VideoFourCc::parse("abc"), None);
+        assert_eq!(VideoFourCc::Hevc.to_string(), "hvc1");
+    }
+
+    #[test]
+    fn can_parse_one_track_multitrack_header() {
+        let header = VideoTagHeader::parse(&[0x96, 0x01, b'h', b'v', b'c', b'1', 0x03, 0x00, 0x00, 0x21, 0xaa]).unwrap();
+
+        assert_eq!(header.frame_type, VideoFrameType::Keyframe, "Incorrect frame type");
+        assert_eq!(header.codec_id, VideoCodecId::FourCc(VideoFourCc::Hevc), "Incorrect codec id");
+        assert_eq!(header.ex_packet_type, Some(ExVideoPacketType::CodedFrames), "Incorrect packet type");
+        assert_eq!(header.multitrack_type, Some(MultitrackType::OneTrack), "Incorrect multitrack type");
+        assert_eq!(header.track_id, 3, "Incorrect track id");
+        assert_eq!(header.composition_time, Some(33), "Incorrect composition time");
+        assert_eq!(header.header_size(), 10, "Incorrect header length");
+    }
+
+    #[test]
+    fn can_parse_many_track_multitrack_header() {
+        let header = VideoTagHeader::parse(&[0x96, 0x10, b'a', b'v', b'0', b'1', 0x01, 0x00, 0x00, 0x01, 0xaa]).unwrap();
+
+        assert_eq!(header.ex_packet_type, Some(ExVideoPacketType::SequenceStart), "Incorrect packet type");
+        assert_eq!(header.multitrack_type, Some(MultitrackType::ManyTracks), "Incorrect multitrack type");
+        assert_eq!(header.track_id, 1, "Incorrect track id");
+        assert_eq!(header.header_size(), 10, "Incorrect header length");
+    }
+
+    #[test]
+    fn error_when_multitrack_type_is_unknown() {
+        let error = VideoTagHeader::parse(&[0x96, 0x31, b'a', b'v', b'0', b'1', 0x01]).unwrap_err();
+
+        match error.kind {
+            MediaParseErrorKind::UnknownMultitrackType(3) => (),
+            x => panic!("Unexpected error kind: {:?}", x),
+        }
+    }
+
+    #[test]
+    fn can_build_multitrack_header_with_body() {
+        let header = VideoTagHeader {
+            frame_type: VideoFrameType::InterFrame,
+            codec_id: VideoCodecId::FourCc(VideoFourCc::Avc),
+            avc_packet_type: None,
+            ex_packet_type: Some(ExVideoPacketType::CodedFrames),
+            composition_time: Some(0),
+            multitrack_type: Some(MultitrackType::ManyTracks),
+            track_id: 2,
+        };
+
+        let bytes = header.to_bytes_with_body(&[1, 2]);
+
+        assert_eq!(&bytes[..], &[0xa6, 0x11, b'a', b'v', b'c', b'1', 0x02, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 1, 2]);
+        assert_eq!(VideoTagHeader::parse(&bytes).unwrap(), header, "Header did not round trip");
+    }
+}
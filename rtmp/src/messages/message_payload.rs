@@ -1,7 +1,7 @@
 use std::fmt;
 use bytes::Bytes;
 use ::time::RtmpTimestamp;
-use ::messages::{MessageDeserializationError, MessageSerializationError};
+use ::messages::{MessageDeserializationError, MessageDeserializationErrorKind, MessageSerializationError};
 use ::messages::RtmpMessage;
 use super::types;
 
@@ -41,9 +41,9 @@ impl MessagePayload {
     /// Deserializes the message data in the specified payload into its corresponding
     /// `RtmpMessage`.
     ///
-    /// Note that flash clients (like Wowza's test client) lie and mark amf0 data and commands as
-    /// amf3 values.  It is unknown why this happens, but any Amf3 command/data messages that are
-    /// seen are deserialized as amf0.  So far this has not caused any issues.
+    /// Note that flash clients (like Wowza's test client) mark amf0 data and commands as amf3
+    /// messages, while still encoding their values as amf0.  Amf3 command and data messages are
+    /// therefore read as amf0 values, with any real amf3 values wrapped in `Amf0Value::AvmPlus`.
     pub fn to_rtmp_message(&self) -> Result<RtmpMessage, MessageDeserializationError> {
         match self.type_id {
             1 => types::set_chunk_size::deserialize(self.data.clone()),
@@ -54,27 +54,29 @@ impl MessagePayload {
             6 => types::set_peer_bandwidth::deserialize(self.data.clone()),
             8 => types::audio_data::deserialize(self.data.clone()),
             9 => types::video_data::deserialize(self.data.clone()),
+            15 => types::amf3_data::deserialize(self.data.clone()),
+            16 => types::shared_object::deserialize_amf3(self.data.clone()),
+            17 => types::amf3_command::deserialize(self.data.clone()),
             18 => types::amf0_data::deserialize(self.data.clone()),
+            19 => types::shared_object::deserialize_amf0(self.data.clone()),
             20 => types::amf0_command::deserialize(self.data.clone()),
-
-            // For some reason Flash players (like wowza's test player) send messages
-            // that are flagged as amf3 encoded, but in reality they are amf0 encoded
-            15 => types::amf0_data::deserialize(self.data.clone()),
-
-            17 => {
-                // Fake amf3 commands usually seem to have a 0 in front of the amf0 data.
-                if self.data.len() > 0 && self.data[0] == 0x00 {
-                    let slice = self.data.slice(1..);
-                    types::amf0_command::deserialize(slice)
-                } else {
-                    types::amf0_command::deserialize(self.data.clone())
-                }
-            },
+            22 => types::aggregate::deserialize(self.data.clone()),
 
             _ => Ok(RtmpMessage::Unknown { type_id: self.type_id, data: self.data.clone() })
         }
     }
 
+    /// Splits an aggregate message payload into the payloads of the individual messages it
+    /// contains.  The resulting payloads are on the same message stream as the aggregate message,
+    /// with timestamps rebased relative to the aggregate message's timestamp.
+    pub fn split_aggregate(&self) -> Result<Vec<MessagePayload>, MessageDeserializationError> {
+        if self.type_id != 22 {
+            return Err(MessageDeserializationError { kind: MessageDeserializationErrorKind::InvalidMessageFormat });
+        }
+
+        types::aggregate::split(self.data.clone(), self.timestamp, self.message_stream_id)
+    }
+
     /// This creates a `MessagePayload` from an `RtmpMessage`.
     ///
     /// Since RTMP messages do not contain timestamp or the conversation stream id these must be
@@ -92,12 +94,27 @@ impl MessagePayload {
             RtmpMessage::Acknowledgement { sequence_number }
             => types::acknowledgement::serialize(sequence_number)?,
 
+            RtmpMessage::Aggregate { data }
+            => types::aggregate::serialize(data)?,
+
             RtmpMessage::Amf0Command { command_name, transaction_id, command_object, additional_arguments }
             => types::amf0_command::serialize(command_name, transaction_id, command_object, additional_arguments)?,
 
             RtmpMessage::Amf0Data { values }
             => types::amf0_data::serialize(values)?,
 
+            RtmpMessage::Amf3Command { command_name, transaction_id, command_object, additional_arguments }
+            => types::amf3_command::serialize(command_name, transaction_id, command_object, additional_arguments)?,
+
+            RtmpMessage::Amf3Data { values }
+            => types::amf3_data::serialize(values)?,
+
+            RtmpMessage::Amf0SharedObject { name, version, persistent, events }
+            => types::shared_object::serialize_amf0(name, version, persistent, events)?,
+
+            RtmpMessage::Amf3SharedObject { name, version, persistent, events }
+            => types::shared_object::serialize_amf3(name, version, persistent, events)?,
+
             RtmpMessage::AudioData { data }
             => types::audio_data::serialize(data)?,
 
@@ -130,9 +147,10 @@ impl MessagePayload {
 mod tests {
     use super::{RtmpMessage, MessagePayload};
     use bytes::{Bytes, BytesMut, BufMut};
-    use ::messages::{PeerBandwidthLimitType, UserControlEventType};
+    use ::messages::{PeerBandwidthLimitType, UserControlEventType, SharedObjectEvent};
     use ::time::RtmpTimestamp;
     use rml_amf0::Amf0Value;
+    use rml_amf3::Amf3Value;
 
     #[test]
     fn can_get_payload_from_abort_message() {
@@ -160,6 +178,52 @@ mod tests {
         assert_eq!(result.timestamp, 55, "Incorrect timestamp");
     }
 
+    #[test]
+    fn can_get_payload_from_aggregate_message() {
+        let timestamp = RtmpTimestamp::new(55);
+        let stream_id = 52;
+        let message = RtmpMessage::Aggregate { data: Bytes::from(vec![1,2,3]) };
+        let result = MessagePayload::from_rtmp_message(message, timestamp, stream_id).unwrap();
+
+        assert_eq!(&result.data[..], &[1,2,3], "Incorrect payload data");
+        assert_eq!(result.type_id, 22, "Incorrect type id");
+        assert_eq!(result.message_stream_id, stream_id, "Incorrect message stream id");
+        assert_eq!(result.timestamp, 55, "Incorrect timestamp");
+    }
+
+    #[test]
+    fn can_split_aggregate_payload() {
+        let data = vec![
+            9, 0, 0, 2, 0, 0, 5, 0, 0, 0, 0, 1, 2, 0, 0, 0, 13,
+            8, 0, 0, 1, 0, 0, 9, 0, 0, 0, 0, 3, 0, 0, 0, 12,
+        ];
+
+        let payload = MessagePayload {
+            timestamp: RtmpTimestamp::new(100),
+            type_id: 22,
+            message_stream_id: 3,
+            data: Bytes::from(data),
+        };
+
+        let result = payload.split_aggregate().unwrap();
+
+        assert_eq!(result.len(), 2, "Unexpected number of payloads");
+        assert_eq!(result[0].to_rtmp_message().unwrap(), RtmpMessage::VideoData { data: Bytes::from(vec![1, 2]) });
+        assert_eq!(result[0].timestamp, 100, "Incorrect first timestamp");
+        assert_eq!(result[0].message_stream_id, 3, "Incorrect first message stream id");
+        assert_eq!(result[1].to_rtmp_message().unwrap(), RtmpMessage::AudioData { data: Bytes::from(vec![3]) });
+        assert_eq!(result[1].timestamp, 104, "Incorrect second timestamp");
+        assert_eq!(result[1].message_stream_id, 3, "Incorrect second message stream id");
+    }
+
+    #[test]
+    fn cannot_split_non_aggregate_payload() {
+        let message = RtmpMessage::VideoData { data: Bytes::from(vec![1,2,3]) };
+        let payload = MessagePayload::from_rtmp_message(message, RtmpTimestamp::new(0), 1).unwrap();
+
+        assert!(payload.split_aggregate().is_err(), "Expected an error");
+    }
+
     #[test]
     fn can_get_payload_from_amf0_command_message() {
         let timestamp = RtmpTimestamp::new(55);
@@ -409,8 +473,14 @@ mod tests {
         payload.data = new_data.freeze();
 
         let result = payload.to_rtmp_message().unwrap();
+        let expected = RtmpMessage::Amf3Command {
+            command_name: "test".to_string(),
+            transaction_id: 15.0,
+            command_object: Amf0Value::Number(23.0),
+            additional_arguments: vec![Amf0Value::Null]
+        };
 
-        assert_eq!(result, message);
+        assert_eq!(result, expected);
     }
 
     #[test]
@@ -419,8 +489,67 @@ mod tests {
         let mut payload = MessagePayload::from_rtmp_message(message.clone(), RtmpTimestamp::new(0), 15).unwrap();
         payload.type_id = 15;
 
+        let result = payload.to_rtmp_message().unwrap();
+        let expected = RtmpMessage::Amf3Data { values: vec![Amf0Value::Number(23.3)]};
+
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_get_rtmp_message_for_amf3_command_payload() {
+        let message = RtmpMessage::Amf3Command {
+            command_name: "test".to_string(),
+            transaction_id: 15.0,
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![Amf0Value::AvmPlus(Amf3Value::Integer(5))]
+        };
+
+        let payload = MessagePayload::from_rtmp_message(message.clone(), RtmpTimestamp::new(0), 15).unwrap();
+        let result = payload.to_rtmp_message().unwrap();
+
+        assert_eq!(payload.type_id, 17, "Incorrect type id");
+        assert_eq!(result, message);
+    }
+
+    #[test]
+    fn can_get_rtmp_message_for_amf3_data_payload() {
+        let message = RtmpMessage::Amf3Data { values: vec![Amf0Value::AvmPlus(Amf3Value::Double(23.3))]};
+        let payload = MessagePayload::from_rtmp_message(message.clone(), RtmpTimestamp::new(0), 15).unwrap();
+        let result = payload.to_rtmp_message().unwrap();
+
+        assert_eq!(payload.type_id, 15, "Incorrect type id");
+        assert_eq!(result, message);
+    }
+
+    #[test]
+    fn can_get_rtmp_message_for_amf0_shared_object_payload() {
+        let message = RtmpMessage::Amf0SharedObject {
+            name: "test".to_string(),
+            version: 2,
+            persistent: true,
+            events: vec![SharedObjectEvent::Use],
+        };
+
+        let payload = MessagePayload::from_rtmp_message(message.clone(), RtmpTimestamp::new(0), 15).unwrap();
+        let result = payload.to_rtmp_message().unwrap();
+
+        assert_eq!(payload.type_id, 19, "Incorrect type id");
+        assert_eq!(result, message);
+    }
+
+    #[test]
+    fn can_get_rtmp_message_for_amf3_shared_object_payload() {
+        let message = RtmpMessage::Amf3SharedObject {
+            name: "test".to_string(),
+            version: 2,
+            persistent: false,
+            events: vec![SharedObjectEvent::Release],
+        };
+
+        let payload = MessagePayload::from_rtmp_message(message.clone(), RtmpTimestamp::new(0), 15).unwrap();
         let result = payload.to_rtmp_message().unwrap();
 
+        assert_eq!(payload.type_id, 16, "Incorrect type id");
         assert_eq!(result, message);
     }
 }
@@ -57,6 +57,46 @@ pub enum UserControlEventType {
     PingResponse
 }
 
+/// An individual change or notification carried inside of a shared object message
+#[derive(PartialEq, Debug, Clone)]
+pub enum SharedObjectEvent {
+    /// The client wants to connect to the shared object
+    Use,
+
+    /// The client no longer needs the shared object
+    Release,
+
+    /// The client is requesting that the named property be changed to the specified value
+    RequestChange { name: String, value: Amf0Value },
+
+    /// Notifies the client that the named property has changed to the specified value
+    Change { name: String, value: Amf0Value },
+
+    /// Notifies the client that its request to change the named property was accepted
+    Success { name: String },
+
+    /// A message that is broadcast to all clients connected to the shared object
+    SendMessage { values: Vec<Amf0Value> },
+
+    /// An error or warning notification for the shared object
+    Status { code: String, level: String },
+
+    /// Notifies the client that the shared object's data has been cleared
+    Clear,
+
+    /// Notifies the client that the named property has been removed
+    Remove { name: String },
+
+    /// The client is requesting that the named property be removed
+    RequestRemove { name: String },
+
+    /// Notifies the client that it has successfully connected to the shared object
+    UseSuccess,
+
+    /// A shared object event with a type that we do not know about
+    Unknown { event_type: u8, data: Bytes },
+}
+
 /// An enumeration of all types of RTMP messages that are supported
 #[derive(PartialEq, Debug, Clone)]
 pub enum RtmpMessage {
@@ -72,12 +112,30 @@ pub enum RtmpMessage {
     /// acknowledgement.
     Acknowledgement { sequence_number: u32 },
 
+    /// A group of audio, video and data messages packed together as FLV tags.  The individual
+    /// messages can be retrieved with `MessagePayload::split_aggregate()`.
+    Aggregate { data: Bytes },
+
     /// A command being sent, encoded with amf0 values
     Amf0Command { command_name: String, transaction_id: f64, command_object: Amf0Value, additional_arguments: Vec<Amf0Value> },
 
     /// A message containing an array of data encoded as amf0 values
     Amf0Data { values: Vec<Amf0Value> },
 
+    /// A command being sent with amf3 framing.  The values are amf0 encoded, with any amf3
+    /// values wrapped in `Amf0Value::AvmPlus`.
+    Amf3Command { command_name: String, transaction_id: f64, command_object: Amf0Value, additional_arguments: Vec<Amf0Value> },
+
+    /// A message containing an array of data with amf3 framing.  The values are amf0 encoded, with
+    /// any amf3 values wrapped in `Amf0Value::AvmPlus`.
+    Amf3Data { values: Vec<Amf0Value> },
+
+    /// A shared object message encoded with amf0 values
+    Amf0SharedObject { name: String, version: u32, persistent: bool, events: Vec<SharedObjectEvent> },
+
+    /// A shared object message with amf3 framing
+    Amf3SharedObject { name: String, version: u32, persistent: bool, events: Vec<SharedObjectEvent> },
+
     /// A message containing audio data
     AudioData { data: Bytes },
 
@@ -110,8 +168,13 @@ impl RtmpMessage {
             RtmpMessage::Unknown { type_id, data: _ } => type_id,
             RtmpMessage::Abort { stream_id: _ } => 2_u8,
             RtmpMessage::Acknowledgement { sequence_number: _ } => 3_u8,
+            RtmpMessage::Aggregate { data: _ } => 22_u8,
             RtmpMessage::Amf0Command { command_name: _, transaction_id: _, command_object: _, additional_arguments: _ } => 20_u8,
             RtmpMessage::Amf0Data { values: _ } => 18_u8,
+            RtmpMessage::Amf3Command { command_name: _, transaction_id: _, command_object: _, additional_arguments: _ } => 17_u8,
+            RtmpMessage::Amf3Data { values: _ } => 15_u8,
+            RtmpMessage::Amf0SharedObject { name: _, version: _, persistent: _, events: _ } => 19_u8,
+            RtmpMessage::Amf3SharedObject { name: _, version: _, persistent: _, events: _ } => 16_u8,
             RtmpMessage::AudioData { data: _ } => 8_u8,
             RtmpMessage::SetChunkSize { size: _ } => 1_u8,
             RtmpMessage::SetPeerBandwidth { size: _, limit_type: _ } => 6_u8,
@@ -17,6 +17,11 @@ pub enum MessageSerializationErrorKind {
     #[fail(display = "Cannot serialize a SetChunkSize message with a size of 2147483648 or greater")]
     InvalidChunkSize ,
 
+    /// A string that is serialized with a 16 bit length prefix (such as a shared object name)
+    /// was longer than 65,535 bytes
+    #[fail(display = "Cannot serialize a string with a length of 65536 bytes or greater")]
+    StringTooLong,
+
     /// The values provided could not be serialized into valid AMF0 encoded data
     #[fail(display = "The values provided could not be serialized into valid AMF0 encoded data")]
     Amf0SerializationError(#[cause] Amf0SerializationError),
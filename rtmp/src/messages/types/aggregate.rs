@@ -0,0 +1,160 @@
+use std::io::Cursor;
+use byteorder::{BigEndian, ReadBytesExt};
+use bytes::{Buf, Bytes};
+
+use ::time::RtmpTimestamp;
+use ::messages::{MessageDeserializationError, MessageDeserializationErrorKind, MessageSerializationError};
+use ::messages::{MessagePayload, RtmpMessage};
+
+/// Each sub-message is an FLV tag, made up of an 11 byte header, the message body, and a 4 byte
+/// back pointer containing the size of the tag.
+const TAG_HEADER_SIZE: usize = 11;
+const BACK_POINTER_SIZE: usize = 4;
+
+pub fn serialize(bytes: Bytes) -> Result<Bytes, MessageSerializationError> {
+    Ok(bytes)
+}
+
+pub fn deserialize(data: Bytes) -> Result<RtmpMessage, MessageDeserializationError> {
+    Ok(RtmpMessage::Aggregate {
+        data
+    })
+}
+
+/// Splits the body of an aggregate message into the payloads of its sub-messages.
+///
+/// Sub-message timestamps are rebased so that the first sub-message has the timestamp of the
+/// aggregate message itself, and later sub-messages keep their offset from the first one.
+pub fn split(data: Bytes, timestamp: RtmpTimestamp, message_stream_id: u32) -> Result<Vec<MessagePayload>, MessageDeserializationError> {
+    let mut cursor = Cursor::new(data);
+    let mut payloads = Vec::new();
+    let mut first_timestamp = None;
+
+    while cursor.has_remaining() {
+        if cursor.remaining() < TAG_HEADER_SIZE {
+            return Err(MessageDeserializationError { kind: MessageDeserializationErrorKind::InvalidMessageFormat });
+        }
+
+        let type_id = cursor.read_u8()?;
+        let data_size = cursor.read_u24::<BigEndian>()? as usize;
+        let lower_timestamp = cursor.read_u24::<BigEndian>()?;
+        let upper_timestamp = cursor.read_u8()? as u32;
+        let _ = cursor.read_u24::<BigEndian>()?; // stream id, always 0
+
+        if cursor.remaining() < data_size {
+            return Err(MessageDeserializationError { kind: MessageDeserializationErrorKind::InvalidMessageFormat });
+        }
+
+        let start = cursor.position() as usize;
+        let body = cursor.get_ref().slice(start..start + data_size);
+        cursor.set_position((start + data_size) as u64);
+
+        // Some encoders leave off the back pointer of the final tag
+        if cursor.remaining() >= BACK_POINTER_SIZE {
+            let _ = cursor.read_u32::<BigEndian>()?;
+        } else {
+            cursor.set_position(cursor.get_ref().len() as u64);
+        }
+
+        let tag_timestamp = RtmpTimestamp::new((upper_timestamp << 24) | lower_timestamp);
+        let first_timestamp = *first_timestamp.get_or_insert(tag_timestamp);
+
+        payloads.push(MessagePayload {
+            timestamp: timestamp + (tag_timestamp - first_timestamp),
+            type_id,
+            message_stream_id,
+            data: body,
+        });
+    }
+
+    Ok(payloads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{serialize, deserialize, split};
+    use std::io::{Cursor, Write};
+    use byteorder::{BigEndian, WriteBytesExt};
+    use bytes::Bytes;
+    use ::messages::RtmpMessage;
+    use ::time::RtmpTimestamp;
+
+    fn write_tag(cursor: &mut Cursor<Vec<u8>>, type_id: u8, timestamp: u32, data: &[u8], include_back_pointer: bool) {
+        cursor.write_u8(type_id).unwrap();
+        cursor.write_u24::<BigEndian>(data.len() as u32).unwrap();
+        cursor.write_u24::<BigEndian>(timestamp & 0x00ff_ffff).unwrap();
+        cursor.write_u8((timestamp >> 24) as u8).unwrap();
+        cursor.write_u24::<BigEndian>(0).unwrap();
+        cursor.write_all(data).unwrap();
+
+        if include_back_pointer {
+            cursor.write_u32::<BigEndian>(11 + data.len() as u32).unwrap();
+        }
+    }
+
+    #[test]
+    fn can_serialize_message() {
+        let expected = vec![1,2,3,4];
+        let raw_message = serialize(Bytes::from(vec![1,2,3,4])).unwrap();
+
+        assert_eq!(&raw_message[..], &expected[..]);
+    }
+
+    #[test]
+    fn can_deserialize_message() {
+        let data = Bytes::from(vec![1,2,3,4]);
+        let expected = RtmpMessage::Aggregate { data: Bytes::from(vec![1,2,3,4]) };
+
+        let result = deserialize(data).unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn can_split_into_sub_messages_with_rebased_timestamps() {
+        let mut cursor = Cursor::new(Vec::new());
+        write_tag(&mut cursor, 9, 0x0100_0010, &[1, 2, 3], true);
+        write_tag(&mut cursor, 8, 0x0100_0030, &[4, 5], true);
+        write_tag(&mut cursor, 18, 0x0100_0040, &[6], true);
+
+        let payloads = split(Bytes::from(cursor.into_inner()), RtmpTimestamp::new(1000), 5).unwrap();
+
+        assert_eq!(payloads.len(), 3, "Unexpected number of payloads");
+
+        assert_eq!(payloads[0].type_id, 9, "Incorrect first type id");
+        assert_eq!(payloads[0].timestamp, RtmpTimestamp::new(1000), "Incorrect first timestamp");
+        assert_eq!(payloads[0].message_stream_id, 5, "Incorrect first message stream id");
+        assert_eq!(&payloads[0].data[..], &[1, 2, 3], "Incorrect first data");
+
+        assert_eq!(payloads[1].type_id, 8, "Incorrect second type id");
+        assert_eq!(payloads[1].timestamp, RtmpTimestamp::new(1032), "Incorrect second timestamp");
+        assert_eq!(&payloads[1].data[..], &[4, 5], "Incorrect second data");
+
+        assert_eq!(payloads[2].type_id, 18, "Incorrect third type id");
+        assert_eq!(payloads[2].timestamp, RtmpTimestamp::new(1048), "Incorrect third timestamp");
+        assert_eq!(&payloads[2].data[..], &[6], "Incorrect third data");
+    }
+
+    #[test]
+    fn can_split_when_last_back_pointer_is_missing() {
+        let mut cursor = Cursor::new(Vec::new());
+        write_tag(&mut cursor, 9, 0, &[1, 2, 3], true);
+        write_tag(&mut cursor, 8, 10, &[4, 5], false);
+
+        let payloads = split(Bytes::from(cursor.into_inner()), RtmpTimestamp::new(0), 1).unwrap();
+
+        assert_eq!(payloads.len(), 2, "Unexpected number of payloads");
+        assert_eq!(&payloads[1].data[..], &[4, 5], "Incorrect second data");
+    }
+
+    #[test]
+    fn error_when_tag_is_larger_than_remaining_data() {
+        let mut cursor = Cursor::new(Vec::new());
+        write_tag(&mut cursor, 9, 0, &[1, 2, 3], true);
+
+        let mut bytes = cursor.into_inner();
+        bytes.truncate(13);
+
+        let result = split(Bytes::from(bytes), RtmpTimestamp::new(0), 1);
+        assert!(result.is_err(), "Expected an error");
+    }
+}
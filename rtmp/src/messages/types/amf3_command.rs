@@ -0,0 +1,120 @@
+use bytes::{Bytes, BytesMut, BufMut};
+use rml_amf0::Amf0Value;
+
+use ::messages::{MessageDeserializationError, MessageSerializationError};
+use ::messages::{RtmpMessage};
+use super::amf0_command;
+
+/// Amf3 commands are prefixed with a single format byte, followed by amf0 encoded values.
+/// Values that are actually Amf3 encoded are wrapped in an avmplus-object amf0 marker.
+const FORMAT_BYTE: u8 = 0x00;
+
+pub fn serialize(command_name: String,
+                 transaction_id: f64,
+                 command_object: Amf0Value,
+                 additional_arguments: Vec<Amf0Value>) -> Result<Bytes, MessageSerializationError> {
+    let values = amf0_command::serialize(command_name, transaction_id, command_object, additional_arguments)?;
+
+    let mut bytes = BytesMut::with_capacity(values.len() + 1);
+    bytes.put_u8(FORMAT_BYTE);
+    bytes.extend_from_slice(&values);
+
+    Ok(bytes.freeze())
+}
+
+pub fn deserialize(data: Bytes) -> Result<RtmpMessage, MessageDeserializationError> {
+    // Some clients leave off the format byte, so only skip it if it's present
+    let data = if !data.is_empty() && data[0] == FORMAT_BYTE {
+        data.slice(1..)
+    } else {
+        data
+    };
+
+    match amf0_command::deserialize(data)? {
+        RtmpMessage::Amf0Command {command_name, transaction_id, command_object, additional_arguments}
+            => Ok(RtmpMessage::Amf3Command {command_name, transaction_id, command_object, additional_arguments}),
+
+        other => Ok(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{serialize, deserialize};
+    use std::io::Cursor;
+    use bytes::Bytes;
+    use rml_amf0::Amf0Value;
+    use rml_amf0;
+    use rml_amf3::Amf3Value;
+
+    use ::messages::RtmpMessage;
+
+    #[test]
+    fn can_serialize_message() {
+        let raw_message = serialize(
+            "test".to_string(),
+            23.0,
+            Amf0Value::Null,
+            vec![Amf0Value::AvmPlus(Amf3Value::Integer(5))]
+        ).unwrap();
+
+        assert_eq!(raw_message[0], 0x00, "Incorrect format byte");
+
+        let mut cursor = Cursor::new(raw_message.slice(1..));
+        let result = rml_amf0::deserialize(&mut cursor).unwrap();
+
+        let expected = vec![
+            Amf0Value::Utf8String("test".to_string()),
+            Amf0Value::Number(23.0),
+            Amf0Value::Null,
+            Amf0Value::AvmPlus(Amf3Value::Integer(5)),
+        ];
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn can_deserialize_message() {
+        let values = vec![
+            Amf0Value::Utf8String("test".to_string()),
+            Amf0Value::Number(23.0),
+            Amf0Value::Null,
+            Amf0Value::AvmPlus(Amf3Value::Utf8String("abc".to_string())),
+        ];
+
+        let mut bytes = vec![0x00];
+        bytes.extend(rml_amf0::serialize(&values).unwrap());
+
+        let expected = RtmpMessage::Amf3Command {
+            command_name: "test".to_string(),
+            transaction_id: 23.0,
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![Amf0Value::AvmPlus(Amf3Value::Utf8String("abc".to_string()))]
+        };
+
+        let result = deserialize(Bytes::from(bytes)).unwrap();
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn can_deserialize_message_without_format_byte() {
+        let values = vec![
+            Amf0Value::Utf8String("test".to_string()),
+            Amf0Value::Number(23.0),
+            Amf0Value::Null,
+        ];
+
+        let bytes = Bytes::from(rml_amf0::serialize(&values).unwrap());
+        let expected = RtmpMessage::Amf3Command {
+            command_name: "test".to_string(),
+            transaction_id: 23.0,
+            command_object: Amf0Value::Null,
+            additional_arguments: Vec::new(),
+        };
+
+        let result = deserialize(bytes).unwrap();
+
+        assert_eq!(expected, result);
+    }
+}
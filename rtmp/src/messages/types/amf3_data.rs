@@ -0,0 +1,56 @@
+use std::io::Cursor;
+use bytes::Bytes;
+use rml_amf0;
+use rml_amf0::Amf0Value;
+
+use ::messages::{MessageDeserializationError, MessageSerializationError};
+use ::messages::{RtmpMessage};
+
+/// Amf3 data messages are encoded as amf0 values, with any amf3 values wrapped in an
+/// avmplus-object amf0 marker.
+pub fn serialize(values: Vec<Amf0Value>) -> Result<Bytes, MessageSerializationError> {
+    let bytes = rml_amf0::serialize(&values)?;
+
+    Ok(Bytes::from(bytes))
+}
+
+pub fn deserialize(data: Bytes) -> Result<RtmpMessage, MessageDeserializationError> {
+    let mut cursor = Cursor::new(data);
+    let values = rml_amf0::deserialize(&mut cursor)?;
+
+    Ok(RtmpMessage::Amf3Data { values })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{serialize, deserialize};
+    use std::io::Cursor;
+    use bytes::Bytes;
+    use rml_amf0::Amf0Value;
+    use rml_amf0;
+    use rml_amf3::Amf3Value;
+
+    use ::messages::{RtmpMessage};
+
+    #[test]
+    fn can_serialize_message() {
+        let values = vec![Amf0Value::Utf8String("abc".to_string()), Amf0Value::AvmPlus(Amf3Value::Integer(52))];
+        let raw_message = serialize(values.clone()).unwrap();
+
+        let mut cursor = Cursor::new(raw_message);
+        let result = rml_amf0::deserialize(&mut cursor).unwrap();
+
+        assert_eq!(&values[..], &result[..]);
+    }
+
+    #[test]
+    fn can_deserialize_message() {
+        let values = vec![Amf0Value::Utf8String("abc".to_string()), Amf0Value::AvmPlus(Amf3Value::Integer(52))];
+        let bytes = Bytes::from(rml_amf0::serialize(&values).unwrap());
+        let result = deserialize(bytes).unwrap();
+
+        let expected = RtmpMessage::Amf3Data { values };
+
+        assert_eq!(expected, result);
+    }
+}
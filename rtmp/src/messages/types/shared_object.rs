@@ -0,0 +1,344 @@
+use std::io::{Cursor, Read, Write};
+use byteorder::{BigEndian, WriteBytesExt, ReadBytesExt};
+use bytes::{Buf, Bytes};
+use rml_amf0;
+use rml_amf0::Amf0Value;
+
+use ::messages::{MessageDeserializationError, MessageDeserializationErrorKind};
+use ::messages::{MessageSerializationError, MessageSerializationErrorKind};
+use ::messages::{RtmpMessage, SharedObjectEvent};
+
+/// Amf3 shared object messages have the same layout as amf0 shared object messages, but are
+/// prefixed with a single format byte.
+const AMF3_FORMAT_BYTE: u8 = 0x00;
+const PERSISTENT_FLAG: u32 = 2;
+
+pub fn serialize_amf0(name: String,
+                      version: u32,
+                      persistent: bool,
+                      events: Vec<SharedObjectEvent>) -> Result<Bytes, MessageSerializationError> {
+    let mut cursor = Cursor::new(Vec::new());
+    write_shared_object(&mut cursor, name, version, persistent, events)?;
+
+    Ok(Bytes::from(cursor.into_inner()))
+}
+
+pub fn serialize_amf3(name: String,
+                      version: u32,
+                      persistent: bool,
+                      events: Vec<SharedObjectEvent>) -> Result<Bytes, MessageSerializationError> {
+    let mut cursor = Cursor::new(Vec::new());
+    cursor.write_u8(AMF3_FORMAT_BYTE)?;
+    write_shared_object(&mut cursor, name, version, persistent, events)?;
+
+    Ok(Bytes::from(cursor.into_inner()))
+}
+
+pub fn deserialize_amf0(data: Bytes) -> Result<RtmpMessage, MessageDeserializationError> {
+    let (name, version, persistent, events) = read_shared_object(data)?;
+
+    Ok(RtmpMessage::Amf0SharedObject { name, version, persistent, events })
+}
+
+pub fn deserialize_amf3(data: Bytes) -> Result<RtmpMessage, MessageDeserializationError> {
+    let data = if !data.is_empty() && data[0] == AMF3_FORMAT_BYTE {
+        data.slice(1..)
+    } else {
+        data
+    };
+
+    let (name, version, persistent, events) = read_shared_object(data)?;
+
+    Ok(RtmpMessage::Amf3SharedObject { name, version, persistent, events })
+}
+
+fn write_shared_object(cursor: &mut Cursor<Vec<u8>>,
+                       name: String,
+                       version: u32,
+                       persistent: bool,
+                       events: Vec<SharedObjectEvent>) -> Result<(), MessageSerializationError> {
+    write_string(cursor, &name)?;
+    cursor.write_u32::<BigEndian>(version)?;
+    cursor.write_u32::<BigEndian>(if persistent { PERSISTENT_FLAG } else { 0 })?;
+    cursor.write_u32::<BigEndian>(0)?;
+
+    for event in events {
+        let (event_type, data) = serialize_event(event)?;
+        cursor.write_u8(event_type)?;
+        cursor.write_u32::<BigEndian>(data.len() as u32)?;
+        cursor.write_all(&data)?;
+    }
+
+    Ok(())
+}
+
+fn serialize_event(event: SharedObjectEvent) -> Result<(u8, Vec<u8>), MessageSerializationError> {
+    let mut cursor = Cursor::new(Vec::new());
+    let event_type = match event {
+        SharedObjectEvent::Use => 1,
+        SharedObjectEvent::Release => 2,
+
+        SharedObjectEvent::RequestChange { name, value } => {
+            write_string(&mut cursor, &name)?;
+            cursor.write_all(&rml_amf0::serialize(&vec![value])?)?;
+            3
+        },
+
+        SharedObjectEvent::Change { name, value } => {
+            write_string(&mut cursor, &name)?;
+            cursor.write_all(&rml_amf0::serialize(&vec![value])?)?;
+            4
+        },
+
+        SharedObjectEvent::Success { name } => {
+            write_string(&mut cursor, &name)?;
+            5
+        },
+
+        SharedObjectEvent::SendMessage { values } => {
+            cursor.write_all(&rml_amf0::serialize(&values)?)?;
+            6
+        },
+
+        SharedObjectEvent::Status { code, level } => {
+            write_string(&mut cursor, &code)?;
+            write_string(&mut cursor, &level)?;
+            7
+        },
+
+        SharedObjectEvent::Clear => 8,
+
+        SharedObjectEvent::Remove { name } => {
+            write_string(&mut cursor, &name)?;
+            9
+        },
+
+        SharedObjectEvent::RequestRemove { name } => {
+            write_string(&mut cursor, &name)?;
+            10
+        },
+
+        SharedObjectEvent::UseSuccess => 11,
+
+        SharedObjectEvent::Unknown { event_type, data } => {
+            cursor.write_all(&data)?;
+            event_type
+        },
+    };
+
+    Ok((event_type, cursor.into_inner()))
+}
+
+fn read_shared_object(data: Bytes) -> Result<(String, u32, bool, Vec<SharedObjectEvent>), MessageDeserializationError> {
+    let mut cursor = Cursor::new(data);
+    let name = read_string(&mut cursor)?;
+    let version = cursor.read_u32::<BigEndian>()?;
+    let persistent = cursor.read_u32::<BigEndian>()? == PERSISTENT_FLAG;
+    let _ = cursor.read_u32::<BigEndian>()?;
+
+    let mut events = Vec::new();
+    while cursor.has_remaining() {
+        let event_type = cursor.read_u8()?;
+        let length = cursor.read_u32::<BigEndian>()? as usize;
+        if cursor.remaining() < length {
+            return Err(MessageDeserializationError { kind: MessageDeserializationErrorKind::InvalidMessageFormat });
+        }
+
+        let start = cursor.position() as usize;
+        let event_data = cursor.get_ref().slice(start..start + length);
+        cursor.set_position((start + length) as u64);
+
+        events.push(deserialize_event(event_type, event_data)?);
+    }
+
+    Ok((name, version, persistent, events))
+}
+
+fn deserialize_event(event_type: u8, data: Bytes) -> Result<SharedObjectEvent, MessageDeserializationError> {
+    let mut cursor = Cursor::new(data);
+    let event = match event_type {
+        1 => SharedObjectEvent::Use,
+        2 => SharedObjectEvent::Release,
+
+        3 => {
+            let name = read_string(&mut cursor)?;
+            let value = read_single_value(&mut cursor)?;
+            SharedObjectEvent::RequestChange { name, value }
+        },
+
+        4 => {
+            let name = read_string(&mut cursor)?;
+            let value = read_single_value(&mut cursor)?;
+            SharedObjectEvent::Change { name, value }
+        },
+
+        5 => SharedObjectEvent::Success { name: read_string(&mut cursor)? },
+        6 => SharedObjectEvent::SendMessage { values: rml_amf0::deserialize(&mut cursor)? },
+
+        7 => {
+            let code = read_string(&mut cursor)?;
+            let level = read_string(&mut cursor)?;
+            SharedObjectEvent::Status { code, level }
+        },
+
+        8 => SharedObjectEvent::Clear,
+        9 => SharedObjectEvent::Remove { name: read_string(&mut cursor)? },
+        10 => SharedObjectEvent::RequestRemove { name: read_string(&mut cursor)? },
+        11 => SharedObjectEvent::UseSuccess,
+        _ => SharedObjectEvent::Unknown { event_type, data: cursor.into_inner() },
+    };
+
+    Ok(event)
+}
+
+fn read_single_value(cursor: &mut Cursor<Bytes>) -> Result<Amf0Value, MessageDeserializationError> {
+    let mut values = rml_amf0::deserialize(cursor)?;
+    if values.len() != 1 {
+        return Err(MessageDeserializationError { kind: MessageDeserializationErrorKind::InvalidMessageFormat });
+    }
+
+    Ok(values.remove(0))
+}
+
+fn write_string(cursor: &mut Cursor<Vec<u8>>, value: &str) -> Result<(), MessageSerializationError> {
+    if value.len() > u16::MAX as usize {
+        return Err(MessageSerializationError { kind: MessageSerializationErrorKind::StringTooLong });
+    }
+
+    cursor.write_u16::<BigEndian>(value.len() as u16)?;
+    cursor.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn read_string(cursor: &mut Cursor<Bytes>) -> Result<String, MessageDeserializationError> {
+    let length = cursor.read_u16::<BigEndian>()? as usize;
+    let mut buffer = vec![0_u8; length];
+    cursor.read_exact(&mut buffer)?;
+
+    String::from_utf8(buffer)
+        .map_err(|_| MessageDeserializationError { kind: MessageDeserializationErrorKind::InvalidMessageFormat })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{serialize_amf0, serialize_amf3, deserialize_amf0, deserialize_amf3};
+    use std::io::{Cursor, Write};
+    use byteorder::{BigEndian, WriteBytesExt};
+    use bytes::Bytes;
+    use rml_amf0::Amf0Value;
+    use rml_amf0;
+
+    use ::messages::{RtmpMessage, SharedObjectEvent};
+
+    #[test]
+    fn can_serialize_amf0_message() {
+        let events = vec![
+            SharedObjectEvent::Use,
+            SharedObjectEvent::Change { name: "abc".to_string(), value: Amf0Value::Number(5.0) },
+        ];
+
+        let result = serialize_amf0("test".to_string(), 3, true, events).unwrap();
+
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_u16::<BigEndian>(4).unwrap();
+        cursor.write_all(b"test").unwrap();
+        cursor.write_u32::<BigEndian>(3).unwrap();
+        cursor.write_u32::<BigEndian>(2).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u8(1).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+
+        let value = rml_amf0::serialize(&vec![Amf0Value::Number(5.0)]).unwrap();
+        cursor.write_u8(4).unwrap();
+        cursor.write_u32::<BigEndian>(5 + value.len() as u32).unwrap();
+        cursor.write_u16::<BigEndian>(3).unwrap();
+        cursor.write_all(b"abc").unwrap();
+        cursor.write_all(&value).unwrap();
+
+        let expected = cursor.into_inner();
+        assert_eq!(&expected[..], &result[..]);
+    }
+
+    #[test]
+    fn amf3_message_is_prefixed_with_format_byte() {
+        let amf0 = serialize_amf0("test".to_string(), 3, false, vec![SharedObjectEvent::Clear]).unwrap();
+        let amf3 = serialize_amf3("test".to_string(), 3, false, vec![SharedObjectEvent::Clear]).unwrap();
+
+        assert_eq!(amf3[0], 0x00, "Incorrect format byte");
+        assert_eq!(&amf3[1..], &amf0[..]);
+    }
+
+    #[test]
+    fn can_deserialize_amf0_message() {
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_u16::<BigEndian>(4).unwrap();
+        cursor.write_all(b"test").unwrap();
+        cursor.write_u32::<BigEndian>(3).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+
+        cursor.write_u8(5).unwrap();
+        cursor.write_u32::<BigEndian>(5).unwrap();
+        cursor.write_u16::<BigEndian>(3).unwrap();
+        cursor.write_all(b"abc").unwrap();
+
+        cursor.write_u8(99).unwrap();
+        cursor.write_u32::<BigEndian>(2).unwrap();
+        cursor.write_all(&[1, 2]).unwrap();
+
+        let result = deserialize_amf0(Bytes::from(cursor.into_inner())).unwrap();
+        let expected = RtmpMessage::Amf0SharedObject {
+            name: "test".to_string(),
+            version: 3,
+            persistent: false,
+            events: vec![
+                SharedObjectEvent::Success { name: "abc".to_string() },
+                SharedObjectEvent::Unknown { event_type: 99, data: Bytes::from(vec![1, 2]) },
+            ],
+        };
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn can_round_trip_all_event_types() {
+        let events = vec![
+            SharedObjectEvent::Use,
+            SharedObjectEvent::Release,
+            SharedObjectEvent::RequestChange { name: "a".to_string(), value: Amf0Value::Boolean(true) },
+            SharedObjectEvent::Change { name: "b".to_string(), value: Amf0Value::Utf8String("c".to_string()) },
+            SharedObjectEvent::Success { name: "d".to_string() },
+            SharedObjectEvent::SendMessage { values: vec![Amf0Value::Utf8String("e".to_string()), Amf0Value::Null] },
+            SharedObjectEvent::Status { code: "f".to_string(), level: "error".to_string() },
+            SharedObjectEvent::Clear,
+            SharedObjectEvent::Remove { name: "g".to_string() },
+            SharedObjectEvent::RequestRemove { name: "h".to_string() },
+            SharedObjectEvent::UseSuccess,
+        ];
+
+        let bytes = serialize_amf3("test".to_string(), 1, true, events.clone()).unwrap();
+        let result = deserialize_amf3(bytes).unwrap();
+        let expected = RtmpMessage::Amf3SharedObject {
+            name: "test".to_string(),
+            version: 1,
+            persistent: true,
+            events,
+        };
+
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn event_with_length_past_end_of_message_is_invalid() {
+        let mut cursor = Cursor::new(Vec::new());
+        cursor.write_u16::<BigEndian>(0).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u32::<BigEndian>(0).unwrap();
+        cursor.write_u8(5).unwrap();
+        cursor.write_u32::<BigEndian>(50).unwrap();
+
+        let result = deserialize_amf0(Bytes::from(cursor.into_inner()));
+        assert!(result.is_err(), "Expected an error");
+    }
+}
@@ -0,0 +1,479 @@
+//! Support for the Adobe Media Server style of connection authentication (`authmod=adobe`).
+//!
+//! The authentication is performed as a challenge/response over multiple connect requests:
+//!
+//! 1. The client connects without any credentials and is rejected with a description containing
+//!    `code=403 need auth; authmod=adobe`.
+//! 2. The client connects with `?authmod=adobe&user=<user>` appended to its app name and tcUrl,
+//!    and is rejected with a description containing
+//!    `?reason=needauth&user=<user>&salt=<salt>&challenge=<challenge>&opaque=<opaque>`.
+//! 3. The client connects with
+//!    `?authmod=adobe&user=<user>&challenge=<client challenge>&response=<response>&opaque=<opaque>`
+//!    appended, where the response proves it knows the password for the user.  The server accepts
+//!    the connection if the response matches the one it calculated itself.
+//!
+//! Many clients (such as FFmpeg) open a new connection for each attempt, so a single
+//! `AdobeAuthenticator` should be shared by all connections to the server.
+//!
+//! Challenges are not stored by the authenticator.  The opaque value carries the time the
+//! challenge was issued, and the salt is derived from the opaque value and user with a secret
+//! only the authenticator knows, so issuing challenges does not use up any memory.
+
+use base64;
+use hmac::{Hmac, Mac, NewMac};
+use md5::{Digest, Md5};
+use rand;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use super::{Clock, ConnectionInfo, ServerSession, ServerSessionError, ServerSessionResult, SystemClock};
+
+const REJECTION_CODE: &str = "NetConnection.Connect.Rejected";
+
+/// How long a client has to respond to a challenge, in milliseconds
+const CHALLENGE_LIFETIME_MS: u64 = 60_000;
+
+/// Credentials a `ClientSession` uses to answer `authmod=adobe` challenges from the server
+#[derive(PartialEq, Debug, Clone)]
+pub struct AdobeAuthCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Retrieves the passwords of the users allowed to connect through an `AdobeAuthenticator`
+pub trait AdobeAuthUserLookup {
+    /// Returns the password of the specified user, or `None` if the user does not exist
+    fn get_password(&self, username: &str) -> Option<String>;
+}
+
+/// The outcome of an `AdobeAuthenticator` handling a connection request
+#[derive(PartialEq, Debug, Clone)]
+pub enum AdobeAuthOutcome {
+    /// The client proved it knows the password of the user and the connection request was
+    /// accepted.  The app name does not contain the authentication query.
+    Authenticated {
+        username: String,
+        app_name: String,
+    },
+
+    /// The client did not provide a user, so the connection request was rejected with a
+    /// notice that authentication is required
+    AuthenticationRequired,
+
+    /// The connection request was rejected with a challenge the client must respond to
+    ChallengeIssued {
+        username: String,
+    },
+
+    /// The connection request was rejected because the user does not exist
+    UnknownUser {
+        username: String,
+    },
+
+    /// The connection request was rejected because the client's response was for a challenge
+    /// that was not issued to the user, had expired or was already answered, or did not match
+    /// the user's password
+    AuthenticationFailed {
+        username: String,
+    },
+}
+
+/// Drives the `authmod=adobe` challenge/response for connection requests raised by
+/// `ServerSession`s, verifying credentials against the passwords provided by the user lookup.
+pub struct AdobeAuthenticator<L: AdobeAuthUserLookup> {
+    user_lookup: L,
+    clock: Arc<dyn Clock>,
+    secret: [u8; 32],
+
+    // Opaque values of challenges that were answered correctly, along with the time they expire
+    // at, so a response can't be replayed while its challenge is still valid
+    answered_challenges: HashMap<String, u64>,
+}
+
+impl<L: AdobeAuthUserLookup> AdobeAuthenticator<L> {
+    /// Creates a new authenticator that verifies users against the specified lookup
+    pub fn new(user_lookup: L) -> AdobeAuthenticator<L> {
+        AdobeAuthenticator::with_clock(user_lookup, Arc::new(SystemClock))
+    }
+
+    /// Creates a new authenticator that verifies users against the specified lookup, using the
+    /// specified clock to expire challenges
+    pub fn with_clock(user_lookup: L, clock: Arc<dyn Clock>) -> AdobeAuthenticator<L> {
+        AdobeAuthenticator {
+            user_lookup,
+            clock,
+            secret: rand::random(),
+            answered_challenges: HashMap::new(),
+        }
+    }
+
+    /// Handles a `ConnectionRequested` event raised by the session by either accepting or
+    /// rejecting the request based on the authentication parameters in the app name (or in the
+    /// tcUrl when the app name does not have any).  The returned results must be processed just
+    /// like the results of `ServerSession::accept_request()` or `ServerSession::reject_request()`.
+    /// Accepted sessions are connected to the app name without the authentication query.
+    pub fn handle_connection_request(&mut self,
+                                     session: &mut ServerSession,
+                                     request_id: u32,
+                                     connection_info: &ConnectionInfo)
+        -> Result<(AdobeAuthOutcome, Vec<ServerSessionResult>), ServerSessionError> {
+
+        let (app_name, mut query) = split_query(&connection_info.app_name);
+        if query.is_empty() {
+            if let Some(ref tc_url) = connection_info.tc_url {
+                query = split_query(tc_url).1;
+            }
+        }
+
+        let parameters = parse_query(query);
+        let username = match (parameters.get("authmod"), parameters.get("user")) {
+            (Some(&"adobe"), Some(username)) => username.to_string(),
+            _ => {
+                let description = "[ AccessManager.Reject ] : [ code=403 need auth; authmod=adobe ] : ";
+                let results = session.reject_request(request_id, REJECTION_CODE, description)?;
+                return Ok((AdobeAuthOutcome::AuthenticationRequired, results));
+            }
+        };
+
+        let password = match self.user_lookup.get_password(&username) {
+            Some(password) => password,
+            None => {
+                let description = "[ AccessManager.Reject ] : [ authmod=adobe ] : ?reason=nosuchuser";
+                let results = session.reject_request(request_id, REJECTION_CODE, description)?;
+                return Ok((AdobeAuthOutcome::UnknownUser {username}, results));
+            }
+        };
+
+        let (response, client_challenge) = match (parameters.get("response"), parameters.get("challenge")) {
+            (Some(response), Some(challenge)) => (*response, *challenge),
+            _ => {
+                let description = self.issue_challenge(&username);
+                let results = session.reject_request(request_id, REJECTION_CODE, &description)?;
+                return Ok((AdobeAuthOutcome::ChallengeIssued {username}, results));
+            }
+        };
+
+        let now = self.clock.get_time_ms();
+        self.answered_challenges.retain(|_, expires_at| *expires_at >= now);
+
+        let opaque = parameters.get("opaque").cloned().unwrap_or("");
+        let expires_at = get_challenge_issue_time(opaque)
+            .map(|issued_at| issued_at.saturating_add(CHALLENGE_LIFETIME_MS));
+
+        let is_valid = match expires_at {
+            Some(expires_at) if now <= expires_at && !self.answered_challenges.contains_key(opaque) => {
+                let salt = self.get_salt(&username, opaque);
+                let expected = calculate_response(&username, &password, &salt, opaque, client_challenge);
+                constant_time_eq(expected.as_bytes(), response.as_bytes())
+            },
+
+            _ => false,
+        };
+
+        if let (true, Some(expires_at)) = (is_valid, expires_at) {
+            // Each challenge can only be answered once
+            self.answered_challenges.insert(opaque.to_string(), expires_at);
+        } else {
+            let description = "[ AccessManager.Reject ] : [ authmod=adobe ] : ?reason=authfailed";
+            let results = session.reject_request(request_id, REJECTION_CODE, description)?;
+            return Ok((AdobeAuthOutcome::AuthenticationFailed {username}, results));
+        }
+
+        let results = session.accept_connection_request_as(request_id, app_name)?;
+        let outcome = AdobeAuthOutcome::Authenticated {username, app_name: app_name.to_string()};
+        Ok((outcome, results))
+    }
+
+    fn issue_challenge(&self, username: &str) -> String {
+        // The opaque value is the issue time followed by random data, so it's unique even for
+        // challenges issued in the same millisecond
+        let opaque = format!("{:016x}{}", self.clock.get_time_ms(), generate_random_string());
+        let salt = self.get_salt(username, &opaque);
+        let challenge = generate_random_string();
+
+        format!("[ AccessManager.Reject ] : [ authmod=adobe ] : ?reason=needauth&user={}&salt={}&challenge={}&opaque={}",
+                username, salt, challenge, opaque)
+    }
+
+    /// Derives the salt of the challenge with the opaque value for the user.  The secret makes
+    /// the salt impossible to predict, so the opaque value does not need to be stored.
+    fn get_salt(&self, username: &str, opaque: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_varkey(&self.secret).unwrap();
+        mac.update(format!("{}:{}:{}", username.len(), username, opaque).as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .take(8)
+            .map(|x| format!("{:02x}", x))
+            .collect()
+    }
+}
+
+/// Reads the time a challenge was issued at from the start of its opaque value
+fn get_challenge_issue_time(opaque: &str) -> Option<u64> {
+    if opaque.len() != 24 || !opaque.is_ascii() {
+        return None;
+    }
+
+    u64::from_str_radix(&opaque[..16], 16).ok()
+}
+
+/// Calculates the response a client sends to prove it knows the password of the user.  The
+/// opaque value is used in the calculation when the server sent one, otherwise the server's
+/// challenge is used in its place.
+pub(super) fn calculate_response(username: &str,
+                                 password: &str,
+                                 salt: &str,
+                                 opaque_or_challenge: &str,
+                                 client_challenge: &str) -> String {
+    let hash = base64::encode(Md5::digest(format!("{}{}{}", username, salt, password).as_bytes()));
+    base64::encode(Md5::digest(format!("{}{}{}", hash, opaque_or_challenge, client_challenge).as_bytes()))
+}
+
+/// Compares two values without exiting early on the first differing byte, so the time taken does
+/// not reveal how much of a response was correct
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+
+    left.iter().zip(right.iter()).fold(0_u8, |difference, (x, y)| difference | (x ^ y)) == 0
+}
+
+/// Splits a value into everything before the first `?` and everything after it
+pub(super) fn split_query(value: &str) -> (&str, &str) {
+    match value.find('?') {
+        Some(index) => (&value[..index], &value[index + 1..]),
+        None => (value, ""),
+    }
+}
+
+/// Parses `key=value` pairs separated by `&`.  Values are not percent decoded, as the base64
+/// values used by this authentication scheme are sent as is.
+pub(super) fn parse_query(query: &str) -> HashMap<&str, &str> {
+    query.split('&')
+        .filter(|x| !x.is_empty())
+        .map(|pair| match pair.find('=') {
+            Some(index) => (&pair[..index], &pair[index + 1..]),
+            None => (pair, ""),
+        })
+        .collect()
+}
+
+/// Random values are hex encoded so they never contain characters with special meaning in urls
+/// (such as a trailing `/`, which is stripped from app names)
+fn generate_random_string() -> String {
+    format!("{:08x}", rand::random::<u32>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use chunk_io::{ChunkDeserializer, ChunkSerializer};
+    use messages::RtmpMessage;
+    use rml_amf0::Amf0Value;
+    use sessions::{ManualClock, ServerSessionConfig, ServerSessionEvent};
+    use time::RtmpTimestamp;
+
+    struct SingleUserLookup;
+
+    impl AdobeAuthUserLookup for SingleUserLookup {
+        fn get_password(&self, username: &str) -> Option<String> {
+            if username == "user" {
+                Some("secret".to_string())
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn response_matches_known_value() {
+        // Value calculated with the algorithm used by FFmpeg's `do_adobe_auth()`
+        let response = calculate_response("user", "secret", "salt", "opaque", "0000abcd");
+        let hash = base64::encode(Md5::digest(b"usersaltsecret"));
+        let expected = base64::encode(Md5::digest(format!("{}opaque0000abcd", hash).as_bytes()));
+
+        assert_eq!(response, expected);
+    }
+
+    #[test]
+    fn constant_time_eq_compares_whole_values() {
+        assert!(constant_time_eq(b"abcd", b"abcd"));
+        assert!(!constant_time_eq(b"abcd", b"abce"));
+        assert!(!constant_time_eq(b"abcd", b"xbcd"));
+        assert!(!constant_time_eq(b"abcd", b"abc"));
+    }
+
+    #[test]
+    fn connect_without_user_is_told_authentication_is_required() {
+        let mut authenticator = AdobeAuthenticator::new(SingleUserLookup);
+        let (outcome, description) = connect(&mut authenticator, "live");
+
+        assert_eq!(outcome, AdobeAuthOutcome::AuthenticationRequired);
+        assert!(description.contains("code=403 need auth; authmod=adobe"), "Unexpected description: {}", description);
+    }
+
+    #[test]
+    fn connect_with_unknown_user_is_rejected() {
+        let mut authenticator = AdobeAuthenticator::new(SingleUserLookup);
+        let (outcome, description) = connect(&mut authenticator, "live?authmod=adobe&user=other");
+
+        assert_eq!(outcome, AdobeAuthOutcome::UnknownUser {username: "other".to_string()});
+        assert!(description.contains("?reason=nosuchuser"), "Unexpected description: {}", description);
+    }
+
+    #[test]
+    fn correct_response_to_challenge_is_accepted() {
+        let mut authenticator = AdobeAuthenticator::new(SingleUserLookup);
+        let (outcome, description) = connect(&mut authenticator, "live?authmod=adobe&user=user");
+        assert_eq!(outcome, AdobeAuthOutcome::ChallengeIssued {username: "user".to_string()});
+
+        let app_name = get_response_app_name(&description, "secret");
+        let (outcome, _) = connect(&mut authenticator, &app_name);
+
+        assert_eq!(outcome, AdobeAuthOutcome::Authenticated {username: "user".to_string(), app_name: "live".to_string()});
+    }
+
+    #[test]
+    fn incorrect_password_is_rejected() {
+        let mut authenticator = AdobeAuthenticator::new(SingleUserLookup);
+        let (_, description) = connect(&mut authenticator, "live?authmod=adobe&user=user");
+
+        let app_name = get_response_app_name(&description, "wrong");
+        let (outcome, description) = connect(&mut authenticator, &app_name);
+
+        assert_eq!(outcome, AdobeAuthOutcome::AuthenticationFailed {username: "user".to_string()});
+        assert!(description.contains("?reason=authfailed"), "Unexpected description: {}", description);
+    }
+
+    #[test]
+    fn challenge_cannot_be_answered_twice() {
+        let mut authenticator = AdobeAuthenticator::new(SingleUserLookup);
+        let (_, description) = connect(&mut authenticator, "live?authmod=adobe&user=user");
+
+        let app_name = get_response_app_name(&description, "secret");
+        let (outcome, _) = connect(&mut authenticator, &app_name);
+        assert_eq!(outcome, AdobeAuthOutcome::Authenticated {username: "user".to_string(), app_name: "live".to_string()});
+
+        let (outcome, _) = connect(&mut authenticator, &app_name);
+        assert_eq!(outcome, AdobeAuthOutcome::AuthenticationFailed {username: "user".to_string()});
+    }
+
+    #[test]
+    fn challenge_is_still_valid_after_many_more_are_issued() {
+        let mut authenticator = AdobeAuthenticator::new(SingleUserLookup);
+        let (_, description) = connect(&mut authenticator, "live?authmod=adobe&user=user");
+
+        for _ in 0..2000 {
+            authenticator.issue_challenge("user");
+        }
+
+        let app_name = get_response_app_name(&description, "secret");
+        let (outcome, _) = connect(&mut authenticator, &app_name);
+
+        assert_eq!(outcome, AdobeAuthOutcome::Authenticated {username: "user".to_string(), app_name: "live".to_string()});
+    }
+
+    #[test]
+    fn expired_challenge_is_rejected() {
+        let clock = ManualClock::new(100_000);
+        let mut authenticator = AdobeAuthenticator::with_clock(SingleUserLookup, Arc::new(clock.clone()));
+        let (_, description) = connect(&mut authenticator, "live?authmod=adobe&user=user");
+
+        clock.advance(CHALLENGE_LIFETIME_MS + 1);
+        let app_name = get_response_app_name(&description, "secret");
+        let (outcome, _) = connect(&mut authenticator, &app_name);
+
+        assert_eq!(outcome, AdobeAuthOutcome::AuthenticationFailed {username: "user".to_string()});
+    }
+
+    #[test]
+    fn challenge_with_altered_issue_time_is_rejected() {
+        let clock = ManualClock::new(100_000);
+        let mut authenticator = AdobeAuthenticator::with_clock(SingleUserLookup, Arc::new(clock.clone()));
+        let (_, description) = connect(&mut authenticator, "live?authmod=adobe&user=user");
+
+        clock.advance(CHALLENGE_LIFETIME_MS + 1);
+        let (_, query) = split_query(&description);
+        let parameters = parse_query(query);
+        let opaque = format!("{:016x}{}", clock.get_time_ms(), &parameters["opaque"][16..]);
+        let response = calculate_response("user", "secret", parameters["salt"], &opaque, "0000abcd");
+        let app_name = format!("live?authmod=adobe&user=user&challenge=0000abcd&response={}&opaque={}", response, opaque);
+        let (outcome, _) = connect(&mut authenticator, &app_name);
+
+        assert_eq!(outcome, AdobeAuthOutcome::AuthenticationFailed {username: "user".to_string()});
+    }
+
+    #[test]
+    fn challenge_from_another_authenticator_is_rejected() {
+        let mut authenticator = AdobeAuthenticator::new(SingleUserLookup);
+        let mut other = AdobeAuthenticator::new(SingleUserLookup);
+        let (_, description) = connect(&mut other, "live?authmod=adobe&user=user");
+
+        let app_name = get_response_app_name(&description, "secret");
+        let (outcome, _) = connect(&mut authenticator, &app_name);
+
+        assert_eq!(outcome, AdobeAuthOutcome::AuthenticationFailed {username: "user".to_string()});
+    }
+
+    fn get_response_app_name(description: &str, password: &str) -> String {
+        let (_, query) = split_query(description);
+        let parameters = parse_query(query);
+        let response = calculate_response("user", password, parameters["salt"], parameters["opaque"], "0000abcd");
+
+        format!("live?authmod=adobe&user=user&challenge=0000abcd&response={}&opaque={}", response, parameters["opaque"])
+    }
+
+    /// Sends a connect request for the app name through a new session, and returns the outcome
+    /// along with the description of the rejection (if it was rejected)
+    fn connect(authenticator: &mut AdobeAuthenticator<SingleUserLookup>, app_name: &str) -> (AdobeAuthOutcome, String) {
+        let (mut session, initial_results) = ServerSession::new(ServerSessionConfig::new()).unwrap();
+        let mut serializer = ChunkSerializer::new();
+        let mut deserializer = ChunkDeserializer::new();
+
+        let mut properties = HashMap::new();
+        properties.insert("app".to_string(), Amf0Value::Utf8String(app_name.to_string()));
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: "connect".to_string(),
+            transaction_id: 1.0,
+            command_object: Amf0Value::Object(properties),
+            additional_arguments: vec![],
+        };
+
+        let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+        let packet = serializer.serialize(&payload, true, false).unwrap();
+        let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+
+        let (request_id, connection_info) = match results.into_iter().next() {
+            Some(ServerSessionResult::RaisedEvent(ServerSessionEvent::ConnectionRequested {request_id, connection_info, ..}))
+                => (request_id, connection_info),
+            x => panic!("Expected connection requested event, instead received: {:?}", x),
+        };
+
+        let (outcome, results) = authenticator.handle_connection_request(&mut session, request_id, &connection_info).unwrap();
+
+        let mut description = String::new();
+        for result in initial_results.into_iter().chain(results) {
+            if let ServerSessionResult::OutboundResponse(packet) = result {
+                let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+                match payload.to_rtmp_message().unwrap() {
+                    RtmpMessage::Amf0Command {ref command_name, ref additional_arguments, ..} if command_name == "_error" => {
+                        if let Some(Amf0Value::Object(ref properties)) = additional_arguments.first() {
+                            if let Some(Amf0Value::Utf8String(ref value)) = properties.get("description") {
+                                description = value.clone();
+                            }
+                        }
+                    },
+
+                    RtmpMessage::SetChunkSize {size} => deserializer.set_max_chunk_size(size as usize).unwrap(),
+                    _ => (),
+                }
+            }
+        }
+
+        (outcome, description)
+    }
+}
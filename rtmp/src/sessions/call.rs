@@ -0,0 +1,24 @@
+use rml_amf0::Amf0Value;
+
+/// Identifies a call made to the peer, so the `_result` or `_error` it responds with can be
+/// matched up with the call that caused it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallHandle(pub(super) u32);
+
+/// The response to send back for a command the peer has called
+#[derive(PartialEq, Debug, Clone)]
+pub enum CallResponse {
+    /// The call succeeded, and the peer should be sent a `_result` command with the
+    /// specified values
+    Result {
+        command_object: Amf0Value,
+        additional_values: Vec<Amf0Value>,
+    },
+
+    /// The call failed, and the peer should be sent an `_error` command with the
+    /// specified values
+    Error {
+        command_object: Amf0Value,
+        additional_values: Vec<Amf0Value>,
+    },
+}
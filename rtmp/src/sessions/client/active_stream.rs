@@ -0,0 +1,8 @@
+use super::ClientStreamState;
+
+pub struct ActiveStream {
+    /// The RTMP stream id, which is only known once the server responds to `createStream`
+    pub stream_id: Option<u32>,
+    pub current_state: ClientStreamState,
+    pub fc_published_stream_key: Option<String>,
+}
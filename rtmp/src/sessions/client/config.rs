@@ -1,3 +1,7 @@
+use chunk_io::ChunkDeserializerLimits;
+use sessions::{AdobeAuthCredentials, Clock, EnhancedRtmpCapabilities, SystemClock};
+use std::sync::Arc;
+
 /// Configuration options that govern how a RTMP client session should operate
 #[derive(Clone)]
 pub struct ClientSessionConfig {
@@ -6,6 +10,26 @@ pub struct ClientSessionConfig {
     pub window_ack_size: u32,
     pub chunk_size: u32,
     pub tc_url: Option<String>,
+
+    /// The Enhanced RTMP video codecs this client supports.  When not empty these are advertised
+    /// to the server in the connect request.
+    pub enhanced_rtmp: EnhancedRtmpCapabilities,
+
+    /// Limits on how much inbound data the server can make the session hold on to
+    pub deserializer_limits: ChunkDeserializerLimits,
+
+    /// Credentials used to answer the server when it requires `authmod=adobe` authentication.
+    /// When set, connection requests rejected with an authentication challenge are retried
+    /// automatically over the same connection.
+    pub adobe_auth: Option<AdobeAuthCredentials>,
+
+    /// When true, `releaseStream` and `FCPublish` are sent prior to requesting to publish, and
+    /// `FCUnpublish` is sent when publishing is stopped, as encoders like OBS and FFmpeg do.
+    /// Some servers (and CDNs) require these commands before accepting a publisher.
+    pub send_fc_publish: bool,
+
+    /// The clock used to timestamp outbound messages and ping requests
+    pub clock: Arc<dyn Clock>,
 }
 
 impl ClientSessionConfig {
@@ -16,7 +40,12 @@ impl ClientSessionConfig {
             playback_buffer_length_ms: 2_000,
             window_ack_size: 2_500_000,
             chunk_size: 4096,
-            tc_url: None
+            tc_url: None,
+            enhanced_rtmp: EnhancedRtmpCapabilities::new(),
+            deserializer_limits: ChunkDeserializerLimits::new(),
+            adobe_auth: None,
+            send_fc_publish: false,
+            clock: Arc::new(SystemClock),
         }
     }
 }
\ No newline at end of file
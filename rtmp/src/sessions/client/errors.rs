@@ -2,7 +2,7 @@ use std::fmt;
 use failure::{Backtrace, Fail};
 use ::chunk_io::{ChunkSerializationError, ChunkDeserializationError};
 use ::messages::{MessageSerializationError, MessageDeserializationError};
-use ::sessions::ClientState;
+use ::sessions::{ClientState, ClientStreamState, StreamHandle};
 
 /// Error state when a client session encounters an error
 #[derive(Debug)]
@@ -40,6 +40,21 @@ pub enum ClientSessionErrorKind {
         current_state: ClientState
     },
 
+    /// Encountered if a request is made on a stream, or a message is received for it, while the
+    /// stream is not in a valid state for that purpose.
+    #[fail(display = "The request could not be performed while {:?} is in the {:?} state", stream, current_state)]
+    StreamInInvalidState {
+        stream: StreamHandle,
+        current_state: ClientStreamState,
+    },
+
+    /// Encountered when a request is made on a stream handle that the session is not tracking,
+    /// such as one that has already been stopped.
+    #[fail(display = "The stream {:?} is not active on this session", stream)]
+    UnknownStream {
+        stream: StreamHandle,
+    },
+
     /// Encountered when attempting to send a message that requires having an active stream
     /// opened but none is marked down.  This is almost always a bug with the `ClientSession` as
     /// this means we are in a valid state (e.g. `Playing` or `Publishing`) yet we never recorded
@@ -1,7 +1,8 @@
 use bytes::Bytes;
 use rml_amf0::Amf0Value;
-use ::sessions::StreamMetadata;
+use ::sessions::{CallHandle, StreamMetadata};
 use ::time::RtmpTimestamp;
+use super::StreamHandle;
 
 /// Events that can be raised by the client session so that custom business logic can be written
 /// to react to it
@@ -15,30 +16,75 @@ pub enum ClientSessionEvent {
         description: String,
     },
 
+    /// The server rejected the connection request with an `authmod=adobe` challenge, and a new
+    /// connection request answering it has been sent with the query appended to the app name and
+    /// tcUrl.  If the server closed the connection instead of waiting for the new request, the
+    /// query should be passed to `ClientSession::request_connection_with_adobe_auth_query()` on
+    /// a new connection.
+    ConnectionRequestRetriedWithAdobeAuth {
+        auth_query: String,
+    },
+
     /// The server has accepted our request to play video back from a stream key
-    PlaybackRequestAccepted,
+    PlaybackRequestAccepted {
+        stream: StreamHandle,
+    },
 
     /// The server has accepted our request to publish video
-    PublishRequestAccepted,
+    PublishRequestAccepted {
+        stream: StreamHandle,
+    },
+
+    /// The server has paused playback of the stream
+    PlaybackPaused {
+        stream: StreamHandle,
+    },
+
+    /// The server has resumed playback of a paused stream
+    PlaybackResumed {
+        stream: StreamHandle,
+    },
+
+    /// The server has moved the playback position of the stream, either due to a seek request
+    /// or because audio or video was re-enabled.
+    SeekNotified {
+        stream: StreamHandle,
+    },
+
+    /// The server signaled that media is starting to be sent on the stream (`StreamBegin`)
+    StreamBegan {
+        stream: StreamHandle,
+    },
+
+    /// The server signaled that there is no more media to send on the stream (`StreamEOF`),
+    /// such as when playback was paused or a recorded stream reached its end.
+    StreamEnded {
+        stream: StreamHandle,
+    },
 
     /// The server has sent over new metadata for the stream
     StreamMetadataReceived {
+        stream: StreamHandle,
         metadata: StreamMetadata,
     },
 
     /// The server has sent over video data for the stream
     VideoDataReceived {
+        stream: StreamHandle,
         timestamp: RtmpTimestamp,
         data: Bytes,
     },
 
     /// The server has sent over audio data for the stream
     AudioDataReceived {
+        stream: StreamHandle,
         timestamp: RtmpTimestamp,
         data: Bytes,
     },
 
-    /// The server sent an Amf0 command that was not able to be handled
+    /// The server sent an Amf0 command that was not able to be handled.  If the server is
+    /// expecting a response (non-zero `transaction_id`) one can be sent with
+    /// `ClientSession::respond_to_call()`.
     UnhandleableAmf0Command {
         command_name: String,
         transaction_id: f64,
@@ -53,10 +99,26 @@ pub enum ClientSessionEvent {
         additional_values: Vec<Amf0Value>,
     },
 
+    /// The server responded with a `_result` to a call made with `ClientSession::call()`
+    CallResultReceived {
+        handle: CallHandle,
+        command_object: Amf0Value,
+        additional_values: Vec<Amf0Value>,
+    },
+
+    /// The server responded with an `_error` to a call made with `ClientSession::call()`
+    CallErrorReceived {
+        handle: CallHandle,
+        command_object: Amf0Value,
+        additional_values: Vec<Amf0Value>,
+    },
+
     /// The server sent an `onStatus` message with a `code` property that we don't know
-    /// how to handle.
+    /// how to handle.  The stream is `None` if the message was not sent on a stream this
+    /// session is playing or publishing on.
     UnhandleableOnStatusCode {
         code: String,
+        stream: Option<StreamHandle>,
     },
 
     /// The client has sent an acknowledgement that they have received the specified number of bytes
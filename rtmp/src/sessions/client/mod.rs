@@ -1,3 +1,4 @@
+mod active_stream;
 mod config;
 mod errors;
 mod events;
@@ -5,6 +6,7 @@ mod outstanding_transaction;
 mod publish_request_type;
 mod result;
 mod state;
+mod stream_handle;
 
 #[cfg(test)]
 mod tests;
@@ -14,17 +16,19 @@ pub use self::errors::{ClientSessionError, ClientSessionErrorKind};
 pub use self::events::ClientSessionEvent;
 pub use self::publish_request_type::PublishRequestType;
 pub use self::result::ClientSessionResult;
-pub use self::state::ClientState;
+pub use self::state::{ClientState, ClientStreamState};
+pub use self::stream_handle::StreamHandle;
 
+use self::active_stream::ActiveStream;
 use self::outstanding_transaction::{OutstandingTransaction, TransactionPurpose};
 use bytes::Bytes;
 use chunk_io::{ChunkDeserializer, ChunkSerializer, Packet};
-use messages::{RtmpMessage, UserControlEventType};
+use messages::{MessagePayload, RtmpMessage, UserControlEventType};
+use rand;
 use rml_amf0::Amf0Value;
-use sessions::StreamMetadata;
+use sessions::adobe_auth;
+use sessions::{CallHandle, CallResponse, EnhancedRtmpCapabilities, StreamMetadata};
 use std::collections::HashMap;
-use std::mem;
-use std::time::SystemTime;
 use time::RtmpTimestamp;
 
 type ClientResult = Result<Vec<ClientSessionResult>, ClientSessionError>;
@@ -35,22 +39,23 @@ type ClientResult = Result<Vec<ClientSessionResult>, ClientSessionError>;
 /// includes how to connect to an application on the server, requesting publishing or playback,
 /// and reacting to events and responses the server may send.
 ///
-/// While RTMP connections can support multiple media operations simultaneously the `ClientSession`
-/// has been designed for the main use case of one operation per connection.
+/// A single connection can play and publish on any number of streams at the same time.  Each
+/// playback or publish request is given a `StreamHandle`, which is used to perform further
+/// operations on that stream and identifies which stream raised events belong to.
 ///
 /// Due to the way the header compression properties of the RTMP chunking protocol works,
 /// is is required that:
 ///
 /// * All bytes **after** the handshake has been completed are passed into the `ClientSession` in
-/// the order they were received
+///   the order they were received
 /// * All responses generated by the session are sent to the server **in order**
 /// * No extraneous bytes are passed into the session, and only bytes generated by the session are
-/// sent to the server.
+///   sent to the server.
 ///
 /// Any violation of these points have a high probability of causing RTMP chunk parsing errors
 /// by either the `ClientSession` or the peer.
 pub struct ClientSession {
-    start_time: SystemTime,
+    start_time: u64,
     serializer: ChunkSerializer,
     deserializer: ChunkDeserializer,
     config: ClientSessionConfig,
@@ -58,7 +63,11 @@ pub struct ClientSession {
     outstanding_transactions: HashMap<u32, OutstandingTransaction>,
     current_state: ClientState,
     connected_app_name: Option<String>,
-    active_stream_id: Option<u32>,
+    server_enhanced_rtmp: Option<EnhancedRtmpCapabilities>,
+    active_streams: HashMap<StreamHandle, ActiveStream>,
+    next_stream_handle: u32,
+    adobe_auth_user_sent: bool,
+    adobe_auth_response_sent: bool,
     peer_window_ack_size: Option<u32>,
     bytes_received: u64,
     bytes_received_since_last_ack: u32,
@@ -73,14 +82,18 @@ impl ClientSession {
         config: ClientSessionConfig,
     ) -> Result<(ClientSession, Vec<ClientSessionResult>), ClientSessionError> {
         let mut session = ClientSession {
-            start_time: SystemTime::now(),
+            start_time: config.clock.get_time_ms(),
             serializer: ChunkSerializer::new(),
-            deserializer: ChunkDeserializer::new(),
+            deserializer: ChunkDeserializer::with_limits(config.deserializer_limits.clone()),
             next_transaction_id: 1,
             outstanding_transactions: HashMap::new(),
             current_state: ClientState::Disconnected,
-            active_stream_id: None,
+            active_streams: HashMap::new(),
+            next_stream_handle: 1,
+            adobe_auth_user_sent: false,
+            adobe_auth_response_sent: false,
             connected_app_name: None,
+            server_enhanced_rtmp: None,
             peer_window_ack_size: None,
             bytes_received: 0,
             bytes_received_since_last_ack: 0,
@@ -98,7 +111,10 @@ impl ClientSession {
 
     /// Takes in any number of bytes from the peer and processes them.  Any resulting responses or
     /// events are returned.
-    pub fn handle_input(&mut self, bytes: &[u8]) -> ClientResult {
+    ///
+    /// The data of audio and video messages is sliced out of the passed in bytes whenever
+    /// possible, so media data does not get copied on its way to the raised events.
+    pub fn handle_input(&mut self, bytes: Bytes) -> ClientResult {
         let mut results = Vec::new();
         self.bytes_received += bytes.len() as u64;
 
@@ -118,28 +134,39 @@ impl ClientSession {
 
         let mut bytes_to_process = bytes;
         loop {
-            match self.deserializer.get_next_message(bytes_to_process)? {
+            match self.deserializer.get_next_message_from_bytes(bytes_to_process)? {
                 None => break, // no more messages
                 Some(payload) => {
                     let message = payload.to_rtmp_message()?;
                     let mut message_results = match message {
+                        RtmpMessage::Abort { stream_id } => self.handle_abort(stream_id)?,
+
                         RtmpMessage::Acknowledgement { sequence_number } => {
                             self.handle_acknowledgement(sequence_number)?
                         }
 
+                        RtmpMessage::Aggregate { .. } => self.handle_aggregate(&payload)?,
+
                         RtmpMessage::Amf0Command {
                             command_name,
                             transaction_id,
                             command_object,
                             additional_arguments,
+                        }
+                        | RtmpMessage::Amf3Command {
+                            command_name,
+                            transaction_id,
+                            command_object,
+                            additional_arguments,
                         } => self.handle_amf0_command(
+                            payload.message_stream_id,
                             command_name,
                             transaction_id,
                             command_object,
                             additional_arguments,
                         )?,
 
-                        RtmpMessage::Amf0Data { values } => {
+                        RtmpMessage::Amf0Data { values } | RtmpMessage::Amf3Data { values } => {
                             self.handle_amf0_data(values, payload.message_stream_id)?
                         }
 
@@ -177,7 +204,7 @@ impl ClientSession {
                     };
 
                     results.append(&mut message_results);
-                    bytes_to_process = &[];
+                    bytes_to_process = Bytes::new();
                 }
             }
         }
@@ -185,11 +212,36 @@ impl ClientSession {
         Ok(results)
     }
 
+    /// Returns the Enhanced RTMP capabilities the server replied with when it accepted our
+    /// connection request.  This is `None` if the server did not advertise Enhanced RTMP support.
+    pub fn get_enhanced_rtmp_capabilities(&self) -> Option<&EnhancedRtmpCapabilities> {
+        self.server_enhanced_rtmp.as_ref()
+    }
+
     /// Forms an RTMP message requesting a connection to the specified application on the server.
     /// An event will be raised when the request is accepted or rejected.
+    ///
+    /// If `adobe_auth` credentials are configured and the server rejects the request with an
+    /// `authmod=adobe` challenge, the session automatically sends a new connection request
+    /// answering the challenge instead of raising a rejection event.  The query sent with the new
+    /// request is raised in a `ConnectionRequestRetriedWithAdobeAuth` event, so that when the
+    /// server closes the connection after its rejection the query can be passed to
+    /// `request_connection_with_adobe_auth_query()` on a new connection.
     pub fn request_connection(
         &mut self,
         app_name: String,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        self.request_connection_with_adobe_auth_query(app_name, String::new())
+    }
+
+    /// Forms an RTMP message requesting a connection to the specified application, with the
+    /// query from a `ConnectionRequestRetriedWithAdobeAuth` event raised by a prior session
+    /// appended to the app name and tcUrl.  This continues `authmod=adobe` authentication on a
+    /// new connection, for servers that close the connection after rejecting a request.
+    pub fn request_connection_with_adobe_auth_query(
+        &mut self,
+        app_name: String,
+        auth_query: String,
     ) -> Result<ClientSessionResult, ClientSessionError> {
         match self.current_state {
             ClientState::Disconnected => (),
@@ -199,49 +251,22 @@ impl ClientSession {
             }
         }
 
-        let transaction_id = self.get_next_transaction_id();
-        let transaction = OutstandingTransaction::ConnectionRequested {
-            app_name: app_name.clone(),
-        };
-        self.outstanding_transactions
-            .insert(transaction_id, transaction);
-
-        let mut properties = HashMap::new();
-        properties.insert("app".to_string(), Amf0Value::Utf8String(app_name));
-        properties.insert(
-            "flashVer".to_string(),
-            Amf0Value::Utf8String(self.config.flash_version.clone()),
-        );
-        properties.insert("objectEncoding".to_string(), Amf0Value::Number(0.0));
-
-        // Some implementations require a tcUrl to be sent up with the connection request
-        match &self.config.tc_url {
-            Some(tc_url) => {
-                properties.insert("tcUrl".to_string(), Amf0Value::Utf8String(tc_url.clone()));
-            }
-            None => (),
-        };
-
-        let message = RtmpMessage::Amf0Command {
-            command_name: "connect".to_string(),
-            command_object: Amf0Value::Object(properties),
-            additional_arguments: vec![],
-            transaction_id: transaction_id as f64,
-        };
-
-        let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
-
+        let parameters = adobe_auth::parse_query(adobe_auth::split_query(&auth_query).1);
+        self.adobe_auth_user_sent = parameters.contains_key("user");
+        self.adobe_auth_response_sent = parameters.contains_key("response");
+        let packet = self.create_connection_request(app_name, &auth_query)?;
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
 
     /// Starts the process of requesting playback on the server for the specified stream key.  An
     /// event will be raised when the request is accepted or rejected.  Once accepted we will
     /// receive audio, video, and metadata information via `ClientSessionEvent`s.
+    ///
+    /// The returned handle identifies this stream in later calls and in raised events.
     pub fn request_playback(
         &mut self,
         stream_key: String,
-    ) -> Result<ClientSessionResult, ClientSessionError> {
+    ) -> Result<(StreamHandle, ClientSessionResult), ClientSessionError> {
         match self.current_state {
             ClientState::Connected => (),
             _ => {
@@ -252,34 +277,32 @@ impl ClientSession {
             }
         }
 
-        let transaction_id = self.get_next_transaction_id();
-        let transaction = OutstandingTransaction::CreateStream {
-            purpose: TransactionPurpose::PlayRequest { stream_key },
-        };
-
-        self.outstanding_transactions
-            .insert(transaction_id, transaction);
+        let stream = self.get_next_stream_handle();
+        let purpose = TransactionPurpose::PlayRequest { stream_key };
+        let packet = self.create_stream_request(stream, purpose)?;
 
-        let message = RtmpMessage::Amf0Command {
-            command_name: "createStream".to_string(),
-            transaction_id: transaction_id as f64,
-            command_object: Amf0Value::Null,
-            additional_arguments: Vec::new(),
+        let active_stream = ActiveStream {
+            stream_id: None,
+            current_state: ClientStreamState::PlayRequested,
+            fc_published_stream_key: None,
         };
 
-        let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
-
-        Ok(ClientSessionResult::OutboundResponse(packet))
+        self.active_streams.insert(stream, active_stream);
+        Ok((stream, ClientSessionResult::OutboundResponse(packet)))
     }
 
     /// Starts the process of requesting to publish to the server on the specified stream key.  An
     /// event will be raised when the request is accepted or rejected.
+    ///
+    /// The returned handle identifies this stream in later calls and in raised events.
+    ///
+    /// If `send_fc_publish` is enabled in the configuration, the returned packet also contains
+    /// the `releaseStream` and `FCPublish` commands that precede the request.
     pub fn request_publishing(
         &mut self,
         stream_key: String,
         publish_type: PublishRequestType,
-    ) -> Result<ClientSessionResult, ClientSessionError> {
+    ) -> Result<(StreamHandle, ClientSessionResult), ClientSessionError> {
         match self.current_state {
             ClientState::Connected => (),
             _ => {
@@ -290,84 +313,161 @@ impl ClientSession {
             }
         }
 
-        let transaction_id = self.get_next_transaction_id();
-        let transaction = OutstandingTransaction::CreateStream {
-            purpose: TransactionPurpose::PublishRequest {
-                stream_key,
-                request_type: publish_type,
-            },
-        };
+        let mut bytes = Vec::new();
+        let mut fc_published_stream_key = None;
+        if self.config.send_fc_publish {
+            for command_name in &["releaseStream", "FCPublish"] {
+                let packet = self.create_fc_command(command_name, &stream_key)?;
+                bytes.extend_from_slice(&packet.bytes);
+            }
 
-        self.outstanding_transactions
-            .insert(transaction_id, transaction);
+            fc_published_stream_key = Some(stream_key.clone());
+        }
 
-        let message = RtmpMessage::Amf0Command {
-            command_name: "createStream".to_string(),
-            transaction_id: transaction_id as f64,
-            command_object: Amf0Value::Null,
-            additional_arguments: Vec::new(),
+        let stream = self.get_next_stream_handle();
+        let purpose = TransactionPurpose::PublishRequest {
+            stream_key,
+            request_type: publish_type,
         };
 
-        let payload = message.into_message_payload(self.get_epoch(), 0)?;
-        let packet = self.serializer.serialize(&payload, false, false)?;
+        let mut packet = self.create_stream_request(stream, purpose)?;
+        if !bytes.is_empty() {
+            bytes.extend_from_slice(&packet.bytes);
+            packet.bytes = bytes;
+        }
 
-        Ok(ClientSessionResult::OutboundResponse(packet))
+        let active_stream = ActiveStream {
+            stream_id: None,
+            current_state: ClientStreamState::PublishRequested,
+            fc_published_stream_key,
+        };
+
+        self.active_streams.insert(stream, active_stream);
+        Ok((stream, ClientSessionResult::OutboundResponse(packet)))
     }
 
-    /// If currently playing on a stream key, this is used to tell the server we no longer want to
-    /// play video from the stream.
-    pub fn stop_playback(&mut self) -> ClientResult {
+    /// If currently playing on the specified stream, this is used to tell the server we no longer
+    /// want to play video from the stream.
+    pub fn stop_playback(&mut self, stream: StreamHandle) -> ClientResult {
         // Validate we are in a state to do this
-        match self.current_state {
-            ClientState::Playing { .. } => (),
-            ClientState::PlayRequested { .. } => (),
+        match self.active_streams.get(&stream).map(|x| &x.current_state) {
+            Some(ClientStreamState::Playing) => (),
+            Some(ClientStreamState::PlayRequested) => (),
             _ => return Ok(Vec::new()), // Nothing to stop since we aren't performing playback
         }
 
-        self.current_state = ClientState::Connected;
-        match mem::replace(&mut self.active_stream_id, None) {
-            None => Ok(Vec::new()), // Should never happen since we should always have a valid stream id
-            Some(stream_id) => {
-                let message = RtmpMessage::Amf0Command {
-                    command_name: "deleteStream".to_string(),
-                    transaction_id: 0.0, // always 0 per spec
-                    command_object: Amf0Value::Null,
-                    additional_arguments: vec![Amf0Value::Number(stream_id as f64)],
-                };
-
-                let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
-                let packet = self.serializer.serialize(&payload, false, false)?;
-                Ok(vec![ClientSessionResult::OutboundResponse(packet)])
+        let mut results = Vec::new();
+        if let Some(active_stream) = self.active_streams.remove(&stream) {
+            // Without a stream id the `createStream` request is still outstanding, and the
+            // stream will be deleted once the server responds to it
+            if let Some(stream_id) = active_stream.stream_id {
+                let packet = self.create_delete_stream_request(stream_id)?;
+                results.push(ClientSessionResult::OutboundResponse(packet));
             }
         }
+
+        Ok(results)
     }
 
-    /// If currently publishing on a stream key, this is used to tell the server we no longer want
-    /// to publish to that stream.
-    pub fn stop_publishing(&mut self) -> ClientResult {
+    /// If currently publishing on the specified stream, this is used to tell the server we no
+    /// longer want to publish to that stream.
+    pub fn stop_publishing(&mut self, stream: StreamHandle) -> ClientResult {
         // Validate we are in a state to do this
-        match self.current_state {
-            ClientState::Publishing { .. } => (),
-            ClientState::PublishRequested { .. } => (),
-            _ => return Ok(Vec::new()), // Nothing to stop since we aren't performing playback
+        match self.active_streams.get(&stream).map(|x| &x.current_state) {
+            Some(ClientStreamState::Publishing) => (),
+            Some(ClientStreamState::PublishRequested) => (),
+            _ => return Ok(Vec::new()), // Nothing to stop since we aren't publishing
         }
 
-        self.current_state = ClientState::Connected;
-        match mem::replace(&mut self.active_stream_id, None) {
-            None => Ok(Vec::new()), // Should never happen since we should always have a valid stream id
-            Some(stream_id) => {
-                let message = RtmpMessage::Amf0Command {
-                    command_name: "deleteStream".to_string(),
-                    transaction_id: 0.0, // always 0 per spec
-                    command_object: Amf0Value::Null,
-                    additional_arguments: vec![Amf0Value::Number(stream_id as f64)],
-                };
+        let mut results = Vec::new();
+        if let Some(active_stream) = self.active_streams.remove(&stream) {
+            if let Some(stream_key) = active_stream.fc_published_stream_key {
+                let packet = self.create_fc_command("FCUnpublish", &stream_key)?;
+                results.push(ClientSessionResult::OutboundResponse(packet));
+            }
 
-                let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
-                let packet = self.serializer.serialize(&payload, false, false)?;
-                Ok(vec![ClientSessionResult::OutboundResponse(packet)])
+            if let Some(stream_id) = active_stream.stream_id {
+                let packet = self.create_delete_stream_request(stream_id)?;
+                results.push(ClientSessionResult::OutboundResponse(packet));
             }
         }
+
+        Ok(results)
+    }
+
+    /// Returns the current state of the specified stream, or `None` if the stream has been
+    /// stopped or the handle is not known to this session.
+    pub fn get_stream_state(&self, stream: StreamHandle) -> Option<&ClientStreamState> {
+        self.active_streams.get(&stream).map(|x| &x.current_state)
+    }
+
+    /// Returns the RTMP stream id the server allocated for the specified stream.  This is `None`
+    /// until the server has responded to the stream's `createStream` request.
+    pub fn get_stream_id(&self, stream: StreamHandle) -> Option<u32> {
+        self.active_streams.get(&stream).and_then(|x| x.stream_id)
+    }
+
+    /// Asks the server to pause playback on the specified stream.  The position is the playback
+    /// position, in milliseconds, the client paused at.  A `PlaybackPaused` event is raised
+    /// once the server has paused the stream.
+    pub fn pause_playback(
+        &mut self,
+        stream: StreamHandle,
+        position_ms: u32,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        let arguments = vec![
+            Amf0Value::Boolean(true),
+            Amf0Value::Number(position_ms as f64),
+        ];
+
+        self.create_playback_command(stream, "pause", arguments)
+    }
+
+    /// Asks the server to resume playback on a paused stream from the specified position, in
+    /// milliseconds.  A `PlaybackResumed` event is raised once the server has resumed the stream.
+    pub fn resume_playback(
+        &mut self,
+        stream: StreamHandle,
+        position_ms: u32,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        let arguments = vec![
+            Amf0Value::Boolean(false),
+            Amf0Value::Number(position_ms as f64),
+        ];
+
+        self.create_playback_command(stream, "pause", arguments)
+    }
+
+    /// Asks the server to move playback of the specified stream to a position, in milliseconds,
+    /// such as when seeking through a recorded or DVR stream.  A `SeekNotified` event is raised
+    /// once the server has moved playback.
+    pub fn seek(
+        &mut self,
+        stream: StreamHandle,
+        position_ms: u32,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        let arguments = vec![Amf0Value::Number(position_ms as f64)];
+        self.create_playback_command(stream, "seek", arguments)
+    }
+
+    /// Tells the server whether it should send audio for the specified stream
+    pub fn set_receive_audio(
+        &mut self,
+        stream: StreamHandle,
+        enabled: bool,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        let arguments = vec![Amf0Value::Boolean(enabled)];
+        self.create_playback_command(stream, "receiveAudio", arguments)
+    }
+
+    /// Tells the server whether it should send video for the specified stream
+    pub fn set_receive_video(
+        &mut self,
+        stream: StreamHandle,
+        enabled: bool,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        let arguments = vec![Amf0Value::Boolean(enabled)];
+        self.create_playback_command(stream, "receiveVideo", arguments)
     }
 
     /// Sends a ping request to the server.  An event will be raised when we get a response back
@@ -385,29 +485,71 @@ impl ClientSession {
         Ok((packet, current_epoch))
     }
 
-    /// If publishing, this allows us to send encoder metadata to the server to send to all
-    /// players.
+    /// Calls a method on the server with the specified command object and arguments.  If the
+    /// server responds with a `_result` or `_error` a `CallResultReceived` or `CallErrorReceived`
+    /// event will be raised with the returned handle.
+    pub fn call(
+        &mut self,
+        method: &str,
+        command_object: Amf0Value,
+        args: Vec<Amf0Value>,
+    ) -> Result<(Packet, CallHandle), ClientSessionError> {
+        let transaction_id = self.get_next_transaction_id();
+        let handle = CallHandle(transaction_id);
+        self.outstanding_transactions
+            .insert(transaction_id, OutstandingTransaction::Call { handle });
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: method.to_string(),
+            transaction_id: transaction_id as f64,
+            command_object,
+            additional_arguments: args,
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        Ok((packet, handle))
+    }
+
+    /// Responds to a command the server called (raised as an `UnhandleableAmf0Command` event)
+    /// with either a `_result` or an `_error`.
+    pub fn respond_to_call(
+        &mut self,
+        transaction_id: f64,
+        response: CallResponse,
+    ) -> Result<Packet, ClientSessionError> {
+        let (command_name, command_object, additional_arguments) = match response {
+            CallResponse::Result {
+                command_object,
+                additional_values,
+            } => ("_result", command_object, additional_values),
+
+            CallResponse::Error {
+                command_object,
+                additional_values,
+            } => ("_error", command_object, additional_values),
+        };
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: command_name.to_string(),
+            transaction_id,
+            command_object,
+            additional_arguments,
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        Ok(packet)
+    }
+
+    /// If publishing on the specified stream, this allows us to send encoder metadata to the
+    /// server to send to all players.
     pub fn publish_metadata(
         &mut self,
+        stream: StreamHandle,
         metadata: &StreamMetadata,
     ) -> Result<ClientSessionResult, ClientSessionError> {
-        match self.current_state {
-            ClientState::Publishing => (),
-            _ => {
-                let kind = ClientSessionErrorKind::SessionInInvalidState {
-                    current_state: self.current_state.clone(),
-                };
-                return Err(ClientSessionError { kind });
-            }
-        }
-
-        let active_stream_id = match self.active_stream_id {
-            Some(x) => x,
-            None => {
-                let kind = ClientSessionErrorKind::NoKnownActiveStreamIdWhenRequired;
-                return Err(ClientSessionError { kind });
-            }
-        };
+        let active_stream_id = self.get_stream_id_in_state(stream, ClientStreamState::Publishing)?;
 
         let mut properties = HashMap::new();
         if let Some(x) = metadata.video_width {
@@ -468,30 +610,16 @@ impl ClientSession {
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
 
-    /// If publishing, this allows us to send video data to the server on the publishing stream.
+    /// If publishing on the specified stream, this allows us to send video data to the server on
+    /// that stream.
     pub fn publish_video_data(
         &mut self,
+        stream: StreamHandle,
         data: Bytes,
         timestamp: RtmpTimestamp,
         can_be_dropped: bool,
     ) -> Result<ClientSessionResult, ClientSessionError> {
-        match self.current_state {
-            ClientState::Publishing => (),
-            _ => {
-                let kind = ClientSessionErrorKind::SessionInInvalidState {
-                    current_state: self.current_state.clone(),
-                };
-                return Err(ClientSessionError { kind });
-            }
-        }
-
-        let active_stream_id = match self.active_stream_id {
-            Some(x) => x,
-            None => {
-                let kind = ClientSessionErrorKind::NoKnownActiveStreamIdWhenRequired;
-                return Err(ClientSessionError { kind });
-            }
-        };
+        let active_stream_id = self.get_stream_id_in_state(stream, ClientStreamState::Publishing)?;
 
         let message = RtmpMessage::VideoData { data };
         let payload = message.into_message_payload(timestamp, active_stream_id)?;
@@ -499,30 +627,16 @@ impl ClientSession {
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
 
-    /// If publishing, this allows us to send audio data to the server on the publishing stream.
+    /// If publishing on the specified stream, this allows us to send audio data to the server on
+    /// that stream.
     pub fn publish_audio_data(
         &mut self,
+        stream: StreamHandle,
         data: Bytes,
         timestamp: RtmpTimestamp,
         can_be_dropped: bool,
     ) -> Result<ClientSessionResult, ClientSessionError> {
-        match self.current_state {
-            ClientState::Publishing => (),
-            _ => {
-                let kind = ClientSessionErrorKind::SessionInInvalidState {
-                    current_state: self.current_state.clone(),
-                };
-                return Err(ClientSessionError { kind });
-            }
-        }
-
-        let active_stream_id = match self.active_stream_id {
-            Some(x) => x,
-            None => {
-                let kind = ClientSessionErrorKind::NoKnownActiveStreamIdWhenRequired;
-                return Err(ClientSessionError { kind });
-            }
-        };
+        let active_stream_id = self.get_stream_id_in_state(stream, ClientStreamState::Publishing)?;
 
         let message = RtmpMessage::AudioData { data };
         let payload = message.into_message_payload(timestamp, active_stream_id)?;
@@ -530,33 +644,66 @@ impl ClientSession {
         Ok(ClientSessionResult::OutboundResponse(packet))
     }
 
+    fn handle_aggregate(&mut self, payload: &MessagePayload) -> ClientResult {
+        let mut results = Vec::new();
+        for sub_payload in payload.split_aggregate()? {
+            let mut sub_results = match sub_payload.to_rtmp_message()? {
+                RtmpMessage::AudioData { data } => self.handle_audio_data(
+                    sub_payload.message_stream_id,
+                    data,
+                    sub_payload.timestamp,
+                )?,
+
+                RtmpMessage::VideoData { data } => self.handle_video_data(
+                    sub_payload.message_stream_id,
+                    data,
+                    sub_payload.timestamp,
+                )?,
+
+                RtmpMessage::Amf0Data { values } | RtmpMessage::Amf3Data { values } => {
+                    self.handle_amf0_data(values, sub_payload.message_stream_id)?
+                }
+
+                _ => vec![ClientSessionResult::UnhandleableMessageReceived(sub_payload)],
+            };
+
+            results.append(&mut sub_results);
+        }
+
+        Ok(results)
+    }
+
     fn handle_video_data(
         &self,
         stream_id: u32,
         data: Bytes,
         timestamp: RtmpTimestamp,
     ) -> ClientResult {
+        // Validate we are active on the stream this message came from
+        let (stream, active_stream) = match self.find_stream(stream_id) {
+            Some(x) => x,
+            None => return Ok(Vec::new()), // not active on this stream
+        };
+
         // PlayRequested state is allowed because some servers send video data prior to the
         // `NetStream.Play.Start` command.
-        match self.current_state {
-            ClientState::PlayRequested { .. } => (),
-            ClientState::Playing { .. } => (),
-            _ => {
-                let kind = ClientSessionErrorKind::SessionInInvalidState {
-                    current_state: self.current_state.clone(),
+        match active_stream.current_state {
+            ClientStreamState::PlayRequested => (),
+            ClientStreamState::Playing => (),
+            ref state => {
+                let kind = ClientSessionErrorKind::StreamInInvalidState {
+                    stream,
+                    current_state: state.clone(),
                 };
                 return Err(ClientSessionError { kind });
             }
         }
 
-        // Validate we are active on the stream this message came from
-        match self.active_stream_id {
-            None => return Ok(Vec::new()), // not active on any stream
-            Some(active_stream_id) if active_stream_id != stream_id => return Ok(Vec::new()), // not active on this stream
-            Some(_) => (),
-        }
-
-        let event = ClientSessionEvent::VideoDataReceived { data, timestamp };
+        let event = ClientSessionEvent::VideoDataReceived {
+            stream,
+            data,
+            timestamp,
+        };
         Ok(vec![ClientSessionResult::RaisedEvent(event)])
     }
 
@@ -566,27 +713,31 @@ impl ClientSession {
         data: Bytes,
         timestamp: RtmpTimestamp,
     ) -> ClientResult {
+        // Validate we are active on the stream this message came from
+        let (stream, active_stream) = match self.find_stream(stream_id) {
+            Some(x) => x,
+            None => return Ok(Vec::new()), // not active on this stream
+        };
+
         // PlayRequested state is allowed because some servers send audio data prior to the
         // `NetStream.Play.Start` command.
-        match self.current_state {
-            ClientState::PlayRequested { .. } => (),
-            ClientState::Playing { .. } => (),
-            _ => {
-                let kind = ClientSessionErrorKind::SessionInInvalidState {
-                    current_state: self.current_state.clone(),
+        match active_stream.current_state {
+            ClientStreamState::PlayRequested => (),
+            ClientStreamState::Playing => (),
+            ref state => {
+                let kind = ClientSessionErrorKind::StreamInInvalidState {
+                    stream,
+                    current_state: state.clone(),
                 };
                 return Err(ClientSessionError { kind });
             }
         }
 
-        // Validate we are active on the stream this message came from
-        match self.active_stream_id {
-            None => return Ok(Vec::new()), // not active on any stream
-            Some(active_stream_id) if active_stream_id != stream_id => return Ok(Vec::new()), // not active on this stream
-            Some(_) => (),
-        }
-
-        let event = ClientSessionEvent::AudioDataReceived { data, timestamp };
+        let event = ClientSessionEvent::AudioDataReceived {
+            stream,
+            data,
+            timestamp,
+        };
         Ok(vec![ClientSessionResult::RaisedEvent(event)])
     }
 
@@ -597,16 +748,15 @@ impl ClientSession {
         }
 
         // Validate we are active on the stream this message came from
-        match self.active_stream_id {
-            None => return Ok(Vec::new()), // not active on any stream
-            Some(active_stream_id) if active_stream_id != stream_id => return Ok(Vec::new()), // not active on this stream
-            Some(_) => (),
-        }
+        let stream = match self.find_stream(stream_id) {
+            Some((stream, _)) => stream,
+            None => return Ok(Vec::new()), // not active on this stream
+        };
 
         let first_element = data.remove(0);
         match first_element {
             Amf0Value::Utf8String(ref value) if value == "onMetaData" => {
-                self.handle_amf0_data_on_meta_data(data)
+                self.handle_amf0_data_on_meta_data(stream, data)
             }
 
             _ => Ok(Vec::new()),
@@ -615,6 +765,7 @@ impl ClientSession {
 
     fn handle_amf0_command(
         &mut self,
+        stream_id: u32,
         name: String,
         transaction_id: f64,
         command_object: Amf0Value,
@@ -631,7 +782,21 @@ impl ClientSession {
                 command_object,
                 additional_args,
             ),
-            "onStatus" => self.handle_on_status_command(additional_args),
+            "onStatus" => self.handle_on_status_command(stream_id, additional_args),
+
+            // Replies to `FC*` commands are informational only
+            "onFCPublish" | "onFCUnpublish" => Ok(Vec::new()),
+
+            // The server times how long we take to reply to measure our bandwidth
+            "onBWCheck" => {
+                let response = CallResponse::Result {
+                    command_object: Amf0Value::Null,
+                    additional_values: vec![Amf0Value::Undefined],
+                };
+
+                let packet = self.respond_to_call(transaction_id, response)?;
+                Ok(vec![ClientSessionResult::OutboundResponse(packet)])
+            }
 
             _ => {
                 let event = ClientSessionEvent::UnhandleableAmf0Command {
@@ -669,7 +834,7 @@ impl ClientSession {
         };
 
         match outstanding_transaction {
-            OutstandingTransaction::ConnectionRequested { app_name: _ } => {
+            OutstandingTransaction::ConnectionRequested { app_name } => {
                 let description = if !additional_args.is_empty() {
                     if let Amf0Value::Object(mut properties) = additional_args.remove(0) {
                         if let Some(Amf0Value::Utf8String(value)) = properties.remove("description")
@@ -685,14 +850,36 @@ impl ClientSession {
                     "".to_string()
                 };
 
+                if let Some(auth_query) = self.get_adobe_auth_query(&description) {
+                    let packet = self.create_connection_request(app_name, &auth_query)?;
+                    let event = ClientSessionEvent::ConnectionRequestRetriedWithAdobeAuth { auth_query };
+                    return Ok(vec![
+                        ClientSessionResult::OutboundResponse(packet),
+                        ClientSessionResult::RaisedEvent(event),
+                    ]);
+                }
+
                 let event = ClientSessionEvent::ConnectionRequestRejected { description };
                 Ok(vec![ClientSessionResult::RaisedEvent(event)])
             }
 
-            OutstandingTransaction::CreateStream { purpose: _ } => {
+            OutstandingTransaction::CreateStream { .. } => {
                 let kind = ClientSessionErrorKind::CreateStreamFailed;
                 Err(ClientSessionError { kind })
             }
+
+            // Servers that don't support these commands reject them, which is fine
+            OutstandingTransaction::FcCommand => Ok(Vec::new()),
+
+            OutstandingTransaction::Call { handle } => {
+                let event = ClientSessionEvent::CallErrorReceived {
+                    handle,
+                    command_object,
+                    additional_values: additional_args,
+                };
+
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
         }
     }
 
@@ -723,6 +910,13 @@ impl ClientSession {
                 self.current_state = ClientState::Connected;
                 self.connected_app_name = Some(app_name);
 
+                if let Some(properties) = command_object.get_object_properties() {
+                    let capabilities = EnhancedRtmpCapabilities::read_properties(&properties);
+                    if !capabilities.is_empty() {
+                        self.server_enhanced_rtmp = Some(capabilities);
+                    }
+                }
+
                 let message = RtmpMessage::WindowAcknowledgement {
                     size: self.config.window_ack_size,
                 };
@@ -735,7 +929,7 @@ impl ClientSession {
                 ])
             }
 
-            OutstandingTransaction::CreateStream { purpose } => {
+            OutstandingTransaction::CreateStream { stream, purpose } => {
                 if additional_args.is_empty() {
                     let kind = ClientSessionErrorKind::CreateStreamResponseHadNoStreamNumber;
                     return Err(ClientSessionError { kind });
@@ -749,12 +943,17 @@ impl ClientSession {
                     }
                 };
 
-                self.active_stream_id = Some(stream_id);
+                match self.active_streams.get_mut(&stream) {
+                    Some(active_stream) => active_stream.stream_id = Some(stream_id),
+                    None => {
+                        // The stream was stopped before the server created it
+                        let packet = self.create_delete_stream_request(stream_id)?;
+                        return Ok(vec![ClientSessionResult::OutboundResponse(packet)]);
+                    }
+                }
 
                 match purpose {
                     TransactionPurpose::PlayRequest { stream_key } => {
-                        self.current_state = ClientState::PlayRequested;
-
                         let buffer_message = RtmpMessage::UserControl {
                             event_type: UserControlEventType::SetBufferLength,
                             buffer_length: Some(self.config.playback_buffer_length_ms),
@@ -788,8 +987,6 @@ impl ClientSession {
                         stream_key,
                         request_type,
                     } => {
-                        self.current_state = ClientState::PublishRequested;
-
                         let publish_type_string = match request_type {
                             PublishRequestType::Live => "live".to_string(),
                             PublishRequestType::Record => "record".to_string(),
@@ -814,10 +1011,26 @@ impl ClientSession {
                     }
                 }
             }
+
+            OutstandingTransaction::FcCommand => Ok(Vec::new()),
+
+            OutstandingTransaction::Call { handle } => {
+                let event = ClientSessionEvent::CallResultReceived {
+                    handle,
+                    command_object,
+                    additional_values: additional_args,
+                };
+
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
         }
     }
 
-    fn handle_on_status_command(&mut self, mut arguments: Vec<Amf0Value>) -> ClientResult {
+    fn handle_on_status_command(
+        &mut self,
+        stream_id: u32,
+        mut arguments: Vec<Amf0Value>,
+    ) -> ClientResult {
         if arguments.is_empty() {
             let kind = ClientSessionErrorKind::InvalidOnStatusArguments;
             return Err(ClientSessionError { kind });
@@ -840,70 +1053,87 @@ impl ClientSession {
             }
         };
 
-        match code.as_ref() {
-            "NetStream.Play.Start" => self.handle_play_start(),
-            "NetStream.Publish.Start" => self.handle_publish_start(),
+        let stream = self.find_stream(stream_id).map(|(stream, _)| stream);
+        match (code.as_ref(), stream) {
+            ("NetStream.Play.Start", Some(stream)) => self.handle_play_start(stream),
+            ("NetStream.Publish.Start", Some(stream)) => self.handle_publish_start(stream),
+            ("NetStream.Pause.Notify", Some(stream)) => {
+                let event = ClientSessionEvent::PlaybackPaused { stream };
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
+
+            ("NetStream.Unpause.Notify", Some(stream)) => {
+                let event = ClientSessionEvent::PlaybackResumed { stream };
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
 
-            x => {
-                let event = ClientSessionEvent::UnhandleableOnStatusCode {
-                    code: x.to_string(),
-                };
+            ("NetStream.Seek.Notify", Some(stream)) => {
+                let event = ClientSessionEvent::SeekNotified { stream };
+                Ok(vec![ClientSessionResult::RaisedEvent(event)])
+            }
+
+            (_, stream) => {
+                let event = ClientSessionEvent::UnhandleableOnStatusCode { code, stream };
                 Ok(vec![ClientSessionResult::RaisedEvent(event)])
             }
         }
     }
 
-    fn handle_play_start(&mut self) -> ClientResult {
-        match self.current_state {
-            ClientState::PlayRequested => (),
-            _ => {
-                let kind = ClientSessionErrorKind::SessionInInvalidState {
-                    current_state: self.current_state.clone(),
-                };
-                return Err(ClientSessionError { kind });
-            }
-        };
+    fn handle_play_start(&mut self, stream: StreamHandle) -> ClientResult {
+        // Servers send another `NetStream.Play.Start` after seeking or re-enabling media
+        if self.get_stream_state(stream) == Some(&ClientStreamState::Playing) {
+            return Ok(Vec::new());
+        }
 
-        self.current_state = ClientState::Playing;
+        self.change_stream_state(
+            stream,
+            ClientStreamState::PlayRequested,
+            ClientStreamState::Playing,
+        )?;
 
-        let event = ClientSessionEvent::PlaybackRequestAccepted;
+        let event = ClientSessionEvent::PlaybackRequestAccepted { stream };
         Ok(vec![ClientSessionResult::RaisedEvent(event)])
     }
 
-    fn handle_publish_start(&mut self) -> ClientResult {
-        match self.current_state {
-            ClientState::PublishRequested => (),
-            _ => {
-                let kind = ClientSessionErrorKind::SessionInInvalidState {
-                    current_state: self.current_state.clone(),
-                };
-                return Err(ClientSessionError { kind });
-            }
-        };
+    fn handle_publish_start(&mut self, stream: StreamHandle) -> ClientResult {
+        self.change_stream_state(
+            stream,
+            ClientStreamState::PublishRequested,
+            ClientStreamState::Publishing,
+        )?;
 
-        self.current_state = ClientState::Publishing;
-        let event = ClientSessionEvent::PublishRequestAccepted;
+        let event = ClientSessionEvent::PublishRequestAccepted { stream };
         Ok(vec![ClientSessionResult::RaisedEvent(event)])
     }
 
-    fn handle_amf0_data_on_meta_data(&mut self, mut data: Vec<Amf0Value>) -> ClientResult {
+    fn handle_amf0_data_on_meta_data(
+        &mut self,
+        stream: StreamHandle,
+        mut data: Vec<Amf0Value>,
+    ) -> ClientResult {
         if data.is_empty() {
             // No data so ignore it
             return Ok(Vec::new());
         }
 
-        let properties = match data.remove(0) {
-            Amf0Value::Object(properties) => properties,
-            _ => return Ok(Vec::new()), // malformed so ignore it
+        let properties = match data.remove(0).get_object_properties() {
+            Some(properties) => properties,
+            None => return Ok(Vec::new()), // malformed so ignore it
         };
 
         let mut metadata = StreamMetadata::new();
         metadata.apply_metadata_values(properties);
 
-        let event = ClientSessionEvent::StreamMetadataReceived { metadata };
+        let event = ClientSessionEvent::StreamMetadataReceived { stream, metadata };
         Ok(vec![ClientSessionResult::RaisedEvent(event)])
     }
 
+    fn handle_abort(&mut self, stream_id: u32) -> ClientResult {
+        // The stream id of an abort message refers to the chunk stream the message was sent on
+        self.deserializer.abort_message(stream_id);
+        Ok(Vec::new())
+    }
+
     fn handle_acknowledgement(&mut self, sequence_number: u32) -> ClientResult {
         let event = ClientSessionEvent::AcknowledgementReceived {
             bytes_received: sequence_number,
@@ -920,16 +1150,38 @@ impl ClientSession {
         &mut self,
         event_type: UserControlEventType,
         timestamp: Option<RtmpTimestamp>,
-        _stream_id: Option<u32>,
+        stream_id: Option<u32>,
         _buffer_length: Option<u32>,
     ) -> ClientResult {
         match event_type {
             UserControlEventType::PingRequest => self.handle_ping_request(timestamp),
             UserControlEventType::PingResponse => self.handle_ping_response(timestamp),
+            UserControlEventType::StreamBegin | UserControlEventType::StreamEof => {
+                self.handle_stream_boundary(event_type, stream_id)
+            }
+
             _ => Ok(Vec::new()),
         }
     }
 
+    fn handle_stream_boundary(
+        &mut self,
+        event_type: UserControlEventType,
+        stream_id: Option<u32>,
+    ) -> ClientResult {
+        let stream = match stream_id.and_then(|id| self.find_stream(id)) {
+            Some((stream, _)) => stream,
+            None => return Ok(Vec::new()), // not active on this stream
+        };
+
+        let event = match event_type {
+            UserControlEventType::StreamBegin => ClientSessionEvent::StreamBegan { stream },
+            _ => ClientSessionEvent::StreamEnded { stream },
+        };
+
+        Ok(vec![ClientSessionResult::RaisedEvent(event)])
+    }
+
     fn handle_ping_request(&mut self, timestamp: Option<RtmpTimestamp>) -> ClientResult {
         let message = RtmpMessage::UserControl {
             event_type: UserControlEventType::PingResponse,
@@ -955,18 +1207,258 @@ impl ClientSession {
     }
 
     fn get_epoch(&self) -> RtmpTimestamp {
-        match self.start_time.elapsed() {
-            Ok(duration) => {
-                let milliseconds =
-                    (duration.as_secs() * 1000) + (duration.subsec_nanos() as u64 / 1_000_000);
+        // If time went backwards just consider time as at epoch
+        let milliseconds = self
+            .config
+            .clock
+            .get_time_ms()
+            .saturating_sub(self.start_time);
+
+        // Casting to u32 should auto-wrap the value as expected.  If not a stream will probably
+        // break after 49 days but testing shows it should wrap
+        RtmpTimestamp::new(milliseconds as u32)
+    }
+
+    fn create_connection_request(
+        &mut self,
+        app_name: String,
+        auth_query: &str,
+    ) -> Result<Packet, ClientSessionError> {
+        let transaction_id = self.get_next_transaction_id();
+        let transaction = OutstandingTransaction::ConnectionRequested {
+            app_name: app_name.clone(),
+        };
+        self.outstanding_transactions
+            .insert(transaction_id, transaction);
+
+        let mut properties = HashMap::new();
+        properties.insert(
+            "app".to_string(),
+            Amf0Value::Utf8String(format!("{}{}", app_name, auth_query)),
+        );
+        properties.insert(
+            "flashVer".to_string(),
+            Amf0Value::Utf8String(self.config.flash_version.clone()),
+        );
+        properties.insert("objectEncoding".to_string(), Amf0Value::Number(0.0));
+
+        // Some implementations require a tcUrl to be sent up with the connection request
+        if let Some(tc_url) = &self.config.tc_url {
+            let tc_url = format!("{}{}", tc_url, auth_query);
+            properties.insert("tcUrl".to_string(), Amf0Value::Utf8String(tc_url));
+        }
+
+        self.config.enhanced_rtmp.write_properties(&mut properties);
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: "connect".to_string(),
+            command_object: Amf0Value::Object(properties),
+            additional_arguments: vec![],
+            transaction_id: transaction_id as f64,
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+
+        Ok(packet)
+    }
+
+    /// Determines the query to append to the app name and tcUrl for the next connection attempt
+    /// if the rejection description contains an `authmod=adobe` challenge we can answer.  Only
+    /// one attempt is made for each step, so a failed attempt is reported as a rejection.
+    fn get_adobe_auth_query(&mut self, description: &str) -> Option<String> {
+        let credentials = match self.config.adobe_auth {
+            Some(ref credentials) => credentials.clone(),
+            None => return None,
+        };
+
+        if description.contains("?reason=needauth") {
+            if self.adobe_auth_response_sent {
+                return None;
+            }
+
+            let (_, query) = adobe_auth::split_query(description);
+            let parameters = adobe_auth::parse_query(query);
+            let salt = parameters.get("salt")?;
+            let challenge = parameters.get("challenge")?;
+            let opaque = parameters.get("opaque");
+
+            let client_challenge = format!("{:08x}", rand::random::<u32>());
+            let response = adobe_auth::calculate_response(
+                &credentials.username,
+                &credentials.password,
+                salt,
+                opaque.unwrap_or(challenge),
+                &client_challenge,
+            );
+
+            let mut auth_query = format!(
+                "?authmod=adobe&user={}&challenge={}&response={}",
+                credentials.username, client_challenge, response
+            );
+
+            if let Some(opaque) = opaque {
+                auth_query.push_str(&format!("&opaque={}", opaque));
+            }
+
+            self.adobe_auth_response_sent = true;
+            return Some(auth_query);
+        }
+
+        if description.contains("code=403 need auth")
+            && description.contains("authmod=adobe")
+            && !self.adobe_auth_user_sent
+        {
+            self.adobe_auth_user_sent = true;
+            return Some(format!("?authmod=adobe&user={}", credentials.username));
+        }
+
+        None
+    }
+
+    fn create_fc_command(
+        &mut self,
+        command_name: &str,
+        stream_key: &str,
+    ) -> Result<Packet, ClientSessionError> {
+        let transaction_id = self.get_next_transaction_id();
+        self.outstanding_transactions
+            .insert(transaction_id, OutstandingTransaction::FcCommand);
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: command_name.to_string(),
+            transaction_id: transaction_id as f64,
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![Amf0Value::Utf8String(stream_key.to_string())],
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        Ok(packet)
+    }
+
+    fn create_stream_request(
+        &mut self,
+        stream: StreamHandle,
+        purpose: TransactionPurpose,
+    ) -> Result<Packet, ClientSessionError> {
+        let transaction_id = self.get_next_transaction_id();
+        let transaction = OutstandingTransaction::CreateStream { stream, purpose };
+        self.outstanding_transactions
+            .insert(transaction_id, transaction);
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: "createStream".to_string(),
+            transaction_id: transaction_id as f64,
+            command_object: Amf0Value::Null,
+            additional_arguments: Vec::new(),
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        Ok(packet)
+    }
+
+    fn create_delete_stream_request(&mut self, stream_id: u32) -> Result<Packet, ClientSessionError> {
+        let message = RtmpMessage::Amf0Command {
+            command_name: "deleteStream".to_string(),
+            transaction_id: 0.0, // always 0 per spec
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![Amf0Value::Number(stream_id as f64)],
+        };
 
-                // Casting to u32 should auto-wrap the value as expected.  If not a stream will probably
-                // break after 49 days but testing shows it should wrap
-                RtmpTimestamp::new(milliseconds as u32)
+        let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        Ok(packet)
+    }
+
+    /// Finds the stream the server allocated the specified RTMP stream id for
+    fn find_stream(&self, stream_id: u32) -> Option<(StreamHandle, &ActiveStream)> {
+        self.active_streams
+            .iter()
+            .find(|&(_, active_stream)| active_stream.stream_id == Some(stream_id))
+            .map(|(stream, active_stream)| (*stream, active_stream))
+    }
+
+    fn change_stream_state(
+        &mut self,
+        stream: StreamHandle,
+        expected_state: ClientStreamState,
+        new_state: ClientStreamState,
+    ) -> Result<(), ClientSessionError> {
+        let active_stream = match self.active_streams.get_mut(&stream) {
+            Some(x) => x,
+            None => {
+                let kind = ClientSessionErrorKind::UnknownStream { stream };
+                return Err(ClientSessionError { kind });
             }
+        };
 
-            Err(_) => RtmpTimestamp::new(0), // Time went backwards, so just consider time as at epoch
+        if active_stream.current_state != expected_state {
+            let kind = ClientSessionErrorKind::StreamInInvalidState {
+                stream,
+                current_state: active_stream.current_state.clone(),
+            };
+            return Err(ClientSessionError { kind });
         }
+
+        active_stream.current_state = new_state;
+        Ok(())
+    }
+
+    fn create_playback_command(
+        &mut self,
+        stream: StreamHandle,
+        command_name: &str,
+        arguments: Vec<Amf0Value>,
+    ) -> Result<ClientSessionResult, ClientSessionError> {
+        let stream_id = self.get_stream_id_in_state(stream, ClientStreamState::Playing)?;
+        let message = RtmpMessage::Amf0Command {
+            command_name: command_name.to_string(),
+            transaction_id: 0.0,
+            command_object: Amf0Value::Null,
+            additional_arguments: arguments,
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        Ok(ClientSessionResult::OutboundResponse(packet))
+    }
+
+    fn get_stream_id_in_state(
+        &self,
+        stream: StreamHandle,
+        expected_state: ClientStreamState,
+    ) -> Result<u32, ClientSessionError> {
+        let active_stream = match self.active_streams.get(&stream) {
+            Some(x) => x,
+            None => {
+                let kind = ClientSessionErrorKind::UnknownStream { stream };
+                return Err(ClientSessionError { kind });
+            }
+        };
+
+        if active_stream.current_state != expected_state {
+            let kind = ClientSessionErrorKind::StreamInInvalidState {
+                stream,
+                current_state: active_stream.current_state.clone(),
+            };
+            return Err(ClientSessionError { kind });
+        }
+
+        match active_stream.stream_id {
+            Some(x) => Ok(x),
+            None => {
+                let kind = ClientSessionErrorKind::NoKnownActiveStreamIdWhenRequired;
+                Err(ClientSessionError { kind })
+            }
+        }
+    }
+
+    fn get_next_stream_handle(&mut self) -> StreamHandle {
+        let stream = StreamHandle(self.next_stream_handle);
+        self.next_stream_handle += 1;
+        stream
     }
 
     fn get_next_transaction_id(&mut self) -> u32 {
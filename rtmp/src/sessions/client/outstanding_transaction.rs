@@ -1,4 +1,5 @@
-use super::PublishRequestType;
+use super::{PublishRequestType, StreamHandle};
+use sessions::CallHandle;
 
 pub enum TransactionPurpose {
     PlayRequest {
@@ -17,6 +18,15 @@ pub enum OutstandingTransaction {
     },
 
     CreateStream {
+        stream: StreamHandle,
         purpose: TransactionPurpose,
     },
-}
\ No newline at end of file
+
+    /// A `releaseStream` or `FC*` command, whose response does not affect the session
+    FcCommand,
+
+    /// A call made via `ClientSession::call()`
+    Call {
+        handle: CallHandle,
+    },
+}
@@ -1,11 +1,16 @@
-#[derive(Clone, Debug)]
+/// The state of the session's connection to the server
+#[derive(Clone, Debug, PartialEq)]
 pub enum ClientState {
     /// Client has not connected to an application on the server yet,
     Disconnected,
 
     /// The client has connected to an application on the server
     Connected,
+}
 
+/// The state of a single stream the client is playing or publishing on
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientStreamState {
     /// Playback has been requested for a stream key and we are still waiting for a response
     PlayRequested,
 
@@ -17,4 +22,4 @@ pub enum ClientState {
 
     /// We are currently publishing to the server
     Publishing,
-}
\ No newline at end of file
+}
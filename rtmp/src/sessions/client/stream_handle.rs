@@ -0,0 +1,6 @@
+/// Identifies a stream the client requested to play or publish on.
+///
+/// Handles are allocated by the `ClientSession` when playback or publishing is requested, since
+/// the RTMP stream id is not known until the server responds to the `createStream` request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StreamHandle(pub(super) u32);
@@ -6,6 +6,8 @@ use rml_amf0::Amf0Value;
 use chunk_io::{ChunkDeserializer, ChunkSerializer, Packet};
 use messages::{MessagePayload, RtmpMessage,UserControlEventType};
 use bytes::BytesMut;
+use sessions::{adobe_auth, AdobeAuthCredentials, FOURCC_CAN_DECODE, ManualClock};
+use std::sync::Arc;
 
 #[test]
 fn new_session_creates_set_chunk_size_message() {
@@ -91,6 +93,109 @@ fn can_send_connect_request_with_tc_url() {
     }
 }
 
+#[test]
+fn can_send_connect_request_with_enhanced_rtmp_capabilities() {
+    let mut config = ClientSessionConfig::new();
+    config.enhanced_rtmp.fourcc_list = vec!["hvc1".to_string(), "av01".to_string()];
+    config.enhanced_rtmp.video_fourcc_info_map.insert("hvc1".to_string(), FOURCC_CAN_DECODE);
+
+    let mut deserializer = ChunkDeserializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, vec![results]);
+
+    assert_eq!(responses.len(), 1, "Expected 1 response");
+    match responses.remove(0) {
+        (_, RtmpMessage::Amf0Command {command_object: Amf0Value::Object(properties), ..}) => {
+            let expected_list = Amf0Value::StrictArray(vec![
+                Amf0Value::Utf8String("hvc1".to_string()),
+                Amf0Value::Utf8String("av01".to_string()),
+            ]);
+
+            let mut expected_map = HashMap::new();
+            expected_map.insert("hvc1".to_string(), Amf0Value::Number(1.0));
+
+            assert_eq!(properties.get("fourCcList"), Some(&expected_list), "Unexpected fourCcList");
+            assert_eq!(properties.get("videoFourCcInfoMap"), Some(&Amf0Value::Object(expected_map)), "Unexpected videoFourCcInfoMap");
+        },
+
+        x => panic!("Expected Amf0Command with object, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn connect_request_does_not_include_enhanced_rtmp_properties_by_default() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, vec![results]);
+
+    match responses.remove(0) {
+        (_, RtmpMessage::Amf0Command {command_object: Amf0Value::Object(properties), ..}) => {
+            assert!(!properties.contains_key("fourCcList"), "Unexpected fourCcList");
+            assert!(!properties.contains_key("videoFourCcInfoMap"), "Unexpected videoFourCcInfoMap");
+        },
+
+        x => panic!("Expected Amf0Command with object, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn server_enhanced_rtmp_capabilities_available_after_connection_accepted() {
+    let mut config = ClientSessionConfig::new();
+    config.enhanced_rtmp.fourcc_list = vec!["hvc1".to_string()];
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    consume_results(&mut deserializer, vec![results]);
+
+    let mut command_properties = HashMap::new();
+    command_properties.insert("fmsVer".to_string(), Amf0Value::Utf8String("fms".to_string()));
+    command_properties.insert("fourCcList".to_string(), Amf0Value::StrictArray(vec![Amf0Value::Utf8String("hvc1".to_string())]));
+
+    let mut additional_properties = HashMap::new();
+    additional_properties.insert("code".to_string(), Amf0Value::Utf8String("NetConnection.Connect.Success".to_string()));
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "_result".to_string(),
+        transaction_id: 1.0,
+        command_object: Amf0Value::Object(command_properties),
+        additional_arguments: vec![Amf0Value::Object(additional_properties)],
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let capabilities = session.get_enhanced_rtmp_capabilities().unwrap();
+    assert_eq!(capabilities.fourcc_list, vec!["hvc1".to_string()], "Unexpected fourcc list");
+    assert!(capabilities.supports("hvc1"), "Expected hvc1 to be supported");
+    assert!(!capabilities.supports("av01"), "Expected av01 to not be supported");
+}
+
+#[test]
+fn no_enhanced_rtmp_capabilities_when_server_does_not_advertise_them() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+
+    assert_eq!(session.get_enhanced_rtmp_capabilities(), None, "Expected no capabilities");
+}
+
 #[test]
 fn can_process_connect_success_response() {
     let app_name = "test".to_string();
@@ -104,7 +209,7 @@ fn can_process_connect_success_response() {
     consume_results(&mut deserializer, vec![results]);
 
     let response = get_connect_success_response(&mut serializer);
-    let results = session.handle_input(&response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Expected one event returned");
@@ -127,7 +232,7 @@ fn event_raised_when_connect_request_rejected() {
     consume_results(&mut deserializer, vec![results]);
 
     let response = get_connect_error_response(&mut serializer);
-    let results = session.handle_input(&response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Expected one event returned");
@@ -140,6 +245,166 @@ fn event_raised_when_connect_request_rejected() {
     }
 }
 
+#[test]
+fn connect_request_retried_with_user_when_server_requires_adobe_auth() {
+    let mut config = ClientSessionConfig::new();
+    config.tc_url = Some("rtmp://127.0.0.1/test".to_string());
+    config.adobe_auth = Some(AdobeAuthCredentials {username: "user".to_string(), password: "secret".to_string()});
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    consume_results(&mut deserializer, vec![results]);
+
+    let description = "[ AccessManager.Reject ] : [ code=403 need auth; authmod=adobe ] : ";
+    let response = get_connect_rejection_response(&mut serializer, 1.0, description);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
+
+    let expected_event = ClientSessionEvent::ConnectionRequestRetriedWithAdobeAuth {auth_query: "?authmod=adobe&user=user".to_string()};
+    assert_eq!(events, vec![expected_event], "Unexpected events");
+    let (app, tc_url) = get_connect_app_and_tc_url(responses);
+    assert_eq!(app, "test?authmod=adobe&user=user", "Unexpected app");
+    assert_eq!(tc_url, Some("rtmp://127.0.0.1/test?authmod=adobe&user=user".to_string()), "Unexpected tcUrl");
+}
+
+#[test]
+fn connect_request_answers_adobe_auth_challenge() {
+    let mut config = ClientSessionConfig::new();
+    config.adobe_auth = Some(AdobeAuthCredentials {username: "user".to_string(), password: "secret".to_string()});
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    consume_results(&mut deserializer, vec![results]);
+
+    let description = "[ AccessManager.Reject ] : [ authmod=adobe ] : ?reason=needauth&user=user&salt=abc&challenge=def&opaque=ghi";
+    let response = get_connect_rejection_response(&mut serializer, 1.0, description);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
+
+    let (app, _) = get_connect_app_and_tc_url(responses);
+    let (app_name, query) = adobe_auth::split_query(&app);
+    let expected_event = ClientSessionEvent::ConnectionRequestRetriedWithAdobeAuth {auth_query: format!("?{}", query)};
+    assert_eq!(events, vec![expected_event], "Unexpected events");
+
+    let parameters = adobe_auth::parse_query(query);
+    let client_challenge = parameters["challenge"];
+    let expected_response = adobe_auth::calculate_response("user", "secret", "abc", "ghi", client_challenge);
+
+    assert_eq!(app_name, "test", "Unexpected app name");
+    assert_eq!(parameters.get("authmod"), Some(&"adobe"), "Unexpected authmod");
+    assert_eq!(parameters.get("user"), Some(&"user"), "Unexpected user");
+    assert_eq!(parameters.get("opaque"), Some(&"ghi"), "Unexpected opaque");
+    assert_eq!(parameters.get("response"), Some(&expected_response.as_str()), "Unexpected response");
+
+    let response = get_connect_success_response_for_transaction(&mut serializer, 2.0);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events, vec![ClientSessionEvent::ConnectionRequestAccepted], "Unexpected events");
+}
+
+#[test]
+fn rejection_raised_when_adobe_auth_response_fails() {
+    let mut config = ClientSessionConfig::new();
+    config.adobe_auth = Some(AdobeAuthCredentials {username: "user".to_string(), password: "secret".to_string()});
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    consume_results(&mut deserializer, vec![results]);
+
+    let description = "[ AccessManager.Reject ] : [ authmod=adobe ] : ?reason=needauth&user=user&salt=abc&challenge=def&opaque=ghi";
+    let response = get_connect_rejection_response(&mut serializer, 1.0, description);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let description = "[ AccessManager.Reject ] : [ authmod=adobe ] : ?reason=authfailed";
+    let response = get_connect_rejection_response(&mut serializer, 2.0, description);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 0, "Expected no responses");
+    assert_eq!(events, vec![ClientSessionEvent::ConnectionRequestRejected {description: description.to_string()}], "Unexpected events");
+}
+
+#[test]
+fn adobe_auth_can_continue_on_new_connection() {
+    let mut config = ClientSessionConfig::new();
+    config.tc_url = Some("rtmp://127.0.0.1/test".to_string());
+    config.adobe_auth = Some(AdobeAuthCredentials {username: "user".to_string(), password: "secret".to_string()});
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let auth_query = "?authmod=adobe&user=user".to_string();
+    let results = session.request_connection_with_adobe_auth_query("test".to_string(), auth_query).unwrap();
+    let (responses, _) = split_results(&mut deserializer, vec![results]);
+    let (app, tc_url) = get_connect_app_and_tc_url(responses);
+    assert_eq!(app, "test?authmod=adobe&user=user", "Unexpected app");
+    assert_eq!(tc_url, Some("rtmp://127.0.0.1/test?authmod=adobe&user=user".to_string()), "Unexpected tcUrl");
+
+    let description = "[ AccessManager.Reject ] : [ authmod=adobe ] : ?reason=needauth&user=user&salt=abc&challenge=def&opaque=ghi";
+    let response = get_connect_rejection_response(&mut serializer, 1.0, description);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+    let auth_query = match events.into_iter().next() {
+        Some(ClientSessionEvent::ConnectionRequestRetriedWithAdobeAuth {auth_query}) => auth_query,
+        x => panic!("Expected ConnectionRequestRetriedWithAdobeAuth event, instead received: {:?}", x),
+    };
+
+    // The server closed the connection, so the response is sent through a new one
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let results = session.request_connection_with_adobe_auth_query("test".to_string(), auth_query.clone()).unwrap();
+    let (responses, _) = split_results(&mut deserializer, vec![results]);
+    let (app, _) = get_connect_app_and_tc_url(responses);
+    assert_eq!(app, format!("test{}", auth_query), "Unexpected app");
+
+    // A second challenge is not answered, as the response has already been sent
+    let response = get_connect_rejection_response(&mut serializer, 1.0, description);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 0, "Expected no responses");
+    assert_eq!(events, vec![ClientSessionEvent::ConnectionRequestRejected {description: description.to_string()}], "Unexpected events");
+}
+
+#[test]
+fn adobe_auth_challenge_not_answered_without_credentials() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let results = session.request_connection("test".to_string()).unwrap();
+    consume_results(&mut deserializer, vec![results]);
+
+    let description = "[ AccessManager.Reject ] : [ code=403 need auth; authmod=adobe ] : ";
+    let response = get_connect_rejection_response(&mut serializer, 1.0, description);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 0, "Expected no responses");
+    assert_eq!(events, vec![ClientSessionEvent::ConnectionRequestRejected {description: description.to_string()}], "Unexpected events");
+}
+
 #[test]
 fn error_thrown_when_connect_request_made_after_successful_connection() {
     let app_name = "test".to_string();
@@ -153,7 +418,7 @@ fn error_thrown_when_connect_request_made_after_successful_connection() {
     consume_results(&mut deserializer, vec![results]);
 
     let response = get_connect_success_response(&mut serializer);
-    let results = session.handle_input(&response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
     consume_results(&mut deserializer, results);
 
     let error = session.request_connection(app_name.clone()).unwrap_err();
@@ -176,7 +441,7 @@ fn successful_connect_request_sends_window_ack_size() {
     consume_results(&mut deserializer, vec![results]);
 
     let response = get_connect_success_response(&mut serializer);
-    let results = session.handle_input(&response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses received");
@@ -202,7 +467,7 @@ fn successful_play_request_workflow() {
 
     perform_successful_connect(app_name.clone(), &mut session, &mut serializer, &mut deserializer);
 
-    let result = session.request_playback(stream_key.clone()).unwrap();
+    let (stream, result) = session.request_playback(stream_key.clone()).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -219,7 +484,7 @@ fn successful_play_request_workflow() {
     };
 
     let (created_stream_id, create_stream_response) = get_create_stream_success_response(transaction_id, &mut serializer);
-    let results = session.handle_input(&create_stream_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(create_stream_response.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 2, "Expected one response returned");
@@ -249,12 +514,12 @@ fn successful_play_request_workflow() {
     };
 
     let play_response = get_play_success_response(&mut serializer, created_stream_id);
-    let results = session.handle_input(&play_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(play_response.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Expected one event returned");
     match events.remove(0) {
-        ClientSessionEvent::PlaybackRequestAccepted => (),
+        ClientSessionEvent::PlaybackRequestAccepted {stream: event_stream} => assert_eq!(event_stream, stream, "Unexpected stream"),
         x => panic!("Expected playback accepted event, instead received: {:?}", x),
     }
 }
@@ -268,7 +533,7 @@ fn active_play_session_raises_events_when_stream_metadata_changes() {
     consume_results(&mut deserializer, initial_results);
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
-    let stream_id = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
 
     let mut properties = HashMap::new();
     properties.insert("width".to_string(), Amf0Value::Number(1920_f64));
@@ -293,12 +558,13 @@ fn active_play_session_raises_events_when_stream_metadata_changes() {
 
     let payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events received");
     match events.remove(0) {
-        ClientSessionEvent::StreamMetadataReceived {metadata} => {
+        ClientSessionEvent::StreamMetadataReceived {stream: event_stream, metadata} => {
+            assert_eq!(event_stream, stream, "Unexpected stream");
             assert_eq!(metadata.video_width, Some(1920), "Unexpected video width");
             assert_eq!(metadata.video_height, Some(1080), "Unexpected video height");
             assert_eq!(metadata.video_codec, Some("avc1".to_string()), "Unexpected video codec");
@@ -325,18 +591,19 @@ fn active_play_session_raises_events_when_video_data_received() {
     consume_results(&mut deserializer, initial_results);
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
-    let stream_id = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
 
     let video_data = Bytes::from(vec![1,2,3,4,5]);
     let message = RtmpMessage::VideoData {data: video_data.clone()};
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events received");
     match events.remove(0) {
-        ClientSessionEvent::VideoDataReceived {data, timestamp} => {
+        ClientSessionEvent::VideoDataReceived {stream: event_stream, data, timestamp} => {
+            assert_eq!(event_stream, stream, "Unexpected stream");
             assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
             assert_eq!(&data[..], &video_data[..], "Unexpected video data");
         },
@@ -345,6 +612,90 @@ fn active_play_session_raises_events_when_video_data_received() {
     }
 }
 
+#[test]
+fn aborted_video_message_is_discarded() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    // Only send the first chunk of a message that spans multiple chunks
+    let message = RtmpMessage::VideoData {data: Bytes::from(vec![9_u8; 200])};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes[..140].to_vec())).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+    assert_eq!(events.len(), 0, "Unexpected number of events received");
+
+    let abort_packet = serializer.abort_message(9, RtmpTimestamp::new(1234)).unwrap();
+    let results = session.handle_input(Bytes::from(abort_packet.bytes)).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let message = RtmpMessage::VideoData {data: Bytes::from(vec![1, 2, 3])};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1300), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events received");
+    match events.remove(0) {
+        ClientSessionEvent::VideoDataReceived {stream: event_stream, data, timestamp} => {
+            assert_eq!(event_stream, stream, "Unexpected stream");
+            assert_eq!(timestamp, RtmpTimestamp::new(1300), "Unexpected timestamp");
+            assert_eq!(&data[..], &[1, 2, 3], "Unexpected video data");
+        },
+
+        x => panic!("Expected video data received event, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn active_play_session_raises_events_for_each_message_in_aggregate() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let aggregate_data = Bytes::from(vec![
+        9, 0, 0, 3, 0, 0, 10, 0, 0, 0, 0, 1, 2, 3, 0, 0, 0, 14,
+        8, 0, 0, 2, 0, 0, 30, 0, 0, 0, 0, 4, 5, 0, 0, 0, 13,
+    ]);
+    let message = RtmpMessage::Aggregate {data: aggregate_data};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 2, "Unexpected number of events received");
+    match events.remove(0) {
+        ClientSessionEvent::VideoDataReceived {stream: event_stream, data, timestamp} => {
+            assert_eq!(event_stream, stream, "Unexpected stream");
+            assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
+            assert_eq!(&data[..], &[1, 2, 3], "Unexpected video data");
+        },
+
+        x => panic!("Expected video data received event, instead received: {:?}", x),
+    }
+
+    match events.remove(0) {
+        ClientSessionEvent::AudioDataReceived {stream: event_stream, data, timestamp} => {
+            assert_eq!(event_stream, stream, "Unexpected stream");
+            assert_eq!(timestamp, RtmpTimestamp::new(1254), "Unexpected timestamp");
+            assert_eq!(&data[..], &[4, 5], "Unexpected audio data");
+        },
+
+        x => panic!("Expected audio data received event, instead received: {:?}", x),
+    }
+}
+
 #[test]
 fn active_play_session_raises_events_when_audio_data_received() {
     let config = ClientSessionConfig::new();
@@ -354,18 +705,19 @@ fn active_play_session_raises_events_when_audio_data_received() {
     consume_results(&mut deserializer, initial_results);
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
-    let stream_id = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
 
     let audio_data = Bytes::from(vec![1,2,3,4,5]);
     let message = RtmpMessage::AudioData {data: audio_data.clone()};
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events received");
     match events.remove(0) {
-        ClientSessionEvent::AudioDataReceived {data, timestamp} => {
+        ClientSessionEvent::AudioDataReceived {stream: event_stream, data, timestamp} => {
+            assert_eq!(event_stream, stream, "Unexpected stream");
             assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
             assert_eq!(&data[..], &audio_data[..], "Unexpected audio data");
         },
@@ -386,7 +738,7 @@ fn can_receive_audio_data_prior_to_play_request_being_accepted() {
 
     perform_successful_connect(app_name.clone(), &mut session, &mut serializer, &mut deserializer);
 
-    let result = session.request_playback(stream_key.clone()).unwrap();
+    let (stream, result) = session.request_playback(stream_key.clone()).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -403,7 +755,7 @@ fn can_receive_audio_data_prior_to_play_request_being_accepted() {
     };
 
     let (created_stream_id, create_stream_response) = get_create_stream_success_response(transaction_id, &mut serializer);
-    let results = session.handle_input(&create_stream_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(create_stream_response.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 2, "Expected one response returned");
@@ -436,12 +788,13 @@ fn can_receive_audio_data_prior_to_play_request_being_accepted() {
     let message = RtmpMessage::AudioData {data: audio_data.clone()};
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), created_stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events received");
     match events.remove(0) {
-        ClientSessionEvent::AudioDataReceived {data, timestamp} => {
+        ClientSessionEvent::AudioDataReceived {stream: event_stream, data, timestamp} => {
+            assert_eq!(event_stream, stream, "Unexpected stream");
             assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
             assert_eq!(&data[..], &audio_data[..], "Unexpected audio data");
         },
@@ -462,7 +815,7 @@ fn can_receive_video_data_prior_to_play_request_being_accepted() {
 
     perform_successful_connect(app_name.clone(), &mut session, &mut serializer, &mut deserializer);
 
-    let result = session.request_playback(stream_key.clone()).unwrap();
+    let (stream, result) = session.request_playback(stream_key.clone()).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -479,7 +832,7 @@ fn can_receive_video_data_prior_to_play_request_being_accepted() {
     };
 
     let (created_stream_id, create_stream_response) = get_create_stream_success_response(transaction_id, &mut serializer);
-    let results = session.handle_input(&create_stream_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(create_stream_response.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 2, "Expected one response returned");
@@ -512,12 +865,13 @@ fn can_receive_video_data_prior_to_play_request_being_accepted() {
     let message = RtmpMessage::VideoData {data: video_data.clone()};
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), created_stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events received");
     match events.remove(0) {
-        ClientSessionEvent::VideoDataReceived {data, timestamp} => {
+        ClientSessionEvent::VideoDataReceived {stream: event_stream, data, timestamp} => {
+            assert_eq!(event_stream, stream, "Unexpected stream");
             assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
             assert_eq!(&data[..], &video_data[..], "Unexpected video data");
         },
@@ -535,9 +889,9 @@ fn can_stop_playback() {
     consume_results(&mut deserializer, initial_results);
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
-    let stream_id = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
 
-    let results = session.stop_playback().unwrap();
+    let results = session.stop_playback(stream).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -574,7 +928,7 @@ fn automatically_responds_to_ping_requests() {
 
     let payload = message.into_message_payload(RtmpTimestamp::new(6000), 0).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Expected one response for handling ping request");
@@ -607,7 +961,7 @@ fn event_raised_when_ping_response_received() {
 
     let payload = message.into_message_payload(RtmpTimestamp::new(6000), 0).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "One event expected");
@@ -644,6 +998,25 @@ fn can_send_ping_request() {
     }
 }
 
+#[test]
+fn ping_request_timestamp_comes_from_configured_clock() {
+    let clock = ManualClock::new(10_000);
+    let mut config = ClientSessionConfig::new();
+    config.clock = Arc::new(clock.clone());
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+
+    clock.advance(250);
+    let (packet, sent_timestamp) = session.send_ping_request().unwrap();
+    let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+    assert_eq!(sent_timestamp, RtmpTimestamp::new(250), "Unexpected ping timestamp");
+    assert_eq!(payload.timestamp, RtmpTimestamp::new(250), "Unexpected message timestamp");
+}
+
 #[test]
 fn sends_ack_after_receiving_window_ack_bytes() {
     let config = ClientSessionConfig::new();
@@ -658,7 +1031,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let window_ack_message = RtmpMessage::WindowAcknowledgement {size: 100};
     let window_ack_payload = window_ack_message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let window_ack_packet = serializer.serialize(&window_ack_payload, false, false).unwrap();
-    let results = session.handle_input(&window_ack_packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(window_ack_packet.bytes)).unwrap();
     consume_results(&mut deserializer, results);
 
     let mut bytes = BytesMut::new();
@@ -666,7 +1039,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_message = RtmpMessage::VideoData {data: bytes.freeze()};
     let video_payload = video_message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
-    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(video_packet.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -680,7 +1053,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_message = RtmpMessage::VideoData {data: bytes.freeze()};
     let video_payload = video_message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
-    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(video_packet.bytes)).unwrap();
     let (responses, _) = split_results(&mut deserializer, results);
     assert_eq!(responses.len(), 0, "Expected no responses");
 
@@ -689,7 +1062,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_message = RtmpMessage::VideoData {data: bytes.freeze()};
     let video_payload = video_message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
-    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(video_packet.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
     match responses.remove(0) {
@@ -711,7 +1084,7 @@ fn event_raised_when_server_sends_an_acknowledgement() {
     let message = RtmpMessage::Acknowledgement {sequence_number: 1234};
     let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events");
@@ -735,7 +1108,7 @@ fn successful_publish_request_workflow() {
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
 
-    let result = session.request_publishing(stream_key.clone(), PublishRequestType::Live).unwrap();
+    let (stream, result) = session.request_publishing(stream_key.clone(), PublishRequestType::Live).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -752,7 +1125,7 @@ fn successful_publish_request_workflow() {
     };
 
     let (created_stream_id, create_stream_response) = get_create_stream_success_response(transaction_id, &mut serializer);
-    let results = session.handle_input(&create_stream_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(create_stream_response.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -771,12 +1144,12 @@ fn successful_publish_request_workflow() {
     };
 
     let publish_response = get_publish_success_response(&mut serializer, created_stream_id);
-    let results = session.handle_input(&publish_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(publish_response.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events");
     match events.remove(0) {
-        ClientSessionEvent::PublishRequestAccepted => (),
+        ClientSessionEvent::PublishRequestAccepted {stream: event_stream} => assert_eq!(event_stream, stream, "Unexpected stream"),
         x => panic!("Expected publish request accepted event, instead received: {:?}", x),
     }
 }
@@ -790,7 +1163,7 @@ fn publisher_can_send_metadata() {
     consume_results(&mut deserializer, initial_results);
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
-    let stream_id = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
 
     let mut metadata = StreamMetadata::new();
     metadata.video_width = Some(100);
@@ -805,7 +1178,7 @@ fn publisher_can_send_metadata() {
     metadata.audio_is_stereo = Some(true);
     metadata.encoder = Some("encoder".to_string());
 
-    let result = session.publish_metadata(&metadata).unwrap();
+    let result = session.publish_metadata(stream, &metadata).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
     assert_eq!(responses.len() , 1, "Unexpected number of responses");
@@ -856,10 +1229,10 @@ fn publisher_can_send_video_data() {
     consume_results(&mut deserializer, initial_results);
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
-    let stream_id = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
 
     let data = Bytes::from(vec![1,2,3,4,5]);
-    let result = session.publish_video_data(data.clone(), RtmpTimestamp::new(1234), false).unwrap();
+    let result = session.publish_video_data(stream, data.clone(), RtmpTimestamp::new(1234), false).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -883,10 +1256,10 @@ fn publisher_can_send_audio_data() {
     consume_results(&mut deserializer, initial_results);
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
-    let stream_id = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
 
     let data = Bytes::from(vec![1,2,3,4,5]);
-    let result = session.publish_audio_data(data.clone(), RtmpTimestamp::new(1234), false).unwrap();
+    let result = session.publish_audio_data(stream, data.clone(), RtmpTimestamp::new(1234), false).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, vec![result]);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -910,9 +1283,9 @@ fn can_stop_publishing() {
     consume_results(&mut deserializer, initial_results);
 
     perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
-    let stream_id = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
 
-    let results = session.stop_publishing().unwrap();
+    let results = session.stop_publishing(stream).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -930,6 +1303,486 @@ fn can_stop_publishing() {
     }
 }
 
+#[test]
+fn publish_request_sends_release_stream_and_fc_publish_when_configured() {
+    let mut config = ClientSessionConfig::new();
+    config.send_fc_publish = true;
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+
+    let (_, result) = session.request_publishing("abcd".to_string(), PublishRequestType::Live).unwrap();
+    let packet = match result {
+        ClientSessionResult::OutboundResponse(packet) => packet,
+        x => panic!("Expected outbound response, instead received: {:?}", x),
+    };
+
+    let mut commands = Vec::new();
+    let mut bytes = &packet.bytes[..];
+    while let Some(payload) = deserializer.get_next_message(bytes).unwrap() {
+        match payload.to_rtmp_message().unwrap() {
+            RtmpMessage::Amf0Command {command_name, additional_arguments, ..} => commands.push((command_name, additional_arguments)),
+            x => panic!("Expected Amf0 command, instead received: {:?}", x),
+        }
+
+        bytes = &[];
+    }
+
+    let stream_key_argument = vec![Amf0Value::Utf8String("abcd".to_string())];
+    assert_eq!(commands, vec![
+        ("releaseStream".to_string(), stream_key_argument.clone()),
+        ("FCPublish".to_string(), stream_key_argument),
+        ("createStream".to_string(), vec![]),
+    ]);
+}
+
+#[test]
+fn stopping_publishing_sends_fc_unpublish_when_configured() {
+    let mut config = ClientSessionConfig::new();
+    config.send_fc_publish = true;
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+
+    let (stream, result) = session.request_publishing("abcd".to_string(), PublishRequestType::Live).unwrap();
+    let transaction_id = match result {
+        ClientSessionResult::OutboundResponse(packet) => {
+            let mut transaction_id = 0.0;
+            let mut bytes = &packet.bytes[..];
+            while let Some(payload) = deserializer.get_next_message(bytes).unwrap() {
+                if let RtmpMessage::Amf0Command {transaction_id: id, ..} = payload.to_rtmp_message().unwrap() {
+                    transaction_id = id;
+                }
+
+                bytes = &[];
+            }
+
+            transaction_id
+        },
+
+        x => panic!("Expected outbound response, instead received: {:?}", x),
+    };
+
+    let (stream_id, response) = get_create_stream_success_response(transaction_id, &mut serializer);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let response = get_publish_success_response(&mut serializer, stream_id);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let results = session.stop_publishing(stream).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of responses");
+    match responses[0] {
+        (_, RtmpMessage::Amf0Command {ref command_name, ref additional_arguments, ..}) => {
+            assert_eq!(command_name, "FCUnpublish", "Unexpected command name");
+            assert_eq!(additional_arguments, &vec![Amf0Value::Utf8String("abcd".to_string())], "Unexpected arguments");
+        },
+
+        ref x => panic!("Expected Amf0 command, instead received: {:?}", x),
+    }
+
+    match responses[1] {
+        (_, RtmpMessage::Amf0Command {ref command_name, ..}) => assert_eq!(command_name, "deleteStream", "Unexpected command name"),
+        ref x => panic!("Expected Amf0 command, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn can_play_and_publish_on_separate_streams_at_the_same_time() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (play_stream, play_stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+    let (publish_stream, publish_stream_id) = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+
+    assert_ne!(play_stream, publish_stream, "Expected distinct stream handles");
+    assert_eq!(session.get_stream_state(play_stream), Some(&ClientStreamState::Playing), "Unexpected play stream state");
+    assert_eq!(session.get_stream_state(publish_stream), Some(&ClientStreamState::Publishing), "Unexpected publish stream state");
+    assert_eq!(session.get_stream_id(play_stream), Some(play_stream_id), "Unexpected play stream id");
+    assert_eq!(session.get_stream_id(publish_stream), Some(publish_stream_id), "Unexpected publish stream id");
+
+    let data = Bytes::from(vec![1,2,3]);
+    let result = session.publish_video_data(publish_stream, data.clone(), RtmpTimestamp::new(5), false).unwrap();
+    let (responses, _) = split_results(&mut deserializer, vec![result]);
+    assert_eq!(responses[0].0.message_stream_id, publish_stream_id, "Unexpected published message stream id");
+
+    let message = RtmpMessage::VideoData {data: data.clone()};
+    let payload = message.into_message_payload(RtmpTimestamp::new(5), play_stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events, vec![ClientSessionEvent::VideoDataReceived {
+        stream: play_stream,
+        timestamp: RtmpTimestamp::new(5),
+        data,
+    }], "Unexpected events");
+}
+
+#[test]
+fn stopping_one_stream_does_not_affect_other_streams() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (first_stream, first_stream_id) = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+    let (second_stream, second_stream_id) = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+
+    let results = session.stop_publishing(first_stream).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses[0] {
+        (_, RtmpMessage::Amf0Command {ref command_name, ref additional_arguments, ..}) => {
+            assert_eq!(command_name, "deleteStream", "Unexpected command name");
+            assert_eq!(additional_arguments, &vec![Amf0Value::Number(first_stream_id as f64)], "Unexpected arguments");
+        },
+
+        ref x => panic!("Expected Amf0 command, instead received: {:?}", x),
+    }
+
+    assert_eq!(session.get_stream_state(first_stream), None, "Expected first stream to be stopped");
+    match session.publish_audio_data(first_stream, Bytes::from(vec![1]), RtmpTimestamp::new(0), false) {
+        Err(ClientSessionError {kind: ClientSessionErrorKind::UnknownStream {stream}}) => assert_eq!(stream, first_stream),
+        x => panic!("Expected unknown stream error, instead received: {:?}", x),
+    }
+
+    let result = session.publish_audio_data(second_stream, Bytes::from(vec![1]), RtmpTimestamp::new(0), false).unwrap();
+    let (responses, _) = split_results(&mut deserializer, vec![result]);
+    assert_eq!(responses[0].0.message_stream_id, second_stream_id, "Unexpected message stream id");
+}
+
+#[test]
+fn stream_stopped_before_created_is_deleted_once_server_creates_it() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+
+    let (stream, result) = session.request_playback("abcd".to_string()).unwrap();
+    let (mut responses, _) = split_results(&mut deserializer, vec![result]);
+    let transaction_id = match responses.remove(0) {
+        (_, RtmpMessage::Amf0Command {transaction_id, ..}) => transaction_id,
+        x => panic!("Expected Amf0 command, instead received: {:?}", x),
+    };
+
+    let results = session.stop_playback(stream).unwrap();
+    assert_eq!(results.len(), 0, "Expected no responses before the stream was created");
+
+    let (stream_id, response) = get_create_stream_success_response(transaction_id, &mut serializer);
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
+    let (responses, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 0, "Expected no events");
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses[0] {
+        (_, RtmpMessage::Amf0Command {ref command_name, ref additional_arguments, ..}) => {
+            assert_eq!(command_name, "deleteStream", "Unexpected command name");
+            assert_eq!(additional_arguments, &vec![Amf0Value::Number(stream_id as f64)], "Unexpected arguments");
+        },
+
+        ref x => panic!("Expected Amf0 command, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn cannot_publish_media_on_a_playback_stream() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (stream, _) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    match session.publish_video_data(stream, Bytes::from(vec![1]), RtmpTimestamp::new(0), false) {
+        Err(ClientSessionError {kind: ClientSessionErrorKind::StreamInInvalidState {current_state, ..}}) => {
+            assert_eq!(current_state, ClientStreamState::Playing, "Unexpected stream state");
+        },
+
+        x => panic!("Expected stream in invalid state error, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn can_send_pause_and_seek_commands_on_playing_stream() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let results = vec![
+        session.pause_playback(stream, 1000).unwrap(),
+        session.resume_playback(stream, 1000).unwrap(),
+        session.seek(stream, 5000).unwrap(),
+        session.set_receive_audio(stream, false).unwrap(),
+        session.set_receive_video(stream, true).unwrap(),
+    ];
+
+    let (responses, _) = split_results(&mut deserializer, results);
+    let commands = responses.into_iter()
+        .map(|(payload, message)| match message {
+            RtmpMessage::Amf0Command {command_name, transaction_id, additional_arguments, ..} => {
+                assert_eq!(payload.message_stream_id, stream_id, "Unexpected message stream id");
+                assert_eq!(transaction_id, 0.0, "Unexpected transaction id");
+                (command_name, additional_arguments)
+            },
+
+            x => panic!("Expected Amf0 command, instead received: {:?}", x),
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(commands, vec![
+        ("pause".to_string(), vec![Amf0Value::Boolean(true), Amf0Value::Number(1000.0)]),
+        ("pause".to_string(), vec![Amf0Value::Boolean(false), Amf0Value::Number(1000.0)]),
+        ("seek".to_string(), vec![Amf0Value::Number(5000.0)]),
+        ("receiveAudio".to_string(), vec![Amf0Value::Boolean(false)]),
+        ("receiveVideo".to_string(), vec![Amf0Value::Boolean(true)]),
+    ]);
+}
+
+#[test]
+fn cannot_pause_a_publishing_stream() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (stream, _) = perform_successful_publish_request(&mut session, &mut serializer, &mut deserializer);
+
+    match session.pause_playback(stream, 0) {
+        Err(ClientSessionError {kind: ClientSessionErrorKind::StreamInInvalidState {current_state, ..}}) => {
+            assert_eq!(current_state, ClientStreamState::Publishing, "Unexpected stream state");
+        },
+
+        x => panic!("Expected stream in invalid state error, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn events_raised_for_pause_unpause_and_seek_notifications() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let mut events = Vec::new();
+    for code in &["NetStream.Pause.Notify", "NetStream.Unpause.Notify", "NetStream.Seek.Notify", "NetStream.Play.Start"] {
+        let packet = get_on_status_packet(&mut serializer, stream_id, code);
+        let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+        let (_, mut new_events) = split_results(&mut deserializer, results);
+        events.append(&mut new_events);
+    }
+
+    assert_eq!(events, vec![
+        ClientSessionEvent::PlaybackPaused {stream},
+        ClientSessionEvent::PlaybackResumed {stream},
+        ClientSessionEvent::SeekNotified {stream},
+    ], "Unexpected events");
+    assert_eq!(session.get_stream_state(stream), Some(&ClientStreamState::Playing), "Unexpected stream state");
+}
+
+#[test]
+fn events_raised_for_stream_begin_and_eof_user_controls() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+    let (stream, stream_id) = perform_successful_play_request(config, &mut session, &mut serializer, &mut deserializer);
+
+    let mut events = Vec::new();
+    for event_type in vec![UserControlEventType::StreamEof, UserControlEventType::StreamBegin] {
+        let message = RtmpMessage::UserControl {event_type, stream_id: Some(stream_id), buffer_length: None, timestamp: None};
+        let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+        let packet = serializer.serialize(&payload, false, false).unwrap();
+        let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+        let (_, mut new_events) = split_results(&mut deserializer, results);
+        events.append(&mut new_events);
+    }
+
+    assert_eq!(events, vec![
+        ClientSessionEvent::StreamEnded {stream},
+        ClientSessionEvent::StreamBegan {stream},
+    ], "Unexpected events");
+}
+
+#[test]
+fn call_results_and_errors_raise_events_for_the_call_handle() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+
+    let mut calls = Vec::new();
+    for method in vec!["first", "second"] {
+        let (packet, handle) = session.call(method, Amf0Value::Null, vec![Amf0Value::Number(1.0)]).unwrap();
+        let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+        match payload.to_rtmp_message().unwrap() {
+            RtmpMessage::Amf0Command {command_name, transaction_id, additional_arguments, ..} => {
+                assert_eq!(command_name, method, "Unexpected command name");
+                assert_eq!(additional_arguments, vec![Amf0Value::Number(1.0)], "Unexpected arguments");
+                calls.push((handle, transaction_id));
+            },
+
+            x => panic!("Expected Amf0Command, instead received: {:?}", x),
+        }
+    }
+
+    assert_ne!(calls[0].0, calls[1].0, "Expected each call to have its own handle");
+    assert_ne!(calls[0].1, calls[1].1, "Expected each call to have its own transaction id");
+
+    let mut events = Vec::new();
+    for (command_name, transaction_id) in vec![("_error", calls[1].1), ("_result", calls[0].1)] {
+        let message = RtmpMessage::Amf0Command {
+            command_name: command_name.to_string(),
+            transaction_id,
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![Amf0Value::Boolean(true)],
+        };
+
+        let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+        let packet = serializer.serialize(&payload, false, false).unwrap();
+        let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+        let (_, mut new_events) = split_results(&mut deserializer, results);
+        events.append(&mut new_events);
+    }
+
+    assert_eq!(events, vec![
+        ClientSessionEvent::CallErrorReceived {
+            handle: calls[1].0,
+            command_object: Amf0Value::Null,
+            additional_values: vec![Amf0Value::Boolean(true)],
+        },
+        ClientSessionEvent::CallResultReceived {
+            handle: calls[0].0,
+            command_object: Amf0Value::Null,
+            additional_values: vec![Amf0Value::Boolean(true)],
+        },
+    ], "Unexpected events");
+}
+
+#[test]
+fn can_respond_to_server_initiated_call() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "getClientInfo".to_string(),
+        transaction_id: 12.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: Vec::new(),
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+    let transaction_id = match events.first() {
+        Some(ClientSessionEvent::UnhandleableAmf0Command {command_name, transaction_id, ..}) => {
+            assert_eq!(command_name, "getClientInfo", "Unexpected command name");
+            *transaction_id
+        },
+
+        x => panic!("Expected UnhandleableAmf0Command event, instead received: {:?}", x),
+    };
+
+    let responses = vec![
+        CallResponse::Result {command_object: Amf0Value::Null, additional_values: vec![Amf0Value::Utf8String("info".to_string())]},
+        CallResponse::Error {command_object: Amf0Value::Null, additional_values: Vec::new()},
+    ];
+
+    let mut command_names = Vec::new();
+    for response in responses {
+        let packet = session.respond_to_call(transaction_id, response.clone()).unwrap();
+        let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+        match payload.to_rtmp_message().unwrap() {
+            RtmpMessage::Amf0Command {command_name, transaction_id, additional_arguments, ..} => {
+                assert_eq!(transaction_id, 12.0, "Unexpected transaction id");
+                match response {
+                    CallResponse::Result {additional_values, ..} | CallResponse::Error {additional_values, ..}
+                        => assert_eq!(additional_arguments, additional_values, "Unexpected arguments"),
+                }
+
+                command_names.push(command_name);
+            },
+
+            x => panic!("Expected Amf0Command, instead received: {:?}", x),
+        }
+    }
+
+    assert_eq!(command_names, vec!["_result".to_string(), "_error".to_string()], "Unexpected response commands");
+}
+
+#[test]
+fn on_bw_check_from_server_is_answered_automatically() {
+    let config = ClientSessionConfig::new();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ClientSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_successful_connect("test".to_string(), &mut session, &mut serializer, &mut deserializer);
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "onBWCheck".to_string(),
+        transaction_id: 5.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![Amf0Value::StrictArray(vec![Amf0Value::Number(1.0)])],
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (mut responses, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 0, "Expected no events");
+    assert_eq!(responses.len(), 1, "Expected one response");
+    match responses.remove(0).1 {
+        RtmpMessage::Amf0Command {command_name, transaction_id, ..} => {
+            assert_eq!(command_name, "_result", "Unexpected command name");
+            assert_eq!(transaction_id, 5.0, "Unexpected transaction id");
+        },
+
+        x => panic!("Expected Amf0Command, instead received: {:?}", x),
+    }
+}
+
 fn split_results(deserializer: &mut ChunkDeserializer, mut results: Vec<ClientSessionResult>)
     -> (Vec<(MessagePayload, RtmpMessage)>, Vec<ClientSessionEvent>) {
     let mut responses = Vec::new();
@@ -1012,6 +1865,49 @@ fn get_connect_error_response(serializer: &mut ChunkSerializer) -> Packet {
     serializer.serialize(&payload, false, false).unwrap()
 }
 
+fn get_connect_success_response_for_transaction(serializer: &mut ChunkSerializer, transaction_id: f64) -> Packet {
+    let message = RtmpMessage::Amf0Command {
+        command_name: "_result".to_string(),
+        transaction_id,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![],
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    serializer.serialize(&payload, false, false).unwrap()
+}
+
+fn get_connect_rejection_response(serializer: &mut ChunkSerializer, transaction_id: f64, description: &str) -> Packet {
+    let mut additional_properties = HashMap::new();
+    additional_properties.insert("level".to_string(), Amf0Value::Utf8String("error".to_string()));
+    additional_properties.insert("code".to_string(), Amf0Value::Utf8String("NetConnection.Connect.Rejected".to_string()));
+    additional_properties.insert("description".to_string(), Amf0Value::Utf8String(description.to_string()));
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "_error".to_string(),
+        transaction_id,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![Amf0Value::Object(additional_properties)],
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    serializer.serialize(&payload, false, false).unwrap()
+}
+
+fn get_connect_app_and_tc_url(mut responses: Vec<(MessagePayload, RtmpMessage)>) -> (String, Option<String>) {
+    assert_eq!(responses.len(), 1, "Expected 1 response");
+    match responses.remove(0) {
+        (_, RtmpMessage::Amf0Command {command_name, command_object: Amf0Value::Object(mut properties), ..}) => {
+            assert_eq!(command_name, "connect", "Unexpected command name");
+            let app = properties.remove("app").and_then(|x| x.get_string()).unwrap();
+            let tc_url = properties.remove("tcUrl").and_then(|x| x.get_string());
+            (app, tc_url)
+        },
+
+        x => panic!("Expected connect command, instead received: {:?}", x),
+    }
+}
+
 fn get_create_stream_success_response(transaction_id: f64, serializer: &mut ChunkSerializer) -> (u32, Packet) {
     let stream_id = rand::random::<u32>();
     let message = RtmpMessage::Amf0Command {
@@ -1027,9 +1923,13 @@ fn get_create_stream_success_response(transaction_id: f64, serializer: &mut Chun
 }
 
 fn get_play_success_response(serializer: &mut ChunkSerializer, stream_id: u32) -> Packet {
+    get_on_status_packet(serializer, stream_id, "NetStream.Play.Start")
+}
+
+fn get_on_status_packet(serializer: &mut ChunkSerializer, stream_id: u32, code: &str) -> Packet {
     let mut additional_properties = HashMap::new();
     additional_properties.insert("level".to_string(), Amf0Value::Utf8String("status".to_string()));
-    additional_properties.insert("code".to_string(), Amf0Value::Utf8String("NetStream.Play.Start".to_string()));
+    additional_properties.insert("code".to_string(), Amf0Value::Utf8String(code.to_string()));
     additional_properties.insert("description".to_string(), Amf0Value::Utf8String("hi".to_string()));
 
     let message = RtmpMessage::Amf0Command {
@@ -1068,7 +1968,7 @@ fn perform_successful_connect(app_name: String,
     consume_results(deserializer, vec![results]);
 
     let response = get_connect_success_response(serializer);
-    let results = session.handle_input(&response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(response.bytes)).unwrap();
     let (_, mut events) = split_results(deserializer, results);
 
     assert_eq!(events.len(), 1, "Expected one event returned");
@@ -1081,9 +1981,9 @@ fn perform_successful_connect(app_name: String,
 fn perform_successful_play_request(config: ClientSessionConfig,
                                    session: &mut ClientSession,
                                    serializer: &mut ChunkSerializer,
-                                   deserializer: &mut ChunkDeserializer) -> u32 {
+                                   deserializer: &mut ChunkDeserializer) -> (StreamHandle, u32) {
     let stream_key = "abcd".to_string();
-    let result = session.request_playback(stream_key.clone()).unwrap();
+    let (stream, result) = session.request_playback(stream_key.clone()).unwrap();
     let (mut responses, _) = split_results(deserializer, vec![result]);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -1100,7 +2000,7 @@ fn perform_successful_play_request(config: ClientSessionConfig,
     };
 
     let (created_stream_id, create_stream_response) = get_create_stream_success_response(transaction_id, serializer);
-    let results = session.handle_input(&create_stream_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(create_stream_response.bytes)).unwrap();
     let (mut responses, _) = split_results(deserializer, results);
 
     assert_eq!(responses.len(), 2, "Expected one response returned");
@@ -1130,23 +2030,23 @@ fn perform_successful_play_request(config: ClientSessionConfig,
     };
 
     let play_response = get_play_success_response(serializer, created_stream_id);
-    let results = session.handle_input(&play_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(play_response.bytes)).unwrap();
     let (_, mut events) = split_results(deserializer, results);
 
     assert_eq!(events.len(), 1, "Expected one event returned");
     match events.remove(0) {
-        ClientSessionEvent::PlaybackRequestAccepted => (),
+        ClientSessionEvent::PlaybackRequestAccepted {stream: event_stream} => assert_eq!(event_stream, stream, "Unexpected stream"),
         x => panic!("Expected playback accepted event, instead received: {:?}", x),
     }
 
-    created_stream_id
+    (stream, created_stream_id)
 }
 
 fn perform_successful_publish_request(session: &mut ClientSession,
                                       serializer: &mut ChunkSerializer,
-                                      deserializer: &mut ChunkDeserializer) -> u32 {
+                                      deserializer: &mut ChunkDeserializer) -> (StreamHandle, u32) {
     let stream_key = "abcd".to_string();
-    let result = session.request_publishing(stream_key.clone(), PublishRequestType::Live).unwrap();
+    let (stream, result) = session.request_publishing(stream_key.clone(), PublishRequestType::Live).unwrap();
     let (mut responses, _) = split_results(deserializer, vec![result]);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -1163,7 +2063,7 @@ fn perform_successful_publish_request(session: &mut ClientSession,
     };
 
     let (created_stream_id, create_stream_response) = get_create_stream_success_response(transaction_id, serializer);
-    let results = session.handle_input(&create_stream_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(create_stream_response.bytes)).unwrap();
     let (mut responses, _) = split_results(deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -1182,14 +2082,14 @@ fn perform_successful_publish_request(session: &mut ClientSession,
     };
 
     let publish_response = get_publish_success_response(serializer, created_stream_id);
-    let results = session.handle_input(&publish_response.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(publish_response.bytes)).unwrap();
     let (_, mut events) = split_results(deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events");
     match events.remove(0) {
-        ClientSessionEvent::PublishRequestAccepted => (),
+        ClientSessionEvent::PublishRequestAccepted {stream: event_stream} => assert_eq!(event_stream, stream, "Unexpected stream"),
         x => panic!("Expected publish request accepted event, instead received: {:?}", x),
     }
 
-    created_stream_id
+    (stream, created_stream_id)
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Provides the current time to sessions, which use it to timestamp the messages they send, and
+/// to `auth::TokenValidator`, which uses it to check whether tokens have expired.
+pub trait Clock: Send + Sync {
+    /// Returns the number of milliseconds elapsed since the unix epoch.  Sessions only use the
+    /// difference between two calls, so a clock only given to sessions can start at any time.
+    fn get_time_ms(&self) -> u64;
+}
+
+/// A clock that reads the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn get_time_ms(&self) -> u64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_millis() as u64,
+            Err(_) => 0,
+        }
+    }
+}
+
+/// A clock whose time only changes when told to, for use in tests and simulations.  Clones share
+/// the same time, so a clone can be kept to control the clock after it's given to a session.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    time_ms: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    /// Creates a clock set to the specified time (in milliseconds)
+    pub fn new(time_ms: u64) -> ManualClock {
+        ManualClock {
+            time_ms: Arc::new(AtomicU64::new(time_ms)),
+        }
+    }
+
+    /// Sets the clock to the specified time (in milliseconds)
+    pub fn set(&self, time_ms: u64) {
+        self.time_ms.store(time_ms, Ordering::SeqCst);
+    }
+
+    /// Moves the clock forward by the specified number of milliseconds
+    pub fn advance(&self, milliseconds: u64) {
+        self.time_ms.fetch_add(milliseconds, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn get_time_ms(&self) -> u64 {
+        self.time_ms.load(Ordering::SeqCst)
+    }
+}
@@ -12,28 +12,130 @@ being managed (in any direction) each connection should have its own, distinct,
 It is also expected that a session has been created *after* handshaking has been completed.
 */
 
+mod adobe_auth;
+mod call;
 mod client;
+mod clock;
 mod server;
 
+pub use self::adobe_auth::{AdobeAuthenticator, AdobeAuthCredentials, AdobeAuthOutcome, AdobeAuthUserLookup};
+pub use self::call::{CallHandle, CallResponse};
+pub use self::clock::{Clock, ManualClock, SystemClock};
+
 pub use self::client::ClientSession;
 pub use self::client::ClientSessionConfig;
 pub use self::client::ClientSessionError;
 pub use self::client::ClientSessionErrorKind;
 pub use self::client::ClientSessionEvent;
 pub use self::client::ClientSessionResult;
-pub use self::client::ClientState;
+pub use self::client::{ClientState, ClientStreamState, StreamHandle};
 pub use self::client::PublishRequestType;
 
 pub use self::server::ServerSession;
+pub use self::server::ConnectionInfo;
 pub use self::server::ServerSessionConfig;
 pub use self::server::ServerSessionError;
 pub use self::server::ServerSessionErrorKind;
 pub use self::server::ServerSessionEvent;
 pub use self::server::ServerSessionResult;
+pub use self::server::{PlayStartValue, PublishMode};
 
 use rml_amf0::Amf0Value;
 use std::collections::HashMap;
 
+/// Flag in `EnhancedRtmpCapabilities::video_fourcc_info_map` indicating the codec can be decoded
+pub const FOURCC_CAN_DECODE: u32 = 0x01;
+
+/// Flag in `EnhancedRtmpCapabilities::video_fourcc_info_map` indicating the codec can be encoded
+pub const FOURCC_CAN_ENCODE: u32 = 0x02;
+
+/// Flag in `EnhancedRtmpCapabilities::video_fourcc_info_map` indicating the codec can be forwarded
+pub const FOURCC_CAN_FORWARD: u32 = 0x04;
+
+/// The Enhanced RTMP video codecs supported by a peer, as exchanged in the `connect` command
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct EnhancedRtmpCapabilities {
+    /// FourCC codes (e.g. `hvc1`, `av01`, `vp09`) of the supported codecs.  A code of `*` means
+    /// that any codec is supported.
+    pub fourcc_list: Vec<String>,
+
+    /// FourCC codes mapped to a combination of the `FOURCC_CAN_DECODE`, `FOURCC_CAN_ENCODE` and
+    /// `FOURCC_CAN_FORWARD` flags
+    pub video_fourcc_info_map: HashMap<String, u32>,
+}
+
+impl EnhancedRtmpCapabilities {
+    /// Creates a new (and empty) set of capabilities
+    pub fn new() -> EnhancedRtmpCapabilities {
+        EnhancedRtmpCapabilities::default()
+    }
+
+    /// Returns true if no Enhanced RTMP codecs are supported
+    pub fn is_empty(&self) -> bool {
+        self.fourcc_list.is_empty() && self.video_fourcc_info_map.is_empty()
+    }
+
+    /// Returns true if the specified FourCC code is supported
+    pub fn supports(&self, fourcc: &str) -> bool {
+        self.fourcc_list.iter()
+            .chain(self.video_fourcc_info_map.keys())
+            .any(|x| x == fourcc || x == "*")
+    }
+
+    /// Creates the capabilities to reply to the peer with, containing only our codecs that the
+    /// peer also supports.
+    fn negotiate(&self, peer: &EnhancedRtmpCapabilities) -> EnhancedRtmpCapabilities {
+        let fourcc_list = self.fourcc_list.iter()
+            .filter(|x| peer.supports(x) || (*x == "*" && !peer.is_empty()))
+            .cloned()
+            .collect();
+
+        let video_fourcc_info_map = self.video_fourcc_info_map.iter()
+            .filter(|&(key, _)| peer.supports(key) || (key == "*" && !peer.is_empty()))
+            .map(|(key, value)| (key.clone(), *value))
+            .collect();
+
+        EnhancedRtmpCapabilities { fourcc_list, video_fourcc_info_map }
+    }
+
+    fn read_properties(properties: &HashMap<String, Amf0Value>) -> EnhancedRtmpCapabilities {
+        let mut capabilities = EnhancedRtmpCapabilities::new();
+        if let Some(Amf0Value::StrictArray(values)) = properties.get("fourCcList") {
+            capabilities.fourcc_list = values.iter()
+                .filter_map(|x| x.clone().get_string())
+                .collect();
+        }
+
+        if let Some(value) = properties.get("videoFourCcInfoMap") {
+            if let Some(map) = value.clone().get_object_properties() {
+                capabilities.video_fourcc_info_map = map.into_iter()
+                    .filter_map(|(key, value)| value.get_number().map(|x| (key, x as u32)))
+                    .collect();
+            }
+        }
+
+        capabilities
+    }
+
+    fn write_properties(&self, properties: &mut HashMap<String, Amf0Value>) {
+        if !self.fourcc_list.is_empty() {
+            let values = self.fourcc_list.iter()
+                .map(|x| Amf0Value::Utf8String(x.clone()))
+                .collect();
+
+            properties.insert("fourCcList".to_string(), Amf0Value::StrictArray(values));
+        }
+
+        if !self.video_fourcc_info_map.is_empty() {
+            let map = self.video_fourcc_info_map.iter()
+                .map(|(key, value)| (key.clone(), Amf0Value::Number(*value as f64)))
+                .collect();
+
+            properties.insert("videoFourCcInfoMap".to_string(), Amf0Value::Object(map));
+        }
+    }
+}
+
 /// Contains the metadata information a stream may advertise on publishing
 #[derive(PartialEq, Debug, Clone)]
 pub struct StreamMetadata {
@@ -84,7 +186,7 @@ impl StreamMetadata {
                 }
 
                 "videocodecid" => {
-                    if let Some(x) = value.get_string() {
+                    if let Some(x) = get_codec_id(value) {
                         self.video_codec = Some(x)
                     }
                 }
@@ -102,7 +204,7 @@ impl StreamMetadata {
                 }
 
                 "audiocodecid" => {
-                    if let Some(x) = value.get_string() {
+                    if let Some(x) = get_codec_id(value) {
                         self.audio_codec = Some(x)
                     }
                 }
@@ -142,3 +244,23 @@ impl StreamMetadata {
         }
     }
 }
+
+/// Codec ids may be sent as strings, legacy numeric ids (e.g. `7` for AVC), or Enhanced RTMP
+/// FourCC codes packed into a number (e.g. `hvc1` as 0x68766331).  FourCC codes are returned
+/// as their string form and other numbers as their decimal form.
+fn get_codec_id(value: Amf0Value) -> Option<String> {
+    match value {
+        Amf0Value::Utf8String(x) => Some(x),
+        Amf0Value::Number(x) => {
+            let number = x as u32;
+            let bytes = [(number >> 24) as u8, (number >> 16) as u8, (number >> 8) as u8, number as u8];
+            if number > 0x00ff_ffff && bytes.iter().all(|x| x.is_ascii_alphanumeric()) {
+                Some(String::from_utf8_lossy(&bytes).into_owned())
+            } else {
+                Some(number.to_string())
+            }
+        }
+
+        _ => None,
+    }
+}
@@ -1,4 +1,8 @@
 
+use std::sync::Arc;
+use ::chunk_io::ChunkDeserializerLimits;
+use ::sessions::{Clock, EnhancedRtmpCapabilities, SystemClock};
+
 /// The configuration options that govern how a RTMP server session should operate
 #[derive(Clone)]
 pub struct ServerSessionConfig {
@@ -6,6 +10,25 @@ pub struct ServerSessionConfig {
     pub chunk_size: u32,
     pub peer_bandwidth: u32,
     pub window_ack_size: u32,
+
+    /// The Enhanced RTMP video codecs this server supports.  These are only sent to clients that
+    /// advertise Enhanced RTMP support in their connect request.
+    pub enhanced_rtmp: EnhancedRtmpCapabilities,
+
+    /// Limits on how much inbound data the client can make the session hold on to
+    pub deserializer_limits: ChunkDeserializerLimits,
+
+    /// When true, the client's bandwidth is measured with `onBWCheck` calls once its connection
+    /// request is accepted.  The results are sent to the client in an `onBWDone` call and raised
+    /// as a `BandwidthMeasured` event.
+    pub bandwidth_check_enabled: bool,
+
+    /// When true, Enhanced RTMP multitrack audio and video is split into one data received event
+    /// per track.  Otherwise the publisher's messages are raised as is, with a track id of 0.
+    pub demux_multitrack_media: bool,
+
+    /// The clock used to timestamp outbound messages and ping requests
+    pub clock: Arc<dyn Clock>,
 }
 
 impl ServerSessionConfig {
@@ -16,6 +39,11 @@ impl ServerSessionConfig {
             peer_bandwidth: 2_500_000,
             window_ack_size: 1_073_741_824,
             chunk_size: 4096,
+            enhanced_rtmp: EnhancedRtmpCapabilities::new(),
+            deserializer_limits: ChunkDeserializerLimits::new(),
+            bandwidth_check_enabled: false,
+            demux_multitrack_media: false,
+            clock: Arc::new(SystemClock),
         }
     }
 }
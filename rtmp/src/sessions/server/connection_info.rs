@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use rml_amf0::Amf0Value;
+
+/// The properties a client sent as part of its `connect` command
+#[derive(PartialEq, Debug, Clone)]
+pub struct ConnectionInfo {
+    /// The application name the client is connecting to, without any trailing slash
+    pub app_name: String,
+
+    /// The url of the server the client is connecting to (`tcUrl`)
+    pub tc_url: Option<String>,
+
+    /// The url of the swf file that is making the connection (`swfUrl`)
+    pub swf_url: Option<String>,
+
+    /// The url of the web page the swf file was loaded from (`pageUrl`)
+    pub page_url: Option<String>,
+
+    /// The version of the flash player or encoder, e.g. `FMLE/3.0` or `LNX 9,0,124,2` (`flashVer`)
+    pub flash_version: Option<String>,
+
+    /// Bit flags of the audio codecs the client supports (`audioCodecs`)
+    pub audio_codecs: Option<f64>,
+
+    /// Bit flags of the video codecs the client supports (`videoCodecs`)
+    pub video_codecs: Option<f64>,
+
+    /// Bit flags of the capabilities the client supports (`capabilities`)
+    pub capabilities: Option<f64>,
+
+    /// The AMF encoding method the client requested (`objectEncoding`)
+    pub object_encoding: f64,
+
+    /// The raw command object the client sent, including any properties not listed above.  An
+    /// AMF3 command object is converted to an AMF0 object.
+    pub command_object: Amf0Value,
+
+    /// Any optional arguments the client sent after the command object
+    pub additional_arguments: Vec<Amf0Value>,
+}
+
+impl ConnectionInfo {
+    pub(super) fn new(app_name: String, command_object: Amf0Value, additional_arguments: Vec<Amf0Value>) -> ConnectionInfo {
+        let empty_properties = HashMap::new();
+        let properties = match command_object {
+            Amf0Value::Object(ref properties) => properties,
+            _ => &empty_properties,
+        };
+
+        ConnectionInfo {
+            app_name,
+            tc_url: get_string(properties, "tcUrl"),
+            swf_url: get_string(properties, "swfUrl"),
+            page_url: get_string(properties, "pageUrl"),
+            flash_version: get_string(properties, "flashVer"),
+            audio_codecs: get_number(properties, "audioCodecs"),
+            video_codecs: get_number(properties, "videoCodecs"),
+            capabilities: get_number(properties, "capabilities"),
+            object_encoding: get_number(properties, "objectEncoding").unwrap_or(0.0),
+            command_object,
+            additional_arguments,
+        }
+    }
+}
+
+fn get_string(properties: &HashMap<String, Amf0Value>, name: &str) -> Option<String> {
+    match properties.get(name) {
+        Some(Amf0Value::Utf8String(value)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn get_number(properties: &HashMap<String, Amf0Value>, name: &str) -> Option<f64> {
+    match properties.get(name) {
+        Some(Amf0Value::Number(value)) => Some(*value),
+        _ => None,
+    }
+}
@@ -46,7 +46,15 @@ pub enum ServerSessionErrorKind {
     ActionAttemptedOnInactiveStream {
         action: String,
         stream_id: u32,
-    }
+    },
+
+    /// An action was attempted on a stream that is not playing or publishing as the action
+    /// requires
+    #[fail(display = "The '{}' action can not be performed on stream id {} in its current state", action, stream_id)]
+    ActionAttemptedOnStreamInInvalidState {
+        action: String,
+        stream_id: u32,
+    },
 }
 
 impl fmt::Display for ServerSessionError {
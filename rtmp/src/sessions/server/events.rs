@@ -1,8 +1,8 @@
 use bytes::Bytes;
 use rml_amf0::Amf0Value;
 use ::time::RtmpTimestamp;
-use ::sessions::StreamMetadata;
-use super::PublishMode;
+use ::sessions::{CallHandle, StreamMetadata};
+use super::{ConnectionInfo, PublishMode};
 
 /// Represents where RTMP playback should start from
 #[derive(PartialEq, Debug, Clone)]
@@ -26,15 +26,40 @@ pub enum ServerSessionEvent {
         new_chunk_size: u32,
     },
 
-    /// The client is requesting a connection on the specified RTMP application name
+    /// The client is requesting a connection on the specified RTMP application name.  All other
+    /// properties and arguments the client sent with the `connect` command are provided in
+    /// `connection_info`.
     ConnectionRequested {
         request_id: u32,
         app_name: String,
+        connection_info: Box<ConnectionInfo>,
     },
 
-    /// The client is requesting a stream key be released for use.
+    /// The client is requesting a stream key be released for use, usually sent by encoders prior
+    /// to publishing.  The session has already replied with a `_result`.
     ReleaseStreamRequested {
-        request_id: u32,
+        app_name: String,
+        stream_key: String,
+    },
+
+    /// The client announced it is about to publish on the specified stream key (`FCPublish`).
+    /// The session has already replied with a `_result` and an `onFCPublish` status.
+    FcPublishRequested {
+        app_name: String,
+        stream_key: String,
+    },
+
+    /// The client announced it is done publishing on the specified stream key (`FCUnpublish`).
+    /// The session has already replied with a `_result` and an `onFCUnpublish` status.
+    FcUnpublishRequested {
+        app_name: String,
+        stream_key: String,
+    },
+
+    /// The client is subscribing to the specified stream key prior to playing it, as done by
+    /// players that work with Akamai style CDNs (`FCSubscribe`).  The session has already
+    /// replied with an `onFCSubscribe` status.
+    FcSubscribeRequested {
         app_name: String,
         stream_key: String,
     },
@@ -60,23 +85,31 @@ pub enum ServerSessionEvent {
         metadata: StreamMetadata,
     },
 
-    /// Audio data was received from the client
+    /// Audio data was received from the client.  When `ServerSessionConfig::demux_multitrack_media`
+    /// is set, Enhanced RTMP multitrack audio is raised as one event per track, with the data laid
+    /// out as a regular single track audio message.
     AudioDataReceived {
         app_name: String,
         stream_key: String,
         data: Bytes,
         timestamp: RtmpTimestamp,
+        track_id: u8,
     },
 
-    /// Video data received from the client
+    /// Video data received from the client.  When `ServerSessionConfig::demux_multitrack_media`
+    /// is set, Enhanced RTMP multitrack video is raised as one event per track, with the data laid
+    /// out as a regular single track video message.
     VideoDataReceived {
         app_name: String,
         stream_key: String,
         data: Bytes,
         timestamp: RtmpTimestamp,
+        track_id: u8,
     },
 
-    /// The client sent an Amf0 command that was not able to be handled
+    /// The client sent an Amf0 command that was not able to be handled.  If the client is
+    /// expecting a response (non-zero `transaction_id`) one can be sent with
+    /// `ServerSession::respond_to_call()`.
     UnhandleableAmf0Command {
         command_name: String,
         transaction_id: f64,
@@ -95,6 +128,49 @@ pub enum ServerSessionEvent {
         stream_id: u32,
     },
 
+    /// The client is requesting playback of the specified stream be paused (`paused` is true) or
+    /// resumed at the specified position.  Accepting it sends the client a
+    /// `NetStream.Pause.Notify` or `NetStream.Unpause.Notify` status.  A later pause request for
+    /// the same stream replaces this one if it hasn't been accepted or rejected yet, after which
+    /// its request id is no longer valid.  The same applies to the seek, receive audio and receive
+    /// video requests.
+    PauseRequested {
+        request_id: u32,
+        app_name: String,
+        stream_key: String,
+        paused: bool,
+        position_ms: u32,
+        stream_id: u32,
+    },
+
+    /// The client is requesting playback of the specified stream move to a new position.
+    /// Accepting it sends the client a `NetStream.Seek.Notify` status.
+    SeekRequested {
+        request_id: u32,
+        app_name: String,
+        stream_key: String,
+        position_ms: u32,
+        stream_id: u32,
+    },
+
+    /// The client is requesting audio for the stream it's playing be enabled or disabled
+    ReceiveAudioRequested {
+        request_id: u32,
+        app_name: String,
+        stream_key: String,
+        enabled: bool,
+        stream_id: u32,
+    },
+
+    /// The client is requesting video for the stream it's playing be enabled or disabled
+    ReceiveVideoRequested {
+        request_id: u32,
+        app_name: String,
+        stream_key: String,
+        enabled: bool,
+        stream_id: u32,
+    },
+
     /// The client is finished with playback of the specified stream
     PlayStreamFinished {
         app_name: String,
@@ -110,4 +186,25 @@ pub enum ServerSessionEvent {
     PingResponseReceived {
         timestamp: RtmpTimestamp,
     },
+
+    /// The client's bandwidth was measured, due to `ServerSessionConfig::bandwidth_check_enabled`
+    /// being set.  The client has already been sent the results in an `onBWDone` call.
+    BandwidthMeasured {
+        kbps: u32,
+        latency_ms: u32,
+    },
+
+    /// The client responded with a `_result` to a call made with `ServerSession::call()`
+    CallResultReceived {
+        handle: CallHandle,
+        command_object: Amf0Value,
+        additional_values: Vec<Amf0Value>,
+    },
+
+    /// The client responded with an `_error` to a call made with `ServerSession::call()`
+    CallErrorReceived {
+        handle: CallHandle,
+        command_object: Amf0Value,
+        additional_values: Vec<Amf0Value>,
+    },
 }
\ No newline at end of file
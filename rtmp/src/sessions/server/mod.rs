@@ -1,8 +1,10 @@
 mod active_stream;
 mod config;
+mod connection_info;
 mod errors;
 mod events;
 mod outstanding_requests;
+mod outstanding_transaction;
 mod publish_mode;
 mod result;
 mod session_state;
@@ -11,23 +13,32 @@ mod session_state;
 mod tests;
 
 use std::collections::HashMap;
-use std::time::SystemTime;
+use std::mem;
+use std::sync::Arc;
 use bytes::Bytes;
 use rml_amf0::Amf0Value;
+use rml_amf3::Amf3Value;
 use ::chunk_io::{ChunkSerializer, ChunkDeserializer, Packet};
-use ::messages::{RtmpMessage, UserControlEventType, PeerBandwidthLimitType};
-use ::sessions::{StreamMetadata};
+use ::media;
+use ::messages::{MessagePayload, RtmpMessage, UserControlEventType, PeerBandwidthLimitType};
+use ::sessions::{CallHandle, CallResponse, Clock, EnhancedRtmpCapabilities, StreamMetadata};
 use ::time::RtmpTimestamp;
 use self::active_stream::{ActiveStream, StreamState};
 use self::outstanding_requests::OutstandingRequest;
+use self::outstanding_transaction::OutstandingTransaction;
 use self::session_state::SessionState;
 
 pub use self::errors::{ServerSessionError, ServerSessionErrorKind};
 pub use self::config::ServerSessionConfig;
+pub use self::connection_info::ConnectionInfo;
 pub use self::events::{ServerSessionEvent, PlayStartValue};
 pub use self::publish_mode::PublishMode;
 pub use self::result::ServerSessionResult;
 
+/// The number of values sent in each `onBWCheck` payload.  The empty first round measures the
+/// latency, which is subtracted from the later rounds when calculating throughput.
+const BANDWIDTH_CHECK_PAYLOAD_SIZES: [usize; 4] = [0, 1_200, 4_800, 12_000];
+
 /// A session that represents the server side of a single RTMP connection.
 ///
 /// The `ServerSession` encapsulates the process of parsing RTMP chunks coming in from a client
@@ -46,14 +57,23 @@ pub use self::result::ServerSessionResult;
 /// high probability of causing RTMP chunk parsing errors by the peer or by the `ServerSession`
 /// instance itself.
 pub struct ServerSession {
-    start_time: SystemTime,
+    clock: Arc<dyn Clock>,
+    start_time: u64,
     serializer: ChunkSerializer,
     deserializer: ChunkDeserializer,
     connected_app_name: Option<String>,
+    connection_info: Option<ConnectionInfo>,
     outstanding_requests: HashMap<u32, OutstandingRequest>,
     next_request_number: u32,
+    outstanding_transactions: HashMap<u32, OutstandingTransaction>,
+    next_transaction_id: u32,
+    bandwidth_check_enabled: bool,
+    demux_multitrack_media: bool,
     current_state: SessionState,
     fms_version: String,
+    enhanced_rtmp: EnhancedRtmpCapabilities,
+    peer_enhanced_rtmp: EnhancedRtmpCapabilities,
+    negotiated_enhanced_rtmp: Option<EnhancedRtmpCapabilities>,
     object_encoding: f64,
     active_streams: HashMap<u32, ActiveStream>,
     next_stream_id: u32,
@@ -70,14 +90,23 @@ impl ServerSession {
     /// stream id 0 (as well as other important initial information
     pub fn new(config: ServerSessionConfig) -> Result<(ServerSession, Vec<ServerSessionResult>), ServerSessionError> {
         let mut session = ServerSession {
-            start_time: SystemTime::now(),
+            start_time: config.clock.get_time_ms(),
+            clock: config.clock.clone(),
             serializer: ChunkSerializer::new(),
-            deserializer: ChunkDeserializer::new(),
+            deserializer: ChunkDeserializer::with_limits(config.deserializer_limits.clone()),
             connected_app_name: None,
+            connection_info: None,
             outstanding_requests: HashMap::new(),
             next_request_number: 0,
+            outstanding_transactions: HashMap::new(),
+            next_transaction_id: 1,
+            bandwidth_check_enabled: config.bandwidth_check_enabled,
+            demux_multitrack_media: config.demux_multitrack_media,
             current_state: SessionState::Started,
             fms_version: config.fms_version,
+            enhanced_rtmp: config.enhanced_rtmp,
+            peer_enhanced_rtmp: EnhancedRtmpCapabilities::new(),
+            negotiated_enhanced_rtmp: None,
             object_encoding: 0.0,
             active_streams: HashMap::new(),
             next_stream_id: 1,
@@ -112,23 +141,30 @@ impl ServerSession {
         let peer_packet = session.serializer.serialize(&peer_payload, true, false)?;
         results.push(ServerSessionResult::OutboundResponse(peer_packet));
 
-        let bw_done_message = RtmpMessage::Amf0Command {
-            command_name: "onBWDone".to_string(),
-            transaction_id: 0.0,
-            command_object: Amf0Value::Null,
-            additional_arguments: vec![Amf0Value::Number(8192_f64)]
-        };
-
-        let bw_done_payload = bw_done_message.into_message_payload(session.get_epoch(), 0)?;
-        let bw_done_packet = session.serializer.serialize(&bw_done_payload, true, false)?;
-        results.push(ServerSessionResult::OutboundResponse(bw_done_packet));
+        // When the bandwidth check is enabled the client is sent onBWDone with the measured
+        // values once the check completes instead
+        if !session.bandwidth_check_enabled {
+            let bw_done_message = RtmpMessage::Amf0Command {
+                command_name: "onBWDone".to_string(),
+                transaction_id: 0.0,
+                command_object: Amf0Value::Null,
+                additional_arguments: vec![Amf0Value::Number(8192_f64)]
+            };
+
+            let bw_done_payload = bw_done_message.into_message_payload(session.get_epoch(), 0)?;
+            let bw_done_packet = session.serializer.serialize(&bw_done_payload, true, false)?;
+            results.push(ServerSessionResult::OutboundResponse(bw_done_packet));
+        }
 
         Ok((session, results))
     }
 
     /// Takes in bytes that are encoding RTMP chunks and returns any responses or events that can
     /// be reacted to.
-    pub fn handle_input(&mut self, bytes: &[u8]) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+    ///
+    /// The data of audio and video messages is sliced out of the passed in bytes whenever
+    /// possible, so media data does not get copied on its way to the raised events.
+    pub fn handle_input(&mut self, bytes: Bytes) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         let mut results = Vec::new();
         self.bytes_received += bytes.len() as u64;
 
@@ -147,7 +183,7 @@ impl ServerSession {
         let mut bytes_to_process = bytes;
 
         loop {
-            match self.deserializer.get_next_message(bytes_to_process)? {
+            match self.deserializer.get_next_message_from_bytes(bytes_to_process)? {
                 None => break,
                 Some(payload) => {
                     let message = payload.to_rtmp_message()?;
@@ -159,10 +195,16 @@ impl ServerSession {
                         RtmpMessage::Acknowledgement{sequence_number} 
                             => self.handle_acknowledgement_message(sequence_number)?,
 
+                        RtmpMessage::Aggregate{data: _}
+                            => self.handle_aggregate(&payload)?,
+
                         RtmpMessage::Amf0Command{command_name, transaction_id, command_object, additional_arguments}
-                            => self.handle_amf0_command(payload.message_stream_id, command_name, transaction_id, command_object, additional_arguments)?,
+                            => self.handle_amf0_command(payload.message_stream_id, command_name, transaction_id, command_object, additional_arguments, false)?,
 
-                        RtmpMessage::Amf0Data{values}
+                        RtmpMessage::Amf3Command{command_name, transaction_id, command_object, additional_arguments}
+                            => self.handle_amf0_command(payload.message_stream_id, command_name, transaction_id, command_object, additional_arguments, true)?,
+
+                        RtmpMessage::Amf0Data{values} | RtmpMessage::Amf3Data{values}
                             => self.handle_amf0_data(values, payload.message_stream_id)?,
 
                         RtmpMessage::AudioData{data}
@@ -187,7 +229,7 @@ impl ServerSession {
                     };
 
                     results.append(&mut message_results);
-                    bytes_to_process = &[];
+                    bytes_to_process = Bytes::new();
                 }
             }
         }
@@ -195,6 +237,19 @@ impl ServerSession {
         Ok(results)
     }
 
+    /// Returns the Enhanced RTMP capabilities that were sent to the client when its connection
+    /// request was accepted.  This is `None` if the client or the server do not support
+    /// Enhanced RTMP.
+    pub fn get_enhanced_rtmp_capabilities(&self) -> Option<&EnhancedRtmpCapabilities> {
+        self.negotiated_enhanced_rtmp.as_ref()
+    }
+
+    /// Returns the properties the client sent in its `connect` command.  This is `None` until
+    /// the client's connection request has been accepted.
+    pub fn get_connection_info(&self) -> Option<&ConnectionInfo> {
+        self.connection_info.as_ref()
+    }
+
     /// Tells the server session that it should accept an outstanding request
     pub fn accept_request(&mut self, request_id: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         let request = match self.outstanding_requests.remove(&request_id) {
@@ -203,14 +258,76 @@ impl ServerSession {
         };
 
         match request {
-            OutstandingRequest::ConnectionRequest {app_name, transaction_id}
-                => self.accept_connection_request(app_name, transaction_id),
+            OutstandingRequest::ConnectionRequest {connection_info, transaction_id, is_amf3} => {
+                let app_name = connection_info.app_name.clone();
+                self.accept_connection_request(connection_info, transaction_id, is_amf3, app_name)
+            },
 
             OutstandingRequest::PublishRequested {stream_key, mode, stream_id}
                 => self.accept_publish_request(stream_id, stream_key, mode),
 
             OutstandingRequest::PlayRequested {stream_key, stream_id}
                 => self.accept_play_request(stream_id, stream_key),
+
+            OutstandingRequest::PauseRequested {stream_key, stream_id, paused}
+                => self.accept_pause_request(stream_id, stream_key, paused),
+
+            OutstandingRequest::SeekRequested {stream_key, stream_id, position_ms}
+                => self.accept_seek_request(stream_id, stream_key, position_ms),
+
+            OutstandingRequest::ReceiveAudioRequested {stream_key, stream_id, enabled}
+            | OutstandingRequest::ReceiveVideoRequested {stream_key, stream_id, enabled}
+                => self.accept_receive_media_request(stream_id, stream_key, enabled),
+        }
+    }
+
+    /// Accepts an outstanding connection request, but with the session connected to the specified
+    /// app name instead of the one the client requested.  This allows parts of the requested app
+    /// name that are not part of the app itself (such as an authentication query) to be left out
+    /// of the app name raised with later events.
+    pub fn accept_connection_request_as(&mut self, request_id: u32, app_name: &str) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        match self.outstanding_requests.remove(&request_id) {
+            Some(OutstandingRequest::ConnectionRequest {connection_info, transaction_id, is_amf3})
+                => self.accept_connection_request(connection_info, transaction_id, is_amf3, app_name.to_string()),
+
+            Some(request) => {
+                self.outstanding_requests.insert(request_id, request);
+                Err(ServerSessionError{kind: ServerSessionErrorKind::InvalidRequestId})
+            },
+
+            None => Err(ServerSessionError{kind: ServerSessionErrorKind::InvalidRequestId}),
+        }
+    }
+
+    /// Tells the server session that it should reject an outstanding request.  The `code` and
+    /// `description` are sent to the client as part of the error status, so they should be
+    /// codes the client understands (e.g. `NetConnection.Connect.Rejected`,
+    /// `NetStream.Publish.BadName`, `NetStream.Play.StreamNotFound` or `NetStream.Seek.Failed`).
+    /// Clients don't expect a reply to `receiveAudio` and `receiveVideo` commands, so rejecting
+    /// those requests sends nothing.
+    pub fn reject_request(&mut self, request_id: u32, code: &str, description: &str) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let request = match self.outstanding_requests.remove(&request_id) {
+            Some(x) => x,
+            None => return Err(ServerSessionError{kind: ServerSessionErrorKind::InvalidRequestId}),
+        };
+
+        match request {
+            OutstandingRequest::ConnectionRequest {connection_info: _, transaction_id, is_amf3}
+                => self.reject_connection_request(transaction_id, is_amf3, code, description),
+
+            OutstandingRequest::PublishRequested {stream_key: _, mode: _, stream_id}
+                => self.reject_stream_request(stream_id, code, description),
+
+            OutstandingRequest::PlayRequested {stream_key: _, stream_id}
+                => self.reject_stream_request(stream_id, code, description),
+
+            OutstandingRequest::PauseRequested {stream_id, ..}
+            | OutstandingRequest::SeekRequested {stream_id, ..}
+                => self.reject_stream_request(stream_id, code, description),
+
+            OutstandingRequest::ReceiveAudioRequested {..}
+            | OutstandingRequest::ReceiveVideoRequested {..}
+                => Ok(Vec::new()),
         }
     }
 
@@ -295,7 +412,116 @@ impl ServerSession {
         Ok((packet, epoch))
     }
 
-    fn handle_abort_message(&self, _stream_id: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+    /// Ends playback on the specified stream, such as when the stream no longer exists or the
+    /// client is no longer allowed to watch it.  The client is sent a `StreamEOF` user control
+    /// and a `NetStream.Play.Stop` status with the reason as its description.  The stream can
+    /// be used for another `play` request afterwards.
+    pub fn end_playback(&mut self, stream_id: u32, reason: &str) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let action = "end playback";
+        {
+            let active_stream = self.get_stream_for_action(stream_id, action)?;
+            match active_stream.current_state {
+                StreamState::Playing {..} => (),
+                _ => return Err(create_invalid_stream_state_error(action, stream_id)),
+            }
+
+            active_stream.current_state = StreamState::Created;
+        }
+
+        let eof_packet = self.create_user_control_packet(UserControlEventType::StreamEof, stream_id)?;
+        let stop_packet = self.create_status_packet(stream_id, "status", "NetStream.Play.Stop", reason)?;
+
+        Ok(vec![
+            ServerSessionResult::OutboundResponse(eof_packet),
+            ServerSessionResult::OutboundResponse(stop_packet),
+        ])
+    }
+
+    /// Tells the client playing the specified stream that the stream's publisher has stopped
+    /// publishing, by sending a `NetStream.Play.UnpublishNotify` status and a `StreamEOF` user
+    /// control.  The client remains subscribed, so `notify_publish_started()` can be used once a
+    /// publisher returns.
+    pub fn notify_unpublished(&mut self, stream_id: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let stream_key = self.get_playing_stream_key(stream_id, "notify unpublished")?;
+        let description = format!("{} is now unpublished", stream_key);
+        let status_packet = self.create_status_packet(stream_id, "status", "NetStream.Play.UnpublishNotify", &description)?;
+        let eof_packet = self.create_user_control_packet(UserControlEventType::StreamEof, stream_id)?;
+
+        Ok(vec![
+            ServerSessionResult::OutboundResponse(status_packet),
+            ServerSessionResult::OutboundResponse(eof_packet),
+        ])
+    }
+
+    /// Tells the client playing the specified stream that a publisher has started publishing on
+    /// it, by sending a `StreamBegin` user control and a `NetStream.Play.PublishNotify` status.
+    pub fn notify_publish_started(&mut self, stream_id: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let stream_key = self.get_playing_stream_key(stream_id, "notify publish started")?;
+        let begin_packet = self.create_user_control_packet(UserControlEventType::StreamBegin, stream_id)?;
+        let description = format!("{} is now published", stream_key);
+        let status_packet = self.create_status_packet(stream_id, "status", "NetStream.Play.PublishNotify", &description)?;
+
+        Ok(vec![
+            ServerSessionResult::OutboundResponse(begin_packet),
+            ServerSessionResult::OutboundResponse(status_packet),
+        ])
+    }
+
+    /// Stops the client from publishing on the specified stream, such as when its stream key has
+    /// been revoked.  The client is sent a `NetStream.Unpublish.Success` status with the reason
+    /// as its description, and any media it sends on the stream afterwards is ignored.
+    pub fn kick_publisher(&mut self, stream_id: u32, reason: &str) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let action = "kick publisher";
+        {
+            let active_stream = self.get_stream_for_action(stream_id, action)?;
+            match active_stream.current_state {
+                StreamState::Publishing {..} => (),
+                _ => return Err(create_invalid_stream_state_error(action, stream_id)),
+            }
+
+            active_stream.current_state = StreamState::Created;
+        }
+
+        let packet = self.create_status_packet(stream_id, "status", "NetStream.Unpublish.Success", reason)?;
+        Ok(vec![ServerSessionResult::OutboundResponse(packet)])
+    }
+
+    /// Calls a method on the client with the specified command object and arguments.  If the
+    /// client responds with a `_result` or `_error` a `CallResultReceived` or `CallErrorReceived`
+    /// event will be raised with the returned handle.
+    pub fn call(&mut self, method: &str, command_object: Amf0Value, args: Vec<Amf0Value>) -> Result<(Packet, CallHandle), ServerSessionError> {
+        let transaction_id = self.get_next_transaction_id();
+        let handle = CallHandle(transaction_id);
+        let message = RtmpMessage::Amf0Command {
+            command_name: method.to_string(),
+            transaction_id: transaction_id as f64,
+            command_object,
+            additional_arguments: args,
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        self.outstanding_transactions.insert(transaction_id, OutstandingTransaction::Call {handle});
+        Ok((packet, handle))
+    }
+
+    /// Responds to a command the client called (raised as an `UnhandleableAmf0Command` event)
+    /// with either a `_result` or an `_error`.
+    pub fn respond_to_call(&mut self, transaction_id: f64, response: CallResponse) -> Result<Packet, ServerSessionError> {
+        let packet = match response {
+            CallResponse::Result {command_object, additional_values}
+                => self.create_success_response(transaction_id, command_object, additional_values, 0)?,
+
+            CallResponse::Error {command_object, additional_values}
+                => self.create_error_response(transaction_id, command_object, additional_values, 0)?,
+        };
+
+        Ok(packet)
+    }
+
+    fn handle_abort_message(&mut self, stream_id: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        // The stream id of an abort message refers to the chunk stream the message was sent on
+        self.deserializer.abort_message(stream_id);
         Ok(Vec::new())
     }
 
@@ -304,19 +530,52 @@ impl ServerSession {
         Ok(vec![ServerSessionResult::RaisedEvent(event)])
     }
 
+    fn handle_aggregate(&mut self, payload: &MessagePayload) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let mut results = Vec::new();
+        for sub_payload in payload.split_aggregate()? {
+            let mut sub_results = match sub_payload.to_rtmp_message()? {
+                RtmpMessage::AudioData{data}
+                    => self.handle_audio_data(data, sub_payload.message_stream_id, sub_payload.timestamp)?,
+
+                RtmpMessage::VideoData{data}
+                    => self.handle_video_data(data, sub_payload.message_stream_id, sub_payload.timestamp)?,
+
+                RtmpMessage::Amf0Data{values} | RtmpMessage::Amf3Data{values}
+                    => self.handle_amf0_data(values, sub_payload.message_stream_id)?,
+
+                _ => vec![ServerSessionResult::UnhandleableMessageReceived(sub_payload)],
+            };
+
+            results.append(&mut sub_results);
+        }
+
+        Ok(results)
+    }
+
     fn handle_amf0_command(&mut self,
                            stream_id: u32,
                            name: String,
                            transaction_id: f64,
                            command_object: Amf0Value,
-                           additional_args: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+                           additional_args: Vec<Amf0Value>,
+                           is_amf3: bool) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         let results = match name.as_str() {
-            "connect" => self.handle_command_connect(transaction_id, command_object)?,
+            "connect" => self.handle_command_connect(transaction_id, command_object, additional_args, is_amf3)?,
             "closeStream" => self.handle_command_close_stream(additional_args)?,
             "createStream" => self.handle_command_create_stream(transaction_id)?,
             "deleteStream" => self.handle_command_delete_stream(additional_args)?,
+            "releaseStream" => self.handle_command_release_stream(stream_id, transaction_id, additional_args)?,
+            "FCPublish" => self.handle_command_fc_publish(stream_id, transaction_id, additional_args)?,
+            "FCUnpublish" => self.handle_command_fc_unpublish(stream_id, transaction_id, additional_args)?,
+            "FCSubscribe" => self.handle_command_fc_subscribe(stream_id, transaction_id, additional_args)?,
             "play" => self.handle_command_play(stream_id, transaction_id, additional_args)?,
             "publish" => self.handle_command_publish(stream_id, transaction_id, additional_args)?,
+            "pause" => self.handle_command_pause(stream_id, additional_args)?,
+            "seek" => self.handle_command_seek(stream_id, additional_args)?,
+            "receiveAudio" => self.handle_command_receive_audio(stream_id, additional_args)?,
+            "receiveVideo" => self.handle_command_receive_video(stream_id, additional_args)?,
+            "_result" | "_error" if self.outstanding_transactions.contains_key(&(transaction_id as u32))
+                => self.handle_call_response(name == "_result", transaction_id, command_object, additional_args)?,
 
             _ => vec![ServerSessionResult::RaisedEvent(ServerSessionEvent::UnhandleableAmf0Command {
                 command_name: name,
@@ -329,31 +588,133 @@ impl ServerSession {
         Ok(results)
     }
 
-    fn handle_command_connect(&mut self, transaction_id: f64, command_object: Amf0Value) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
-        let mut properties = match command_object {
-            Amf0Value::Object(properties) => properties,
-            _ => return Err(ServerSessionError{kind: ServerSessionErrorKind::NoAppNameForConnectionRequest}),
-        };
+    fn handle_call_response(&mut self,
+                            is_success: bool,
+                            transaction_id: f64,
+                            command_object: Amf0Value,
+                            additional_values: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let event = match self.outstanding_transactions.remove(&(transaction_id as u32)) {
+            Some(OutstandingTransaction::Call {handle}) => if is_success {
+                ServerSessionEvent::CallResultReceived {handle, command_object, additional_values}
+            } else {
+                ServerSessionEvent::CallErrorReceived {handle, command_object, additional_values}
+            },
+
+            Some(OutstandingTransaction::BandwidthCheck {round, sent_at, packet_size, latency_ms, total_bytes, total_time_ms}) => {
+                // Clients that don't support bandwidth checks reply with an error, so just stop
+                if !is_success {
+                    return Ok(Vec::new());
+                }
+
+                let round_trip_ms = (self.get_epoch() - sent_at).value;
+                let (latency_ms, total_bytes, total_time_ms) = match round {
+                    0 => (round_trip_ms, total_bytes, total_time_ms),
+                    _ => (latency_ms, total_bytes + packet_size, total_time_ms + round_trip_ms.saturating_sub(latency_ms)),
+                };
 
-        let app_name = match properties.remove("app") {
-            Some(Amf0Value::Utf8String(mut app)) => {
-                if app.ends_with('/') {
-                    app.pop();
+                if round + 1 < BANDWIDTH_CHECK_PAYLOAD_SIZES.len() {
+                    let packet = self.create_bandwidth_check_packet(round + 1, latency_ms, total_bytes, total_time_ms)?;
+                    return Ok(vec![ServerSessionResult::OutboundResponse(packet)]);
                 }
 
-                app
+                return self.complete_bandwidth_check(latency_ms, total_bytes, total_time_ms);
             },
-            _ => return Err(ServerSessionError{kind: ServerSessionErrorKind::NoAppNameForConnectionRequest}),
+
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+    }
+
+    fn create_bandwidth_check_packet(&mut self, round: usize, latency_ms: u32, total_bytes: usize, total_time_ms: u32) -> Result<Packet, ServerSessionError> {
+        let transaction_id = self.get_next_transaction_id();
+        let payload_values = (0..BANDWIDTH_CHECK_PAYLOAD_SIZES[round])
+            .map(|x| Amf0Value::Number(x as f64))
+            .collect();
+
+        let message = RtmpMessage::Amf0Command {
+            command_name: "onBWCheck".to_string(),
+            transaction_id: transaction_id as f64,
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![Amf0Value::StrictArray(payload_values)],
+        };
+
+        let sent_at = self.get_epoch();
+        let payload = message.into_message_payload(sent_at, 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        self.outstanding_transactions.insert(transaction_id, OutstandingTransaction::BandwidthCheck {
+            round,
+            sent_at,
+            packet_size: packet.bytes.len(),
+            latency_ms,
+            total_bytes,
+            total_time_ms,
+        });
+
+        Ok(packet)
+    }
+
+    fn complete_bandwidth_check(&mut self, latency_ms: u32, total_bytes: usize, total_time_ms: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        // Bytes per millisecond times 8 is kilobits per second
+        let kbps = ((total_bytes as u64 * 8) / u64::from(total_time_ms.max(1))) as u32;
+
+        // Arguments follow FMS: kbit down, kilobytes down, delta time in seconds, and latency
+        let message = RtmpMessage::Amf0Command {
+            command_name: "onBWDone".to_string(),
+            transaction_id: 0.0,
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![
+                Amf0Value::Number(kbps as f64),
+                Amf0Value::Number((total_bytes / 1024) as f64),
+                Amf0Value::Number(total_time_ms as f64 / 1000.0),
+                Amf0Value::Number(latency_ms as f64),
+            ],
         };
 
-        self.object_encoding = match properties.remove("objectEncoding") {
-            Some(Amf0Value::Number(number)) => number,
-            _ => 0.0,
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        let event = ServerSessionEvent::BandwidthMeasured {kbps, latency_ms};
+
+        Ok(vec![
+            ServerSessionResult::OutboundResponse(packet),
+            ServerSessionResult::RaisedEvent(event),
+        ])
+    }
+
+    fn handle_command_connect(&mut self,
+                              transaction_id: f64,
+                              command_object: Amf0Value,
+                              additional_args: Vec<Amf0Value>,
+                              is_amf3: bool) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let command_object = unwrap_avmplus_object(command_object);
+        let app_name = {
+            let properties = match command_object {
+                Amf0Value::Object(ref properties) => properties,
+                _ => return Err(ServerSessionError{kind: ServerSessionErrorKind::NoAppNameForConnectionRequest}),
+            };
+
+            self.peer_enhanced_rtmp = EnhancedRtmpCapabilities::read_properties(properties);
+
+            match properties.get("app") {
+                Some(Amf0Value::Utf8String(app)) => {
+                    let mut app = app.clone();
+                    if app.ends_with('/') {
+                        app.pop();
+                    }
+
+                    app
+                },
+                _ => return Err(ServerSessionError{kind: ServerSessionErrorKind::NoAppNameForConnectionRequest}),
+            }
         };
 
+        let connection_info = Box::new(ConnectionInfo::new(app_name.clone(), command_object, additional_args));
+        self.object_encoding = connection_info.object_encoding;
+
         let request = OutstandingRequest::ConnectionRequest {
-            app_name: app_name.clone(),
+            connection_info: connection_info.clone(),
             transaction_id,
+            is_amf3,
         };
 
         let request_number = self.next_request_number;
@@ -363,6 +724,7 @@ impl ServerSession {
         let event = ServerSessionEvent::ConnectionRequested {
             app_name,
             request_id: request_number,
+            connection_info,
         };
 
         Ok(vec![ServerSessionResult::RaisedEvent(event)])
@@ -492,6 +854,110 @@ impl ServerSession {
         Ok(result)
     }
 
+    fn handle_command_release_stream(&mut self, stream_id: u32, transaction_id: f64, arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let (app_name, stream_key) = match self.get_fc_command_target(arguments) {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut results = self.create_fc_command_responses(stream_id, transaction_id, None, &stream_key)?;
+        let event = ServerSessionEvent::ReleaseStreamRequested {app_name, stream_key};
+        results.push(ServerSessionResult::RaisedEvent(event));
+
+        Ok(results)
+    }
+
+    fn handle_command_fc_publish(&mut self, stream_id: u32, transaction_id: f64, arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let (app_name, stream_key) = match self.get_fc_command_target(arguments) {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+
+        let status = Some(("onFCPublish", "NetStream.Publish.Start"));
+        let mut results = self.create_fc_command_responses(stream_id, transaction_id, status, &stream_key)?;
+        let event = ServerSessionEvent::FcPublishRequested {app_name, stream_key};
+        results.push(ServerSessionResult::RaisedEvent(event));
+
+        Ok(results)
+    }
+
+    fn handle_command_fc_unpublish(&mut self, stream_id: u32, transaction_id: f64, arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let (app_name, stream_key) = match self.get_fc_command_target(arguments) {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+
+        let status = Some(("onFCUnpublish", "NetStream.Unpublish.Success"));
+        let mut results = self.create_fc_command_responses(stream_id, transaction_id, status, &stream_key)?;
+        let event = ServerSessionEvent::FcUnpublishRequested {app_name, stream_key};
+        results.push(ServerSessionResult::RaisedEvent(event));
+
+        Ok(results)
+    }
+
+    fn handle_command_fc_subscribe(&mut self, stream_id: u32, transaction_id: f64, arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let (app_name, stream_key) = match self.get_fc_command_target(arguments) {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+
+        let status = Some(("onFCSubscribe", "NetStream.Play.Start"));
+        let mut results = self.create_fc_command_responses(stream_id, transaction_id, status, &stream_key)?;
+        let event = ServerSessionEvent::FcSubscribeRequested {app_name, stream_key};
+        results.push(ServerSessionResult::RaisedEvent(event));
+
+        Ok(results)
+    }
+
+    /// Gets the app name and stream key that a `releaseStream` or `FC*` command applies to.  These
+    /// commands are only informational, so they are ignored if they are not valid.
+    fn get_fc_command_target(&self, mut arguments: Vec<Amf0Value>) -> Option<(String, String)> {
+        if self.current_state != SessionState::Connected {
+            return None;
+        }
+
+        let app_name = self.connected_app_name.clone()?;
+        if arguments.is_empty() {
+            return None;
+        }
+
+        match arguments.remove(0) {
+            Amf0Value::Utf8String(stream_key) => Some((app_name, stream_key)),
+            _ => None,
+        }
+    }
+
+    /// Creates the replies real servers send to `releaseStream` and `FC*` commands, which is a
+    /// `_result` (when the client is expecting one) followed by an optional status command
+    /// (e.g. `onFCPublish`) describing the stream key.
+    fn create_fc_command_responses(&mut self,
+                                   stream_id: u32,
+                                   transaction_id: f64,
+                                   status: Option<(&str, &str)>,
+                                   stream_key: &str) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let mut results = Vec::new();
+        if transaction_id != 0.0 {
+            let packet = self.create_success_response(transaction_id, Amf0Value::Null, vec![Amf0Value::Undefined], stream_id)?;
+            results.push(ServerSessionResult::OutboundResponse(packet));
+        }
+
+        if let Some((command_name, code)) = status {
+            let status_object = create_status_object("status", code, stream_key);
+            let message = RtmpMessage::Amf0Command {
+                command_name: command_name.to_string(),
+                transaction_id: 0.0,
+                command_object: Amf0Value::Null,
+                additional_arguments: vec![Amf0Value::Object(status_object)]
+            };
+
+            let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
+            let packet = self.serializer.serialize(&payload, false, false)?;
+            results.push(ServerSessionResult::OutboundResponse(packet));
+        }
+
+        Ok(results)
+    }
+
     fn handle_command_publish(&mut self, stream_id: u32, transaction_id: f64, mut arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         if arguments.len() < 2 {
             let packet = self.create_error_packet("NetStream.Publish.Start", "Invalid publish arguments", transaction_id, stream_id)?;
@@ -658,6 +1124,167 @@ impl ServerSession {
         Ok(vec![ServerSessionResult::RaisedEvent(event)])
     }
 
+    fn handle_command_pause(&mut self, stream_id: u32, arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let (app_name, stream_key) = match self.get_playing_stream_target(stream_id) {
+            Some(x) => x,
+            None => return self.reject_stream_request(stream_id, "NetStream.Pause.Failed", "Can't pause a stream that is not playing"),
+        };
+
+        // Arguments are the pause flag followed by the playback position in milliseconds
+        let paused = match arguments.first() {
+            Some(&Amf0Value::Boolean(x)) => x,
+            _ => return self.reject_stream_request(stream_id, "NetStream.Pause.Failed", "Invalid pause arguments"),
+        };
+
+        let position_ms = get_position_ms(arguments.get(1));
+        let request = OutstandingRequest::PauseRequested {
+            stream_key: stream_key.clone(),
+            stream_id,
+            paused,
+        };
+
+        let request_number = self.add_playback_request(stream_id, request);
+
+        let event = ServerSessionEvent::PauseRequested {
+            request_id: request_number,
+            app_name,
+            stream_key,
+            paused,
+            position_ms,
+            stream_id,
+        };
+
+        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+    }
+
+    fn handle_command_seek(&mut self, stream_id: u32, arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let (app_name, stream_key) = match self.get_playing_stream_target(stream_id) {
+            Some(x) => x,
+            None => return self.reject_stream_request(stream_id, "NetStream.Seek.Failed", "Can't seek on a stream that is not playing"),
+        };
+
+        let position_ms = match arguments.first() {
+            Some(&Amf0Value::Number(_)) => get_position_ms(arguments.first()),
+            _ => return self.reject_stream_request(stream_id, "NetStream.Seek.Failed", "Invalid seek arguments"),
+        };
+
+        let request = OutstandingRequest::SeekRequested {
+            stream_key: stream_key.clone(),
+            stream_id,
+            position_ms,
+        };
+
+        let request_number = self.add_playback_request(stream_id, request);
+
+        let event = ServerSessionEvent::SeekRequested {
+            request_id: request_number,
+            app_name,
+            stream_key,
+            position_ms,
+            stream_id,
+        };
+
+        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+    }
+
+    fn handle_command_receive_audio(&mut self, stream_id: u32, arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let (app_name, stream_key, enabled) = match self.get_receive_media_target(stream_id, arguments) {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+
+        let request = OutstandingRequest::ReceiveAudioRequested {
+            stream_key: stream_key.clone(),
+            stream_id,
+            enabled,
+        };
+
+        let request_id = self.add_playback_request(stream_id, request);
+        let event = ServerSessionEvent::ReceiveAudioRequested {request_id, app_name, stream_key, enabled, stream_id};
+        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+    }
+
+    fn handle_command_receive_video(&mut self, stream_id: u32, arguments: Vec<Amf0Value>) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let (app_name, stream_key, enabled) = match self.get_receive_media_target(stream_id, arguments) {
+            Some(x) => x,
+            None => return Ok(Vec::new()),
+        };
+
+        let request = OutstandingRequest::ReceiveVideoRequested {
+            stream_key: stream_key.clone(),
+            stream_id,
+            enabled,
+        };
+
+        let request_id = self.add_playback_request(stream_id, request);
+        let event = ServerSessionEvent::ReceiveVideoRequested {request_id, app_name, stream_key, enabled, stream_id};
+        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+    }
+
+    /// Gets the app name, stream key and enabled flag for a `receiveAudio` or `receiveVideo`
+    /// command.  Per the RTMP specification the server never replies to these with an error, so
+    /// invalid commands are ignored.
+    fn get_receive_media_target(&self, stream_id: u32, arguments: Vec<Amf0Value>) -> Option<(String, String, bool)> {
+        let (app_name, stream_key) = self.get_playing_stream_target(stream_id)?;
+        let enabled = match arguments.first() {
+            Some(&Amf0Value::Boolean(x)) => x,
+            _ => return None,
+        };
+
+        Some((app_name, stream_key, enabled))
+    }
+
+    /// Records an outstanding request that changes the playback of a stream.  Only the latest
+    /// request of each kind is kept for a stream, so a client sending these commands faster than
+    /// they are accepted or rejected can't grow the outstanding requests without bound.
+    fn add_playback_request(&mut self, stream_id: u32, request: OutstandingRequest) -> u32 {
+        let kind = mem::discriminant(&request);
+        self.outstanding_requests.retain(|_, existing| {
+            let existing_stream_id = match *existing {
+                OutstandingRequest::PauseRequested {stream_id, ..}
+                | OutstandingRequest::SeekRequested {stream_id, ..}
+                | OutstandingRequest::ReceiveAudioRequested {stream_id, ..}
+                | OutstandingRequest::ReceiveVideoRequested {stream_id, ..} => stream_id,
+                _ => return true,
+            };
+
+            existing_stream_id != stream_id || mem::discriminant(existing) != kind
+        });
+
+        let request_number = self.next_request_number;
+        self.next_request_number += 1;
+        self.outstanding_requests.insert(request_number, request);
+
+        request_number
+    }
+
+    /// Gets the app name and stream key being played on the specified stream, if the stream is
+    /// currently being played.
+    fn get_playing_stream_target(&self, stream_id: u32) -> Option<(String, String)> {
+        let app_name = self.connected_app_name.clone()?;
+        match self.active_streams.get(&stream_id) {
+            Some(&ActiveStream {current_state: StreamState::Playing {ref stream_key}}) => Some((app_name, stream_key.clone())),
+            _ => None,
+        }
+    }
+
+    fn get_stream_for_action(&mut self, stream_id: u32, action: &str) -> Result<&mut ActiveStream, ServerSessionError> {
+        match self.active_streams.get_mut(&stream_id) {
+            Some(active_stream) => Ok(active_stream),
+            None => Err(ServerSessionError {kind: ServerSessionErrorKind::ActionAttemptedOnInactiveStream {
+                action: action.to_string(),
+                stream_id
+            }}),
+        }
+    }
+
+    fn get_playing_stream_key(&mut self, stream_id: u32, action: &str) -> Result<String, ServerSessionError> {
+        match self.get_stream_for_action(stream_id, action)?.current_state {
+            StreamState::Playing {ref stream_key} => Ok(stream_key.clone()),
+            _ => Err(create_invalid_stream_state_error(action, stream_id)),
+        }
+    }
+
     fn handle_amf0_data(&mut self, mut data: Vec<Amf0Value>, stream_id: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         if data.is_empty() {
             // No data so just do nothing
@@ -740,14 +1367,26 @@ impl ServerSession {
             None => return Ok(Vec::new()), // Audio sent over an invalid stream, ignore it
         };
 
-        let event = ServerSessionEvent::AudioDataReceived {
-            stream_key: publish_stream_key,
-            app_name,
-            timestamp,
-            data,
+        let tracks = if self.demux_multitrack_media {
+            match media::demux_audio(&data) {
+                Ok(frames) => frames.into_iter().map(|frame| (frame.track_id, frame.data)).collect(),
+                Err(_) => vec![(0, data)], // Not data we understand, so pass it along as is
+            }
+        } else {
+            vec![(0, data)]
         };
 
-        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+        let results = tracks.into_iter()
+            .map(|(track_id, data)| ServerSessionResult::RaisedEvent(ServerSessionEvent::AudioDataReceived {
+                stream_key: publish_stream_key.clone(),
+                app_name: app_name.clone(),
+                timestamp,
+                data,
+                track_id,
+            }))
+            .collect();
+
+        Ok(results)
     }
 
     fn handle_set_chunk_size(&mut self, size: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
@@ -808,14 +1447,26 @@ impl ServerSession {
             None => return Ok(Vec::new()), // Video sent over an invalid stream, ignore it
         };
 
-        let event = ServerSessionEvent::VideoDataReceived {
-            stream_key: publish_stream_key,
-            app_name,
-            timestamp,
-            data,
+        let tracks = if self.demux_multitrack_media {
+            match media::demux_video(&data) {
+                Ok(frames) => frames.into_iter().map(|frame| (frame.track_id, frame.data)).collect(),
+                Err(_) => vec![(0, data)], // Not data we understand, so pass it along as is
+            }
+        } else {
+            vec![(0, data)]
         };
 
-        Ok(vec![ServerSessionResult::RaisedEvent(event)])
+        let results = tracks.into_iter()
+            .map(|(track_id, data)| ServerSessionResult::RaisedEvent(ServerSessionEvent::VideoDataReceived {
+                stream_key: publish_stream_key.clone(),
+                app_name: app_name.clone(),
+                timestamp,
+                data,
+                track_id,
+            }))
+            .collect();
+
+        Ok(results)
     }
 
     fn handle_window_acknowledgement(&mut self, size: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
@@ -823,30 +1474,47 @@ impl ServerSession {
         Ok(Vec::new())
     }
 
-    fn accept_connection_request(&mut self, app_name: String, transaction_id: f64) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+    fn accept_connection_request(&mut self,
+                                 connection_info: Box<ConnectionInfo>,
+                                 transaction_id: f64,
+                                 is_amf3: bool,
+                                 app_name: String) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
         self.connected_app_name = Some(app_name.clone());
+        self.connection_info = Some(*connection_info);
         self.current_state = SessionState::Connected;
 
         let mut command_object_properties = HashMap::new();
         command_object_properties.insert("fmsVer".to_string(), Amf0Value::Utf8String(self.fms_version.clone()));
         command_object_properties.insert("capabilities".to_string(), Amf0Value::Number(31.0));
 
+        if !self.peer_enhanced_rtmp.is_empty() && !self.enhanced_rtmp.is_empty() {
+            let negotiated = self.enhanced_rtmp.negotiate(&self.peer_enhanced_rtmp);
+            negotiated.write_properties(&mut command_object_properties);
+            self.negotiated_enhanced_rtmp = Some(negotiated);
+        }
+
         let description = "Successfully connected on app: ".to_string() + &app_name;
         let mut additional_properties = create_status_object("status", "NetConnection.Connect.Success", description.as_ref());
         additional_properties.insert("objectEncoding".to_string(), Amf0Value::Number(self.object_encoding));
 
-        let message = RtmpMessage::Amf0Command {
-            command_name: "_result".to_string(),
+        let message = create_command_message(
+            is_amf3,
+            "_result",
             transaction_id,
-            command_object: Amf0Value::Object(command_object_properties),
-            additional_arguments: vec![Amf0Value::Object(additional_properties)]
-        };
+            Amf0Value::Object(command_object_properties),
+            vec![Amf0Value::Object(additional_properties)],
+        );
 
         let payload = message.into_message_payload(self.get_epoch(), 0)?;
         let packet = self.serializer.serialize(&payload, false, false)?;
-        
-        
-        Ok(vec![ServerSessionResult::OutboundResponse(packet)])
+        let mut results = vec![ServerSessionResult::OutboundResponse(packet)];
+
+        if self.bandwidth_check_enabled {
+            let packet = self.create_bandwidth_check_packet(0, 0, 0, 0)?;
+            results.push(ServerSessionResult::OutboundResponse(packet));
+        }
+
+        Ok(results)
     }
 
     fn accept_publish_request(&mut self, stream_id: u32, stream_key: String, mode: PublishMode) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
@@ -971,6 +1639,117 @@ impl ServerSession {
         ])
     }
 
+    fn accept_pause_request(&mut self, stream_id: u32, stream_key: String, paused: bool) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        if !self.active_streams.contains_key(&stream_id) {
+            return Err(ServerSessionError {kind: ServerSessionErrorKind::ActionAttemptedOnInactiveStream {
+                action: "pause".to_string(),
+                stream_id
+            }});
+        }
+
+        let (event_type, code, description) = if paused {
+            (UserControlEventType::StreamEof, "NetStream.Pause.Notify", format!("Paused stream key {}", stream_key))
+        } else {
+            (UserControlEventType::StreamBegin, "NetStream.Unpause.Notify", format!("Unpaused stream key {}", stream_key))
+        };
+
+        let user_control_packet = self.create_user_control_packet(event_type, stream_id)?;
+        let status_packet = self.create_status_packet(stream_id, "status", code, &description)?;
+
+        Ok(vec![
+            ServerSessionResult::OutboundResponse(user_control_packet),
+            ServerSessionResult::OutboundResponse(status_packet),
+        ])
+    }
+
+    fn accept_seek_request(&mut self, stream_id: u32, stream_key: String, position_ms: u32) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        if !self.active_streams.contains_key(&stream_id) {
+            return Err(ServerSessionError {kind: ServerSessionErrorKind::ActionAttemptedOnInactiveStream {
+                action: "seek".to_string(),
+                stream_id
+            }});
+        }
+
+        let description = format!("Seeking to {} ms on stream key {}", position_ms, stream_key);
+        let seek_packet = self.create_status_packet(stream_id, "status", "NetStream.Seek.Notify", &description)?;
+        let stream_begin_packet = self.create_user_control_packet(UserControlEventType::StreamBegin, stream_id)?;
+
+        let description = format!("Successfully started playback on stream key {}", stream_key);
+        let start_packet = self.create_status_packet(stream_id, "status", "NetStream.Play.Start", &description)?;
+
+        Ok(vec![
+            ServerSessionResult::OutboundResponse(seek_packet),
+            ServerSessionResult::OutboundResponse(stream_begin_packet),
+            ServerSessionResult::OutboundResponse(start_packet),
+        ])
+    }
+
+    fn accept_receive_media_request(&mut self, stream_id: u32, stream_key: String, enabled: bool) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        if !self.active_streams.contains_key(&stream_id) {
+            return Err(ServerSessionError {kind: ServerSessionErrorKind::ActionAttemptedOnInactiveStream {
+                action: "receive media".to_string(),
+                stream_id
+            }});
+        }
+
+        // Per the RTMP specification nothing is sent when media is being disabled
+        if !enabled {
+            return Ok(Vec::new());
+        }
+
+        let description = format!("Resuming media on stream key {}", stream_key);
+        let seek_packet = self.create_status_packet(stream_id, "status", "NetStream.Seek.Notify", &description)?;
+
+        let description = format!("Successfully started playback on stream key {}", stream_key);
+        let start_packet = self.create_status_packet(stream_id, "status", "NetStream.Play.Start", &description)?;
+
+        Ok(vec![
+            ServerSessionResult::OutboundResponse(seek_packet),
+            ServerSessionResult::OutboundResponse(start_packet),
+        ])
+    }
+
+    fn reject_connection_request(&mut self, transaction_id: f64, is_amf3: bool, code: &str, description: &str) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let status_object = create_status_object("error", code, description);
+        let message = create_command_message(is_amf3, "_error", transaction_id, Amf0Value::Null, vec![Amf0Value::Object(status_object)]);
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+
+        Ok(vec![ServerSessionResult::OutboundResponse(packet)])
+    }
+
+    fn reject_stream_request(&mut self, stream_id: u32, code: &str, description: &str) -> Result<Vec<ServerSessionResult>, ServerSessionError> {
+        let packet = self.create_status_packet(stream_id, "error", code, description)?;
+        Ok(vec![ServerSessionResult::OutboundResponse(packet)])
+    }
+
+    fn create_status_packet(&mut self, stream_id: u32, level: &str, code: &str, description: &str) -> Result<Packet, ServerSessionError> {
+        let status_object = create_status_object(level, code, description);
+        let message = RtmpMessage::Amf0Command {
+            command_name: "onStatus".to_string(),
+            transaction_id: 0.0,
+            command_object: Amf0Value::Null,
+            additional_arguments: vec![Amf0Value::Object(status_object)]
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), stream_id)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        Ok(packet)
+    }
+
+    fn create_user_control_packet(&mut self, event_type: UserControlEventType, stream_id: u32) -> Result<Packet, ServerSessionError> {
+        let message = RtmpMessage::UserControl {
+            event_type,
+            stream_id: Some(stream_id),
+            buffer_length: None,
+            timestamp: None,
+        };
+
+        let payload = message.into_message_payload(self.get_epoch(), 0)?;
+        let packet = self.serializer.serialize(&payload, false, false)?;
+        Ok(packet)
+    }
+
     fn create_success_response(&mut self,
         transaction_id: f64,
         command_object: Amf0Value,
@@ -1007,18 +1786,19 @@ impl ServerSession {
         Ok(packet)
     }
 
-    fn get_epoch(&self) -> RtmpTimestamp {
-        match self.start_time.elapsed() {
-            Ok(duration) => {
-                let milliseconds = (duration.as_secs() * 1000) + (duration.subsec_nanos() as u64 / 1_000_000);
+    fn get_next_transaction_id(&mut self) -> u32 {
+        let transaction_id = self.next_transaction_id;
+        self.next_transaction_id += 1;
+        transaction_id
+    }
 
-                // Casting to u32 should auto-wrap the value as expected.  If not a stream will probably
-                // break after 49 days but testing shows it should wrap  
-                RtmpTimestamp::new(milliseconds as u32)
-            },
+    fn get_epoch(&self) -> RtmpTimestamp {
+        // If time went backwards just consider time as at epoch
+        let milliseconds = self.clock.get_time_ms().saturating_sub(self.start_time);
 
-            Err(_) => RtmpTimestamp::new(0), // Time went backwards, so just consider time as at epoch
-        }
+        // Casting to u32 should auto-wrap the value as expected.  If not a stream will probably
+        // break after 49 days but testing shows it should wrap
+        RtmpTimestamp::new(milliseconds as u32)
     }
 
     fn create_error_packet(&mut self, code: &str, description: &str, transaction_id: f64, stream_id: u32) -> Result<Packet, ServerSessionError> {
@@ -1028,6 +1808,72 @@ impl ServerSession {
     }
 }
 
+fn create_invalid_stream_state_error(action: &str, stream_id: u32) -> ServerSessionError {
+    ServerSessionError {kind: ServerSessionErrorKind::ActionAttemptedOnStreamInInvalidState {
+        action: action.to_string(),
+        stream_id
+    }}
+}
+
+/// Reads a playback position in milliseconds from a `pause` or `seek` argument, treating missing
+/// or negative positions as the start of the stream.
+fn get_position_ms(value: Option<&Amf0Value>) -> u32 {
+    match value {
+        Some(&Amf0Value::Number(x)) if x >= 0.0 => x as u32,
+        _ => 0,
+    }
+}
+
+/// Creates a command message of the same type (AMF0 or AMF3) as the command it's replying to
+fn create_command_message(is_amf3: bool,
+                          command_name: &str,
+                          transaction_id: f64,
+                          command_object: Amf0Value,
+                          additional_arguments: Vec<Amf0Value>) -> RtmpMessage {
+    let command_name = command_name.to_string();
+    if is_amf3 {
+        RtmpMessage::Amf3Command {command_name, transaction_id, command_object, additional_arguments}
+    } else {
+        RtmpMessage::Amf0Command {command_name, transaction_id, command_object, additional_arguments}
+    }
+}
+
+/// Clients that send their `connect` command as an AMF3 command may encode the command object as
+/// an AMF3 object, so it's converted to an AMF0 object to be read like any other command object
+fn unwrap_avmplus_object(command_object: Amf0Value) -> Amf0Value {
+    match command_object {
+        Amf0Value::AvmPlus(value @ Amf3Value::Object {..}) => convert_amf3_value(value),
+        other => other,
+    }
+}
+
+/// Converts an AMF3 value to its AMF0 equivalent.  Values without one are left wrapped in
+/// `Amf0Value::AvmPlus`.
+fn convert_amf3_value(value: Amf3Value) -> Amf0Value {
+    match value {
+        Amf3Value::Undefined => Amf0Value::Undefined,
+        Amf3Value::Null => Amf0Value::Null,
+        Amf3Value::Boolean(value) => Amf0Value::Boolean(value),
+        Amf3Value::Integer(value) => Amf0Value::Number(f64::from(value)),
+        Amf3Value::Double(value) => Amf0Value::Number(value),
+        Amf3Value::Utf8String(value) => Amf0Value::Utf8String(value),
+        Amf3Value::Array {associative, dense} if associative.is_empty() => {
+            Amf0Value::StrictArray(dense.into_iter().map(convert_amf3_value).collect())
+        },
+
+        Amf3Value::Object {class_name: None, sealed_properties, dynamic_properties} => {
+            let properties = sealed_properties.into_iter()
+                .chain(dynamic_properties.into_iter().flatten())
+                .map(|(name, value)| (name, convert_amf3_value(value)))
+                .collect();
+
+            Amf0Value::Object(properties)
+        },
+
+        other => Amf0Value::AvmPlus(other),
+    }
+}
+
 fn create_status_object(level: &str, code: &str, description: &str) -> HashMap<String, Amf0Value> {
     let mut properties = HashMap::new();
     properties.insert("level".to_string(), Amf0Value::Utf8String(level.to_string()));
@@ -1,9 +1,12 @@
-use super::PublishMode;
+use super::{ConnectionInfo, PublishMode};
 
 pub enum OutstandingRequest {
     ConnectionRequest {
-        app_name: String,
+        connection_info: Box<ConnectionInfo>,
         transaction_id: f64,
+
+        // Whether the connect command was sent as an AMF3 command, which is replied to in kind
+        is_amf3: bool,
     },
 
     PublishRequested {
@@ -16,4 +19,28 @@ pub enum OutstandingRequest {
         stream_key: String,
         stream_id: u32,
     },
+
+    PauseRequested {
+        stream_key: String,
+        stream_id: u32,
+        paused: bool,
+    },
+
+    SeekRequested {
+        stream_key: String,
+        stream_id: u32,
+        position_ms: u32,
+    },
+
+    ReceiveAudioRequested {
+        stream_key: String,
+        stream_id: u32,
+        enabled: bool,
+    },
+
+    ReceiveVideoRequested {
+        stream_key: String,
+        stream_id: u32,
+        enabled: bool,
+    },
 }
@@ -0,0 +1,20 @@
+use ::sessions::CallHandle;
+use ::time::RtmpTimestamp;
+
+/// A command sent to the client that is expecting a `_result` or `_error` response
+pub enum OutstandingTransaction {
+    /// A call made via `ServerSession::call()`
+    Call {
+        handle: CallHandle,
+    },
+
+    /// One round of the bandwidth check, along with the measurements from prior rounds
+    BandwidthCheck {
+        round: usize,
+        sent_at: RtmpTimestamp,
+        packet_size: usize,
+        latency_ms: u32,
+        total_bytes: usize,
+        total_time_ms: u32,
+    },
+}
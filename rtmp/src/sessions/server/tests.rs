@@ -2,8 +2,10 @@ use super::*;
 use std::collections::HashMap;
 use bytes::BytesMut;
 use rml_amf0::Amf0Value;
+use rml_amf3::Amf3Value;
 use ::messages::{RtmpMessage, PeerBandwidthLimitType, UserControlEventType, MessagePayload};
-use ::chunk_io::{ChunkDeserializer};
+use ::chunk_io::{ChunkDeserializer, ChunkDeserializationErrorKind, ChunkDeserializerLimits};
+use ::sessions::{FOURCC_CAN_FORWARD, ManualClock};
 
 const DEFAULT_CHUNK_SIZE: u32 = 1111;
 const DEFAULT_PEER_BANDWIDTH: u32 = 2222;
@@ -47,13 +49,13 @@ fn can_accept_connection_request() {
 
     let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 0.0);
     let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
-    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
     assert_eq!(connect_results.len(), 1, "Unexpected number of responses when handling connect request message");
 
     let (_, events) = split_results(&mut deserializer, connect_results);
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
     let request_id = match events[0] {
-        ServerSessionEvent::ConnectionRequested {ref app_name, request_id} if app_name == "some_app" => request_id,
+        ServerSessionEvent::ConnectionRequested {ref app_name, request_id, ..} if app_name == "some_app" => request_id,
         _ => panic!("First event was not as expected: {:?}", events[0]),
     };
 
@@ -97,18 +99,78 @@ fn connect_request_strips_trailing_slash() {
 
     let connect_payload = create_connect_message("some_app/".to_string(), 15, 0, 0.0);
     let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
-    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
     assert_eq!(connect_results.len(), 1, "Unexpected number of responses when handling connect request message");
 
     let (_, events) = split_results(&mut deserializer, connect_results);
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
     match events[0] {
-        ServerSessionEvent::ConnectionRequested {ref app_name, request_id: _} => assert_eq!(app_name, "some_app", "Unexpected app name"),
+        ServerSessionEvent::ConnectionRequested {ref app_name, ..} => assert_eq!(app_name, "some_app", "Unexpected app name"),
         _ => panic!("First event was not as expected: {:?}", events[0]),
     };
 
 }
 
+#[test]
+fn connection_request_contains_all_connect_properties() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let mut properties = HashMap::new();
+    properties.insert("app".to_string(), Amf0Value::Utf8String("some_app/".to_string()));
+    properties.insert("tcUrl".to_string(), Amf0Value::Utf8String("rtmp://localhost/some_app".to_string()));
+    properties.insert("swfUrl".to_string(), Amf0Value::Utf8String("http://localhost/player.swf".to_string()));
+    properties.insert("pageUrl".to_string(), Amf0Value::Utf8String("http://localhost/index.html".to_string()));
+    properties.insert("flashVer".to_string(), Amf0Value::Utf8String("LNX 9,0,124,2".to_string()));
+    properties.insert("audioCodecs".to_string(), Amf0Value::Number(3191.0));
+    properties.insert("videoCodecs".to_string(), Amf0Value::Number(252.0));
+    properties.insert("capabilities".to_string(), Amf0Value::Number(15.0));
+    properties.insert("objectEncoding".to_string(), Amf0Value::Number(3.0));
+    properties.insert("fpad".to_string(), Amf0Value::Boolean(false));
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "connect".to_string(),
+        transaction_id: 1.0,
+        command_object: Amf0Value::Object(properties.clone()),
+        additional_arguments: vec![Amf0Value::Utf8String("extra".to_string())],
+    };
+
+    let connect_payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
+
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    assert_eq!(events.len(), 1, "Unexpected number of events returned");
+    let (request_id, connection_info) = match events[0] {
+        ServerSessionEvent::ConnectionRequested {request_id, ref connection_info, ..} => (request_id, (**connection_info).clone()),
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    assert_eq!(connection_info, ConnectionInfo {
+        app_name: "some_app".to_string(),
+        tc_url: Some("rtmp://localhost/some_app".to_string()),
+        swf_url: Some("http://localhost/player.swf".to_string()),
+        page_url: Some("http://localhost/index.html".to_string()),
+        flash_version: Some("LNX 9,0,124,2".to_string()),
+        audio_codecs: Some(3191.0),
+        video_codecs: Some(252.0),
+        capabilities: Some(15.0),
+        object_encoding: 3.0,
+        command_object: Amf0Value::Object(properties),
+        additional_arguments: vec![Amf0Value::Utf8String("extra".to_string())],
+    });
+
+    assert_eq!(session.get_connection_info(), None, "Connection info available before accepting");
+
+    let accept_results = session.accept_request(request_id).unwrap();
+    consume_results(&mut deserializer, accept_results);
+
+    assert_eq!(session.get_connection_info(), Some(&connection_info), "Unexpected connection info after accepting");
+}
+
 #[test]
 fn accepted_connection_responds_with_same_object_encoding_value_as_connection_request() {
     let config = get_basic_config();
@@ -119,13 +181,13 @@ fn accepted_connection_responds_with_same_object_encoding_value_as_connection_re
 
     let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 3.0);
     let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
-    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
     assert_eq!(connect_results.len(), 1, "Unexpected number of responses when handling connect request message");
 
     let (_, events) = split_results(&mut deserializer, connect_results);
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
     let request_id = match events[0] {
-        ServerSessionEvent::ConnectionRequested {ref app_name, request_id} if app_name == "some_app" => request_id,
+        ServerSessionEvent::ConnectionRequested {ref app_name, request_id, ..} if app_name == "some_app" => request_id,
         _ => panic!("First event was not as expected: {:?}", events[0]),
     };
 
@@ -177,7 +239,7 @@ fn can_create_stream_on_connected_session() {
 
     let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let packet = serializer.serialize(&payload, true, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses returned");
@@ -219,7 +281,7 @@ fn can_accept_live_publishing_to_requested_stream_key() {
 
     let publish_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
     let publish_packet = serializer.serialize(&publish_payload, false, false).unwrap();
-    let publish_results = session.handle_input(&publish_packet.bytes[..]).unwrap();
+    let publish_results = session.handle_input(Bytes::from(publish_packet.bytes)).unwrap();
     let (_, events) = split_results(&mut deserializer, publish_results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -317,7 +379,7 @@ fn can_receive_and_raise_event_for_metadata_from_obs() {
 
     let metadata_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
     let metadata_packet = serializer.serialize(&metadata_payload, false, false).unwrap();
-    let metadata_results = session.handle_input(&metadata_packet.bytes[..]).unwrap();
+    let metadata_results = session.handle_input(Bytes::from(metadata_packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, metadata_results);
 
     assert_eq!(events.len(), 1, "Unexpected number of metadata events");
@@ -343,6 +405,47 @@ fn can_receive_and_raise_event_for_metadata_from_obs() {
     }
 }
 
+#[test]
+fn can_receive_and_raise_event_for_metadata_sent_as_ecma_array() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let message = RtmpMessage::Amf0Data{
+        values: vec![
+            Amf0Value::Utf8String("@setDataFrame".to_string()),
+            Amf0Value::Utf8String("onMetaData".to_string()),
+            Amf0Value::EcmaArray {
+                associative_count: 2,
+                properties: vec![
+                    ("width".to_string(), Amf0Value::Number(1280_f64)),
+                    ("height".to_string(), Amf0Value::Number(720_f64)),
+                ],
+            },
+        ]
+    };
+
+    let metadata_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
+    let metadata_packet = serializer.serialize(&metadata_payload, false, false).unwrap();
+    let metadata_results = session.handle_input(Bytes::from(metadata_packet.bytes)).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, metadata_results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of metadata events");
+    match events.remove(0) {
+        ServerSessionEvent::StreamMetadataChanged {metadata, ..} => {
+            assert_eq!(metadata.video_width, Some(1280), "Unexpected video width");
+            assert_eq!(metadata.video_height, Some(720), "Unexepcted video height");
+        },
+
+        x => panic!("Unexpected event received: {:?}", x),
+    }
+}
+
 #[test]
 fn can_receive_audio_data_on_published_stream() {
     let config = get_basic_config();
@@ -360,17 +463,18 @@ fn can_receive_audio_data_on_published_stream() {
     let message = RtmpMessage::AudioData {data: Bytes::from(vec![1_u8, 2_u8, 3_u8])};
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
 
     match events.remove(0) {
-        ServerSessionEvent::AudioDataReceived {app_name, stream_key, data, timestamp} => {
+        ServerSessionEvent::AudioDataReceived {app_name, stream_key, data, timestamp, track_id} => {
             assert_eq!(app_name, test_app_name, "Unexpected app name");
             assert_eq!(stream_key, test_stream_key, "Unexpected stream key");
             assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexepcted timestamp");
             assert_eq!(&data[..], &[1_u8, 2_u8, 3_u8], "Unexpected data");
+            assert_eq!(track_id, 0, "Unexpected track id");
         },
 
         event => panic!("Expected AudioDataReceived event, instead got: {:?}", event),
@@ -394,17 +498,217 @@ fn can_receive_video_data_on_published_stream() {
     let message = RtmpMessage::VideoData {data: Bytes::from(vec![1_u8, 2_u8, 3_u8])};
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
 
     match events.remove(0) {
-        ServerSessionEvent::VideoDataReceived {app_name, stream_key, data, timestamp} => {
+        ServerSessionEvent::VideoDataReceived {app_name, stream_key, data, timestamp, track_id} => {
             assert_eq!(app_name, test_app_name, "Unexpected app name");
             assert_eq!(stream_key, test_stream_key, "Unexpected stream key");
             assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
             assert_eq!(&data[..], &[1_u8, 2_u8, 3_u8], "Unexpected data");
+            assert_eq!(track_id, 0, "Unexpected track id");
+        },
+
+        event => panic!("Expected AudioDataReceived event, instead got: {:?}", event),
+    }
+}
+
+#[test]
+fn multitrack_video_raised_as_is_by_default() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let video_data = Bytes::from(vec![
+        0x96, 0x13, b'a', b'v', b'0', b'1',
+        0x00, 0x00, 0x00, 0x01, 0xaa,
+        0x05, 0x00, 0x00, 0x02, 0xbb, 0xcc,
+    ]);
+    let message = RtmpMessage::VideoData {data: video_data.clone()};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events returned");
+    match events[0] {
+        ServerSessionEvent::VideoDataReceived {ref data, track_id, ..} => {
+            assert_eq!(data, &video_data, "Unexpected data");
+            assert_eq!(track_id, 0, "Unexpected track id");
+        },
+
+        ref event => panic!("Expected VideoDataReceived event, instead got: {:?}", event),
+    }
+}
+
+#[test]
+fn multitrack_video_raises_event_for_each_track_when_demuxing() {
+    let mut config = get_basic_config();
+    config.demux_multitrack_media = true;
+    let test_app_name = "some_app".to_string();
+    let test_stream_key = "stream_key".to_string();
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection(test_app_name.as_ref(), &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing(test_stream_key.as_ref(), stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let video_data = Bytes::from(vec![
+        0x96, 0x13, b'a', b'v', b'0', b'1',
+        0x00, 0x00, 0x00, 0x01, 0xaa,
+        0x05, 0x00, 0x00, 0x02, 0xbb, 0xcc,
+    ]);
+    let message = RtmpMessage::VideoData {data: video_data};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 2, "Unexpected number of events returned");
+
+    match events.remove(0) {
+        ServerSessionEvent::VideoDataReceived {data, timestamp, track_id, ..} => {
+            assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
+            assert_eq!(&data[..], &[0x93, b'a', b'v', b'0', b'1', 0xaa], "Unexpected data");
+            assert_eq!(track_id, 0, "Unexpected track id");
+        },
+
+        event => panic!("Expected VideoDataReceived event, instead got: {:?}", event),
+    }
+
+    match events.remove(0) {
+        ServerSessionEvent::VideoDataReceived {data, timestamp, track_id, ..} => {
+            assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
+            assert_eq!(&data[..], &[0x93, b'a', b'v', b'0', b'1', 0xbb, 0xcc], "Unexpected data");
+            assert_eq!(track_id, 5, "Unexpected track id");
+        },
+
+        event => panic!("Expected VideoDataReceived event, instead got: {:?}", event),
+    }
+}
+
+#[test]
+fn aborted_video_message_is_discarded() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    // Only send the first chunk of a message that spans multiple chunks
+    let message = RtmpMessage::VideoData {data: Bytes::from(vec![9_u8; 200])};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes[..140].to_vec())).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+    assert_eq!(events.len(), 0, "Unexpected number of events returned");
+
+    let abort_packet = serializer.abort_message(9, RtmpTimestamp::new(1234)).unwrap();
+    let results = session.handle_input(Bytes::from(abort_packet.bytes)).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let message = RtmpMessage::VideoData {data: Bytes::from(vec![1_u8, 2_u8, 3_u8])};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1300), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events returned");
+    match events.remove(0) {
+        ServerSessionEvent::VideoDataReceived {data, timestamp, ..} => {
+            assert_eq!(timestamp, RtmpTimestamp::new(1300), "Unexpected timestamp");
+            assert_eq!(&data[..], &[1_u8, 2_u8, 3_u8], "Unexpected data");
+        },
+
+        event => panic!("Expected VideoDataReceived event, instead got: {:?}", event),
+    }
+}
+
+#[test]
+fn video_data_larger_than_configured_message_size_limit_is_rejected() {
+    let mut config = get_basic_config();
+    config.deserializer_limits.max_message_size = 1000;
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let message = RtmpMessage::VideoData {data: Bytes::from(vec![1_u8; 1001])};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let error = session.handle_input(Bytes::from(packet.bytes)).unwrap_err();
+
+    match error.kind {
+        ServerSessionErrorKind::ChunkDeserializationError(ref inner) => match inner.kind {
+            ChunkDeserializationErrorKind::MessageTooLarge {message_length: 1001, max_message_size: 1000} => (),
+            ref x => panic!("Unexpected chunk deserialization error kind: {:?}", x),
+        },
+
+        x => panic!("Unexpected error kind: {:?}", x),
+    }
+}
+
+#[test]
+fn can_receive_aggregate_data_on_published_stream() {
+    let config = get_basic_config();
+    let test_app_name = "some_app".to_string();
+    let test_stream_key = "stream_key".to_string();
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection(test_app_name.as_ref(), &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing(test_stream_key.as_ref(), stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let aggregate_data = Bytes::from(vec![
+        9, 0, 0, 3, 0, 0, 10, 0, 0, 0, 0, 1, 2, 3, 0, 0, 0, 14,
+        8, 0, 0, 2, 0, 0, 30, 0, 0, 0, 0, 4, 5, 0, 0, 0, 13,
+    ]);
+    let message = RtmpMessage::Aggregate {data: aggregate_data};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 2, "Unexpected number of events returned");
+
+    match events.remove(0) {
+        ServerSessionEvent::VideoDataReceived {app_name, stream_key, data, timestamp, track_id: _} => {
+            assert_eq!(app_name, test_app_name, "Unexpected app name");
+            assert_eq!(stream_key, test_stream_key, "Unexpected stream key");
+            assert_eq!(timestamp, RtmpTimestamp::new(1234), "Unexpected timestamp");
+            assert_eq!(&data[..], &[1, 2, 3], "Unexpected data");
+        },
+
+        event => panic!("Expected VideoDataReceived event, instead got: {:?}", event),
+    }
+
+    match events.remove(0) {
+        ServerSessionEvent::AudioDataReceived {app_name, stream_key, data, timestamp, track_id: _} => {
+            assert_eq!(app_name, test_app_name, "Unexpected app name");
+            assert_eq!(stream_key, test_stream_key, "Unexpected stream key");
+            assert_eq!(timestamp, RtmpTimestamp::new(1254), "Unexpected timestamp");
+            assert_eq!(&data[..], &[4, 5], "Unexpected data");
         },
 
         event => panic!("Expected AudioDataReceived event, instead got: {:?}", event),
@@ -434,7 +738,7 @@ fn publish_finished_event_raised_when_delete_stream_invoked_on_publishing_stream
 
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -472,7 +776,7 @@ fn publish_finished_event_raised_when_close_stream_invoked_on_publishing_stream(
 
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -514,7 +818,7 @@ fn can_request_publishing_on_closed_stream() {
 
     let publish_payload = message.into_message_payload(RtmpTimestamp::new(2000), stream_id).unwrap();
     let publish_packet = serializer.serialize(&publish_payload, false, false).unwrap();
-    let publish_results = session.handle_input(&publish_packet.bytes[..]).unwrap();
+    let publish_results = session.handle_input(Bytes::from(publish_packet.bytes)).unwrap();
     let (_, events) = split_results(&mut deserializer, publish_results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -555,7 +859,7 @@ fn can_accept_play_command_with_no_optional_parameters_to_requested_stream_key()
 
     let play_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
     let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
-    let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
+    let play_results = session.handle_input(Bytes::from(play_packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, play_results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -685,7 +989,7 @@ fn can_accept_play_command_with_all_optional_parameters_to_requested_stream_key(
 
     let play_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
     let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
-    let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
+    let play_results = session.handle_input(Bytes::from(play_packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, play_results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -731,7 +1035,7 @@ fn play_finished_event_when_close_stream_invoked() {
 
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -770,7 +1074,7 @@ fn play_finished_event_when_delete_stream_invoked_on_playing_stream() {
 
     let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -929,7 +1233,7 @@ fn automatically_responds_to_ping_requests() {
 
     let payload = message.into_message_payload(RtmpTimestamp::new(6000), 0).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Expected one response for handling ping request");
@@ -963,7 +1267,7 @@ fn event_raised_when_ping_response_received() {
 
     let payload = message.into_message_payload(RtmpTimestamp::new(6000), 0).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "One event expected");
@@ -1001,6 +1305,30 @@ fn can_send_ping_request() {
     }
 }
 
+#[test]
+fn outbound_timestamps_come_from_configured_clock() {
+    let clock = ManualClock::new(5_000);
+    let mut config = get_basic_config();
+    config.clock = Arc::new(clock.clone());
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    clock.advance(1_234);
+    let (packet, sent_timestamp) = session.send_ping_request().unwrap();
+    let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+    assert_eq!(sent_timestamp, RtmpTimestamp::new(1_234), "Unexpected ping timestamp");
+    assert_eq!(payload.timestamp, RtmpTimestamp::new(1_234), "Unexpected message timestamp");
+
+    clock.advance(100);
+    let (packet, _) = session.call("test", Amf0Value::Null, Vec::new()).unwrap();
+    let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+    assert_eq!(payload.timestamp, RtmpTimestamp::new(1_334), "Unexpected command timestamp");
+}
+
 #[test]
 fn sends_ack_after_receiving_window_ack_bytes() {
     let config = get_basic_config();
@@ -1015,7 +1343,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let window_ack_message = RtmpMessage::WindowAcknowledgement {size: 100};
     let window_ack_payload = window_ack_message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let window_ack_packet = serializer.serialize(&window_ack_payload, false, false).unwrap();
-    let results = session.handle_input(&window_ack_packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(window_ack_packet.bytes)).unwrap();
     consume_results(&mut deserializer, results);
 
     let mut bytes = BytesMut::new();
@@ -1023,7 +1351,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_message = RtmpMessage::VideoData {data: bytes.freeze()};
     let video_payload = video_message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
-    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(video_packet.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
@@ -1037,7 +1365,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_message = RtmpMessage::VideoData {data: bytes.freeze()};
     let video_payload = video_message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
-    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(video_packet.bytes)).unwrap();
     let (responses, _) = split_results(&mut deserializer, results);
     assert_eq!(responses.len(), 0, "Expected no responses");
 
@@ -1046,7 +1374,7 @@ fn sends_ack_after_receiving_window_ack_bytes() {
     let video_message = RtmpMessage::VideoData {data: bytes.freeze()};
     let video_payload = video_message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let video_packet = serializer.serialize(&video_payload, false, false).unwrap();
-    let results = session.handle_input(&video_packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(video_packet.bytes)).unwrap();
     let (mut responses, _) = split_results(&mut deserializer, results);
     assert_eq!(responses.len(), 1, "Unexpected number of responses");
     match responses.remove(0) {
@@ -1069,7 +1397,7 @@ fn event_raised_when_client_sends_an_acknowledgement() {
     let message = RtmpMessage::Acknowledgement {sequence_number: 1234};
     let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (_, mut events) = split_results(&mut deserializer, results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events");
@@ -1082,47 +1410,1228 @@ fn event_raised_when_client_sends_an_acknowledgement() {
     }
 }
 
-fn get_basic_config() -> ServerSessionConfig {
-    ServerSessionConfig {
-        chunk_size: DEFAULT_CHUNK_SIZE,
-        fms_version: "fms_version".to_string(),
-        peer_bandwidth: DEFAULT_PEER_BANDWIDTH,
-        window_ack_size: DEFAULT_WINDOW_ACK_SIZE,
-    }
-}
+#[test]
+fn can_reject_connection_request() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
 
-fn split_results(deserializer: &mut ChunkDeserializer, mut results: Vec<ServerSessionResult>) -> (Vec<(MessagePayload, RtmpMessage)>, Vec<ServerSessionEvent>) {
-    let mut responses = Vec::new();
-    let mut events = Vec::new();
+    let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 0.0);
+    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested {ref app_name, request_id, ..} if app_name == "some_app" => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
 
-    for result in results.drain(..) {
-        match result {
-            ServerSessionResult::OutboundResponse(packet) => {
-                let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
-                let message = payload.to_rtmp_message().unwrap();
-                match message {
-                    RtmpMessage::SetChunkSize{size} => deserializer.set_max_chunk_size(size as usize).unwrap(),
-                    _ => (),
-                }
+    let reject_results = session.reject_request(request_id, "NetConnection.Connect.Rejected", "Not allowed").unwrap();
+    let (responses, _) = split_results(&mut deserializer, reject_results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses returned");
 
-                println!("response received from server: {:?}", message);
-                responses.push((payload, message));
-            },
+    match responses[0] {
+        (_, RtmpMessage::Amf0Command {
+            ref command_name,
+            transaction_id,
+            command_object: Amf0Value::Null,
+            ref additional_arguments
+        }) if command_name == "_error" => {
+            assert_eq!(transaction_id, 1.0, "Unexpected transaction id");
+            assert_eq!(additional_arguments.len(), 1, "Unexpected number of additional arguments");
+            match additional_arguments[0] {
+                Amf0Value::Object(ref properties) => {
+                    assert_eq!(properties.get("level"), Some(&Amf0Value::Utf8String("error".to_string())), "Unexpected level value");
+                    assert_eq!(properties.get("code"), Some(&Amf0Value::Utf8String("NetConnection.Connect.Rejected".to_string())), "Unexpected code value");
+                    assert_eq!(properties.get("description"), Some(&Amf0Value::Utf8String("Not allowed".to_string())), "Unexpected description value");
+                },
 
-            ServerSessionResult::RaisedEvent(event) => {
-                println!("event received from server: {:?}", event);
-                events.push(event);
-            },
+                _ => panic!("Additional arguments was not an Amf0 object: {:?}", additional_arguments[0]),
+            }
+        },
 
-            _ => (),
-        }
+        _ => panic!("Unexpected first response message: {:?}", responses[0]),
     }
 
-    (responses, events)
+    match session.accept_request(request_id) {
+        Err(ServerSessionError {kind: ServerSessionErrorKind::InvalidRequestId}) => (),
+        x => panic!("Expected invalid request id error, instead received: {:?}", x),
+    }
 }
 
-fn consume_results(deserializer: &mut ChunkDeserializer, results: Vec<ServerSessionResult>) {
-    // Needed to keep the deserializer up to date
+#[test]
+fn amf3_connect_with_avmplus_command_object_is_accepted_with_amf3_response() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let connect_payload = create_amf3_connect_message("some_app/");
+    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested {ref app_name, request_id, ref connection_info}
+            if app_name == "some_app" && connection_info.object_encoding == 3.0 => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    let accept_results = session.accept_request(request_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, accept_results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses returned");
+
+    match responses[0] {
+        (ref payload, RtmpMessage::Amf3Command {ref command_name, transaction_id, ..}) if command_name == "_result" => {
+            assert_eq!(payload.type_id, 17, "Unexpected message type id");
+            assert_eq!(transaction_id, 1.0, "Unexpected transaction id");
+        },
+
+        _ => panic!("Unexpected first response message: {:?}", responses[0]),
+    }
+}
+
+#[test]
+fn rejected_amf3_connect_is_answered_with_amf3_error() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let connect_payload = create_amf3_connect_message("some_app");
+    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested {request_id, ..} => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    let reject_results = session.reject_request(request_id, "NetConnection.Connect.Rejected", "Not allowed").unwrap();
+    let (responses, _) = split_results(&mut deserializer, reject_results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses returned");
+
+    match responses[0] {
+        (ref payload, RtmpMessage::Amf3Command {ref command_name, ..}) if command_name == "_error" => {
+            assert_eq!(payload.type_id, 17, "Unexpected message type id");
+        },
+
+        _ => panic!("Unexpected first response message: {:?}", responses[0]),
+    }
+}
+
+#[test]
+fn can_connect_after_rejected_connection_request() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 0.0);
+    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested {request_id, ..} => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    let reject_results = session.reject_request(request_id, "NetConnection.Connect.Rejected", "Not allowed").unwrap();
+    consume_results(&mut deserializer, reject_results);
+
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+}
+
+#[test]
+fn can_reject_publish_request() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    let message = RtmpMessage::Amf0Command {
+        command_name: "publish".to_string(),
+        transaction_id: 5.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![
+            Amf0Value::Utf8String("stream_key".to_string()),
+            Amf0Value::Utf8String("live".to_string()),
+        ]
+    };
+
+    let publish_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
+    let publish_packet = serializer.serialize(&publish_payload, false, false).unwrap();
+    let publish_results = session.handle_input(Bytes::from(publish_packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, publish_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::PublishStreamRequested {request_id, ..} => request_id,
+        _ => panic!("Unexpected first event found: {:?}", events[0]),
+    };
+
+    let reject_results = session.reject_request(request_id, "NetStream.Publish.BadName", "Stream key in use").unwrap();
+    let (responses, _) = split_results(&mut deserializer, reject_results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses returned");
+
+    match responses[0] {
+        (ref payload, RtmpMessage::Amf0Command {
+            ref command_name,
+            transaction_id: _,
+            command_object: Amf0Value::Null,
+            ref additional_arguments
+        }) if command_name == "onStatus" => {
+            assert_eq!(payload.message_stream_id, stream_id, "Unexpected message stream id");
+            assert_eq!(additional_arguments.len(), 1, "Unexpected number of additional arguments");
+            match additional_arguments[0] {
+                Amf0Value::Object(ref properties) => {
+                    assert_eq!(properties.get("level"), Some(&Amf0Value::Utf8String("error".to_string())), "Unexpected level value");
+                    assert_eq!(properties.get("code"), Some(&Amf0Value::Utf8String("NetStream.Publish.BadName".to_string())), "Unexpected code value");
+                    assert_eq!(properties.get("description"), Some(&Amf0Value::Utf8String("Stream key in use".to_string())), "Unexpected description value");
+                },
+
+                _ => panic!("Additional arguments was not an Amf0 object: {:?}", additional_arguments[0]),
+            }
+        },
+
+        _ => panic!("Unexpected first response message: {:?}", responses[0]),
+    }
+
+    // Stream should still be usable for another publish attempt
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+}
+
+#[test]
+fn can_reject_play_request() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    let message = RtmpMessage::Amf0Command {
+        command_name: "play".to_string(),
+        transaction_id: 4.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![Amf0Value::Utf8String("stream_key".to_string())],
+    };
+
+    let play_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
+    let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
+    let play_results = session.handle_input(Bytes::from(play_packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, play_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::PlayStreamRequested {request_id, ..} => request_id,
+        _ => panic!("Unexpected first event found: {:?}", events[0]),
+    };
+
+    let reject_results = session.reject_request(request_id, "NetStream.Play.StreamNotFound", "No such stream").unwrap();
+    let (responses, _) = split_results(&mut deserializer, reject_results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses returned");
+
+    match responses[0] {
+        (ref payload, RtmpMessage::Amf0Command {
+            ref command_name,
+            transaction_id: _,
+            command_object: Amf0Value::Null,
+            ref additional_arguments
+        }) if command_name == "onStatus" => {
+            assert_eq!(payload.message_stream_id, stream_id, "Unexpected message stream id");
+            match additional_arguments[0] {
+                Amf0Value::Object(ref properties) => {
+                    assert_eq!(properties.get("level"), Some(&Amf0Value::Utf8String("error".to_string())), "Unexpected level value");
+                    assert_eq!(properties.get("code"), Some(&Amf0Value::Utf8String("NetStream.Play.StreamNotFound".to_string())), "Unexpected code value");
+                },
+
+                _ => panic!("Additional arguments was not an Amf0 object: {:?}", additional_arguments[0]),
+            }
+        },
+
+        _ => panic!("Unexpected first response message: {:?}", responses[0]),
+    }
+}
+
+#[test]
+fn connection_accepted_with_negotiated_enhanced_rtmp_capabilities() {
+    let mut config = get_basic_config();
+    config.enhanced_rtmp.fourcc_list = vec!["hvc1".to_string(), "av01".to_string(), "vp09".to_string()];
+    config.enhanced_rtmp.video_fourcc_info_map.insert("hvc1".to_string(), FOURCC_CAN_FORWARD);
+    config.enhanced_rtmp.video_fourcc_info_map.insert("vp09".to_string(), FOURCC_CAN_FORWARD);
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let mut properties = HashMap::new();
+    properties.insert("app".to_string(), Amf0Value::Utf8String("some_app".to_string()));
+    properties.insert("fourCcList".to_string(), Amf0Value::StrictArray(vec![
+        Amf0Value::Utf8String("av01".to_string()),
+        Amf0Value::Utf8String("hvc1".to_string()),
+    ]));
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "connect".to_string(),
+        transaction_id: 1.0,
+        command_object: Amf0Value::Object(properties),
+        additional_arguments: vec![]
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    let packet = serializer.serialize(&payload, true, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested {request_id, ..} => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    let accept_results = session.accept_request(request_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, accept_results);
+    match responses[0] {
+        (_, RtmpMessage::Amf0Command {
+            ref command_name,
+            command_object: Amf0Value::Object(ref properties),
+            ..
+        }) if command_name == "_result" => {
+            let expected_list = Amf0Value::StrictArray(vec![
+                Amf0Value::Utf8String("hvc1".to_string()),
+                Amf0Value::Utf8String("av01".to_string()),
+            ]);
+
+            let mut expected_map = HashMap::new();
+            expected_map.insert("hvc1".to_string(), Amf0Value::Number(4.0));
+
+            assert_eq!(properties.get("fourCcList"), Some(&expected_list), "Unexpected fourCcList");
+            assert_eq!(properties.get("videoFourCcInfoMap"), Some(&Amf0Value::Object(expected_map)), "Unexpected videoFourCcInfoMap");
+        },
+
+        _ => panic!("Unexpected first response message: {:?}", responses[0]),
+    }
+
+    let capabilities = session.get_enhanced_rtmp_capabilities().unwrap();
+    assert!(capabilities.supports("hvc1"), "Expected hvc1 to be negotiated");
+    assert!(capabilities.supports("av01"), "Expected av01 to be negotiated");
+    assert!(!capabilities.supports("vp09"), "Expected vp09 to not be negotiated");
+}
+
+#[test]
+fn no_enhanced_rtmp_capabilities_sent_when_client_does_not_advertise_them() {
+    let mut config = get_basic_config();
+    config.enhanced_rtmp.fourcc_list = vec!["hvc1".to_string()];
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let connect_payload = create_connect_message("some_app".to_string(), 15, 0, 0.0);
+    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested {request_id, ..} => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    let accept_results = session.accept_request(request_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, accept_results);
+    match responses[0] {
+        (_, RtmpMessage::Amf0Command {command_object: Amf0Value::Object(ref properties), ..}) => {
+            assert!(!properties.contains_key("fourCcList"), "Unexpected fourCcList");
+        },
+
+        _ => panic!("Unexpected first response message: {:?}", responses[0]),
+    }
+
+    assert_eq!(session.get_enhanced_rtmp_capabilities(), None, "Expected no capabilities");
+}
+
+#[test]
+fn numeric_and_fourcc_codec_ids_in_metadata_are_converted_to_strings() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let mut properties = HashMap::new();
+    properties.insert("videocodecid".to_string(), Amf0Value::Number(0x6876_6331 as f64));
+    properties.insert("audiocodecid".to_string(), Amf0Value::Number(10.0));
+
+    let message = RtmpMessage::Amf0Data{
+        values: vec![
+            Amf0Value::Utf8String("@setDataFrame".to_string()),
+            Amf0Value::Utf8String("onMetaData".to_string()),
+            Amf0Value::Object(properties),
+        ]
+    };
+
+    let metadata_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
+    let metadata_packet = serializer.serialize(&metadata_payload, false, false).unwrap();
+    let metadata_results = session.handle_input(Bytes::from(metadata_packet.bytes)).unwrap();
+    let (_, mut events) = split_results(&mut deserializer, metadata_results);
+
+    match events.remove(0) {
+        ServerSessionEvent::StreamMetadataChanged {metadata, ..} => {
+            assert_eq!(metadata.video_codec, Some("hvc1".to_string()), "Unexpected video codec");
+            assert_eq!(metadata.audio_codec, Some("10".to_string()), "Unexpected audio codec");
+        },
+
+        event => panic!("Expected StreamMetadataChanged event, instead got: {:?}", event),
+    }
+}
+
+#[test]
+fn release_stream_replies_with_result_and_raises_event() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let (responses, events) = send_stream_key_command("releaseStream", 2.0, "key", &mut session, &mut serializer, &mut deserializer);
+
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses[0] {
+        (_, RtmpMessage::Amf0Command {ref command_name, transaction_id, ref additional_arguments, ..}) => {
+            assert_eq!(command_name, "_result", "Unexpected command name");
+            assert_eq!(transaction_id, 2.0, "Unexpected transaction id");
+            assert_eq!(additional_arguments, &vec![Amf0Value::Undefined], "Unexpected arguments");
+        },
+
+        _ => panic!("Unexpected response: {:?}", responses[0]),
+    }
+
+    assert_eq!(events, vec![ServerSessionEvent::ReleaseStreamRequested {
+        app_name: "some_app".to_string(),
+        stream_key: "key".to_string(),
+    }]);
+}
+
+#[test]
+fn fc_publish_replies_with_result_and_on_fc_publish() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let (responses, events) = send_stream_key_command("FCPublish", 3.0, "key", &mut session, &mut serializer, &mut deserializer);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of responses");
+    assert_vec_contains!(responses, &(_, RtmpMessage::Amf0Command {ref command_name, transaction_id, ..}) if command_name == "_result" && transaction_id == 3.0);
+    assert_status_command(&responses[1].1, "onFCPublish", "NetStream.Publish.Start", "key");
+    assert_eq!(events, vec![ServerSessionEvent::FcPublishRequested {
+        app_name: "some_app".to_string(),
+        stream_key: "key".to_string(),
+    }]);
+}
+
+#[test]
+fn fc_unpublish_replies_with_result_and_on_fc_unpublish() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let (responses, events) = send_stream_key_command("FCUnpublish", 6.0, "key", &mut session, &mut serializer, &mut deserializer);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of responses");
+    assert_vec_contains!(responses, &(_, RtmpMessage::Amf0Command {ref command_name, transaction_id, ..}) if command_name == "_result" && transaction_id == 6.0);
+    assert_status_command(&responses[1].1, "onFCUnpublish", "NetStream.Unpublish.Success", "key");
+    assert_eq!(events, vec![ServerSessionEvent::FcUnpublishRequested {
+        app_name: "some_app".to_string(),
+        stream_key: "key".to_string(),
+    }]);
+}
+
+#[test]
+fn fc_subscribe_replies_with_on_fc_subscribe() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    // Transaction id of 0 means the client does not expect a `_result`
+    let (responses, events) = send_stream_key_command("FCSubscribe", 0.0, "key", &mut session, &mut serializer, &mut deserializer);
+
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    assert_status_command(&responses[0].1, "onFCSubscribe", "NetStream.Play.Start", "key");
+    assert_eq!(events, vec![ServerSessionEvent::FcSubscribeRequested {
+        app_name: "some_app".to_string(),
+        stream_key: "key".to_string(),
+    }]);
+}
+
+#[test]
+fn fc_publish_ignored_before_connection() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+
+    let (responses, events) = send_stream_key_command("FCPublish", 3.0, "key", &mut session, &mut serializer, &mut deserializer);
+
+    assert_eq!(responses.len(), 0, "Expected no responses");
+    assert_eq!(events.len(), 0, "Expected no events");
+}
+
+#[test]
+fn can_accept_pause_request_on_playing_stream() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let arguments = vec![Amf0Value::Boolean(true), Amf0Value::Number(1500.0)];
+    let (_, events) = send_playback_command("pause", arguments, stream_id, &mut session, &mut serializer, &mut deserializer);
+    assert_eq!(events.len(), 1, "Unexpected number of events");
+    let request_id = match events[0] {
+        ServerSessionEvent::PauseRequested {request_id, ref app_name, ref stream_key, paused, position_ms, stream_id: event_stream_id} => {
+            assert_eq!(app_name, "some_app", "Unexpected app name");
+            assert_eq!(stream_key, "key", "Unexpected stream key");
+            assert!(paused, "Expected pause flag to be set");
+            assert_eq!(position_ms, 1500, "Unexpected position");
+            assert_eq!(event_stream_id, stream_id, "Unexpected stream id");
+            request_id
+        },
+
+        ref x => panic!("Expected pause requested event, instead received: {:?}", x),
+    };
+
+    let results = session.accept_request(request_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of responses");
+    match responses[0].1 {
+        RtmpMessage::UserControl {event_type: UserControlEventType::StreamEof, stream_id: Some(id), ..} => assert_eq!(id, stream_id, "Unexpected stream id"),
+        ref x => panic!("Expected stream EOF user control, instead received: {:?}", x),
+    }
+
+    assert_eq!(responses[1].0.message_stream_id, stream_id, "Unexpected message stream id");
+    assert_status_command(&responses[1].1, "onStatus", "NetStream.Pause.Notify", "Paused stream key key");
+}
+
+#[test]
+fn can_accept_unpause_request_on_playing_stream() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let arguments = vec![Amf0Value::Boolean(false), Amf0Value::Number(1500.0)];
+    let (_, events) = send_playback_command("pause", arguments, stream_id, &mut session, &mut serializer, &mut deserializer);
+    let request_id = match events[0] {
+        ServerSessionEvent::PauseRequested {request_id, paused: false, ..} => request_id,
+        ref x => panic!("Expected unpause requested event, instead received: {:?}", x),
+    };
+
+    let results = session.accept_request(request_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of responses");
+    match responses[0].1 {
+        RtmpMessage::UserControl {event_type: UserControlEventType::StreamBegin, stream_id: Some(id), ..} => assert_eq!(id, stream_id, "Unexpected stream id"),
+        ref x => panic!("Expected stream begin user control, instead received: {:?}", x),
+    }
+
+    assert_status_command(&responses[1].1, "onStatus", "NetStream.Unpause.Notify", "Unpaused stream key key");
+}
+
+#[test]
+fn can_accept_seek_request_on_playing_stream() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let (_, events) = send_playback_command("seek", vec![Amf0Value::Number(3000.0)], stream_id, &mut session, &mut serializer, &mut deserializer);
+    assert_eq!(events.len(), 1, "Unexpected number of events");
+    let request_id = match events[0] {
+        ServerSessionEvent::SeekRequested {request_id, position_ms: 3000, ref stream_key, ..} if stream_key == "key" => request_id,
+        ref x => panic!("Expected seek requested event, instead received: {:?}", x),
+    };
+
+    let results = session.accept_request(request_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 3, "Unexpected number of responses");
+    assert_status_command(&responses[0].1, "onStatus", "NetStream.Seek.Notify", "Seeking to 3000 ms on stream key key");
+    match responses[1].1 {
+        RtmpMessage::UserControl {event_type: UserControlEventType::StreamBegin, stream_id: Some(id), ..} => assert_eq!(id, stream_id, "Unexpected stream id"),
+        ref x => panic!("Expected stream begin user control, instead received: {:?}", x),
+    }
+
+    assert_status_command(&responses[2].1, "onStatus", "NetStream.Play.Start", "Successfully started playback on stream key key");
+}
+
+#[test]
+fn can_reject_seek_request() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let (_, events) = send_playback_command("seek", vec![Amf0Value::Number(3000.0)], stream_id, &mut session, &mut serializer, &mut deserializer);
+    let request_id = match events[0] {
+        ServerSessionEvent::SeekRequested {request_id, ..} => request_id,
+        ref x => panic!("Expected seek requested event, instead received: {:?}", x),
+    };
+
+    let results = session.reject_request(request_id, "NetStream.Seek.Failed", "Live streams can't seek").unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    assert_vec_contains!(responses, &(_, RtmpMessage::Amf0Command {ref command_name, ref additional_arguments, ..})
+        if command_name == "onStatus" && additional_arguments.len() == 1);
+}
+
+#[test]
+fn pause_on_stream_that_is_not_playing_is_rejected() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    let arguments = vec![Amf0Value::Boolean(true), Amf0Value::Number(0.0)];
+    let (responses, events) = send_playback_command("pause", arguments, stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    assert_eq!(events.len(), 0, "Expected no events");
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    match responses[0].1 {
+        RtmpMessage::Amf0Command {ref command_name, ref additional_arguments, ..} if command_name == "onStatus" => {
+            match additional_arguments[0] {
+                Amf0Value::Object(ref properties) => {
+                    assert_eq!(properties.get("level"), Some(&Amf0Value::Utf8String("error".to_string())), "Unexpected level");
+                    assert_eq!(properties.get("code"), Some(&Amf0Value::Utf8String("NetStream.Pause.Failed".to_string())), "Unexpected code");
+                },
+
+                ref x => panic!("Expected status object, instead received: {:?}", x),
+            }
+        },
+
+        ref x => panic!("Expected onStatus command, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn receive_video_and_audio_requests_raise_events() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let (_, events) = send_playback_command("receiveVideo", vec![Amf0Value::Boolean(false)], stream_id, &mut session, &mut serializer, &mut deserializer);
+    let request_id = match events[0] {
+        ServerSessionEvent::ReceiveVideoRequested {request_id, enabled: false, ..} => request_id,
+        ref x => panic!("Expected receive video requested event, instead received: {:?}", x),
+    };
+
+    let results = session.accept_request(request_id).unwrap();
+    assert_eq!(results.len(), 0, "Expected no responses when disabling video");
+
+    let (_, events) = send_playback_command("receiveAudio", vec![Amf0Value::Boolean(true)], stream_id, &mut session, &mut serializer, &mut deserializer);
+    let request_id = match events[0] {
+        ServerSessionEvent::ReceiveAudioRequested {request_id, enabled: true, ..} => request_id,
+        ref x => panic!("Expected receive audio requested event, instead received: {:?}", x),
+    };
+
+    let results = session.accept_request(request_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of responses");
+    assert_status_command(&responses[0].1, "onStatus", "NetStream.Seek.Notify", "Resuming media on stream key key");
+    assert_status_command(&responses[1].1, "onStatus", "NetStream.Play.Start", "Successfully started playback on stream key key");
+}
+
+#[test]
+fn rejecting_receive_audio_request_sends_nothing() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let (_, events) = send_playback_command("receiveAudio", vec![Amf0Value::Boolean(false)], stream_id, &mut session, &mut serializer, &mut deserializer);
+    let request_id = match events[0] {
+        ServerSessionEvent::ReceiveAudioRequested {request_id, enabled: false, ..} => request_id,
+        ref x => panic!("Expected receive audio requested event, instead received: {:?}", x),
+    };
+
+    let results = session.reject_request(request_id, "NetStream.Failed", "test").unwrap();
+    assert_eq!(results.len(), 0, "Expected no responses when rejecting receiveAudio");
+}
+
+#[test]
+fn later_pause_request_replaces_pending_pause_request() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let arguments = vec![Amf0Value::Boolean(true), Amf0Value::Number(0.0)];
+    let (_, events) = send_playback_command("pause", arguments, stream_id, &mut session, &mut serializer, &mut deserializer);
+    let first_request_id = match events[0] {
+        ServerSessionEvent::PauseRequested {request_id, ..} => request_id,
+        ref x => panic!("Expected pause requested event, instead received: {:?}", x),
+    };
+
+    let (_, events) = send_playback_command("seek", vec![Amf0Value::Number(1000.0)], stream_id, &mut session, &mut serializer, &mut deserializer);
+    let seek_request_id = match events[0] {
+        ServerSessionEvent::SeekRequested {request_id, ..} => request_id,
+        ref x => panic!("Expected seek requested event, instead received: {:?}", x),
+    };
+
+    let arguments = vec![Amf0Value::Boolean(false), Amf0Value::Number(0.0)];
+    let (_, events) = send_playback_command("pause", arguments, stream_id, &mut session, &mut serializer, &mut deserializer);
+    let second_request_id = match events[0] {
+        ServerSessionEvent::PauseRequested {request_id, paused: false, ..} => request_id,
+        ref x => panic!("Expected pause requested event, instead received: {:?}", x),
+    };
+
+    match session.accept_request(first_request_id) {
+        Err(ServerSessionError {kind: ServerSessionErrorKind::InvalidRequestId}) => (),
+        x => panic!("Expected invalid request id error, instead received: {:?}", x),
+    }
+
+    // Requests of other kinds are kept
+    session.accept_request(seek_request_id).unwrap();
+    session.accept_request(second_request_id).unwrap();
+}
+
+#[test]
+fn unanswered_playback_requests_do_not_accumulate() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    for x in 0..100 {
+        let enabled = x % 2 == 0;
+        send_playback_command("receiveAudio", vec![Amf0Value::Boolean(enabled)], stream_id, &mut session, &mut serializer, &mut deserializer);
+        send_playback_command("receiveVideo", vec![Amf0Value::Boolean(enabled)], stream_id, &mut session, &mut serializer, &mut deserializer);
+        send_playback_command("seek", vec![Amf0Value::Number(x as f64)], stream_id, &mut session, &mut serializer, &mut deserializer);
+    }
+
+    assert_eq!(session.outstanding_requests.len(), 3, "Unexpected number of outstanding requests");
+}
+
+#[test]
+fn end_playback_sends_stream_eof_and_play_stop() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let results = session.end_playback(stream_id, "Stream removed").unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of responses");
+    match responses[0].1 {
+        RtmpMessage::UserControl {event_type: UserControlEventType::StreamEof, stream_id: Some(id), ..} => assert_eq!(id, stream_id, "Unexpected stream id"),
+        ref x => panic!("Expected stream EOF user control, instead received: {:?}", x),
+    }
+
+    assert_eq!(responses[1].0.message_stream_id, stream_id, "Unexpected message stream id");
+    assert_status_command(&responses[1].1, "onStatus", "NetStream.Play.Stop", "Stream removed");
+
+    match session.end_playback(stream_id, "Stream removed") {
+        Err(ServerSessionError {kind: ServerSessionErrorKind::ActionAttemptedOnStreamInInvalidState {..}}) => (),
+        x => panic!("Expected invalid stream state error after playback ended, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn can_notify_player_of_unpublish_and_republish() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let results = session.notify_unpublished(stream_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of unpublish responses");
+    assert_status_command(&responses[0].1, "onStatus", "NetStream.Play.UnpublishNotify", "key is now unpublished");
+    match responses[1].1 {
+        RtmpMessage::UserControl {event_type: UserControlEventType::StreamEof, stream_id: Some(id), ..} => assert_eq!(id, stream_id, "Unexpected stream id"),
+        ref x => panic!("Expected stream EOF user control, instead received: {:?}", x),
+    }
+
+    let results = session.notify_publish_started(stream_id).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 2, "Unexpected number of publish responses");
+    match responses[0].1 {
+        RtmpMessage::UserControl {event_type: UserControlEventType::StreamBegin, stream_id: Some(id), ..} => assert_eq!(id, stream_id, "Unexpected stream id"),
+        ref x => panic!("Expected stream begin user control, instead received: {:?}", x),
+    }
+
+    assert_status_command(&responses[1].1, "onStatus", "NetStream.Play.PublishNotify", "key is now published");
+}
+
+#[test]
+fn kicked_publisher_media_is_ignored() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    let results = session.kick_publisher(stream_id, "Stream key revoked").unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    assert_eq!(responses[0].0.message_stream_id, stream_id, "Unexpected message stream id");
+    assert_status_command(&responses[0].1, "onStatus", "NetStream.Unpublish.Success", "Stream key revoked");
+
+    let message = RtmpMessage::AudioData {data: Bytes::from(vec![1_u8, 2_u8, 3_u8])};
+    let payload = message.into_message_payload(RtmpTimestamp::new(1234), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 0, "Expected no events for media sent after being kicked");
+}
+
+#[test]
+fn stream_termination_on_wrong_stream_type_returns_error() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, initial_results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, initial_results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_playing("key", stream_id, &mut session, &mut serializer, &mut deserializer);
+
+    match session.kick_publisher(stream_id, "reason") {
+        Err(ServerSessionError {kind: ServerSessionErrorKind::ActionAttemptedOnStreamInInvalidState {..}}) => (),
+        x => panic!("Expected invalid stream state error, instead received: {:?}", x),
+    }
+
+    match session.notify_unpublished(stream_id + 1) {
+        Err(ServerSessionError {kind: ServerSessionErrorKind::ActionAttemptedOnInactiveStream {..}}) => (),
+        x => panic!("Expected inactive stream error, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn call_result_from_client_raises_call_result_received_event() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let (packet, handle) = session.call("getStats", Amf0Value::Null, vec![Amf0Value::Number(5.0)]).unwrap();
+    let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+    let transaction_id = match payload.to_rtmp_message().unwrap() {
+        RtmpMessage::Amf0Command {command_name, transaction_id, command_object, additional_arguments} => {
+            assert_eq!(command_name, "getStats", "Unexpected command name");
+            assert_eq!(command_object, Amf0Value::Null, "Unexpected command object");
+            assert_eq!(additional_arguments, vec![Amf0Value::Number(5.0)], "Unexpected arguments");
+            assert_ne!(transaction_id, 0.0, "Expected a transaction id to be allocated");
+            transaction_id
+        },
+
+        x => panic!("Expected Amf0Command, instead received: {:?}", x),
+    };
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "_result".to_string(),
+        transaction_id,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![Amf0Value::Utf8String("stats".to_string())],
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+
+    assert_eq!(events.len(), 1, "Unexpected number of events");
+    match events[0] {
+        ServerSessionEvent::CallResultReceived {handle: ref event_handle, ref command_object, ref additional_values} => {
+            assert_eq!(*event_handle, handle, "Unexpected call handle");
+            assert_eq!(*command_object, Amf0Value::Null, "Unexpected command object");
+            assert_eq!(*additional_values, vec![Amf0Value::Utf8String("stats".to_string())], "Unexpected values");
+        },
+
+        ref x => panic!("Expected CallResultReceived event, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn call_error_from_client_raises_call_error_received_event() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let (_, first_handle) = session.call("first", Amf0Value::Null, Vec::new()).unwrap();
+    let (packet, second_handle) = session.call("second", Amf0Value::Null, Vec::new()).unwrap();
+    assert_ne!(first_handle, second_handle, "Expected each call to have its own handle");
+
+    let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+    let transaction_id = match payload.to_rtmp_message().unwrap() {
+        RtmpMessage::Amf0Command {transaction_id, ..} => transaction_id,
+        x => panic!("Expected Amf0Command, instead received: {:?}", x),
+    };
+
+    let (_, events) = send_stream_key_command("_error", transaction_id, "failed", &mut session, &mut serializer, &mut deserializer);
+    assert_eq!(events.len(), 1, "Unexpected number of events");
+    match events[0] {
+        ServerSessionEvent::CallErrorReceived {handle, ref additional_values, ..} => {
+            assert_eq!(handle, second_handle, "Unexpected call handle");
+            assert_eq!(*additional_values, vec![Amf0Value::Utf8String("failed".to_string())], "Unexpected values");
+        },
+
+        ref x => panic!("Expected CallErrorReceived event, instead received: {:?}", x),
+    }
+
+    // A second response for the same transaction is no longer tied to a call
+    let (_, events) = send_stream_key_command("_error", transaction_id, "failed", &mut session, &mut serializer, &mut deserializer);
+    assert_eq!(events.len(), 1, "Unexpected number of events");
+    match events[0] {
+        ServerSessionEvent::UnhandleableAmf0Command {ref command_name, ..} => assert_eq!(command_name, "_error", "Unexpected command name"),
+        ref x => panic!("Expected UnhandleableAmf0Command event, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn can_respond_to_custom_client_call() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config.clone()).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+
+    let (_, events) = send_stream_key_command("customCommand", 7.0, "abc", &mut session, &mut serializer, &mut deserializer);
+    let transaction_id = match events.first() {
+        Some(ServerSessionEvent::UnhandleableAmf0Command {command_name, transaction_id, ..}) => {
+            assert_eq!(command_name, "customCommand", "Unexpected command name");
+            *transaction_id
+        },
+
+        x => panic!("Expected UnhandleableAmf0Command event, instead received: {:?}", x),
+    };
+
+    let response = CallResponse::Result {command_object: Amf0Value::Null, additional_values: vec![Amf0Value::Boolean(true)]};
+    let packet = session.respond_to_call(transaction_id, response).unwrap();
+    let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+    match payload.to_rtmp_message().unwrap() {
+        RtmpMessage::Amf0Command {command_name, transaction_id, additional_arguments, ..} => {
+            assert_eq!(command_name, "_result", "Unexpected command name");
+            assert_eq!(transaction_id, 7.0, "Unexpected transaction id");
+            assert_eq!(additional_arguments, vec![Amf0Value::Boolean(true)], "Unexpected arguments");
+        },
+
+        x => panic!("Expected Amf0Command, instead received: {:?}", x),
+    }
+
+    let response = CallResponse::Error {command_object: Amf0Value::Null, additional_values: Vec::new()};
+    let packet = session.respond_to_call(transaction_id, response).unwrap();
+    let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+    match payload.to_rtmp_message().unwrap() {
+        RtmpMessage::Amf0Command {command_name, transaction_id, ..} => {
+            assert_eq!(command_name, "_error", "Unexpected command name");
+            assert_eq!(transaction_id, 7.0, "Unexpected transaction id");
+        },
+
+        x => panic!("Expected Amf0Command, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn bandwidth_check_measures_client_and_sends_on_bw_done() {
+    let clock = ManualClock::new(0);
+    let mut config = get_basic_config();
+    config.bandwidth_check_enabled = true;
+    config.clock = Arc::new(clock.clone());
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config).unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+    for (_, message) in responses {
+        if let RtmpMessage::Amf0Command {ref command_name, ..} = message {
+            assert_ne!(command_name, "onBWDone", "Initial onBWDone sent while the bandwidth check is enabled");
+        }
+    }
+
+    let mut responses = accept_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let mut payload_sizes = Vec::new();
+    let mut events = Vec::new();
+    loop {
+        let (command_name, transaction_id, additional_arguments) = match responses.pop() {
+            Some((_, RtmpMessage::Amf0Command {command_name, transaction_id, additional_arguments, ..})) => (command_name, transaction_id, additional_arguments),
+            x => panic!("Expected Amf0Command, instead received: {:?}", x),
+        };
+
+        if command_name == "onBWDone" {
+            assert_eq!(additional_arguments.len(), 4, "Unexpected number of onBWDone arguments");
+            assert_eq!(additional_arguments[2], Amf0Value::Number(0.3), "Unexpected delta time");
+            match events[..] {
+                [ServerSessionEvent::BandwidthMeasured {kbps, latency_ms}] => {
+                    assert_eq!(latency_ms, 10, "Unexpected latency");
+                    assert!(kbps > 0, "Expected a non-zero bandwidth");
+                    assert_eq!(additional_arguments[0], Amf0Value::Number(kbps as f64), "Unexpected kbps");
+                    assert_eq!(additional_arguments[3], Amf0Value::Number(latency_ms as f64), "Unexpected latency");
+                },
+
+                ref x => panic!("Expected a single BandwidthMeasured event, instead received: {:?}", x),
+            }
+
+            break;
+        }
+
+        assert_eq!(command_name, "onBWCheck", "Unexpected command name");
+        assert_ne!(transaction_id, 0.0, "Expected onBWCheck to have a transaction id");
+        match additional_arguments.first() {
+            Some(Amf0Value::StrictArray(values)) => payload_sizes.push(values.len()),
+            x => panic!("Expected payload array, instead received: {:?}", x),
+        }
+
+        // Every round takes the 10ms latency plus 100ms per round after the first
+        clock.advance(if payload_sizes.len() == 1 { 10 } else { 110 });
+
+        let (new_responses, mut new_events) = send_stream_key_command("_result", transaction_id, "", &mut session, &mut serializer, &mut deserializer);
+        responses = new_responses;
+        events.append(&mut new_events);
+    }
+
+    assert_eq!(payload_sizes.len(), 4, "Unexpected number of onBWCheck rounds");
+    assert_eq!(payload_sizes[0], 0, "Expected first round to have an empty payload");
+    assert!(payload_sizes.windows(2).all(|x| x[0] < x[1]), "Expected payload sizes to grow: {:?}", payload_sizes);
+}
+
+#[test]
+fn bandwidth_check_stops_when_client_returns_error() {
+    let mut config = get_basic_config();
+    config.bandwidth_check_enabled = true;
+
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let mut responses = accept_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let transaction_id = match responses.pop() {
+        Some((_, RtmpMessage::Amf0Command {ref command_name, transaction_id, ..})) if command_name == "onBWCheck" => transaction_id,
+        x => panic!("Expected onBWCheck command, instead received: {:?}", x),
+    };
+
+    let (responses, events) = send_stream_key_command("_error", transaction_id, "", &mut session, &mut serializer, &mut deserializer);
+    assert_eq!(responses.len(), 0, "Expected no responses");
+    assert_eq!(events.len(), 0, "Expected no events");
+}
+
+#[test]
+fn bandwidth_check_not_sent_by_default() {
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(get_basic_config()).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let responses = accept_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    assert_eq!(responses.len(), 1, "Expected only the connection response");
+    match responses[0].1 {
+        RtmpMessage::Amf0Command {ref command_name, ..} => assert_eq!(command_name, "_result", "Unexpected command name"),
+        ref x => panic!("Expected Amf0Command, instead received: {:?}", x),
+    }
+}
+
+#[test]
+fn can_accept_connection_request_as_different_app_name() {
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(get_basic_config()).unwrap();
+    consume_results(&mut deserializer, results);
+
+    let connect_payload = create_connect_message("some_app?authmod=adobe&user=user".to_string(), 15, 0, 0.0);
+    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, connect_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested {request_id, ..} => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    let results = session.accept_connection_request_as(request_id, "some_app").unwrap();
+    let (responses, _) = split_results(&mut deserializer, results);
+    assert_eq!(responses.len(), 1, "Unexpected number of responses");
+    assert_status_command(&responses[0].1, "_result", "NetConnection.Connect.Success", "Successfully connected on app: some_app");
+
+    // Publish requests are only accepted by the helper if raised for `some_app`
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+    start_publishing("stream_key", stream_id, &mut session, &mut serializer, &mut deserializer);
+}
+
+#[test]
+fn cannot_accept_non_connection_request_as_different_app_name() {
+    let config = get_basic_config();
+    let mut deserializer = ChunkDeserializer::new();
+    let mut serializer = ChunkSerializer::new();
+    let (mut session, results) = ServerSession::new(config).unwrap();
+    consume_results(&mut deserializer, results);
+    perform_connection("some_app", &mut session, &mut serializer, &mut deserializer);
+    let stream_id = create_active_stream(&mut session, &mut serializer, &mut deserializer);
+
+    let message = RtmpMessage::Amf0Command {
+        command_name: "publish".to_string(),
+        transaction_id: 5.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![
+            Amf0Value::Utf8String("stream_key".to_string()),
+            Amf0Value::Utf8String("live".to_string()),
+        ]
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    let (_, events) = split_results(&mut deserializer, results);
+    let request_id = match events[0] {
+        ServerSessionEvent::PublishStreamRequested {request_id, ..} => request_id,
+        _ => panic!("Unexpected first event found: {:?}", events[0]),
+    };
+
+    match session.accept_connection_request_as(request_id, "other_app") {
+        Err(ServerSessionError {kind: ServerSessionErrorKind::InvalidRequestId}) => (),
+        x => panic!("Expected invalid request id error, instead received: {:?}", x),
+    }
+
+    // The request is still outstanding
+    session.accept_request(request_id).unwrap();
+}
+
+fn get_basic_config() -> ServerSessionConfig {
+    ServerSessionConfig {
+        chunk_size: DEFAULT_CHUNK_SIZE,
+        fms_version: "fms_version".to_string(),
+        peer_bandwidth: DEFAULT_PEER_BANDWIDTH,
+        window_ack_size: DEFAULT_WINDOW_ACK_SIZE,
+        enhanced_rtmp: EnhancedRtmpCapabilities::new(),
+        deserializer_limits: ChunkDeserializerLimits::new(),
+        bandwidth_check_enabled: false,
+        demux_multitrack_media: false,
+        clock: Arc::new(ManualClock::new(0)),
+    }
+}
+
+fn split_results(deserializer: &mut ChunkDeserializer, mut results: Vec<ServerSessionResult>) -> (Vec<(MessagePayload, RtmpMessage)>, Vec<ServerSessionEvent>) {
+    let mut responses = Vec::new();
+    let mut events = Vec::new();
+
+    for result in results.drain(..) {
+        match result {
+            ServerSessionResult::OutboundResponse(packet) => {
+                let payload = deserializer.get_next_message(&packet.bytes[..]).unwrap().unwrap();
+                let message = payload.to_rtmp_message().unwrap();
+                match message {
+                    RtmpMessage::SetChunkSize{size} => deserializer.set_max_chunk_size(size as usize).unwrap(),
+                    _ => (),
+                }
+
+                println!("response received from server: {:?}", message);
+                responses.push((payload, message));
+            },
+
+            ServerSessionResult::RaisedEvent(event) => {
+                println!("event received from server: {:?}", event);
+                events.push(event);
+            },
+
+            _ => (),
+        }
+    }
+
+    (responses, events)
+}
+
+fn consume_results(deserializer: &mut ChunkDeserializer, results: Vec<ServerSessionResult>) {
+    // Needed to keep the deserializer up to date
     split_results(deserializer, results);
 }
 
@@ -1143,16 +2652,36 @@ fn create_connect_message(app_name: String, timestamp: u32, stream_id: u32, obje
     payload
 }
 
+fn create_amf3_connect_message(app_name: &str) -> MessagePayload {
+    let command_object = Amf3Value::Object {
+        class_name: None,
+        sealed_properties: Vec::new(),
+        dynamic_properties: Some(vec![
+            ("app".to_string(), Amf3Value::Utf8String(app_name.to_string())),
+            ("objectEncoding".to_string(), Amf3Value::Integer(3)),
+        ]),
+    };
+
+    let message = RtmpMessage::Amf3Command {
+        command_name: "connect".to_string(),
+        transaction_id: 1.0,
+        command_object: Amf0Value::AvmPlus(command_object),
+        additional_arguments: vec![]
+    };
+
+    message.into_message_payload(RtmpTimestamp::new(15), 0).unwrap()
+}
+
 fn perform_connection(app_name: &str, session: &mut ServerSession, serializer: &mut ChunkSerializer, deserializer: &mut ChunkDeserializer) {
     let connect_payload = create_connect_message(app_name.to_string(), 15, 0, 0.0);
     let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
-    let connect_results = session.handle_input(&connect_packet.bytes[..]).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
     assert_eq!(connect_results.len(), 1, "Unexpected number of responses when handling connect request message");
 
     let (_, events) = split_results(deserializer, connect_results);
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
     let request_id = match events[0] {
-        ServerSessionEvent::ConnectionRequested {ref app_name, request_id} if app_name == "some_app" => request_id,
+        ServerSessionEvent::ConnectionRequested {ref app_name, request_id, ..} if app_name == "some_app" => request_id,
         _ => panic!("First event was not as expected: {:?}", events[0]),
     };
 
@@ -1162,6 +2691,24 @@ fn perform_connection(app_name: &str, session: &mut ServerSession, serializer: &
     // Assume it was successful
 }
 
+fn accept_connection(app_name: &str,
+                     session: &mut ServerSession,
+                     serializer: &mut ChunkSerializer,
+                     deserializer: &mut ChunkDeserializer) -> Vec<(MessagePayload, RtmpMessage)> {
+    let connect_payload = create_connect_message(app_name.to_string(), 15, 0, 0.0);
+    let connect_packet = serializer.serialize(&connect_payload, true, false).unwrap();
+    let connect_results = session.handle_input(Bytes::from(connect_packet.bytes)).unwrap();
+    let (_, events) = split_results(deserializer, connect_results);
+    let request_id = match events[0] {
+        ServerSessionEvent::ConnectionRequested {request_id, ..} => request_id,
+        _ => panic!("First event was not as expected: {:?}", events[0]),
+    };
+
+    let results = session.accept_request(request_id).unwrap();
+    let (responses, _) = split_results(deserializer, results);
+    responses
+}
+
 fn create_active_stream(session: &mut ServerSession, serializer: &mut ChunkSerializer, deserializer: &mut ChunkDeserializer) -> u32 {
     let message = RtmpMessage::Amf0Command {
         command_name: "createStream".to_string(),
@@ -1172,7 +2719,7 @@ fn create_active_stream(session: &mut ServerSession, serializer: &mut ChunkSeria
 
     let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
     let packet = serializer.serialize(&payload, true, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     let (responses, _) = split_results(deserializer, results);
 
     assert_eq!(responses.len(), 1, "Unexpected number of responses returned");
@@ -1204,7 +2751,7 @@ fn close_stream(stream_id: u32, session: &mut ServerSession, serializer: &mut Ch
 
     let payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
     let packet = serializer.serialize(&payload, false, false).unwrap();
-    let results = session.handle_input(&packet.bytes[..]).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
     consume_results(deserializer, results);
 }
 
@@ -1225,7 +2772,7 @@ fn start_publishing(stream_key: &str,
 
     let publish_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
     let publish_packet = serializer.serialize(&publish_payload, false, false).unwrap();
-    let publish_results = session.handle_input(&publish_packet.bytes[..]).unwrap();
+    let publish_results = session.handle_input(Bytes::from(publish_packet.bytes)).unwrap();
     let (_, events) = split_results(deserializer, publish_results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -1265,7 +2812,7 @@ fn start_playing(stream_key: &str,
 
     let play_payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
     let play_packet = serializer.serialize(&play_payload, false, false).unwrap();
-    let play_results = session.handle_input(&play_packet.bytes[..]).unwrap();
+    let play_results = session.handle_input(Bytes::from(play_packet.bytes)).unwrap();
     let (_, mut events) = split_results(deserializer, play_results);
 
     assert_eq!(events.len(), 1, "Unexpected number of events returned");
@@ -1280,3 +2827,60 @@ fn start_playing(stream_key: &str,
     let accept_results = session.accept_request(request_id).unwrap();
     consume_results(deserializer, accept_results);
 }
+
+fn send_stream_key_command(command_name: &str,
+                           transaction_id: f64,
+                           stream_key: &str,
+                           session: &mut ServerSession,
+                           serializer: &mut ChunkSerializer,
+                           deserializer: &mut ChunkDeserializer) -> (Vec<(MessagePayload, RtmpMessage)>, Vec<ServerSessionEvent>) {
+    let message = RtmpMessage::Amf0Command {
+        command_name: command_name.to_string(),
+        transaction_id,
+        command_object: Amf0Value::Null,
+        additional_arguments: vec![Amf0Value::Utf8String(stream_key.to_string())],
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), 0).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    split_results(deserializer, results)
+}
+
+fn assert_status_command(message: &RtmpMessage, expected_name: &str, expected_code: &str, expected_description: &str) {
+    match *message {
+        RtmpMessage::Amf0Command {ref command_name, ref additional_arguments, ..} => {
+            assert_eq!(command_name, expected_name, "Unexpected command name");
+
+            let properties = match additional_arguments.first() {
+                Some(Amf0Value::Object(properties)) => properties,
+                x => panic!("Expected status object, instead received: {:?}", x),
+            };
+
+            assert_eq!(properties.get("level"), Some(&Amf0Value::Utf8String("status".to_string())), "Unexpected level");
+            assert_eq!(properties.get("code"), Some(&Amf0Value::Utf8String(expected_code.to_string())), "Unexpected code");
+            assert_eq!(properties.get("description"), Some(&Amf0Value::Utf8String(expected_description.to_string())), "Unexpected description");
+        },
+
+        _ => panic!("Expected Amf0Command, instead received: {:?}", message),
+    }
+}
+
+fn send_playback_command(command_name: &str,
+                         arguments: Vec<Amf0Value>,
+                         stream_id: u32,
+                         session: &mut ServerSession,
+                         serializer: &mut ChunkSerializer,
+                         deserializer: &mut ChunkDeserializer) -> (Vec<(MessagePayload, RtmpMessage)>, Vec<ServerSessionEvent>) {
+    let message = RtmpMessage::Amf0Command {
+        command_name: command_name.to_string(),
+        transaction_id: 0.0,
+        command_object: Amf0Value::Null,
+        additional_arguments: arguments,
+    };
+
+    let payload = message.into_message_payload(RtmpTimestamp::new(0), stream_id).unwrap();
+    let packet = serializer.serialize(&payload, false, false).unwrap();
+    let results = session.handle_input(Bytes::from(packet.bytes)).unwrap();
+    split_results(deserializer, results)
+}
@@ -79,6 +79,9 @@ fn main() {
                 RtmpMessage::Abort {stream_id}
                     => println!("Abort {{ stream_id: {} }}", stream_id),
 
+                RtmpMessage::Aggregate { data }
+                    => println!("Aggregate {{ data: ({} bytes) }}", data.len()),
+
                 RtmpMessage::Acknowledgement { sequence_number }
                     => println!("Acknowledgement {{ sequence_number: {} }}", sequence_number),
 
@@ -89,6 +92,21 @@ fn main() {
                 RtmpMessage::Amf0Data { values }
                     => println!("RtmpMessage::Amf0Data {{ values: {:?} }}", values),
 
+                RtmpMessage::Amf3Command { command_name, transaction_id, command_object, additional_arguments }
+                    => println!("Amf3Command {{ command_name: {}, transaction_id: {}, command_object: {:?}, additional_arguments: {:?} }}",
+                               command_name, transaction_id, command_object, additional_arguments),
+
+                RtmpMessage::Amf3Data { values }
+                    => println!("RtmpMessage::Amf3Data {{ values: {:?} }}", values),
+
+                RtmpMessage::Amf0SharedObject { name, version, persistent, events }
+                    => println!("Amf0SharedObject {{ name: {}, version: {}, persistent: {}, events: {:?} }}",
+                                name, version, persistent, events),
+
+                RtmpMessage::Amf3SharedObject { name, version, persistent, events }
+                    => println!("Amf3SharedObject {{ name: {}, version: {}, persistent: {}, events: {:?} }}",
+                                name, version, persistent, events),
+
                 RtmpMessage::AudioData { data }
                     => {
                     print!("AudioData: {{ data: ");